@@ -2,4 +2,6 @@ use peg;
 
 fn main() {
     peg::cargo_build("src/sparql/sparql_grammar.rustpeg");
+    peg::cargo_build("src/shex/shexc_grammar.rustpeg");
+    peg::cargo_build("src/store/turtle_star_grammar.rustpeg");
 }
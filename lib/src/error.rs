@@ -0,0 +1,226 @@
+use rio_api::iri::IriParseError;
+use rio_api::parser::ParseError;
+use rio_turtle::TurtleError;
+use rio_xml::RdfXmlError;
+use std::error;
+use std::fmt;
+use std::io;
+use std::str::Utf8Error;
+use std::string::FromUtf8Error;
+
+/// The error type of this crate
+#[derive(Debug)]
+pub enum Error {
+    /// A SPARQL query, a RDF file or a SPARQL results file is not valid
+    Syntax(SyntaxError),
+    /// The evaluation of a SPARQL query failed
+    Evaluation(String),
+    /// The storage layer failed or contains invalid data
+    Storage(Box<dyn error::Error + Send + Sync>),
+    /// An I/O operation failed
+    Io(io::Error),
+    /// The operation relies on a feature that is not supported
+    Unsupported(String),
+}
+
+impl Error {
+    pub(crate) fn syntax(message: impl Into<String>) -> Self {
+        Error::Syntax(SyntaxError::new(message, None))
+    }
+
+    pub(crate) fn evaluation(message: impl Into<String>) -> Self {
+        Error::Evaluation(message.into())
+    }
+
+    pub(crate) fn storage(error: impl Into<Box<dyn error::Error + Send + Sync>>) -> Self {
+        Error::Storage(error.into())
+    }
+
+    pub(crate) fn unsupported(message: impl Into<String>) -> Self {
+        Error::Unsupported(message.into())
+    }
+
+    fn from_rio_parse_error<E: ParseError>(error: E) -> Self {
+        // rio parsers wrap the I/O errors of their readers
+        if let Some(io_error) = error
+            .source()
+            .and_then(|source| source.downcast_ref::<io::Error>())
+        {
+            return Error::Io(io::Error::new(io_error.kind(), error.to_string()));
+        }
+        let position = error
+            .textual_position()
+            .map(|position| TextPosition::new(position.line_number(), position.byte_number()));
+        Error::Syntax(SyntaxError::new(error.to_string(), position))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Syntax(error) => error.fmt(f),
+            Error::Evaluation(message) => write!(f, "{}", message),
+            Error::Storage(error) => error.fmt(f),
+            Error::Io(error) => error.fmt(f),
+            Error::Unsupported(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Syntax(error) => Some(error),
+            Error::Storage(error) => Some(error.as_ref()),
+            Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<SyntaxError> for Error {
+    fn from(error: SyntaxError) -> Self {
+        Error::Syntax(error)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<TurtleError> for Error {
+    fn from(error: TurtleError) -> Self {
+        Self::from_rio_parse_error(error)
+    }
+}
+
+impl From<RdfXmlError> for Error {
+    fn from(error: RdfXmlError) -> Self {
+        Self::from_rio_parse_error(error)
+    }
+}
+
+impl From<IriParseError> for Error {
+    fn from(error: IriParseError) -> Self {
+        Error::syntax(error.to_string())
+    }
+}
+
+impl From<quick_xml::Error> for Error {
+    fn from(error: quick_xml::Error) -> Self {
+        match error {
+            quick_xml::Error::Io(error) => Error::Io(error),
+            error => Error::syntax(error.to_string()),
+        }
+    }
+}
+
+impl From<Utf8Error> for Error {
+    fn from(error: Utf8Error) -> Self {
+        Error::storage(error)
+    }
+}
+
+impl From<FromUtf8Error> for Error {
+    fn from(error: FromUtf8Error) -> Self {
+        Error::storage(error)
+    }
+}
+
+#[cfg(feature = "rocksdb")]
+impl From<rocksdb::Error> for Error {
+    fn from(error: rocksdb::Error) -> Self {
+        Error::storage(error)
+    }
+}
+
+/// A syntax error in a SPARQL query, a RDF file or a SPARQL results file
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct SyntaxError {
+    message: String,
+    position: Option<TextPosition>,
+    expected: Vec<String>,
+    snippet: Option<String>,
+}
+
+impl SyntaxError {
+    pub(crate) fn new(message: impl Into<String>, position: Option<TextPosition>) -> Self {
+        Self {
+            message: message.into(),
+            position,
+            expected: Vec::default(),
+            snippet: None,
+        }
+    }
+
+    pub(crate) fn with_expected(mut self, expected: Vec<String>) -> Self {
+        self.expected = expected;
+        self
+    }
+
+    pub(crate) fn with_snippet(mut self, snippet: impl Into<String>) -> Self {
+        self.snippet = Some(snippet.into());
+        self
+    }
+
+    /// The error message
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The position of the error in the input, if known
+    pub fn position(&self) -> Option<TextPosition> {
+        self.position
+    }
+
+    /// A human readable description of the tokens that were expected at the error position
+    ///
+    /// It is empty if the parser does not provide this information.
+    pub fn expected(&self) -> &[String] {
+        &self.expected
+    }
+
+    /// The line of the input containing the error, if known
+    pub fn snippet(&self) -> Option<&str> {
+        self.snippet.as_deref()
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl error::Error for SyntaxError {}
+
+/// A position in a text
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct TextPosition {
+    line: usize,
+    column: usize,
+}
+
+impl TextPosition {
+    pub(crate) fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+
+    /// The line number, starting from 0
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The column number, starting from 0
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl fmt::Display for TextPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {} column {}", self.line + 1, self.column + 1)
+    }
+}
@@ -13,6 +13,8 @@
 //! use rudf::{Repository, RepositoryConnection, MemoryRepository, Result};
 //! use crate::rudf::sparql::PreparedQuery;
 //! use rudf::sparql::QueryResult;
+//! use rudf::sparql::QueryOptions;
+//! use std::collections::BTreeMap;
 //!
 //! let repository = MemoryRepository::default();
 //! let mut connection = repository.connection().unwrap();
@@ -27,21 +29,28 @@
 //! assert_eq!(vec![quad], results.unwrap());
 //!
 //! // SPARQL query
-//! let prepared_query = connection.prepare_query("SELECT ?s WHERE { ?s ?p ?o }", None).unwrap();
-//! let results = prepared_query.exec().unwrap();
+//! let prepared_query = connection.prepare_query("SELECT ?s WHERE { ?s ?p ?o }", QueryOptions::default()).unwrap();
+//! let results = prepared_query.exec(&BTreeMap::default()).unwrap();
 //! if let QueryResult::Bindings(results) = results {
 //!     assert_eq!(results.into_values_iter().next().unwrap().unwrap()[0], Some(ex.into()));
 //! }
 //! ```
 
+#![recursion_limit = "256"]
+
+mod error;
 pub mod model;
 mod repository;
+pub mod shacl;
+pub mod shex;
 pub mod sparql;
 pub(crate) mod store;
 mod syntax;
 
-pub use failure::Error;
-pub type Result<T> = ::std::result::Result<T, failure::Error>;
+pub use crate::error::Error;
+pub use crate::error::SyntaxError;
+pub use crate::error::TextPosition;
+pub type Result<T> = ::std::result::Result<T, Error>;
 pub use crate::repository::Repository;
 pub use crate::repository::RepositoryConnection;
 pub use crate::store::MemoryRepository;
@@ -30,7 +30,7 @@ impl BlankNode {
 
     /// Returns the underlying ID of this blank node
     pub fn as_str(&self) -> &str {
-        str::from_utf8(&self.str).unwrap()
+        str::from_utf8(&self.str).unwrap().trim_end_matches('\0')
     }
 
     /// Returns the underlying ID of this blank node
@@ -57,3 +57,12 @@ impl<'a> From<&'a BlankNode> for rio::BlankNode<'a> {
         rio::BlankNode { id: node.as_str() }
     }
 }
+
+#[test]
+fn test_as_str() {
+    assert_eq!(BlankNode::new_from_unique_id(0x2a).as_str(), "2a");
+    assert_eq!(
+        BlankNode::new_from_unique_id(u128::MAX).as_str(),
+        "ffffffffffffffffffffffffffffffff"
+    );
+}
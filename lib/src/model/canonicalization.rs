@@ -0,0 +1,400 @@
+//! Implementation of the [RDF Dataset Canonicalization](https://www.w3.org/TR/rdf-canon/) algorithm (RDFC-1.0)
+
+use crate::model::vocab::xsd;
+use crate::model::*;
+use crate::{Error, Result};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::iter::from_fn;
+
+/// The maximal number of calls to the Hash N-Degree Quads algorithm
+///
+/// It protects against [poison graphs](https://www.w3.org/TR/rdf-canon/#dataset-poisoning) that would require an exponential time.
+const MAX_DEEP_ITERATIONS: usize = 100_000;
+
+/// Computes the canonical identifiers (`c14n0`, `c14n1`...) of the blank nodes of the dataset
+pub(crate) fn canonical_labels(dataset: &SimpleDataset) -> Result<HashMap<BlankNode, String>> {
+    let quads = dataset.iter().collect::<Vec<_>>();
+    let canonicalizer = Canonicalizer::new(&quads);
+    Ok(canonicalizer
+        .run()?
+        .into_iter()
+        .map(|(node, label)| (node.clone(), label))
+        .collect())
+}
+
+/// Serializes the dataset as canonical N-Quads using the canonical blank node identifiers
+pub(crate) fn canonical_nquads(dataset: &SimpleDataset) -> Result<String> {
+    let quads = dataset.iter().collect::<Vec<_>>();
+    let labels = Canonicalizer::new(&quads).run()?;
+    let mut lines = quads
+        .iter()
+        .map(|quad| to_nquad(quad, &|node| labels[node].clone()))
+        .collect::<Vec<_>>();
+    lines.sort();
+    Ok(lines.concat())
+}
+
+struct Canonicalizer<'a> {
+    quads: &'a [Quad],
+    /// The indexes of the quads mentioning each blank node
+    blank_node_to_quads: HashMap<&'a BlankNode, Vec<usize>>,
+    canonical_issuer: IdentifierIssuer<'a>,
+    deep_iterations: usize,
+}
+
+impl<'a> Canonicalizer<'a> {
+    fn new(quads: &'a [Quad]) -> Self {
+        let mut blank_node_to_quads: HashMap<&'a BlankNode, Vec<usize>> = HashMap::default();
+        for (i, quad) in quads.iter().enumerate() {
+            for (node, _) in quad_blank_nodes(quad) {
+                let quads = blank_node_to_quads.entry(node).or_default();
+                if quads.last() != Some(&i) {
+                    quads.push(i);
+                }
+            }
+        }
+        Self {
+            quads,
+            blank_node_to_quads,
+            canonical_issuer: IdentifierIssuer::new("c14n"),
+            deep_iterations: 0,
+        }
+    }
+
+    fn run(mut self) -> Result<HashMap<&'a BlankNode, String>> {
+        let mut hash_to_blank_nodes: BTreeMap<String, Vec<&'a BlankNode>> = BTreeMap::default();
+        for node in self.blank_node_to_quads.keys() {
+            hash_to_blank_nodes
+                .entry(self.hash_first_degree_quads(node))
+                .or_default()
+                .push(node);
+        }
+
+        // The blank nodes with a unique hash get their identifiers first
+        let mut non_unique = Vec::default();
+        for nodes in hash_to_blank_nodes.into_values() {
+            if nodes.len() == 1 {
+                self.canonical_issuer.issue(nodes[0]);
+            } else {
+                non_unique.push(nodes);
+            }
+        }
+
+        for nodes in non_unique {
+            let mut hash_path_list = Vec::default();
+            for node in nodes {
+                if self.canonical_issuer.get(node).is_some() {
+                    continue;
+                }
+                let mut temporary_issuer = IdentifierIssuer::new("b");
+                temporary_issuer.issue(node);
+                hash_path_list.push(self.hash_n_degree_quads(node, temporary_issuer)?);
+            }
+            hash_path_list.sort_by(|(a, _), (b, _)| a.cmp(b));
+            for (_, issuer) in hash_path_list {
+                for node in issuer.issued_order {
+                    self.canonical_issuer.issue(node);
+                }
+            }
+        }
+        Ok(self.canonical_issuer.identifiers)
+    }
+
+    fn hash_first_degree_quads(&self, node: &BlankNode) -> String {
+        let mut nquads = self.blank_node_to_quads[node]
+            .iter()
+            .map(|i| {
+                to_nquad(&self.quads[*i], &|other| {
+                    if other == node { "a" } else { "z" }.to_owned()
+                })
+            })
+            .collect::<Vec<_>>();
+        nquads.sort();
+        sha256(&nquads.concat())
+    }
+
+    fn hash_related_blank_node(
+        &self,
+        related: &BlankNode,
+        quad: &Quad,
+        issuer: &IdentifierIssuer<'_>,
+        position: &str,
+    ) -> String {
+        let mut input = position.to_owned();
+        if position != "g" {
+            write!(&mut input, "<{}>", quad.predicate().as_str()).unwrap();
+        }
+        if let Some(identifier) = self
+            .canonical_issuer
+            .get(related)
+            .or_else(|| issuer.get(related))
+        {
+            write!(&mut input, "_:{}", identifier).unwrap();
+        } else {
+            input.push_str(&self.hash_first_degree_quads(related));
+        }
+        sha256(&input)
+    }
+
+    fn hash_n_degree_quads(
+        &mut self,
+        node: &'a BlankNode,
+        mut issuer: IdentifierIssuer<'a>,
+    ) -> Result<(String, IdentifierIssuer<'a>)> {
+        self.deep_iterations += 1;
+        if self.deep_iterations > MAX_DEEP_ITERATIONS {
+            return Err(Error::evaluation(
+                "The dataset canonicalization requires too many computations",
+            ));
+        }
+
+        let mut related_by_hash: BTreeMap<String, Vec<&'a BlankNode>> = BTreeMap::default();
+        for i in &self.blank_node_to_quads[node] {
+            let quad = &self.quads[*i];
+            for (related, position) in quad_blank_nodes(quad) {
+                if related != node {
+                    let hash = self.hash_related_blank_node(related, quad, &issuer, position);
+                    related_by_hash.entry(hash).or_default().push(related);
+                }
+            }
+        }
+
+        let mut data_to_hash = String::default();
+        for (related_hash, nodes) in related_by_hash {
+            data_to_hash.push_str(&related_hash);
+            let mut chosen_path = String::default();
+            let mut chosen_issuer = None;
+            'permutations: for permutation in permutations(&nodes) {
+                let mut issuer_copy = issuer.clone();
+                let mut path = String::default();
+                let mut recursion_list = Vec::default();
+                for related in permutation {
+                    if let Some(identifier) = self.canonical_issuer.get(related) {
+                        write!(&mut path, "_:{}", identifier).unwrap();
+                    } else {
+                        if issuer_copy.get(related).is_none() {
+                            recursion_list.push(related);
+                        }
+                        write!(&mut path, "_:{}", issuer_copy.issue(related)).unwrap();
+                    }
+                    if is_worse_path(&path, &chosen_path) {
+                        continue 'permutations;
+                    }
+                }
+                for related in recursion_list {
+                    let (hash, result_issuer) =
+                        self.hash_n_degree_quads(related, issuer_copy.clone())?;
+                    issuer_copy = result_issuer;
+                    write!(&mut path, "_:{}<{}>", issuer_copy.issue(related), hash).unwrap();
+                    if is_worse_path(&path, &chosen_path) {
+                        continue 'permutations;
+                    }
+                }
+                if chosen_issuer.is_none() || path < chosen_path {
+                    chosen_path = path;
+                    chosen_issuer = Some(issuer_copy);
+                }
+            }
+            data_to_hash.push_str(&chosen_path);
+            if let Some(chosen_issuer) = chosen_issuer {
+                issuer = chosen_issuer;
+            }
+        }
+        Ok((sha256(&data_to_hash), issuer))
+    }
+}
+
+fn is_worse_path(path: &str, chosen_path: &str) -> bool {
+    !chosen_path.is_empty() && path.len() >= chosen_path.len() && path > chosen_path
+}
+
+/// Issues identifiers made of a prefix and of an incremented counter
+#[derive(Clone)]
+struct IdentifierIssuer<'a> {
+    prefix: &'static str,
+    identifiers: HashMap<&'a BlankNode, String>,
+    issued_order: Vec<&'a BlankNode>,
+}
+
+impl<'a> IdentifierIssuer<'a> {
+    fn new(prefix: &'static str) -> Self {
+        Self {
+            prefix,
+            identifiers: HashMap::default(),
+            issued_order: Vec::default(),
+        }
+    }
+
+    fn get(&self, node: &BlankNode) -> Option<&str> {
+        self.identifiers.get(node).map(|id| id.as_str())
+    }
+
+    fn issue(&mut self, node: &'a BlankNode) -> &str {
+        let prefix = self.prefix;
+        let counter = self.issued_order.len();
+        let issued_order = &mut self.issued_order;
+        self.identifiers.entry(node).or_insert_with(|| {
+            issued_order.push(node);
+            format!("{}{}", prefix, counter)
+        })
+    }
+}
+
+/// Returns the blank nodes of a quad with their position (`s`, `o` or `g`)
+///
+/// The blank nodes nested in quoted triples get the position of the quoted triple.
+fn quad_blank_nodes(quad: &Quad) -> Vec<(&BlankNode, &'static str)> {
+    let mut nodes = Vec::default();
+    add_subject_blank_nodes(quad.subject(), "s", &mut nodes);
+    add_object_blank_nodes(quad.object(), "o", &mut nodes);
+    if let Some(graph_name) = quad.graph_name() {
+        add_subject_blank_nodes(graph_name, "g", &mut nodes);
+    }
+    nodes
+}
+
+fn add_subject_blank_nodes<'a>(
+    node: &'a NamedOrBlankNode,
+    position: &'static str,
+    nodes: &mut Vec<(&'a BlankNode, &'static str)>,
+) {
+    match node {
+        NamedOrBlankNode::NamedNode(_) => (),
+        NamedOrBlankNode::BlankNode(node) => nodes.push((node, position)),
+        NamedOrBlankNode::Triple(triple) => add_triple_blank_nodes(triple, position, nodes),
+    }
+}
+
+fn add_object_blank_nodes<'a>(
+    term: &'a Term,
+    position: &'static str,
+    nodes: &mut Vec<(&'a BlankNode, &'static str)>,
+) {
+    match term {
+        Term::NamedNode(_) | Term::Literal(_) => (),
+        Term::BlankNode(node) => nodes.push((node, position)),
+        Term::Triple(triple) => add_triple_blank_nodes(triple, position, nodes),
+    }
+}
+
+fn add_triple_blank_nodes<'a>(
+    triple: &'a Triple,
+    position: &'static str,
+    nodes: &mut Vec<(&'a BlankNode, &'static str)>,
+) {
+    add_subject_blank_nodes(triple.subject(), position, nodes);
+    add_object_blank_nodes(triple.object(), position, nodes);
+}
+
+/// Iterates lazily over the permutations of the elements in lexicographic order of their positions
+fn permutations<T: Copy>(elements: &[T]) -> impl Iterator<Item = Vec<T>> + '_ {
+    let mut positions = Some((0..elements.len()).collect::<Vec<_>>());
+    from_fn(move || {
+        let current = positions.take()?;
+        let permutation = current.iter().map(|i| elements[*i]).collect();
+        positions = next_permutation(current);
+        Some(permutation)
+    })
+}
+
+fn next_permutation(mut positions: Vec<usize>) -> Option<Vec<usize>> {
+    let pivot = (1..positions.len())
+        .rev()
+        .find(|i| positions[i - 1] < positions[*i])?
+        - 1;
+    let successor = (pivot + 1..positions.len())
+        .rev()
+        .find(|i| positions[pivot] < positions[*i])?;
+    positions.swap(pivot, successor);
+    positions[pivot + 1..].reverse();
+    Some(positions)
+}
+
+fn sha256(input: &str) -> String {
+    hex::encode(Sha256::digest(input.as_bytes()))
+}
+
+/// Serializes a quad as a [canonical N-Quads](https://www.w3.org/TR/rdf12-n-quads/#canonical-quads) line
+fn to_nquad(quad: &Quad, label: &dyn Fn(&BlankNode) -> String) -> String {
+    let mut line = String::default();
+    write_subject(&mut line, quad.subject(), label);
+    write!(&mut line, " <{}> ", quad.predicate().as_str()).unwrap();
+    write_object(&mut line, quad.object(), label);
+    if let Some(graph_name) = quad.graph_name() {
+        line.push(' ');
+        write_subject(&mut line, graph_name, label);
+    }
+    line.push_str(" .\n");
+    line
+}
+
+fn write_subject(
+    output: &mut String,
+    node: &NamedOrBlankNode,
+    label: &dyn Fn(&BlankNode) -> String,
+) {
+    match node {
+        NamedOrBlankNode::NamedNode(node) => write!(output, "<{}>", node.as_str()).unwrap(),
+        NamedOrBlankNode::BlankNode(node) => write!(output, "_:{}", label(node)).unwrap(),
+        NamedOrBlankNode::Triple(triple) => write_quoted_triple(output, triple, label),
+    }
+}
+
+fn write_object(output: &mut String, term: &Term, label: &dyn Fn(&BlankNode) -> String) {
+    match term {
+        Term::NamedNode(node) => write!(output, "<{}>", node.as_str()).unwrap(),
+        Term::BlankNode(node) => write!(output, "_:{}", label(node)).unwrap(),
+        Term::Literal(literal) => write_literal(output, literal),
+        Term::Triple(triple) => write_quoted_triple(output, triple, label),
+    }
+}
+
+fn write_quoted_triple(output: &mut String, triple: &Triple, label: &dyn Fn(&BlankNode) -> String) {
+    output.push_str("<< ");
+    write_subject(output, triple.subject(), label);
+    write!(output, " <{}> ", triple.predicate().as_str()).unwrap();
+    write_object(output, triple.object(), label);
+    output.push_str(" >>");
+}
+
+fn write_literal(output: &mut String, literal: &Literal) {
+    output.push('"');
+    for c in literal.value().chars() {
+        match c {
+            '\u{08}' => output.push_str("\\b"),
+            '\t' => output.push_str("\\t"),
+            '\n' => output.push_str("\\n"),
+            '\u{0C}' => output.push_str("\\f"),
+            '\r' => output.push_str("\\r"),
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\u{00}'..='\u{1F}' | '\u{7F}' => write!(output, "\\u{:04X}", c as u32).unwrap(),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+    if let Some(language) = literal.language() {
+        write!(output, "@{}", language).unwrap();
+    } else if literal.datatype() != &*xsd::STRING {
+        write!(output, "^^<{}>", literal.datatype().as_str()).unwrap();
+    }
+}
+
+#[test]
+fn test_permutations() {
+    assert_eq!(permutations::<u8>(&[]).collect::<Vec<_>>(), vec![vec![]]);
+    assert_eq!(
+        permutations(&[1, 2, 3]).collect::<Vec<_>>(),
+        vec![
+            vec![1, 2, 3],
+            vec![1, 3, 2],
+            vec![2, 1, 3],
+            vec![2, 3, 1],
+            vec![3, 1, 2],
+            vec![3, 2, 1]
+        ]
+    );
+    assert_eq!(permutations(&[0; 20]).take(3).count(), 3);
+}
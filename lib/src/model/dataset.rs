@@ -0,0 +1,233 @@
+use crate::model::canonicalization::{canonical_labels, canonical_nquads};
+use crate::model::isomorphism::are_datasets_isomorphic;
+use crate::model::*;
+use crate::Result;
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::{empty, once, FromIterator};
+
+/// A simple implementation of [RDF datasets](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-dataset).
+///
+/// Each graph of the dataset is stored as an indexed [`SimpleGraph`](struct.SimpleGraph.html).
+///
+/// Usage example:
+/// ```
+/// use rudf::model::*;
+/// use rudf::model::SimpleDataset;
+///
+/// let mut dataset = SimpleDataset::default();
+/// let ex = NamedNode::parse("http://example.com").unwrap();
+/// let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), Some(ex.clone().into()));
+/// dataset.insert(quad.clone());
+///
+/// let results: Vec<Quad> = dataset.quads_for_pattern(Some(&ex.clone().into()), None, None, None).collect();
+/// assert_eq!(vec![quad], results);
+/// assert_eq!(1, dataset.graph(Some(&ex.into())).unwrap().len());
+/// assert!(dataset.graph(None).unwrap().is_empty());
+/// ```
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct SimpleDataset {
+    default_graph: SimpleGraph,
+    named_graphs: HashMap<NamedOrBlankNode, SimpleGraph>,
+}
+
+impl SimpleDataset {
+    /// Returns all quads contained by the dataset
+    pub fn iter(&self) -> impl Iterator<Item = Quad> + '_ {
+        self.quads_for_pattern(None, None, None, None)
+    }
+
+    /// Returns the quads matching a pattern
+    ///
+    /// `None` matches any term and `Some(None)` as graph name matches the default graph.
+    pub fn quads_for_pattern<'a>(
+        &'a self,
+        subject: Option<&'a NamedOrBlankNode>,
+        predicate: Option<&'a NamedNode>,
+        object: Option<&'a Term>,
+        graph_name: Option<Option<&'a NamedOrBlankNode>>,
+    ) -> impl Iterator<Item = Quad> + 'a {
+        let graphs: Box<dyn Iterator<Item = (Option<&NamedOrBlankNode>, &SimpleGraph)>> =
+            match graph_name {
+                Some(graph_name) => match self.graph(graph_name) {
+                    Some(graph) => Box::new(once((graph_name, graph))),
+                    None => Box::new(empty()),
+                },
+                None => Box::new(self.graphs()),
+            };
+        graphs.flat_map(move |(graph_name, graph)| {
+            graph
+                .triples_for_pattern(subject, predicate, object)
+                .map(move |t| {
+                    Quad::new(
+                        t.subject().clone(),
+                        t.predicate().clone(),
+                        t.object().clone(),
+                        graph_name.cloned(),
+                    )
+                })
+        })
+    }
+
+    /// Returns the graph with the given name or the default graph if `None`
+    ///
+    /// The default graph always exists and the named graphs exist if they contain at least one triple.
+    pub fn graph(&self, graph_name: Option<&NamedOrBlankNode>) -> Option<&SimpleGraph> {
+        match graph_name {
+            Some(graph_name) => self.named_graphs.get(graph_name),
+            None => Some(&self.default_graph),
+        }
+    }
+
+    /// Returns the names of the named graphs of the dataset
+    pub fn graph_names(&self) -> impl Iterator<Item = &NamedOrBlankNode> {
+        self.named_graphs.keys()
+    }
+
+    /// Returns the default graph followed by the named graphs with their names
+    pub fn graphs(&self) -> impl Iterator<Item = (Option<&NamedOrBlankNode>, &SimpleGraph)> {
+        once((None, &self.default_graph)).chain(
+            self.named_graphs
+                .iter()
+                .map(|(graph_name, graph)| (Some(graph_name), graph)),
+        )
+    }
+
+    /// Checks if the dataset contains the given quad
+    pub fn contains(&self, quad: &Quad) -> bool {
+        match self.graph(quad.graph_name().as_ref()) {
+            Some(graph) => graph.contains(&quad.clone().into_triple()),
+            None => false,
+        }
+    }
+
+    /// Adds a quad to the dataset
+    ///
+    /// Returns `false` if the quad was already in the dataset
+    /// or if it is not inserted because its graph name is a quoted triple, that RDF-star does not allow.
+    pub fn insert(&mut self, quad: Quad) -> bool {
+        let graph = match quad.graph_name() {
+            Some(NamedOrBlankNode::Triple(_)) => return false,
+            Some(graph_name) => self.named_graphs.entry(graph_name.clone()).or_default(),
+            None => &mut self.default_graph,
+        };
+        graph.insert(quad.into_triple())
+    }
+
+    /// Removes a concrete quad from the dataset
+    pub fn remove(&mut self, quad: &Quad) -> bool {
+        let triple = quad.clone().into_triple();
+        match quad.graph_name() {
+            Some(graph_name) => {
+                if let Some(graph) = self.named_graphs.get_mut(graph_name) {
+                    let removed = graph.remove(&triple);
+                    if graph.is_empty() {
+                        self.named_graphs.remove(graph_name);
+                    }
+                    removed
+                } else {
+                    false
+                }
+            }
+            None => self.default_graph.remove(&triple),
+        }
+    }
+
+    /// Returns the number of quads in this dataset
+    pub fn len(&self) -> usize {
+        self.graphs().map(|(_, graph)| graph.len()).sum()
+    }
+
+    /// Checks if this dataset contains a quad
+    pub fn is_empty(&self) -> bool {
+        self.graphs().all(|(_, graph)| graph.is_empty())
+    }
+
+    /// Returns the quads contained in this dataset or in the other one
+    pub fn union(&self, other: &SimpleDataset) -> SimpleDataset {
+        let mut union = self.clone();
+        union.extend(other.iter());
+        union
+    }
+
+    /// Returns the quads contained in both this dataset and the other one
+    pub fn intersection(&self, other: &SimpleDataset) -> SimpleDataset {
+        self.iter().filter(|quad| other.contains(quad)).collect()
+    }
+
+    /// Returns the quads contained in this dataset but not in the other one
+    pub fn difference(&self, other: &SimpleDataset) -> SimpleDataset {
+        self.iter().filter(|quad| !other.contains(quad)).collect()
+    }
+
+    /// Checks if the current dataset is [isomorphic](https://www.w3.org/TR/rdf11-concepts/#dfn-dataset-isomorphism) with an other one
+    ///
+    /// Warning: This algorithm as a worst case complexity in n!
+    pub fn is_isomorphic(&self, other: &SimpleDataset) -> bool {
+        are_datasets_isomorphic(self, other)
+    }
+
+    /// Returns the canonical identifiers (`c14n0`, `c14n1`...) of the dataset blank nodes
+    /// computed using the [RDFC-1.0](https://www.w3.org/TR/rdf-canon/) algorithm
+    ///
+    /// Fails if the dataset requires too many computations to be canonicalized.
+    pub fn canonical_labels(&self) -> Result<HashMap<BlankNode, String>> {
+        canonical_labels(self)
+    }
+
+    /// Serializes the dataset in [canonical N-Quads](https://www.w3.org/TR/rdf-canon/#canonical-quads) with the blank nodes relabelled using the [RDFC-1.0](https://www.w3.org/TR/rdf-canon/) algorithm
+    ///
+    /// Two datasets are isomorphic if and only if they have the same canonical serialization.
+    ///
+    /// Usage example:
+    /// ```
+    /// use rudf::model::*;
+    ///
+    /// let ex = NamedNode::parse("http://example.com").unwrap();
+    /// let dataset: SimpleDataset = vec![
+    ///     Quad::new(BlankNode::default(), ex.clone(), ex.clone(), None),
+    /// ].into_iter().collect();
+    ///
+    /// assert_eq!(
+    ///     "_:c14n0 <http://example.com> <http://example.com> .\n",
+    ///     dataset.to_canonical_nquads().unwrap()
+    /// );
+    /// ```
+    pub fn to_canonical_nquads(&self) -> Result<String> {
+        canonical_nquads(self)
+    }
+}
+
+impl From<SimpleGraph> for SimpleDataset {
+    fn from(graph: SimpleGraph) -> Self {
+        Self {
+            default_graph: graph,
+            named_graphs: HashMap::default(),
+        }
+    }
+}
+
+impl FromIterator<Quad> for SimpleDataset {
+    fn from_iter<I: IntoIterator<Item = Quad>>(iter: I) -> Self {
+        let mut dataset = Self::default();
+        dataset.extend(iter);
+        dataset
+    }
+}
+
+impl Extend<Quad> for SimpleDataset {
+    fn extend<I: IntoIterator<Item = Quad>>(&mut self, iter: I) {
+        for quad in iter {
+            self.insert(quad);
+        }
+    }
+}
+
+impl fmt::Display for SimpleDataset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for q in self.iter() {
+            writeln!(f, "{}", q)?;
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,17 @@
 use crate::model::isomorphism::are_graphs_isomorphic;
 use crate::model::*;
-use std::collections::HashSet;
+use crate::Result;
+use std::borrow::Borrow;
+use std::collections::{hash_set, HashMap, HashSet};
 use std::fmt;
-use std::iter::FromIterator;
+use std::hash::{Hash, Hasher};
+use std::iter::{FromIterator, FusedIterator};
+use std::sync::Arc;
 
 /// A simple implementation of [RDF graphs](https://www.w3.org/TR/rdf11-concepts/#dfn-graph).
 ///
-/// It is not done to hold big graphs.
+/// The triples are indexed by subject, predicate and object so that the pattern lookups do not scan the whole graph.
+/// The indexes share the triples: they do not contain copies of the terms.
 ///
 /// Usage example:
 /// ```
@@ -20,22 +25,95 @@ use std::iter::FromIterator;
 /// let results: Vec<Triple> = graph.triples_for_subject(&ex.into()).cloned().collect();
 /// assert_eq!(vec![triple], results);
 /// ```
-#[derive(Eq, PartialEq, Debug, Clone, Default)]
+#[derive(Clone, Default)]
 pub struct SimpleGraph {
-    triples: HashSet<Triple>,
+    triples: HashSet<Arc<Triple>>,
+    spo: TripleIndex<SubjectKey, PredicateKey>,
+    pos: TripleIndex<PredicateKey, ObjectKey>,
+    osp: TripleIndex<ObjectKey, SubjectKey>,
 }
 
+/// The triples grouped by two of their terms
+type TripleIndex<A, B> = HashMap<A, HashMap<B, HashSet<Arc<Triple>>>>;
+
+/// A triple of the graph used as an index key for one of its terms
+///
+/// It is hashed and compared using only this term, so the index lookups are done with the term itself.
+trait TripleKey: Eq + Hash + Borrow<<Self as TripleKey>::Term> {
+    type Term: Eq + Hash;
+
+    fn new(triple: Arc<Triple>) -> Self;
+
+    fn term(triple: &Triple) -> &Self::Term;
+
+    fn triple(&self) -> &Triple;
+}
+
+macro_rules! triple_key {
+    ($name:ident, $term:ty, $accessor:ident) => {
+        struct $name(Arc<Triple>);
+
+        impl TripleKey for $name {
+            type Term = $term;
+
+            fn new(triple: Arc<Triple>) -> Self {
+                $name(triple)
+            }
+
+            fn term(triple: &Triple) -> &$term {
+                triple.$accessor()
+            }
+
+            fn triple(&self) -> &Triple {
+                &self.0
+            }
+        }
+
+        impl Clone for $name {
+            fn clone(&self) -> Self {
+                $name(self.0.clone())
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.0.$accessor() == other.0.$accessor()
+            }
+        }
+
+        impl Eq for $name {}
+
+        impl Hash for $name {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.0.$accessor().hash(state)
+            }
+        }
+
+        impl Borrow<$term> for $name {
+            fn borrow(&self) -> &$term {
+                self.0.$accessor()
+            }
+        }
+    };
+}
+
+triple_key!(SubjectKey, NamedOrBlankNode, subject);
+triple_key!(PredicateKey, NamedNode, predicate);
+triple_key!(ObjectKey, Term, object);
+
 impl SimpleGraph {
     /// Returns all triples contained by the graph
-    pub fn iter(&self) -> impl Iterator<Item = &Triple> {
-        self.triples.iter()
+    pub fn iter(&self) -> SimpleGraphIter<'_> {
+        SimpleGraphIter {
+            inner: self.triples.iter(),
+        }
     }
 
     pub fn triples_for_subject<'a>(
         &'a self,
         subject: &'a NamedOrBlankNode,
     ) -> impl Iterator<Item = &Triple> + 'a {
-        self.iter().filter(move |t| t.subject() == subject)
+        index_values(&self.spo, subject)
     }
 
     pub fn objects_for_subject_predicate<'a>(
@@ -43,9 +121,7 @@ impl SimpleGraph {
         subject: &'a NamedOrBlankNode,
         predicate: &'a NamedNode,
     ) -> impl Iterator<Item = &Term> + 'a {
-        self.iter()
-            .filter(move |t| t.subject() == subject && t.predicate() == predicate)
-            .map(|t| t.object())
+        index_pair_values(&self.spo, subject, predicate).map(|t| t.object())
     }
 
     pub fn object_for_subject_predicate<'a>(
@@ -62,16 +138,14 @@ impl SimpleGraph {
         subject: &'a NamedOrBlankNode,
         object: &'a Term,
     ) -> impl Iterator<Item = &NamedNode> + 'a {
-        self.iter()
-            .filter(move |t| t.subject() == subject && t.object() == object)
-            .map(|t| t.predicate())
+        index_pair_values(&self.osp, object, subject).map(|t| t.predicate())
     }
 
     pub fn triples_for_predicate<'a>(
         &'a self,
         predicate: &'a NamedNode,
     ) -> impl Iterator<Item = &Triple> + 'a {
-        self.iter().filter(move |t| t.predicate() == predicate)
+        index_values(&self.pos, predicate)
     }
 
     pub fn subjects_for_predicate_object<'a>(
@@ -79,16 +153,42 @@ impl SimpleGraph {
         predicate: &'a NamedNode,
         object: &'a Term,
     ) -> impl Iterator<Item = &NamedOrBlankNode> + 'a {
-        self.iter()
-            .filter(move |t| t.predicate() == predicate && t.object() == object)
-            .map(|t| t.subject())
+        index_pair_values(&self.pos, predicate, object).map(|t| t.subject())
     }
 
     pub fn triples_for_object<'a>(
         &'a self,
         object: &'a Term,
     ) -> impl Iterator<Item = &Triple> + 'a {
-        self.iter().filter(move |t| t.object() == object)
+        index_values(&self.osp, object)
+    }
+
+    /// Returns the triples matching a pattern, `None` matching any term
+    pub fn triples_for_pattern<'a>(
+        &'a self,
+        subject: Option<&'a NamedOrBlankNode>,
+        predicate: Option<&'a NamedNode>,
+        object: Option<&'a Term>,
+    ) -> Box<dyn Iterator<Item = &'a Triple> + 'a> {
+        match (subject, predicate, object) {
+            (Some(subject), Some(predicate), Some(object)) => Box::new(
+                index_pair_values(&self.spo, subject, predicate)
+                    .filter(move |t| t.object() == object),
+            ),
+            (Some(subject), Some(predicate), None) => {
+                Box::new(index_pair_values(&self.spo, subject, predicate))
+            }
+            (Some(subject), None, Some(object)) => {
+                Box::new(index_pair_values(&self.osp, object, subject))
+            }
+            (Some(subject), None, None) => Box::new(index_values(&self.spo, subject)),
+            (None, Some(predicate), Some(object)) => {
+                Box::new(index_pair_values(&self.pos, predicate, object))
+            }
+            (None, Some(predicate), None) => Box::new(index_values(&self.pos, predicate)),
+            (None, None, Some(object)) => Box::new(index_values(&self.osp, object)),
+            (None, None, None) => Box::new(self.iter()),
+        }
     }
 
     /// Checks if the graph contains the given triple
@@ -98,12 +198,25 @@ impl SimpleGraph {
 
     /// Adds a triple to the graph
     pub fn insert(&mut self, triple: Triple) -> bool {
+        if self.triples.contains(&triple) {
+            return false;
+        }
+        let triple = Arc::new(triple);
+        index_insert(&mut self.spo, &triple);
+        index_insert(&mut self.pos, &triple);
+        index_insert(&mut self.osp, &triple);
         self.triples.insert(triple)
     }
 
     /// Removes a concrete triple from the graph
     pub fn remove(&mut self, triple: &Triple) -> bool {
-        self.triples.remove(triple)
+        if !self.triples.remove(triple) {
+            return false;
+        }
+        index_remove(&mut self.spo, triple);
+        index_remove(&mut self.pos, triple);
+        index_remove(&mut self.osp, triple);
+        true
     }
 
     /// Returns the number of triples in this graph
@@ -116,51 +229,316 @@ impl SimpleGraph {
         self.triples.is_empty()
     }
 
+    /// Returns the triples contained in this graph or in the other one
+    pub fn union(&self, other: &SimpleGraph) -> SimpleGraph {
+        let mut union = self.clone();
+        union.extend(other.iter().cloned());
+        union
+    }
+
+    /// Returns the triples contained in both this graph and the other one
+    pub fn intersection(&self, other: &SimpleGraph) -> SimpleGraph {
+        self.iter()
+            .filter(|triple| other.contains(triple))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the triples contained in this graph but not in the other one
+    pub fn difference(&self, other: &SimpleGraph) -> SimpleGraph {
+        self.iter()
+            .filter(|triple| !other.contains(triple))
+            .cloned()
+            .collect()
+    }
+
     /// Checks if the current graph is [isomorphic](https://www.w3.org/TR/rdf11-concepts/#dfn-graph-isomorphism) with an other one
     ///
     /// Warning: This algorithm as a worst case complexity in n!
     pub fn is_isomorphic(&self, other: &SimpleGraph) -> bool {
         are_graphs_isomorphic(self, other)
     }
+
+    /// Returns the canonical identifiers of the graph blank nodes computed using the [RDFC-1.0](https://www.w3.org/TR/rdf-canon/) algorithm
+    pub fn canonical_labels(&self) -> Result<HashMap<BlankNode, String>> {
+        SimpleDataset::from(self.clone()).canonical_labels()
+    }
+
+    /// Serializes the graph in canonical N-Triples with the blank nodes relabelled using the [RDFC-1.0](https://www.w3.org/TR/rdf-canon/) algorithm
+    pub fn to_canonical_ntriples(&self) -> Result<String> {
+        SimpleDataset::from(self.clone()).to_canonical_nquads()
+    }
+}
+
+fn index_values<'a, A: TripleKey, B>(
+    index: &'a TripleIndex<A, B>,
+    a: &A::Term,
+) -> impl Iterator<Item = &'a Triple> + 'a {
+    index
+        .get(a)
+        .into_iter()
+        .flat_map(|by_b| by_b.values())
+        .flatten()
+        .map(|t| &**t)
+}
+
+fn index_pair_values<'a, A: TripleKey, B: TripleKey>(
+    index: &'a TripleIndex<A, B>,
+    a: &A::Term,
+    b: &B::Term,
+) -> impl Iterator<Item = &'a Triple> + 'a {
+    index
+        .get(a)
+        .and_then(|by_b| by_b.get(b))
+        .into_iter()
+        .flatten()
+        .map(|t| &**t)
+}
+
+fn index_insert<A: TripleKey, B: TripleKey>(index: &mut TripleIndex<A, B>, triple: &Arc<Triple>) {
+    index
+        .entry(A::new(triple.clone()))
+        .or_default()
+        .entry(B::new(triple.clone()))
+        .or_default()
+        .insert(triple.clone());
+}
+
+/// Removes the triple from the index
+///
+/// The keys sharing the removed triple are replaced by keys sharing one of the remaining triples
+/// so that the removed triple is freed.
+fn index_remove<A: TripleKey, B: TripleKey>(index: &mut TripleIndex<A, B>, triple: &Triple) {
+    let (a_key, mut by_b) = match index.remove_entry(A::term(triple)) {
+        Some(entry) => entry,
+        None => return,
+    };
+    if let Some((b_key, mut triples)) = by_b.remove_entry(B::term(triple)) {
+        triples.remove(triple);
+        if let Some(remaining) = triples.iter().next() {
+            by_b.insert(rekey(b_key, triple, remaining), triples);
+        }
+    }
+    if let Some(remaining) = by_b.values().flatten().next() {
+        let a_key = rekey(a_key, triple, remaining);
+        index.insert(a_key, by_b);
+    }
+}
+
+fn rekey<K: TripleKey>(key: K, removed: &Triple, remaining: &Arc<Triple>) -> K {
+    if key.triple() == removed {
+        K::new(remaining.clone())
+    } else {
+        key
+    }
+}
+
+impl PartialEq for SimpleGraph {
+    fn eq(&self, other: &Self) -> bool {
+        self.triples == other.triples
+    }
+}
+
+impl Eq for SimpleGraph {}
+
+impl fmt::Debug for SimpleGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimpleGraph")
+            .field("triples", &self.triples)
+            .finish()
+    }
 }
 
 impl IntoIterator for SimpleGraph {
     type Item = Triple;
-    type IntoIter = <HashSet<Triple> as IntoIterator>::IntoIter;
+    type IntoIter = SimpleGraphIntoIter;
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.triples.into_iter()
+    fn into_iter(self) -> SimpleGraphIntoIter {
+        let SimpleGraph { triples, .. } = self;
+        // The indexes are dropped so the triples are not shared anymore
+        SimpleGraphIntoIter {
+            inner: triples.into_iter(),
+        }
     }
 }
 
 impl<'a> IntoIterator for &'a SimpleGraph {
     type Item = &'a Triple;
-    type IntoIter = <&'a HashSet<Triple> as IntoIterator>::IntoIter;
+    type IntoIter = SimpleGraphIter<'a>;
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.triples.iter()
+    fn into_iter(self) -> SimpleGraphIter<'a> {
+        self.iter()
+    }
+}
+
+/// An owning iterator over the triples of a [`SimpleGraph`](struct.SimpleGraph.html).
+///
+/// It replaces the `std::collections::hash_set::IntoIter<Triple>` returned before the graph was indexed.
+#[derive(Debug)]
+pub struct SimpleGraphIntoIter {
+    inner: hash_set::IntoIter<Arc<Triple>>,
+}
+
+impl Iterator for SimpleGraphIntoIter {
+    type Item = Triple;
+
+    fn next(&mut self) -> Option<Triple> {
+        self.inner
+            .next()
+            .map(|t| Arc::try_unwrap(t).unwrap_or_else(|t| (*t).clone()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
     }
 }
 
+impl ExactSizeIterator for SimpleGraphIntoIter {}
+
+impl FusedIterator for SimpleGraphIntoIter {}
+
+/// An iterator over the triples of a [`SimpleGraph`](struct.SimpleGraph.html).
+///
+/// It replaces the `std::collections::hash_set::Iter<'a, Triple>` returned before the graph was indexed.
+#[derive(Clone, Debug)]
+pub struct SimpleGraphIter<'a> {
+    inner: hash_set::Iter<'a, Arc<Triple>>,
+}
+
+impl<'a> Iterator for SimpleGraphIter<'a> {
+    type Item = &'a Triple;
+
+    fn next(&mut self) -> Option<&'a Triple> {
+        self.inner.next().map(|t| &**t)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for SimpleGraphIter<'a> {}
+
+impl<'a> FusedIterator for SimpleGraphIter<'a> {}
+
 impl FromIterator<Triple> for SimpleGraph {
     fn from_iter<I: IntoIterator<Item = Triple>>(iter: I) -> Self {
-        Self {
-            triples: HashSet::from_iter(iter),
-        }
+        let mut graph = Self::default();
+        graph.extend(iter);
+        graph
     }
 }
 
 impl Extend<Triple> for SimpleGraph {
     fn extend<I: IntoIterator<Item = Triple>>(&mut self, iter: I) {
-        self.triples.extend(iter)
+        for triple in iter {
+            self.insert(triple);
+        }
     }
 }
 
 impl fmt::Display for SimpleGraph {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for t in &self.triples {
+        for t in self {
             writeln!(f, "{}", t)?;
         }
         Ok(())
     }
 }
+
+#[test]
+fn test_indexes() {
+    let ex = |name: &str| NamedNode::parse(format!("http://example.com/{}", name)).unwrap();
+    let s = NamedOrBlankNode::from(ex("s"));
+    let s2 = NamedOrBlankNode::from(BlankNode::default());
+    let (p, p2) = (ex("p"), ex("p2"));
+    let (o, o2) = (Term::from(ex("o")), Term::from(Literal::from("o2")));
+    let triples = [
+        Triple::new(s.clone(), p.clone(), o.clone()),
+        Triple::new(s.clone(), p.clone(), o2.clone()),
+        Triple::new(s.clone(), p2.clone(), o.clone()),
+        Triple::new(s2.clone(), p.clone(), o.clone()),
+        Triple::new(s2.clone(), p2.clone(), o2.clone()),
+    ];
+    let mut graph: SimpleGraph = triples.iter().cloned().collect();
+    assert!(!graph.insert(triples[0].clone()));
+    assert_eq!(graph.len(), 5);
+
+    // Each lookup returns the same triples as a scan
+    let check = |graph: &SimpleGraph| {
+        for subject in &[None, Some(&s), Some(&s2)] {
+            for predicate in &[None, Some(&p), Some(&p2)] {
+                for object in &[None, Some(&o), Some(&o2)] {
+                    let mut expected = graph
+                        .iter()
+                        .filter(|t| {
+                            matches_pattern(*subject, t.subject())
+                                && matches_pattern(*predicate, t.predicate())
+                                && matches_pattern(*object, t.object())
+                        })
+                        .map(|t| t.to_string())
+                        .collect::<Vec<_>>();
+                    let mut actual = graph
+                        .triples_for_pattern(*subject, *predicate, *object)
+                        .map(|t| t.to_string())
+                        .collect::<Vec<_>>();
+                    expected.sort();
+                    actual.sort();
+                    assert_eq!(actual, expected);
+                }
+            }
+        }
+    };
+    check(&graph);
+    assert_eq!(graph.objects_for_subject_predicate(&s, &p).count(), 2);
+    assert_eq!(graph.subjects_for_predicate_object(&p, &o).count(), 2);
+    assert_eq!(graph.predicates_for_subject_object(&s, &o).count(), 2);
+
+    // The triples used as index keys are removed while the other triples with the same terms are kept
+    for triple in &triples[..4] {
+        assert!(graph.remove(triple));
+        assert!(!graph.remove(triple));
+        assert!(!graph.contains(triple));
+        check(&graph);
+    }
+    assert_eq!(
+        graph.iter().cloned().collect::<Vec<_>>(),
+        vec![triples[4].clone()]
+    );
+    assert_eq!(graph.spo.len(), 1);
+    assert_eq!(graph.pos.len(), 1);
+    assert_eq!(graph.osp.len(), 1);
+
+    // The index keys do not share the removed triples
+    let mut graph = SimpleGraph::default();
+    graph.insert(triples[0].clone());
+    graph.insert(triples[1].clone());
+    graph.remove(&triples[0]);
+    assert!(index_keys(&graph.spo).all(|t| *t == triples[1]));
+    assert!(index_keys(&graph.pos).all(|t| *t == triples[1]));
+    assert!(index_keys(&graph.osp).all(|t| *t == triples[1]));
+
+    // The iterators give the triples without the index
+    assert_eq!((&graph).into_iter().len(), 1);
+    let mut triples_iter = graph.into_iter();
+    assert_eq!(triples_iter.len(), 1);
+    assert_eq!(triples_iter.next(), Some(triples[1].clone()));
+    assert_eq!(triples_iter.next(), None);
+}
+
+#[cfg(test)]
+fn index_keys<A: TripleKey, B: TripleKey>(
+    index: &TripleIndex<A, B>,
+) -> impl Iterator<Item = &Triple> {
+    index
+        .iter()
+        .flat_map(|(a, by_b)| std::iter::once(a.triple()).chain(by_b.keys().map(|b| b.triple())))
+}
+
+#[cfg(test)]
+fn matches_pattern<T: PartialEq>(pattern: Option<&T>, term: &T) -> bool {
+    match pattern {
+        Some(pattern) => pattern == term,
+        None => true,
+    }
+}
@@ -3,42 +3,156 @@ use std::collections::hash_map::{DefaultHasher, RandomState};
 use std::collections::{BTreeSet, HashMap, HashSet};
 use std::hash::Hash;
 use std::hash::Hasher;
+use std::iter::once;
 
+/// A term of a quad adjacent to a blank node, the blank nodes being replaced by `None`
+type Adjacent = Option<Term>;
+
+/// The position of a blank node in a quad with the other terms of the quad
 #[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Clone, Hash)]
-struct SubjectPredicate<'a> {
-    subject: &'a NamedOrBlankNode,
-    predicate: &'a NamedNode,
+enum Neighbour {
+    Subject(NamedNode, Adjacent, Option<Adjacent>),
+    Object(Adjacent, NamedNode, Option<Adjacent>),
+    GraphName(Adjacent, NamedNode, Adjacent),
 }
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Clone, Hash)]
-struct PredicateObject<'a> {
-    predicate: &'a NamedNode,
-    object: &'a Term,
+/// The non empty graphs of a graph or of a dataset
+///
+/// The graphs are borrowed so that the compared graphs and datasets are not copied.
+struct Graphs<'a> {
+    default_graph: Option<&'a SimpleGraph>,
+    named_graphs: HashMap<&'a NamedOrBlankNode, &'a SimpleGraph>,
+    /// If some blank nodes are in quoted triples, where the graph indexes do not find them
+    has_quoted_blank_nodes: bool,
+}
+
+impl<'a> Graphs<'a> {
+    fn new(graphs: impl Iterator<Item = (Option<&'a NamedOrBlankNode>, &'a SimpleGraph)>) -> Self {
+        let mut default_graph = None;
+        let mut named_graphs = HashMap::default();
+        for (graph_name, graph) in graphs.filter(|(_, graph)| !graph.is_empty()) {
+            match graph_name {
+                Some(graph_name) => {
+                    named_graphs.insert(graph_name, graph);
+                }
+                None => default_graph = Some(graph),
+            }
+        }
+        let has_quoted_blank_nodes = default_graph
+            .into_iter()
+            .chain(named_graphs.values().cloned())
+            .any(|graph| graph.iter().any(|t| quotes_blank_node(t, None)));
+        Self {
+            default_graph,
+            named_graphs,
+            has_quoted_blank_nodes,
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (Option<&'a NamedOrBlankNode>, &'a SimpleGraph)> + '_ {
+        self.default_graph
+            .map(|graph| (None, graph))
+            .into_iter()
+            .chain(
+                self.named_graphs
+                    .iter()
+                    .map(|(graph_name, graph)| (Some(*graph_name), *graph)),
+            )
+    }
+
+    fn graph(&self, graph_name: Option<&NamedOrBlankNode>) -> Option<&'a SimpleGraph> {
+        match graph_name {
+            Some(graph_name) => self.named_graphs.get(graph_name).cloned(),
+            None => self.default_graph,
+        }
+    }
+
+    fn contains(&self, graph_name: Option<&NamedOrBlankNode>, triple: &Triple) -> bool {
+        match self.graph(graph_name) {
+            Some(graph) => graph.contains(triple),
+            None => false,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.iter().map(|(_, graph)| graph.len()).sum()
+    }
+}
+
+/// The blank nodes and the quoted triples containing blank nodes are replaced by `None` so that the hashes do not depend on their labels
+fn adjacent(term: impl Into<Term>) -> Adjacent {
+    match term.into() {
+        Term::BlankNode(_) => None,
+        Term::Triple(ref triple) if !triple_blank_nodes(triple).is_empty() => None,
+        term => Some(term),
+    }
+}
+
+fn adjacent_graph_name(graph_name: Option<&NamedOrBlankNode>) -> Option<Adjacent> {
+    graph_name.map(|graph_name| adjacent(graph_name.clone()))
 }
 
-fn subject_predicates_for_object<'a>(
-    graph: &'a SimpleGraph,
-    object: &'a Term,
-) -> impl Iterator<Item = SubjectPredicate<'a>> + 'a {
-    graph.triples_for_object(object).map(|t| SubjectPredicate {
-        subject: t.subject(),
-        predicate: t.predicate(),
-    })
+fn to_subject(term: &Term) -> Option<NamedOrBlankNode> {
+    match term {
+        Term::NamedNode(node) => Some(node.clone().into()),
+        Term::BlankNode(node) => Some(node.clone().into()),
+        Term::Literal(_) => None,
+        Term::Triple(triple) => Some(NamedOrBlankNode::Triple(triple.clone())),
+    }
 }
 
-fn predicate_objects_for_subject<'a>(
-    graph: &'a SimpleGraph,
-    subject: &'a NamedOrBlankNode,
-) -> impl Iterator<Item = PredicateObject<'a>> + 'a {
-    graph.triples_for_subject(subject).map(|t| PredicateObject {
-        predicate: t.predicate(),
-        object: t.object(),
-    })
+/// The triples of the graphs containing the given term with its neighbours
+fn neighbours(graphs: &Graphs<'_>, term: &Term) -> Vec<(Neighbour, Vec<Term>)> {
+    let mut neighbours = Vec::default();
+    let subject = to_subject(term);
+    for (graph_name, graph) in graphs.iter() {
+        if let Some(subject) = &subject {
+            for t in graph.triples_for_subject(subject) {
+                let mut terms = vec![t.object().clone()];
+                terms.extend(graph_name.map(|g| g.clone().into()));
+                neighbours.push((
+                    Neighbour::Subject(
+                        t.predicate().clone(),
+                        adjacent(t.object().clone()),
+                        adjacent_graph_name(graph_name),
+                    ),
+                    terms,
+                ));
+            }
+        }
+        for t in graph.triples_for_object(term) {
+            let mut terms = vec![t.subject().clone().into()];
+            terms.extend(graph_name.map(|g| g.clone().into()));
+            neighbours.push((
+                Neighbour::Object(
+                    adjacent(t.subject().clone()),
+                    t.predicate().clone(),
+                    adjacent_graph_name(graph_name),
+                ),
+                terms,
+            ));
+        }
+    }
+    if let Some(graph_name) = &subject {
+        if let Some(graph) = graphs.graph(Some(graph_name)) {
+            for t in graph.iter() {
+                neighbours.push((
+                    Neighbour::GraphName(
+                        adjacent(t.subject().clone()),
+                        t.predicate().clone(),
+                        adjacent(t.object().clone()),
+                    ),
+                    vec![t.subject().clone().into(), t.object().clone()],
+                ));
+            }
+        }
+    }
+    neighbours
 }
 
 fn split_hash_buckets<'a>(
     bnodes_by_hash: HashMap<u64, Vec<&'a BlankNode>>,
-    graph: &'a SimpleGraph,
+    graphs: &Graphs<'a>,
     distance: usize,
 ) -> HashMap<u64, Vec<&'a BlankNode>> {
     let mut new_bnodes_by_hash = HashMap::default();
@@ -48,19 +162,12 @@ fn split_hash_buckets<'a>(
             new_bnodes_by_hash.insert(hash, bnodes); // Nothing to improve
         } else {
             for bnode in bnodes {
-                let mut starts = vec![NamedOrBlankNode::from(bnode.clone())];
+                let mut starts = vec![Term::from(bnode.clone())];
                 for _ in 0..distance {
                     let mut new_starts = Vec::default();
-                    for s in starts {
-                        for t in graph.triples_for_subject(&s) {
-                            match t.object() {
-                                Term::NamedNode(t) => new_starts.push(t.clone().into()),
-                                Term::BlankNode(t) => new_starts.push(t.clone().into()),
-                                Term::Literal(_) => (),
-                            }
-                        }
-                        for t in graph.triples_for_object(&s.into()) {
-                            new_starts.push(t.subject().clone());
+                    for s in &starts {
+                        for (_, terms) in neighbours(graphs, s) {
+                            new_starts.extend(terms);
                         }
                     }
                     starts = new_starts;
@@ -70,36 +177,13 @@ fn split_hash_buckets<'a>(
                 let mut hasher = DefaultHasher::default();
                 hash.hash(&mut hasher); // We start with the previous hash
 
-                // NB: we need to sort the triples to have the same hash
-                let mut po_set: BTreeSet<PredicateObject> = BTreeSet::default();
+                // NB: we need to sort the neighbours to have the same hash
+                let mut neighbour_set: BTreeSet<Neighbour> = BTreeSet::default();
                 for start in &starts {
-                    for po in predicate_objects_for_subject(graph, start) {
-                        match &po.object {
-                            Term::BlankNode(_) => (),
-                            _ => {
-                                po_set.insert(po);
-                            }
-                        }
-                    }
+                    neighbour_set.extend(neighbours(graphs, start).into_iter().map(|(n, _)| n));
                 }
-                for po in &po_set {
-                    po.hash(&mut hasher);
-                }
-
-                let mut sp_set: BTreeSet<SubjectPredicate> = BTreeSet::default();
-                let term_starts: Vec<_> = starts.into_iter().map(|t| t.into()).collect();
-                for start in &term_starts {
-                    for sp in subject_predicates_for_object(graph, start) {
-                        match &sp.subject {
-                            NamedOrBlankNode::BlankNode(_) => (),
-                            _ => {
-                                sp_set.insert(sp);
-                            }
-                        }
-                    }
-                }
-                for sp in &sp_set {
-                    sp.hash(&mut hasher);
+                for neighbour in &neighbour_set {
+                    neighbour.hash(&mut hasher);
                 }
 
                 new_bnodes_by_hash
@@ -114,10 +198,10 @@ fn split_hash_buckets<'a>(
 
 fn build_and_check_containment_from_hashes<'a>(
     a_bnodes_by_hash: &mut Vec<(u64, Vec<&'a BlankNode>)>,
-    b_bnodes_by_hash: &'a HashMap<u64, Vec<&'a BlankNode>>,
+    b_bnodes_by_hash: &HashMap<u64, Vec<&'a BlankNode>>,
     a_to_b_mapping: &mut HashMap<&'a BlankNode, &'a BlankNode>,
-    a: &'a SimpleGraph,
-    b: &'a SimpleGraph,
+    a: &Graphs<'a>,
+    b: &Graphs<'a>,
     current_a_nodes: &[&'a BlankNode],
     current_b_nodes: &mut BTreeSet<&'a BlankNode>,
 ) -> bool {
@@ -181,83 +265,185 @@ fn build_and_check_containment_from_hashes<'a>(
 fn check_is_contained_focused<'a>(
     a_to_b_mapping: &mut HashMap<&'a BlankNode, &'a BlankNode>,
     a_bnode_focus: &'a BlankNode,
-    a: &'a SimpleGraph,
-    b: &'a SimpleGraph,
+    a: &Graphs<'a>,
+    b: &Graphs<'a>,
 ) -> bool {
     let a_bnode_subject = a_bnode_focus.clone().into();
     let a_bnode_object = a_bnode_focus.clone().into();
-    let ts_a = a
-        .triples_for_subject(&a_bnode_subject)
-        .chain(a.triples_for_object(&a_bnode_object));
-    for t_a in ts_a {
-        let subject: NamedOrBlankNode = if let NamedOrBlankNode::BlankNode(s_a) = &t_a.subject() {
-            if let Some(s_a) = a_to_b_mapping.get(s_a) {
-                (*s_a).clone().into()
-            } else {
-                continue; // We skip for now
-            }
+    for (graph_name, graph) in a.iter() {
+        let ts_a: Box<dyn Iterator<Item = &Triple>> = if graph_name == Some(&a_bnode_subject) {
+            Box::new(graph.iter())
+        } else if a.has_quoted_blank_nodes {
+            Box::new(
+                graph
+                    .triples_for_subject(&a_bnode_subject)
+                    .chain(graph.triples_for_object(&a_bnode_object))
+                    .chain(
+                        graph
+                            .iter()
+                            .filter(move |t| quotes_blank_node(t, Some(a_bnode_focus))),
+                    ),
+            )
         } else {
-            t_a.subject().clone()
+            Box::new(
+                graph
+                    .triples_for_subject(&a_bnode_subject)
+                    .chain(graph.triples_for_object(&a_bnode_object)),
+            )
         };
-        let predicate = t_a.predicate().clone();
-        let object: Term = if let Term::BlankNode(o_a) = &t_a.object() {
-            if let Some(o_a) = a_to_b_mapping.get(o_a) {
-                (*o_a).clone().into()
-            } else {
-                continue; // We skip for now
-            }
-        } else {
-            t_a.object().clone()
+        let graph_name = match graph_name {
+            Some(graph_name) => match map_subject(a_to_b_mapping, graph_name) {
+                Some(graph_name) => Some(graph_name),
+                None => continue, // We skip for now
+            },
+            None => None,
         };
-        if !b.contains(&Triple::new(subject, predicate, object)) {
-            return false;
+        for t_a in ts_a {
+            let subject = match map_subject(a_to_b_mapping, t_a.subject()) {
+                Some(subject) => subject,
+                None => continue, // We skip for now
+            };
+            let object = match map_term(a_to_b_mapping, t_a.object()) {
+                Some(object) => object,
+                None => continue, // We skip for now
+            };
+            if !b.contains(
+                graph_name.as_ref(),
+                &Triple::new(subject, t_a.predicate().clone(), object),
+            ) {
+                return false;
+            }
         }
     }
 
     true
 }
 
-fn graph_blank_nodes(graph: &SimpleGraph) -> Vec<&BlankNode> {
+/// Maps the blank nodes of the term, including the ones of its quoted triples, or returns `None` if one of them is not mapped yet
+fn map_subject(
+    a_to_b_mapping: &HashMap<&BlankNode, &BlankNode>,
+    node: &NamedOrBlankNode,
+) -> Option<NamedOrBlankNode> {
+    match node {
+        NamedOrBlankNode::BlankNode(node) => a_to_b_mapping
+            .get(node)
+            .map(|node| NamedOrBlankNode::from((*node).clone())),
+        NamedOrBlankNode::Triple(triple) => map_triple(a_to_b_mapping, triple).map(Into::into),
+        node => Some(node.clone()),
+    }
+}
+
+fn map_term(a_to_b_mapping: &HashMap<&BlankNode, &BlankNode>, term: &Term) -> Option<Term> {
+    match term {
+        Term::BlankNode(node) => a_to_b_mapping.get(node).map(|node| (*node).clone().into()),
+        Term::Triple(triple) => map_triple(a_to_b_mapping, triple).map(Into::into),
+        term => Some(term.clone()),
+    }
+}
+
+fn map_triple(a_to_b_mapping: &HashMap<&BlankNode, &BlankNode>, triple: &Triple) -> Option<Triple> {
+    Some(Triple::new(
+        map_subject(a_to_b_mapping, triple.subject())?,
+        triple.predicate().clone(),
+        map_term(a_to_b_mapping, triple.object())?,
+    ))
+}
+
+fn graphs_blank_nodes<'a>(graphs: &Graphs<'a>) -> Vec<&'a BlankNode> {
     let mut blank_nodes: HashSet<&BlankNode, RandomState> = HashSet::default();
-    for t in graph {
-        if let NamedOrBlankNode::BlankNode(subject) = t.subject() {
-            blank_nodes.insert(subject);
+    for (graph_name, graph) in graphs.iter() {
+        if let Some(graph_name) = graph_name {
+            add_subject_blank_nodes(graph_name, &mut blank_nodes);
         }
-        if let Term::BlankNode(object) = &t.object() {
-            blank_nodes.insert(object);
+        for t in graph {
+            add_triple_blank_nodes(t, &mut blank_nodes);
         }
     }
     blank_nodes.into_iter().collect()
 }
 
+/// Returns the blank nodes of the quad, including the ones of its quoted triples
+pub(crate) fn quad_blank_nodes(quad: &Quad) -> Vec<&BlankNode> {
+    let mut nodes = Vec::default();
+    add_subject_blank_nodes(quad.subject(), &mut nodes);
+    add_term_blank_nodes(quad.object(), &mut nodes);
+    if let Some(graph_name) = quad.graph_name() {
+        add_subject_blank_nodes(graph_name, &mut nodes);
+    }
+    nodes
+}
+
+fn triple_blank_nodes(triple: &Triple) -> Vec<&BlankNode> {
+    let mut nodes = Vec::default();
+    add_triple_blank_nodes(triple, &mut nodes);
+    nodes
+}
+
+fn add_subject_blank_nodes<'a>(node: &'a NamedOrBlankNode, nodes: &mut impl Extend<&'a BlankNode>) {
+    match node {
+        NamedOrBlankNode::NamedNode(_) => (),
+        NamedOrBlankNode::BlankNode(node) => nodes.extend(once(node)),
+        NamedOrBlankNode::Triple(triple) => add_triple_blank_nodes(triple, nodes),
+    }
+}
+
+fn add_term_blank_nodes<'a>(term: &'a Term, nodes: &mut impl Extend<&'a BlankNode>) {
+    match term {
+        Term::NamedNode(_) | Term::Literal(_) => (),
+        Term::BlankNode(node) => nodes.extend(once(node)),
+        Term::Triple(triple) => add_triple_blank_nodes(triple, nodes),
+    }
+}
+
+fn add_triple_blank_nodes<'a>(triple: &'a Triple, nodes: &mut impl Extend<&'a BlankNode>) {
+    add_subject_blank_nodes(triple.subject(), nodes);
+    add_term_blank_nodes(triple.object(), nodes);
+}
+
+/// Checks if a quoted triple of the triple contains the blank node, or any blank node if it is `None`
+fn quotes_blank_node(triple: &Triple, node: Option<&BlankNode>) -> bool {
+    let quoted = match (triple.subject(), triple.object()) {
+        (NamedOrBlankNode::Triple(s), Term::Triple(o)) => {
+            let mut nodes = triple_blank_nodes(s);
+            nodes.extend(triple_blank_nodes(o));
+            nodes
+        }
+        (NamedOrBlankNode::Triple(s), _) => triple_blank_nodes(s),
+        (_, Term::Triple(o)) => triple_blank_nodes(o),
+        _ => return false,
+    };
+    match node {
+        Some(node) => quoted.contains(&node),
+        None => !quoted.is_empty(),
+    }
+}
+
+pub(crate) fn has_blank_node(quad: &Quad) -> bool {
+    !quad_blank_nodes(quad).is_empty()
+}
+
 pub fn are_graphs_isomorphic(a: &SimpleGraph, b: &SimpleGraph) -> bool {
+    are_isomorphic(&Graphs::new(once((None, a))), &Graphs::new(once((None, b))))
+}
+
+pub fn are_datasets_isomorphic(a: &SimpleDataset, b: &SimpleDataset) -> bool {
+    are_isomorphic(&Graphs::new(a.graphs()), &Graphs::new(b.graphs()))
+}
+
+fn are_isomorphic(a: &Graphs<'_>, b: &Graphs<'_>) -> bool {
     if a.len() != b.len() {
         return false;
     }
 
     // We check containment of everything buts triples with blank nodes
-    let mut a_bnodes_triples = SimpleGraph::default();
-    for t in a {
-        if t.subject().is_blank_node() || t.object().is_blank_node() {
-            a_bnodes_triples.insert(t.clone());
-        } else if !b.contains(t) {
-            return false; // Triple in a not in b without blank nodes
-        }
-    }
-
-    let mut b_bnodes_triples = SimpleGraph::default();
-    for t in b {
-        if t.subject().is_blank_node() || t.object().is_blank_node() {
-            b_bnodes_triples.insert(t.clone());
-        } else if !a.contains(t) {
-            return false; // Triple in a not in b without blank nodes
-        }
+    if !contains_triples_without_blank_nodes(a, b) || !contains_triples_without_blank_nodes(b, a) {
+        return false;
     }
 
     let mut a_bnodes_by_hash = HashMap::default();
-    a_bnodes_by_hash.insert(0, graph_blank_nodes(&a_bnodes_triples));
+    a_bnodes_by_hash.insert(0, graphs_blank_nodes(a));
     let mut b_bnodes_by_hash = HashMap::default();
-    b_bnodes_by_hash.insert(0, graph_blank_nodes(&b_bnodes_triples));
+    b_bnodes_by_hash.insert(0, graphs_blank_nodes(b));
 
     for distance in 0..5 {
         let max_size = a_bnodes_by_hash
@@ -285,9 +471,166 @@ pub fn are_graphs_isomorphic(a: &SimpleGraph, b: &SimpleGraph) -> bool {
         &mut sorted_a_bnodes_by_hash,
         &b_bnodes_by_hash,
         &mut HashMap::default(),
-        &a_bnodes_triples,
-        &b_bnodes_triples,
+        a,
+        b,
         &[],
         &mut BTreeSet::default(),
     )
 }
+
+/// Checks if the triples of `a` without blank nodes are in `b`
+fn contains_triples_without_blank_nodes(a: &Graphs<'_>, b: &Graphs<'_>) -> bool {
+    a.iter()
+        .filter(|(graph_name, _)| !matches!(graph_name, Some(NamedOrBlankNode::BlankNode(_))))
+        .all(|(graph_name, graph)| {
+            graph
+                .iter()
+                .filter(|t| triple_blank_nodes(t).is_empty())
+                .all(|t| b.contains(graph_name, t))
+        })
+}
+
+#[test]
+fn test_isomorphism() {
+    let ex = NamedNode::parse("http://example.com/p").unwrap();
+    let lit = Term::from(Literal::from("o"));
+    let quads = |nodes: &[BlankNode], graph_name: Option<NamedOrBlankNode>| {
+        // A cycle of blank nodes with a literal on its first node
+        let mut quads = vec![Quad::new(
+            nodes[0].clone(),
+            ex.clone(),
+            lit.clone(),
+            graph_name.clone(),
+        )];
+        for (i, node) in nodes.iter().enumerate() {
+            quads.push(Quad::new(
+                node.clone(),
+                ex.clone(),
+                nodes[(i + 1) % nodes.len()].clone(),
+                graph_name.clone(),
+            ));
+        }
+        quads.into_iter().collect::<SimpleDataset>()
+    };
+    let nodes = |count| (0..count).map(|_| BlankNode::default()).collect::<Vec<_>>();
+    let a = quads(&nodes(3), None);
+    let b = quads(&nodes(3), None);
+    assert!(are_datasets_isomorphic(&a, &b));
+    assert!(are_graphs_isomorphic(
+        a.graph(None).unwrap(),
+        b.graph(None).unwrap()
+    ));
+
+    // Same size but other structure
+    let (a_nodes, b_nodes) = (nodes(3), nodes(3));
+    let mut c = quads(&b_nodes, None);
+    c.remove(&Quad::new(
+        b_nodes[2].clone(),
+        ex.clone(),
+        b_nodes[0].clone(),
+        None,
+    ));
+    c.insert(Quad::new(
+        b_nodes[2].clone(),
+        ex.clone(),
+        b_nodes[1].clone(),
+        None,
+    ));
+    assert!(!are_datasets_isomorphic(&quads(&a_nodes, None), &c));
+    assert!(!are_graphs_isomorphic(
+        quads(&a_nodes, None).graph(None).unwrap(),
+        c.graph(None).unwrap()
+    ));
+
+    // The graph names are compared
+    let graph_name = NamedOrBlankNode::from(ex.clone());
+    assert!(are_datasets_isomorphic(
+        &quads(&nodes(3), Some(graph_name.clone())),
+        &quads(&nodes(3), Some(graph_name.clone()))
+    ));
+    assert!(!are_datasets_isomorphic(
+        &quads(&nodes(3), Some(graph_name)),
+        &quads(&nodes(3), None)
+    ));
+    assert!(are_datasets_isomorphic(
+        &quads(&nodes(3), Some(BlankNode::default().into())),
+        &quads(&nodes(3), Some(BlankNode::default().into()))
+    ));
+    let shared = nodes(3);
+    assert!(!are_datasets_isomorphic(
+        &quads(&shared, Some(shared[1].clone().into())),
+        &quads(&nodes(3), Some(BlankNode::default().into()))
+    ));
+    let other = nodes(3);
+    assert!(are_datasets_isomorphic(
+        &quads(&shared, Some(shared[1].clone().into())),
+        &quads(&other, Some(other[1].clone().into()))
+    ));
+
+    // The triples without blank nodes are compared
+    let mut d = a.clone();
+    d.insert(Quad::new(ex.clone(), ex.clone(), lit.clone(), None));
+    let mut e = b.clone();
+    e.insert(Quad::new(ex.clone(), ex.clone(), ex.clone(), None));
+    assert!(!are_datasets_isomorphic(&d, &e));
+    e.remove(&Quad::new(ex.clone(), ex.clone(), ex.clone(), None));
+    e.insert(Quad::new(ex.clone(), ex.clone(), lit, None));
+    assert!(are_datasets_isomorphic(&d, &e));
+    assert!(!are_datasets_isomorphic(&d, &b));
+}
+
+#[test]
+fn test_isomorphism_with_quoted_triples() {
+    let p = NamedNode::parse("http://example.com/p").unwrap();
+    let q = NamedNode::parse("http://example.com/q").unwrap();
+    let o = NamedNode::parse("http://example.com/o").unwrap();
+    let quoted = |node: &BlankNode| Triple::new(node.clone(), p.clone(), o.clone());
+    let graph = |triples: Vec<Triple>| triples.into_iter().collect::<SimpleGraph>();
+    let (a, b, c) = (
+        BlankNode::default(),
+        BlankNode::default(),
+        BlankNode::default(),
+    );
+
+    // << _:a :p :o >> :q :o
+    assert!(are_graphs_isomorphic(
+        &graph(vec![Triple::new(quoted(&a), q.clone(), o.clone())]),
+        &graph(vec![Triple::new(quoted(&b), q.clone(), o.clone())])
+    ));
+    // :o :q << _:a :p :o >>
+    assert!(are_graphs_isomorphic(
+        &graph(vec![Triple::new(o.clone(), q.clone(), quoted(&a))]),
+        &graph(vec![Triple::new(o.clone(), q.clone(), quoted(&b))])
+    ));
+    // _:a :q << _:a :p :o >> is not _:b :q << _:c :p :o >>
+    assert!(are_graphs_isomorphic(
+        &graph(vec![Triple::new(a.clone(), q.clone(), quoted(&a))]),
+        &graph(vec![Triple::new(b.clone(), q.clone(), quoted(&b))])
+    ));
+    assert!(!are_graphs_isomorphic(
+        &graph(vec![Triple::new(a.clone(), q.clone(), quoted(&a))]),
+        &graph(vec![Triple::new(b.clone(), q.clone(), quoted(&c))])
+    ));
+    // Nested quoted triples
+    let nested = |node: &BlankNode| Triple::new(quoted(node), q.clone(), node.clone());
+    assert!(are_graphs_isomorphic(
+        &graph(vec![
+            Triple::new(nested(&a), q.clone(), o.clone()),
+            Triple::new(a.clone(), p.clone(), o.clone())
+        ]),
+        &graph(vec![
+            Triple::new(nested(&b), q.clone(), o.clone()),
+            Triple::new(b.clone(), p.clone(), o.clone())
+        ])
+    ));
+    assert!(!are_graphs_isomorphic(
+        &graph(vec![
+            Triple::new(nested(&a), q.clone(), o.clone()),
+            Triple::new(a, p.clone(), o.clone())
+        ]),
+        &graph(vec![
+            Triple::new(nested(&b), q.clone(), o.clone()),
+            Triple::new(c, p, o)
+        ])
+    ));
+}
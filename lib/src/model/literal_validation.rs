@@ -0,0 +1,134 @@
+use crate::model::vocab::rdf;
+use crate::model::Literal;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref BOOLEAN: Regex = Regex::new(r"^(?:true|false|1|0)$").unwrap();
+    static ref DECIMAL: Regex = Regex::new(r"^[+-]?(?:[0-9]+(?:\.[0-9]*)?|\.[0-9]+)$").unwrap();
+    static ref INTEGER: Regex = Regex::new(r"^[+-]?[0-9]+$").unwrap();
+    static ref DOUBLE: Regex = Regex::new(
+        r"^(?:[+-]?(?:[0-9]+(?:\.[0-9]*)?|\.[0-9]+)(?:[eE][+-]?[0-9]+)?|[+-]?INF|NaN)$"
+    )
+    .unwrap();
+    static ref DATE: Regex = Regex::new(
+        r"^-?(?:[1-9][0-9]{3,}|0[0-9]{3})-(?:0[1-9]|1[0-2])-(?:0[1-9]|[12][0-9]|3[01])(?:Z|[+-](?:(?:0[0-9]|1[0-3]):[0-5][0-9]|14:00))?$"
+    )
+    .unwrap();
+    static ref TIME: Regex = Regex::new(
+        r"^(?:(?:[01][0-9]|2[0-3]):[0-5][0-9]:[0-5][0-9](?:\.[0-9]+)?|24:00:00(?:\.0+)?)(?:Z|[+-](?:(?:0[0-9]|1[0-3]):[0-5][0-9]|14:00))?$"
+    )
+    .unwrap();
+    static ref DATE_TIME: Regex = Regex::new(
+        r"^-?(?:[1-9][0-9]{3,}|0[0-9]{3})-(?:0[1-9]|1[0-2])-(?:0[1-9]|[12][0-9]|3[01])T(?:(?:[01][0-9]|2[0-3]):[0-5][0-9]:[0-5][0-9](?:\.[0-9]+)?|24:00:00(?:\.0+)?)(Z|[+-](?:(?:0[0-9]|1[0-3]):[0-5][0-9]|14:00))?$"
+    )
+    .unwrap();
+    // The well-formed tags of https://tools.ietf.org/html/bcp47#section-2.1
+    static ref LANGUAGE_TAG: Regex = Regex::new(
+        r"(?ix)^(?:
+            (?:[a-z]{2,3}(?:-[a-z]{3}){0,3}|[a-z]{4}|[a-z]{5,8}) # language
+            (?:-[a-z]{4})? # script
+            (?:-(?:[a-z]{2}|[0-9]{3}))? # region
+            (?:-(?:[a-z0-9]{5,8}|[0-9][a-z0-9]{3}))* # variants
+            (?:-[0-9a-wy-z](?:-[a-z0-9]{2,8})+)* # extensions
+            (?:-x(?:-[a-z0-9]{1,8})+)? # private use
+        |
+            x(?:-[a-z0-9]{1,8})+
+        |
+            en-GB-oed|i-ami|i-bnn|i-default|i-enochian|i-hak|i-klingon|i-lux|i-mingo|i-navajo|i-pwn|i-tao|i-tay|i-tsu|sgn-BE-FR|sgn-BE-NL|sgn-CH-DE
+        )$"
+    )
+    .unwrap();
+}
+
+/// Checks that the lexical form of a literal is valid for its datatype and that its language tag is well-formed
+pub(crate) fn check_literal(literal: &Literal) -> Result<(), String> {
+    if let Some(language) = literal.language() {
+        check_language_tag(language)
+    } else {
+        check_lexical_form(literal.value(), literal.datatype().as_str())
+    }
+}
+
+/// Checks that `value` is in the lexical space of `datatype`
+///
+/// Only the XML Schema datatypes supported by the stores are checked, all the lexical forms of the other datatypes are accepted.
+pub(crate) fn check_lexical_form(value: &str, datatype: &str) -> Result<(), String> {
+    let is_valid = match datatype {
+        "http://www.w3.org/2001/XMLSchema#boolean" => BOOLEAN.is_match(value),
+        "http://www.w3.org/2001/XMLSchema#float" | "http://www.w3.org/2001/XMLSchema#double" => {
+            DOUBLE.is_match(value)
+        }
+        "http://www.w3.org/2001/XMLSchema#decimal" => DECIMAL.is_match(value),
+        "http://www.w3.org/2001/XMLSchema#integer" => INTEGER.is_match(value),
+        "http://www.w3.org/2001/XMLSchema#byte" => is_integer_in(value, -128, 127),
+        "http://www.w3.org/2001/XMLSchema#short" => is_integer_in(value, -32_768, 32_767),
+        "http://www.w3.org/2001/XMLSchema#int" => {
+            is_integer_in(value, i32::MIN.into(), i32::MAX.into())
+        }
+        "http://www.w3.org/2001/XMLSchema#long" => {
+            is_integer_in(value, i64::MIN.into(), i64::MAX.into())
+        }
+        "http://www.w3.org/2001/XMLSchema#unsignedByte" => is_integer_in(value, 0, 255),
+        "http://www.w3.org/2001/XMLSchema#unsignedShort" => is_integer_in(value, 0, 65_535),
+        "http://www.w3.org/2001/XMLSchema#unsignedInt" => is_integer_in(value, 0, u32::MAX.into()),
+        "http://www.w3.org/2001/XMLSchema#unsignedLong" => is_integer_in(value, 0, u64::MAX.into()),
+        "http://www.w3.org/2001/XMLSchema#positiveInteger" => {
+            INTEGER.is_match(value) && !is_negative(value) && !is_zero(value)
+        }
+        "http://www.w3.org/2001/XMLSchema#nonNegativeInteger" => {
+            INTEGER.is_match(value) && (!is_negative(value) || is_zero(value))
+        }
+        "http://www.w3.org/2001/XMLSchema#negativeInteger" => {
+            INTEGER.is_match(value) && is_negative(value) && !is_zero(value)
+        }
+        "http://www.w3.org/2001/XMLSchema#nonPositiveInteger" => {
+            INTEGER.is_match(value) && (is_negative(value) || is_zero(value))
+        }
+        "http://www.w3.org/2001/XMLSchema#date" => DATE.is_match(value),
+        "http://www.w3.org/2001/XMLSchema#time" => TIME.is_match(value),
+        "http://www.w3.org/2001/XMLSchema#dateTime" => DATE_TIME.is_match(value),
+        "http://www.w3.org/2001/XMLSchema#dateTimeStamp" => {
+            matches!(DATE_TIME.captures(value), Some(captures) if captures.get(1).is_some())
+        }
+        _ => datatype != rdf::LANG_STRING.as_str(),
+    };
+    if is_valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "\"{}\" is not a valid lexical form for the datatype <{}>",
+            value, datatype
+        ))
+    }
+}
+
+/// Checks that `language` is a well-formed [BCP47](https://tools.ietf.org/html/bcp47) language tag
+pub(crate) fn check_language_tag(language: &str) -> Result<(), String> {
+    if LANGUAGE_TAG.is_match(language) {
+        Ok(())
+    } else {
+        Err(format!(
+            "\"{}\" is not a well-formed BCP47 language tag",
+            language
+        ))
+    }
+}
+
+fn is_integer_in(value: &str, min: i128, max: i128) -> bool {
+    INTEGER.is_match(value)
+        && value
+            .parse::<i128>()
+            .is_ok_and(|value| min <= value && value <= max)
+}
+
+fn is_negative(value: &str) -> bool {
+    value.starts_with('-')
+}
+
+fn is_zero(value: &str) -> bool {
+    value
+        .trim_start_matches(['+', '-'])
+        .bytes()
+        .all(|b| b == b'0')
+}
@@ -3,17 +3,28 @@
 //! Inspired by [RDFjs](http://rdf.js.org/) and [Apache Commons RDF](http://commons.apache.org/proper/commons-rdf/)
 
 mod blank_node;
+mod canonicalization;
+mod dataset;
 mod graph;
 mod isomorphism;
 mod literal;
+mod literal_validation;
 mod named_node;
+mod patch;
+mod skolemization;
 mod triple;
 pub mod vocab;
 
 pub use crate::model::blank_node::BlankNode;
-pub use crate::model::graph::SimpleGraph;
+pub use crate::model::dataset::SimpleDataset;
+pub use crate::model::graph::{SimpleGraph, SimpleGraphIntoIter, SimpleGraphIter};
 pub use crate::model::literal::Literal;
+pub(crate) use crate::model::literal_validation::{
+    check_language_tag, check_lexical_form, check_literal,
+};
 pub use crate::model::named_node::NamedNode;
+pub use crate::model::patch::DatasetPatch;
+pub use crate::model::skolemization::Skolemizer;
 pub use crate::model::triple::NamedOrBlankNode;
 pub use crate::model::triple::Quad;
 pub use crate::model::triple::Term;
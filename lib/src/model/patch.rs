@@ -0,0 +1,532 @@
+use crate::model::isomorphism::{has_blank_node, quad_blank_nodes};
+use crate::model::*;
+use crate::{Error, Result};
+use rio_api::model as rio;
+use rio_api::parser::QuadsParser;
+use rio_turtle::NQuadsParser;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fmt::Write;
+use std::io::BufRead;
+
+/// A set of quads to remove from a dataset followed by a set of quads to add to it.
+///
+/// It could be computed from two versions of a dataset or of a graph, serialized as a [RDF Patch](https://afs.github.io/rdf-patch/)
+/// or as a [SPARQL 1.1 Update](https://www.w3.org/TR/sparql11-update/) request and applied to a `SimpleDataset` or a `RepositoryConnection`.
+///
+/// The quads containing blank nodes are compared using [isomorphism](https://www.w3.org/TR/rdf11-concepts/#dfn-graph-isomorphism):
+/// a set of quads connected by blank nodes is only considered as changed if it has no isomorphic equivalent in the other version.
+///
+/// Usage example:
+/// ```
+/// use rudf::model::*;
+///
+/// let ex = NamedNode::parse("http://example.com").unwrap();
+/// let old: SimpleGraph = vec![
+///     Triple::new(ex.clone(), ex.clone(), Literal::from("old")),
+///     Triple::new(BlankNode::default(), ex.clone(), ex.clone()),
+/// ].into_iter().collect();
+/// let new: SimpleGraph = vec![
+///     Triple::new(ex.clone(), ex.clone(), Literal::from("new")),
+///     Triple::new(BlankNode::default(), ex.clone(), ex.clone()),
+/// ].into_iter().collect();
+///
+/// let patch = DatasetPatch::diff_graphs(&old, &new, None);
+/// assert_eq!(
+///     "D <http://example.com> <http://example.com> \"old\" .\nA <http://example.com> <http://example.com> \"new\" .\n",
+///     patch.to_string()
+/// );
+///
+/// let mut dataset = SimpleDataset::from(old);
+/// patch.apply(&mut dataset);
+/// assert!(dataset.is_isomorphic(&new.into()));
+/// ```
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct DatasetPatch {
+    deletions: SimpleDataset,
+    insertions: SimpleDataset,
+}
+
+impl DatasetPatch {
+    pub fn new(deletions: SimpleDataset, insertions: SimpleDataset) -> Self {
+        Self {
+            deletions,
+            insertions,
+        }
+    }
+
+    /// Computes the patch transforming the `old` dataset into the `new` one
+    pub fn diff(old: &SimpleDataset, new: &SimpleDataset) -> Self {
+        let mut deletions = SimpleDataset::default();
+        let mut insertions = SimpleDataset::default();
+
+        let mut old_with_blank_nodes = SimpleDataset::default();
+        for quad in old.iter() {
+            if has_blank_node(&quad) {
+                old_with_blank_nodes.insert(quad);
+            } else if !new.contains(&quad) {
+                deletions.insert(quad);
+            }
+        }
+        let mut new_with_blank_nodes = SimpleDataset::default();
+        for quad in new.iter() {
+            if has_blank_node(&quad) {
+                new_with_blank_nodes.insert(quad);
+            } else if !old.contains(&quad) {
+                insertions.insert(quad);
+            }
+        }
+
+        let mut new_components = blank_node_components(&new_with_blank_nodes)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        for old_component in blank_node_components(&old_with_blank_nodes) {
+            if take_isomorphic_component(&mut new_components, &old_component).is_none() {
+                deletions.extend(old_component.iter());
+            }
+        }
+        for new_component in new_components.into_iter().flatten() {
+            insertions.extend(new_component.iter());
+        }
+
+        Self::new(deletions, insertions)
+    }
+
+    /// Computes the patch transforming the `old` version of the graph `graph_name` into the `new` one
+    ///
+    /// `None` is the default graph.
+    pub fn diff_graphs(
+        old: &SimpleGraph,
+        new: &SimpleGraph,
+        graph_name: Option<&NamedOrBlankNode>,
+    ) -> Self {
+        let in_graph = |graph: &SimpleGraph| -> SimpleDataset {
+            graph
+                .iter()
+                .map(|t| t.clone().in_graph(graph_name.cloned()))
+                .collect()
+        };
+        Self::diff(&in_graph(old), &in_graph(new))
+    }
+
+    /// Parses a [RDF Patch](https://afs.github.io/rdf-patch/)
+    ///
+    /// Only the `A` and `D` rows with full IRIs are supported.
+    /// The headers, the transaction rows and the prefix rows are ignored.
+    /// Each blank node label is mapped to a new blank node so the deletions with blank nodes only match the
+    /// existing quads using isomorphism, like in `apply` and `to_sparql_update`.
+    pub fn parse_rdf_patch(reader: impl BufRead) -> Result<Self> {
+        let mut patch = Self::default();
+        let mut blank_nodes = HashMap::default();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            let (operation, quad) = line.split_at(line.find(' ').unwrap_or(line.len()));
+            match operation {
+                "A" | "D" => {
+                    let mut quads = Vec::default();
+                    NQuadsParser::new(quad.as_bytes())?.parse_all(&mut |q| -> Result<()> {
+                        quads.push(convert_rio_quad(q, &mut blank_nodes));
+                        Ok(())
+                    })?;
+                    // A row cancels the previous rows about the same quad
+                    let (added_to, removed_from) = if operation == "A" {
+                        (&mut patch.insertions, &mut patch.deletions)
+                    } else {
+                        (&mut patch.deletions, &mut patch.insertions)
+                    };
+                    for quad in quads {
+                        removed_from.remove(&quad);
+                        added_to.insert(quad);
+                    }
+                }
+                "" | "H" | "TX" | "TC" | "TA" | "PA" | "PD" => (),
+                _ if operation.starts_with('#') => (),
+                _ => {
+                    return Err(Error::syntax(format!(
+                        "Unexpected RDF Patch row {} at line {}",
+                        operation,
+                        i + 1
+                    )))
+                }
+            }
+        }
+        Ok(patch)
+    }
+
+    /// The quads to remove
+    pub fn deletions(&self) -> &SimpleDataset {
+        &self.deletions
+    }
+
+    /// The quads to add
+    pub fn insertions(&self) -> &SimpleDataset {
+        &self.insertions
+    }
+
+    /// Checks if the patch does not change anything
+    pub fn is_empty(&self) -> bool {
+        self.deletions.is_empty() && self.insertions.is_empty()
+    }
+
+    /// Applies the patch to a dataset
+    ///
+    /// Each set of deleted quads connected by blank nodes removes a set of quads of the dataset isomorphic to it, if any.
+    pub fn apply(&self, dataset: &mut SimpleDataset) {
+        let mut deletions_with_blank_nodes = SimpleDataset::default();
+        for quad in self.deletions.iter() {
+            if has_blank_node(&quad) {
+                deletions_with_blank_nodes.insert(quad);
+            } else {
+                dataset.remove(&quad);
+            }
+        }
+        if !deletions_with_blank_nodes.is_empty() {
+            let mut dataset_components =
+                blank_node_components(&dataset.iter().filter(has_blank_node).collect())
+                    .into_iter()
+                    .map(Some)
+                    .collect::<Vec<_>>();
+            for deleted_component in blank_node_components(&deletions_with_blank_nodes) {
+                if let Some(equivalent) =
+                    take_isomorphic_component(&mut dataset_components, &deleted_component)
+                {
+                    for quad in equivalent.iter() {
+                        dataset.remove(&quad);
+                    }
+                }
+            }
+        }
+        dataset.extend(self.insertions.iter());
+    }
+
+    /// Serializes the patch as a [SPARQL 1.1 Update](https://www.w3.org/TR/sparql11-update/) request
+    ///
+    /// Each set of deleted quads connected by blank nodes is removed using its own `DELETE WHERE` operation
+    /// where the blank nodes are replaced by variables, so that it is matched using its IRIs and literals.
+    /// The other ones are removed using `DELETE DATA` and the insertions are done using `INSERT DATA`.
+    ///
+    /// Fails if a blank node is used as graph name in an insertion.
+    pub fn to_sparql_update(&self) -> Result<String> {
+        let mut ground_deletions = Vec::default();
+        let mut deletions_with_blank_nodes = SimpleDataset::default();
+        for quad in sorted_quads(&self.deletions) {
+            if has_blank_node(&quad) {
+                deletions_with_blank_nodes.insert(quad);
+            } else {
+                ground_deletions.push(quad);
+            }
+        }
+        let insertions = sorted_quads(&self.insertions);
+        if insertions
+            .iter()
+            .any(|quad| matches!(quad.graph_name(), Some(NamedOrBlankNode::BlankNode(_))))
+        {
+            return Err(Error::unsupported(
+                "SPARQL INSERT DATA does not support blank nodes as graph names",
+            ));
+        }
+
+        let mut operations = Vec::default();
+        if !ground_deletions.is_empty() {
+            operations.push(format!(
+                "DELETE DATA {{\n{}}}",
+                quads_block(&ground_deletions, &mut |node| node.to_string())
+            ));
+        }
+        let mut blocks = blank_node_components(&deletions_with_blank_nodes)
+            .iter()
+            .map(|component| {
+                let mut variables = HashMap::<BlankNode, String>::default();
+                quads_block(&sorted_quads(component), &mut |node| {
+                    if let NamedOrBlankNode::BlankNode(node) = node {
+                        let id = variables.len();
+                        variables
+                            .entry(node.clone())
+                            .or_insert_with(|| format!("?b{}", id))
+                            .clone()
+                    } else {
+                        node.to_string()
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        blocks.sort();
+        for block in blocks {
+            operations.push(format!("DELETE WHERE {{\n{}}}", block));
+        }
+        if !insertions.is_empty() {
+            operations.push(format!(
+                "INSERT DATA {{\n{}}}",
+                quads_block(&insertions, &mut |node| node.to_string())
+            ));
+        }
+        Ok(operations.join(" ;\n"))
+    }
+}
+
+impl fmt::Display for DatasetPatch {
+    /// Serializes the patch as a [RDF Patch](https://afs.github.io/rdf-patch/)
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for quad in sorted_quads(&self.deletions) {
+            writeln!(f, "D {}", quad)?;
+        }
+        for quad in sorted_quads(&self.insertions) {
+            writeln!(f, "A {}", quad)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the quads of the dataset in a stable order
+fn sorted_quads(dataset: &SimpleDataset) -> Vec<Quad> {
+    let mut quads = dataset
+        .iter()
+        .map(|quad| (quad.to_string(), quad))
+        .collect::<Vec<_>>();
+    quads.sort_by(|(a, _), (b, _)| a.cmp(b));
+    quads.into_iter().map(|(_, quad)| quad).collect()
+}
+
+/// Writes quads as SPARQL triple patterns grouped by graph
+///
+/// `format` is used to serialize the subjects, the objects that are not literals and the graph names.
+fn quads_block(quads: &[Quad], format: &mut dyn FnMut(&NamedOrBlankNode) -> String) -> String {
+    let mut graphs: BTreeMap<Option<String>, String> = BTreeMap::default();
+    for quad in quads {
+        let object = match quad.object() {
+            Term::NamedNode(node) => format(&node.clone().into()),
+            Term::BlankNode(node) => format(&node.clone().into()),
+            Term::Literal(literal) => literal.to_string(),
+            Term::Triple(triple) => Term::Triple(triple.clone()).to_string(),
+        };
+        let block = graphs
+            .entry(quad.graph_name().as_ref().map(&mut *format))
+            .or_default();
+        writeln!(
+            block,
+            "{} {} {} .",
+            format(quad.subject()),
+            quad.predicate(),
+            object
+        )
+        .unwrap();
+    }
+    let mut output = String::default();
+    for (graph_name, block) in graphs {
+        match graph_name {
+            Some(graph_name) => {
+                writeln!(output, "  GRAPH {} {{", graph_name).unwrap();
+                for line in block.lines() {
+                    writeln!(output, "    {}", line).unwrap();
+                }
+                output.push_str("  }\n");
+            }
+            None => {
+                for line in block.lines() {
+                    writeln!(output, "  {}", line).unwrap();
+                }
+            }
+        }
+    }
+    output
+}
+
+/// Splits the dataset in sets of quads connected by blank nodes
+fn blank_node_components(dataset: &SimpleDataset) -> Vec<SimpleDataset> {
+    let quads = dataset.iter().collect::<Vec<_>>();
+    let mut parents: Vec<usize> = (0..quads.len()).collect();
+    fn find(parents: &mut [usize], i: usize) -> usize {
+        let mut root = i;
+        while parents[root] != root {
+            root = parents[root];
+        }
+        parents[i] = root;
+        root
+    }
+
+    let mut quad_for_blank_node: HashMap<&BlankNode, usize> = HashMap::default();
+    for (i, quad) in quads.iter().enumerate() {
+        for node in quad_blank_nodes(quad) {
+            let j = *quad_for_blank_node.entry(node).or_insert(i);
+            let (root_i, root_j) = (find(&mut parents, i), find(&mut parents, j));
+            parents[root_i] = root_j;
+        }
+    }
+
+    let mut components: HashMap<usize, SimpleDataset> = HashMap::default();
+    for (i, quad) in quads.iter().enumerate() {
+        components
+            .entry(find(&mut parents, i))
+            .or_default()
+            .insert(quad.clone());
+    }
+    components.into_values().collect()
+}
+
+/// Removes from `components` the first one isomorphic to `component` and returns it
+fn take_isomorphic_component(
+    components: &mut [Option<SimpleDataset>],
+    component: &SimpleDataset,
+) -> Option<SimpleDataset> {
+    components
+        .iter_mut()
+        .find(|candidate| match candidate {
+            Some(candidate) => {
+                candidate.len() == component.len() && candidate.is_isomorphic(component)
+            }
+            None => false,
+        })
+        .and_then(Option::take)
+}
+
+fn convert_rio_quad(quad: rio::Quad<'_>, blank_nodes: &mut HashMap<String, BlankNode>) -> Quad {
+    let object: Term = match quad.object {
+        rio::Term::NamedNode(node) => NamedNode::new_from_string(node.iri).into(),
+        rio::Term::BlankNode(node) => convert_rio_blank_node(node, blank_nodes).into(),
+        rio::Term::Literal(rio::Literal::Simple { value }) => {
+            Literal::new_simple_literal(value).into()
+        }
+        rio::Term::Literal(rio::Literal::LanguageTaggedString { value, language }) => {
+            Literal::new_language_tagged_literal(value, language).into()
+        }
+        rio::Term::Literal(rio::Literal::Typed { value, datatype }) => {
+            Literal::new_typed_literal(value, NamedNode::new_from_string(datatype.iri)).into()
+        }
+    };
+    Quad::new(
+        convert_rio_named_or_blank_node(quad.subject, blank_nodes),
+        NamedNode::new_from_string(quad.predicate.iri),
+        object,
+        quad.graph_name
+            .map(|g| convert_rio_named_or_blank_node(g, blank_nodes)),
+    )
+}
+
+fn convert_rio_named_or_blank_node(
+    node: rio::NamedOrBlankNode<'_>,
+    blank_nodes: &mut HashMap<String, BlankNode>,
+) -> NamedOrBlankNode {
+    match node {
+        rio::NamedOrBlankNode::NamedNode(node) => NamedNode::new_from_string(node.iri).into(),
+        rio::NamedOrBlankNode::BlankNode(node) => convert_rio_blank_node(node, blank_nodes).into(),
+    }
+}
+
+fn convert_rio_blank_node(
+    node: rio::BlankNode<'_>,
+    blank_nodes: &mut HashMap<String, BlankNode>,
+) -> BlankNode {
+    blank_nodes.entry(node.id.to_owned()).or_default().clone()
+}
+
+#[cfg(test)]
+fn test_dataset(data: &str) -> SimpleDataset {
+    let mut blank_nodes = HashMap::default();
+    let mut dataset = SimpleDataset::default();
+    NQuadsParser::new(data.as_bytes())
+        .unwrap()
+        .parse_all(&mut |q| -> Result<()> {
+            dataset.insert(convert_rio_quad(q, &mut blank_nodes));
+            Ok(())
+        })
+        .unwrap();
+    dataset
+}
+
+#[test]
+fn test_to_sparql_update() {
+    let patch = DatasetPatch::new(
+        test_dataset(
+            "<http://e/a> <http://e/p> \"x\" .
+            _:x <http://e/p> <http://e/a> .
+            _:x <http://e/q> \"1\" .
+            _:y <http://e/p> <http://e/b> .",
+        ),
+        test_dataset("<http://e/a> <http://e/p> \"y\" <http://e/g> ."),
+    );
+    assert_eq!(
+        patch.to_sparql_update().unwrap(),
+        "DELETE DATA {
+  <http://e/a> <http://e/p> \"x\" .
+} ;
+DELETE WHERE {
+  ?b0 <http://e/p> <http://e/a> .
+  ?b0 <http://e/q> \"1\" .
+} ;
+DELETE WHERE {
+  ?b0 <http://e/p> <http://e/b> .
+} ;
+INSERT DATA {
+  GRAPH <http://e/g> {
+    <http://e/a> <http://e/p> \"y\" .
+  }
+}"
+    );
+
+    let patch = DatasetPatch::new(
+        SimpleDataset::default(),
+        test_dataset("<http://e/a> <http://e/p> \"y\" _:g ."),
+    );
+    assert!(patch.to_sparql_update().is_err());
+}
+
+#[test]
+fn test_rdf_patch_round_trip() {
+    let old = test_dataset(
+        "<http://e/a> <http://e/p> \"x\" .
+        _:x <http://e/p> <http://e/a> .
+        _:x <http://e/q> \"1\" .
+        _:y <http://e/p> <http://e/b> .",
+    );
+    let new = test_dataset(
+        "<http://e/a> <http://e/p> \"y\" .
+        _:z <http://e/p> <http://e/b> .
+        _:z <http://e/q> _:z .",
+    );
+    let patch = DatasetPatch::diff(&old, &new);
+    assert_eq!(patch.deletions().len(), 4);
+    assert_eq!(patch.insertions().len(), 3);
+
+    let parsed = DatasetPatch::parse_rdf_patch(patch.to_string().as_bytes()).unwrap();
+    assert!(parsed.deletions().is_isomorphic(patch.deletions()));
+    assert!(parsed.insertions().is_isomorphic(patch.insertions()));
+    let mut dataset = old.clone();
+    parsed.apply(&mut dataset);
+    assert!(dataset.is_isomorphic(&new));
+
+    // The same changes with isomorphic blank nodes do nothing
+    assert!(DatasetPatch::diff(&old, &test_dataset(&old.to_string())).is_empty());
+
+    // Even if the blank nodes are in quoted triples
+    let quoted = |node: BlankNode| -> SimpleDataset {
+        let p = NamedNode::parse("http://e/p").unwrap();
+        let quad = Quad::new(Triple::new(node, p.clone(), p.clone()), p.clone(), p, None);
+        std::iter::once(quad).collect()
+    };
+    assert!(
+        DatasetPatch::diff(&quoted(BlankNode::default()), &quoted(BlankNode::default())).is_empty()
+    );
+}
+
+#[test]
+fn test_parse_rdf_patch_blank_nodes() {
+    let patch = DatasetPatch::parse_rdf_patch(
+        "A _:00000000000000000000000000000001 <http://e/p> <http://e/a> .
+        A _:00000000000000000000000000000001 <http://e/q> <http://e/a> ."
+            .as_bytes(),
+    )
+    .unwrap();
+    let subjects = patch
+        .insertions()
+        .iter()
+        .map(|quad| quad.subject().clone())
+        .collect::<Vec<_>>();
+    assert_eq!(subjects.len(), 2);
+    assert_eq!(subjects[0], subjects[1]);
+    assert_ne!(
+        subjects[0],
+        NamedOrBlankNode::from(BlankNode::new_from_unique_id(1))
+    );
+}
@@ -0,0 +1,242 @@
+use crate::model::*;
+use crate::Result;
+use rio_api::iri::Iri;
+
+/// Replaces [blank nodes](https://www.w3.org/TR/rdf11-concepts/#dfn-blank-node) by [Skolem IRIs](https://www.w3.org/TR/rdf11-concepts/#section-skolemization)
+/// and maps these IRIs back to the same blank nodes.
+///
+/// The Skolem IRIs are built from the blank node ids and are in the `/.well-known/genid/` namespace of a base IRI.
+///
+/// Usage example:
+/// ```
+/// use rudf::model::*;
+///
+/// let skolemizer = Skolemizer::new("http://example.com/data/").unwrap();
+/// let blank_node = BlankNode::default();
+/// let iri = skolemizer.skolemize_blank_node(&blank_node);
+/// assert!(iri.as_str().starts_with("http://example.com/.well-known/genid/"));
+/// assert_eq!(Some(blank_node), skolemizer.deskolemize_named_node(&iri));
+/// ```
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct Skolemizer {
+    prefix: String,
+}
+
+impl Skolemizer {
+    /// Builds a skolemizer minting its IRIs in the `/.well-known/genid/` namespace of `base_iri`
+    pub fn new(base_iri: &str) -> Result<Self> {
+        Ok(Self {
+            prefix: Iri::parse(base_iri)?
+                .resolve("/.well-known/genid/")?
+                .into_inner(),
+        })
+    }
+
+    /// Returns the namespace of the minted Skolem IRIs
+    pub fn namespace(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Returns the Skolem IRI of a blank node
+    pub fn skolemize_blank_node(&self, blank_node: &BlankNode) -> NamedNode {
+        NamedNode::new_from_string(format!("{}{}", self.prefix, blank_node.as_str()))
+    }
+
+    /// Returns the blank node of a Skolem IRI minted by this skolemizer
+    pub fn deskolemize_named_node(&self, named_node: &NamedNode) -> Option<BlankNode> {
+        let id = named_node.as_str().strip_prefix(&self.prefix)?;
+        if id.len() > 32 {
+            return None;
+        }
+        let blank_node = BlankNode::new_from_unique_id(u128::from_str_radix(id, 16).ok()?);
+        if blank_node.as_str() == id {
+            Some(blank_node)
+        } else {
+            None
+        }
+    }
+
+    pub fn skolemize_term(&self, term: &Term) -> Term {
+        match term {
+            Term::BlankNode(blank_node) => self.skolemize_blank_node(blank_node).into(),
+            Term::Triple(triple) => self.skolemize_triple(triple).into(),
+            term => term.clone(),
+        }
+    }
+
+    pub fn deskolemize_term(&self, term: &Term) -> Term {
+        match term {
+            Term::NamedNode(named_node) => match self.deskolemize_named_node(named_node) {
+                Some(blank_node) => blank_node.into(),
+                None => named_node.clone().into(),
+            },
+            Term::Triple(triple) => self.deskolemize_triple(triple).into(),
+            term => term.clone(),
+        }
+    }
+
+    pub fn skolemize_quad(&self, quad: &Quad) -> Quad {
+        Quad::new(
+            self.skolemize_named_or_blank_node(quad.subject()),
+            quad.predicate().clone(),
+            self.skolemize_term(quad.object()),
+            quad.graph_name()
+                .as_ref()
+                .map(|g| self.skolemize_named_or_blank_node(g)),
+        )
+    }
+
+    pub fn deskolemize_quad(&self, quad: &Quad) -> Quad {
+        Quad::new(
+            self.deskolemize_named_or_blank_node(quad.subject()),
+            quad.predicate().clone(),
+            self.deskolemize_term(quad.object()),
+            quad.graph_name()
+                .as_ref()
+                .map(|g| self.deskolemize_named_or_blank_node(g)),
+        )
+    }
+
+    pub fn skolemize_triple(&self, triple: &Triple) -> Triple {
+        Triple::new(
+            self.skolemize_named_or_blank_node(triple.subject()),
+            triple.predicate().clone(),
+            self.skolemize_term(triple.object()),
+        )
+    }
+
+    pub fn deskolemize_triple(&self, triple: &Triple) -> Triple {
+        Triple::new(
+            self.deskolemize_named_or_blank_node(triple.subject()),
+            triple.predicate().clone(),
+            self.deskolemize_term(triple.object()),
+        )
+    }
+
+    fn skolemize_named_or_blank_node(&self, node: &NamedOrBlankNode) -> NamedOrBlankNode {
+        match node {
+            NamedOrBlankNode::BlankNode(blank_node) => self.skolemize_blank_node(blank_node).into(),
+            NamedOrBlankNode::Triple(triple) => self.skolemize_triple(triple).into(),
+            node => node.clone(),
+        }
+    }
+
+    fn deskolemize_named_or_blank_node(&self, node: &NamedOrBlankNode) -> NamedOrBlankNode {
+        match node {
+            NamedOrBlankNode::NamedNode(named_node) => {
+                match self.deskolemize_named_node(named_node) {
+                    Some(blank_node) => blank_node.into(),
+                    None => named_node.clone().into(),
+                }
+            }
+            NamedOrBlankNode::Triple(triple) => self.deskolemize_triple(triple).into(),
+            node => node.clone(),
+        }
+    }
+}
+
+#[test]
+fn test_skolemize_quad() {
+    let skolemizer = Skolemizer::new("http://example.com/data/").unwrap();
+    let ex = NamedNode::parse("http://example.com/p").unwrap();
+    let subject = BlankNode::default();
+    let object = BlankNode::default();
+    let graph_name = BlankNode::default();
+    let quad = Quad::new(
+        subject.clone(),
+        ex.clone(),
+        Triple::new(object.clone(), ex.clone(), ex.clone()),
+        Some(graph_name.clone().into()),
+    );
+
+    let skolemized = skolemizer.skolemize_quad(&quad);
+    assert_eq!(
+        skolemized,
+        Quad::new(
+            skolemizer.skolemize_blank_node(&subject),
+            ex.clone(),
+            Triple::new(skolemizer.skolemize_blank_node(&object), ex.clone(), ex),
+            Some(skolemizer.skolemize_blank_node(&graph_name).into()),
+        )
+    );
+    assert_eq!(skolemizer.deskolemize_quad(&skolemized), quad);
+}
+
+#[test]
+fn test_deskolemize_foreign_iris() {
+    let skolemizer = Skolemizer::new("http://example.com/data/").unwrap();
+    for iri in &[
+        "http://example.com/p",
+        "http://example.org/.well-known/genid/1",
+        "http://example.com/.well-known/genid/",
+        "http://example.com/.well-known/genid/01",
+        "http://example.com/.well-known/genid/A",
+        "http://example.com/.well-known/genid/xyz",
+        "http://example.com/.well-known/genid/100000000000000000000000000000000",
+    ] {
+        let named_node = NamedNode::parse(*iri).unwrap();
+        assert_eq!(
+            skolemizer.deskolemize_named_node(&named_node),
+            None,
+            "{}",
+            iri
+        );
+        assert_eq!(
+            skolemizer.deskolemize_term(&named_node.clone().into()),
+            named_node.into()
+        );
+    }
+    assert_eq!(
+        skolemizer.deskolemize_named_node(
+            &NamedNode::parse("http://example.com/.well-known/genid/a1").unwrap()
+        ),
+        Some(BlankNode::new_from_unique_id(0xa1))
+    );
+}
+
+#[test]
+fn test_skolemization_on_repository() {
+    use crate::{GraphSyntax, MemoryRepository, Repository, RepositoryConnection};
+
+    let repository = MemoryRepository::default();
+    let skolemizer = Skolemizer::new("http://example.com/").unwrap();
+    repository
+        .connection()
+        .unwrap()
+        .enable_skolemization(&skolemizer)
+        .unwrap();
+
+    // The connections opened later skolemize the loaded blank nodes
+    let mut connection = repository.connection().unwrap();
+    connection
+        .load_graph(
+            b"_:a <http://example.com/p> _:b .".as_ref(),
+            GraphSyntax::NTriples,
+            None,
+            None,
+        )
+        .unwrap();
+    let quads = connection
+        .quads_for_pattern(None, None, None, None)
+        .collect::<crate::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(quads.len(), 1);
+    let quad = &quads[0];
+    assert!(!quad.subject().is_blank_node());
+    assert!(!quad.object().is_blank_node());
+
+    // The blank nodes given to the connections are skolemized the same way
+    let deskolemized = skolemizer.deskolemize_quad(quad);
+    assert!(deskolemized.subject().is_blank_node());
+    let connection = repository.connection().unwrap();
+    assert!(connection.contains(&deskolemized).unwrap());
+    assert_eq!(
+        connection
+            .quads_for_pattern(Some(deskolemized.subject()), None, None, None)
+            .count(),
+        1
+    );
+    let mut connection = repository.connection().unwrap();
+    connection.remove(&deskolemized).unwrap();
+    assert!(!connection.contains(quad).unwrap());
+}
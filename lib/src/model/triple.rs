@@ -1,28 +1,39 @@
 use crate::model::blank_node::BlankNode;
 use crate::model::literal::Literal;
 use crate::model::named_node::NamedNode;
+use crate::{Error, Result};
 use rio_api::model as rio;
+use std::convert::TryFrom;
 use std::fmt;
 
-/// The union of [IRIs](https://www.w3.org/TR/rdf11-concepts/#dfn-iri) and [blank nodes](https://www.w3.org/TR/rdf11-concepts/#dfn-blank-node).
+/// The union of [IRIs](https://www.w3.org/TR/rdf11-concepts/#dfn-iri), [blank nodes](https://www.w3.org/TR/rdf11-concepts/#dfn-blank-node)
+/// and [quoted triples](https://w3c.github.io/rdf-star/cg-spec/#dfn-quoted).
 #[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Clone, Hash)]
 pub enum NamedOrBlankNode {
     NamedNode(NamedNode),
     BlankNode(BlankNode),
+    Triple(Box<Triple>),
 }
 
 impl NamedOrBlankNode {
     pub fn is_named_node(&self) -> bool {
         match self {
             NamedOrBlankNode::NamedNode(_) => true,
-            NamedOrBlankNode::BlankNode(_) => false,
+            NamedOrBlankNode::BlankNode(_) | NamedOrBlankNode::Triple(_) => false,
         }
     }
 
     pub fn is_blank_node(&self) -> bool {
         match self {
-            NamedOrBlankNode::NamedNode(_) => false,
             NamedOrBlankNode::BlankNode(_) => true,
+            NamedOrBlankNode::NamedNode(_) | NamedOrBlankNode::Triple(_) => false,
+        }
+    }
+
+    pub fn is_triple(&self) -> bool {
+        match self {
+            NamedOrBlankNode::Triple(_) => true,
+            NamedOrBlankNode::NamedNode(_) | NamedOrBlankNode::BlankNode(_) => false,
         }
     }
 }
@@ -32,6 +43,7 @@ impl fmt::Display for NamedOrBlankNode {
         match self {
             NamedOrBlankNode::NamedNode(node) => node.fmt(f),
             NamedOrBlankNode::BlankNode(node) => node.fmt(f),
+            NamedOrBlankNode::Triple(triple) => write_quoted_triple(triple, f),
         }
     }
 }
@@ -48,46 +60,61 @@ impl From<BlankNode> for NamedOrBlankNode {
     }
 }
 
-impl<'a> From<&'a NamedOrBlankNode> for rio::NamedOrBlankNode<'a> {
-    fn from(node: &'a NamedOrBlankNode) -> Self {
+impl From<Triple> for NamedOrBlankNode {
+    fn from(triple: Triple) -> Self {
+        NamedOrBlankNode::Triple(Box::new(triple))
+    }
+}
+
+impl<'a> TryFrom<&'a NamedOrBlankNode> for rio::NamedOrBlankNode<'a> {
+    type Error = Error;
+
+    fn try_from(node: &'a NamedOrBlankNode) -> Result<Self> {
         match node {
-            NamedOrBlankNode::NamedNode(node) => rio::NamedNode::from(node).into(),
-            NamedOrBlankNode::BlankNode(node) => rio::BlankNode::from(node).into(),
+            NamedOrBlankNode::NamedNode(node) => Ok(rio::NamedNode::from(node).into()),
+            NamedOrBlankNode::BlankNode(node) => Ok(rio::BlankNode::from(node).into()),
+            NamedOrBlankNode::Triple(_) => Err(quoted_triples_not_supported()),
         }
     }
 }
 
 /// A RDF [term](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-term)
-/// It is the union of [IRIs](https://www.w3.org/TR/rdf11-concepts/#dfn-iri), [blank nodes](https://www.w3.org/TR/rdf11-concepts/#dfn-blank-node) and [literals](https://www.w3.org/TR/rdf11-concepts/#dfn-literal).
+/// It is the union of [IRIs](https://www.w3.org/TR/rdf11-concepts/#dfn-iri), [blank nodes](https://www.w3.org/TR/rdf11-concepts/#dfn-blank-node), [literals](https://www.w3.org/TR/rdf11-concepts/#dfn-literal)
+/// and [quoted triples](https://w3c.github.io/rdf-star/cg-spec/#dfn-quoted).
 #[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Clone, Hash)]
 pub enum Term {
     NamedNode(NamedNode),
     BlankNode(BlankNode),
     Literal(Literal),
+    Triple(Box<Triple>),
 }
 
 impl Term {
     pub fn is_named_node(&self) -> bool {
         match self {
             Term::NamedNode(_) => true,
-            Term::BlankNode(_) => false,
-            Term::Literal(_) => false,
+            Term::BlankNode(_) | Term::Literal(_) | Term::Triple(_) => false,
         }
     }
 
     pub fn is_blank_node(&self) -> bool {
         match self {
-            Term::NamedNode(_) => false,
             Term::BlankNode(_) => true,
-            Term::Literal(_) => false,
+            Term::NamedNode(_) | Term::Literal(_) | Term::Triple(_) => false,
         }
     }
 
     pub fn is_literal(&self) -> bool {
         match self {
-            Term::NamedNode(_) => false,
-            Term::BlankNode(_) => false,
             Term::Literal(_) => true,
+            Term::NamedNode(_) | Term::BlankNode(_) | Term::Triple(_) => false,
+        }
+    }
+
+    pub fn is_triple(&self) -> bool {
+        match self {
+            Term::Triple(_) => true,
+            Term::NamedNode(_) | Term::BlankNode(_) | Term::Literal(_) => false,
         }
     }
 }
@@ -98,6 +125,7 @@ impl fmt::Display for Term {
             Term::NamedNode(node) => node.fmt(f),
             Term::BlankNode(node) => node.fmt(f),
             Term::Literal(literal) => literal.fmt(f),
+            Term::Triple(triple) => write_quoted_triple(triple, f),
         }
     }
 }
@@ -120,25 +148,50 @@ impl From<Literal> for Term {
     }
 }
 
+impl From<Triple> for Term {
+    fn from(triple: Triple) -> Self {
+        Term::Triple(Box::new(triple))
+    }
+}
+
 impl From<NamedOrBlankNode> for Term {
     fn from(resource: NamedOrBlankNode) -> Self {
         match resource {
             NamedOrBlankNode::NamedNode(node) => Term::NamedNode(node),
             NamedOrBlankNode::BlankNode(node) => Term::BlankNode(node),
+            NamedOrBlankNode::Triple(triple) => Term::Triple(triple),
         }
     }
 }
 
-impl<'a> From<&'a Term> for rio::Term<'a> {
-    fn from(node: &'a Term) -> Self {
+impl<'a> TryFrom<&'a Term> for rio::Term<'a> {
+    type Error = Error;
+
+    fn try_from(node: &'a Term) -> Result<Self> {
         match node {
-            Term::NamedNode(node) => rio::NamedNode::from(node).into(),
-            Term::BlankNode(node) => rio::BlankNode::from(node).into(),
-            Term::Literal(node) => rio::Literal::from(node).into(),
+            Term::NamedNode(node) => Ok(rio::NamedNode::from(node).into()),
+            Term::BlankNode(node) => Ok(rio::BlankNode::from(node).into()),
+            Term::Literal(node) => Ok(rio::Literal::from(node).into()),
+            Term::Triple(_) => Err(quoted_triples_not_supported()),
         }
     }
 }
 
+/// Writes a quoted triple using the [Turtle-star](https://w3c.github.io/rdf-star/cg-spec/#turtle-star) syntax
+fn write_quoted_triple(triple: &Triple, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+        f,
+        "<< {} {} {} >>",
+        triple.subject, triple.predicate, triple.object
+    )
+}
+
+fn quoted_triples_not_supported() -> Error {
+    Error::unsupported(
+        "Quoted triples are only supported by the N-Triples-star and Turtle-star syntaxes",
+    )
+}
+
 /// A [RDF triple](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-triple)
 #[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Clone, Hash)]
 pub struct Triple {
@@ -208,13 +261,15 @@ impl fmt::Display for Triple {
     }
 }
 
-impl<'a> From<&'a Triple> for rio::Triple<'a> {
-    fn from(node: &'a Triple) -> Self {
-        rio::Triple {
-            subject: node.subject().into(),
+impl<'a> TryFrom<&'a Triple> for rio::Triple<'a> {
+    type Error = Error;
+
+    fn try_from(node: &'a Triple) -> Result<Self> {
+        Ok(rio::Triple {
+            subject: rio::NamedOrBlankNode::try_from(node.subject())?,
             predicate: node.predicate().into(),
-            object: node.object().into(),
-        }
+            object: rio::Term::try_from(node.object())?,
+        })
     }
 }
 
@@ -229,6 +284,8 @@ pub struct Quad {
 
 impl Quad {
     /// Builds a RDF [triple](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-triple) in a [RDF dataset](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-dataset)
+    ///
+    /// The graph name should not be a quoted triple: the quads with such graph names are rejected by the datasets and the repositories.
     pub fn new(
         subject: impl Into<NamedOrBlankNode>,
         predicate: impl Into<NamedNode>,
@@ -1,5 +1,77 @@
 //! Provides ready to use `NamedNode`s for basic RDF vocabularies
 
+pub mod owl {
+    //! [OWL 2](https://www.w3.org/TR/owl2-overview/) vocabulary
+    use crate::model::named_node::NamedNode;
+    use lazy_static::lazy_static;
+
+    lazy_static! {
+        /// The class of asymmetric properties.
+        pub static ref ASYMMETRIC_PROPERTY: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#AsymmetricProperty");
+        /// The class of OWL classes.
+        pub static ref CLASS: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#Class");
+        /// The class of data properties.
+        pub static ref DATATYPE_PROPERTY: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#DatatypeProperty");
+        /// The property that determines that two given individuals are different.
+        pub static ref DIFFERENT_FROM: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#differentFrom");
+        /// The property that determines that two given classes are disjoint.
+        pub static ref DISJOINT_WITH: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#disjointWith");
+        /// The property that determines that two given classes are equivalent.
+        pub static ref EQUIVALENT_CLASS: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#equivalentClass");
+        /// The property that determines that two given properties are equivalent.
+        pub static ref EQUIVALENT_PROPERTY: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#equivalentProperty");
+        /// The class of functional properties.
+        pub static ref FUNCTIONAL_PROPERTY: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#FunctionalProperty");
+        /// The class of inverse-functional properties.
+        pub static ref INVERSE_FUNCTIONAL_PROPERTY: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#InverseFunctionalProperty");
+        /// The property that determines that two given properties are inverse.
+        pub static ref INVERSE_OF: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#inverseOf");
+        /// The class of irreflexive properties.
+        pub static ref IRREFLEXIVE_PROPERTY: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#IrreflexiveProperty");
+        /// The class of named individuals.
+        pub static ref NAMED_INDIVIDUAL: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#NamedIndividual");
+        /// The empty class.
+        pub static ref NOTHING: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#Nothing");
+        /// The class of object properties.
+        pub static ref OBJECT_PROPERTY: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#ObjectProperty");
+        /// The class of ontologies.
+        pub static ref ONTOLOGY: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#Ontology");
+        /// The property that determines the n-tuple of properties that build a sub property chain of a given property.
+        pub static ref PROPERTY_CHAIN_AXIOM: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#propertyChainAxiom");
+        /// The class of reflexive properties.
+        pub static ref REFLEXIVE_PROPERTY: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#ReflexiveProperty");
+        /// The property that determines that two given individuals are equal.
+        pub static ref SAME_AS: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#sameAs");
+        /// The class of symmetric properties.
+        pub static ref SYMMETRIC_PROPERTY: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#SymmetricProperty");
+        /// The class of OWL individuals.
+        pub static ref THING: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#Thing");
+        /// The class of transitive properties.
+        pub static ref TRANSITIVE_PROPERTY: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/2002/07/owl#TransitiveProperty");
+    }
+}
+
 pub mod rdf {
     //! [RDF 1.1](https://www.w3.org/TR/rdf11-concepts/) vocabulary
     use crate::model::named_node::NamedNode;
@@ -113,6 +185,240 @@ pub mod rdfs {
     }
 }
 
+pub mod sh {
+    //! [SHACL](https://www.w3.org/TR/shacl/) vocabulary
+    use crate::model::named_node::NamedNode;
+    use lazy_static::lazy_static;
+
+    lazy_static! {
+        /// The (single) value of this property must be a list of path elements, representing the elements of alternative paths.
+        pub static ref ALTERNATIVE_PATH: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#alternativePath");
+        /// RDF list of shapes to validate the value nodes against.
+        pub static ref AND: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#and");
+        /// A constraint component that can be used to test whether a value node conforms to all members of a provided list of shapes.
+        pub static ref AND_CONSTRAINT_COMPONENT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#AndConstraintComponent");
+        /// The node kind of all blank nodes.
+        pub static ref BLANK_NODE: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#BlankNode");
+        /// The node kind of all blank nodes or IRIs.
+        pub static ref BLANK_NODE_OR_IRI: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#BlankNodeOrIRI");
+        /// The node kind of all blank nodes or literals.
+        pub static ref BLANK_NODE_OR_LITERAL: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#BlankNodeOrLiteral");
+        /// The type that all value nodes must have.
+        pub static ref CLASS: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#class");
+        /// A constraint component that can be used to verify that each value node is an instance of a given type.
+        pub static ref CLASS_CONSTRAINT_COMPONENT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#ClassConstraintComponent");
+        /// True if the validation did not produce any validation results, and false otherwise.
+        pub static ref CONFORMS: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#conforms");
+        /// Specifies an RDF datatype that all value nodes must have.
+        pub static ref DATATYPE: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#datatype");
+        /// A constraint component that can be used to restrict the datatype of all value nodes.
+        pub static ref DATATYPE_CONSTRAINT_COMPONENT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#DatatypeConstraintComponent");
+        /// If set to true then all nodes conform to this.
+        pub static ref DEACTIVATED: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#deactivated");
+        /// Links a resource with its namespace prefix declarations.
+        pub static ref DECLARE: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#declare");
+        /// An optional flag to be used with regular expression pattern matching.
+        pub static ref FLAGS: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#flags");
+        /// The focus node that was validated when the result was produced.
+        pub static ref FOCUS_NODE: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#focusNode");
+        /// Specifies a value that must be among the value nodes.
+        pub static ref HAS_VALUE: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#hasValue");
+        /// A constraint component that can be used to verify that one of the value nodes is a given RDF node.
+        pub static ref HAS_VALUE_CONSTRAINT_COMPONENT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#HasValueConstraintComponent");
+        /// Specifies a list of allowed values so that each value node must be among the members of the given list.
+        pub static ref IN: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#in");
+        /// The severity for an informational validation result.
+        pub static ref INFO: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#Info");
+        /// The (single) value of this property represents an inverse path (object to subject).
+        pub static ref INVERSE_PATH: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#inversePath");
+        /// A constraint component that can be used to exclusively enumerate the permitted value nodes.
+        pub static ref IN_CONSTRAINT_COMPONENT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#InConstraintComponent");
+        /// The node kind of all IRIs.
+        pub static ref IRI: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#IRI");
+        /// The node kind of all IRIs or literals.
+        pub static ref IRI_OR_LITERAL: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#IRIOrLiteral");
+        /// The node kind of all literals.
+        pub static ref LITERAL: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#Literal");
+        /// Specifies the maximum number of values in the set of value nodes.
+        pub static ref MAX_COUNT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#maxCount");
+        /// A constraint component that can be used to restrict the maximum number of value nodes.
+        pub static ref MAX_COUNT_CONSTRAINT_COMPONENT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#MaxCountConstraintComponent");
+        /// Specifies the maximum string length of each value node.
+        pub static ref MAX_LENGTH: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#maxLength");
+        /// A constraint component that can be used to restrict the maximum string length of value nodes.
+        pub static ref MAX_LENGTH_CONSTRAINT_COMPONENT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#MaxLengthConstraintComponent");
+        /// A human-readable message (possibly with placeholders for variables) explaining the cause of the result.
+        pub static ref MESSAGE: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#message");
+        /// Specifies the minimum number of values in the set of value nodes.
+        pub static ref MIN_COUNT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#minCount");
+        /// A constraint component that can be used to restrict the minimum number of value nodes.
+        pub static ref MIN_COUNT_CONSTRAINT_COMPONENT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#MinCountConstraintComponent");
+        /// Specifies the minimum string length of each value node.
+        pub static ref MIN_LENGTH: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#minLength");
+        /// A constraint component that can be used to restrict the minimum string length of value nodes.
+        pub static ref MIN_LENGTH_CONSTRAINT_COMPONENT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#MinLengthConstraintComponent");
+        /// The namespace associated with a prefix in a prefix declaration.
+        pub static ref NAMESPACE: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#namespace");
+        /// Specifies the node shape that all value nodes must conform to.
+        pub static ref NODE: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#node");
+        /// A constraint component that can be used to verify that all value nodes conform to the given node shape.
+        pub static ref NODE_CONSTRAINT_COMPONENT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#NodeConstraintComponent");
+        /// Specifies the node kind (e.g. IRI or literal) each value node.
+        pub static ref NODE_KIND: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#nodeKind");
+        /// A constraint component that can be used to restrict the RDF node kind of each value node.
+        pub static ref NODE_KIND_CONSTRAINT_COMPONENT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#NodeKindConstraintComponent");
+        /// A node shape is a shape that specifies constraint that need to be met with respect to focus nodes.
+        pub static ref NODE_SHAPE: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#NodeShape");
+        /// Specifies a shape that all value nodes must not conform to.
+        pub static ref NOT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#not");
+        /// A constraint component that can be used to verify that value nodes do not conform to a given shape.
+        pub static ref NOT_CONSTRAINT_COMPONENT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#NotConstraintComponent");
+        /// The (single) value of this property represents a path that is matched one or more times.
+        pub static ref ONE_OR_MORE_PATH: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#oneOrMorePath");
+        /// Specifies a list of shapes so that the value nodes must conform to at least one of the shapes.
+        pub static ref OR: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#or");
+        /// A constraint component that can be used to restrict the value nodes so that they conform to at least one out of several provided shapes.
+        pub static ref OR_CONSTRAINT_COMPONENT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#OrConstraintComponent");
+        /// Specifies the property path of a property shape.
+        pub static ref PATH: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#path");
+        /// Specifies a regular expression pattern that the string representations of the value nodes must match.
+        pub static ref PATTERN: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#pattern");
+        /// A constraint component that can be used to verify that every value node matches a given regular expression.
+        pub static ref PATTERN_CONSTRAINT_COMPONENT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#PatternConstraintComponent");
+        /// The prefix of a prefix declaration.
+        pub static ref PREFIX: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#prefix");
+        /// The prefixes that shall be applied before parsing the associated SPARQL query.
+        pub static ref PREFIXES: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#prefixes");
+        /// Links a shape to its property shapes.
+        pub static ref PROPERTY: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#property");
+        /// A constraint component that can be used to verify that all value nodes conform to the given property shape.
+        pub static ref PROPERTY_CONSTRAINT_COMPONENT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#PropertyConstraintComponent");
+        /// A property shape is a shape that specifies constraints on the values of a focus node for a given property or path.
+        pub static ref PROPERTY_SHAPE: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#PropertyShape");
+        /// The validation results contained in a validation report.
+        pub static ref RESULT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#result");
+        /// Human-readable messages explaining the cause of the result.
+        pub static ref RESULT_MESSAGE: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#resultMessage");
+        /// The path of a validation result, based on the path of the validated property shape.
+        pub static ref RESULT_PATH: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#resultPath");
+        /// The severity of the result, e.g. warning.
+        pub static ref RESULT_SEVERITY: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#resultSeverity");
+        /// The SPARQL SELECT query to execute.
+        pub static ref SELECT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#select");
+        /// Defines the severity that validation results produced by a shape must have.
+        pub static ref SEVERITY: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#severity");
+        /// A shape is a collection of constraints that may be targeted for certain nodes.
+        pub static ref SHAPE: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#Shape");
+        /// The constraint that was validated when the result was produced.
+        pub static ref SOURCE_CONSTRAINT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#sourceConstraint");
+        /// The constraint component that is the source of the result.
+        pub static ref SOURCE_CONSTRAINT_COMPONENT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#sourceConstraintComponent");
+        /// The shape that is was validated when the result was produced.
+        pub static ref SOURCE_SHAPE: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#sourceShape");
+        /// Links a shape with SPARQL constraints.
+        pub static ref SPARQL: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#sparql");
+        /// A constraint component that can be used to define constraints based on SPARQL queries.
+        pub static ref SPARQL_CONSTRAINT_COMPONENT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#SPARQLConstraintComponent");
+        /// Links a shape to a class, indicating that all instances of the class must conform to the shape.
+        pub static ref TARGET_CLASS: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#targetClass");
+        /// Links a shape to individual nodes, indicating that these nodes must conform to the shape.
+        pub static ref TARGET_NODE: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#targetNode");
+        /// Links a shape to a property, indicating that all all objects of triples that have the given property as their predicate must conform to the shape.
+        pub static ref TARGET_OBJECTS_OF: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#targetObjectsOf");
+        /// Links a shape to a property, indicating that all subjects of triples that have the given property as their predicate must conform to the shape.
+        pub static ref TARGET_SUBJECTS_OF: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#targetSubjectsOf");
+        /// The class of SHACL validation reports.
+        pub static ref VALIDATION_REPORT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#ValidationReport");
+        /// The class of validation results.
+        pub static ref VALIDATION_RESULT: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#ValidationResult");
+        /// An RDF node that has caused the result.
+        pub static ref VALUE: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#value");
+        /// The severity for a violation validation result.
+        pub static ref VIOLATION: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#Violation");
+        /// The severity for a warning validation result.
+        pub static ref WARNING: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#Warning");
+        /// The (single) value of this property represents a path that is matched zero or more times.
+        pub static ref ZERO_OR_MORE_PATH: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#zeroOrMorePath");
+        /// The (single) value of this property represents a path that is matched zero or one times.
+        pub static ref ZERO_OR_ONE_PATH: NamedNode =
+            NamedNode::new_from_string("http://www.w3.org/ns/shacl#zeroOrOnePath");
+    }
+}
+
 pub mod xsd {
     //! `NamedNode`s for [RDF compatible XSD datatypes](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-compatible-xsd-types)
     use crate::model::named_node::NamedNode;
@@ -1,6 +1,7 @@
 use crate::model::*;
-use crate::sparql::PreparedQuery;
+use crate::sparql::{PreparedQuery, QueryOptions, QueryPlan};
 use crate::{DatasetSyntax, GraphSyntax, Result};
+use std::collections::BTreeMap;
 use std::io::BufRead;
 
 /// A `Repository` stores a [RDF dataset](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-dataset)
@@ -16,6 +17,8 @@ use std::io::BufRead;
 /// use rudf::{Repository, RepositoryConnection, MemoryRepository, Result};
 /// use crate::rudf::sparql::PreparedQuery;
 /// use rudf::sparql::QueryResult;
+/// use rudf::sparql::QueryOptions;
+/// use std::collections::BTreeMap;
 ///
 /// let repository = MemoryRepository::default();
 /// let mut connection = repository.connection().unwrap();
@@ -30,8 +33,8 @@ use std::io::BufRead;
 /// assert_eq!(vec![quad], results.unwrap());
 ///
 /// // SPARQL query
-/// let prepared_query = connection.prepare_query("SELECT ?s WHERE { ?s ?p ?o }", None).unwrap();
-/// let results = prepared_query.exec().unwrap();
+/// let prepared_query = connection.prepare_query("SELECT ?s WHERE { ?s ?p ?o }", QueryOptions::default()).unwrap();
+/// let results = prepared_query.exec(&BTreeMap::default()).unwrap();
 /// if let QueryResult::Bindings(results) = results {
 ///     assert_eq!(results.into_values_iter().next().unwrap().unwrap()[0], Some(ex.into()));
 /// }
@@ -46,6 +49,10 @@ use std::io::BufRead;
 ///
 /// Quads insertion and deletion should respect [ACID](https://en.wikipedia.org/wiki/ACID) properties for all implementation.
 /// No complex transaction support is provided yet.
+///
+/// Literals are returned with the lexical form they have been loaded with.
+/// For example `"01"^^xsd:integer` is not normalized to `"1"^^xsd:integer`: these are two different terms
+/// that are still equal according to the SPARQL `=` operator and ordered by value.
 pub trait Repository {
     type Connection: RepositoryConnection;
 
@@ -60,12 +67,19 @@ pub trait RepositoryConnection: Clone {
     ///
     /// The implementation is a work in progress, SPARQL 1.1 specific features are not implemented yet.
     ///
+    /// The `options` allow to set the base IRI of the query or to enable the RDFS entailment regime.
+    /// They replace the former `base_iri: Option<&str>` parameter:
+    /// `prepare_query(query, Some(base_iri))` should now be written `prepare_query(query, QueryOptions::default().with_base_iri(base_iri))`
+    /// and `prepare_query(query, None)` should be written `prepare_query(query, QueryOptions::default())`.
+    ///
     /// Usage example:
     /// ```
     /// use rudf::model::*;
     /// use rudf::{Repository, RepositoryConnection, MemoryRepository};
     /// use rudf::sparql::PreparedQuery;
     /// use rudf::sparql::QueryResult;
+    /// use rudf::sparql::QueryOptions;
+    /// use std::collections::BTreeMap;
     ///
     /// let repository = MemoryRepository::default();
     /// let mut connection = repository.connection().unwrap();
@@ -75,13 +89,22 @@ pub trait RepositoryConnection: Clone {
     /// connection.insert(&Quad::new(ex.clone(), ex.clone(), ex.clone(), None));
     ///
     /// // SPARQL query
-    /// let prepared_query = connection.prepare_query("SELECT ?s WHERE { ?s ?p ?o }", None).unwrap();
-    /// let results = prepared_query.exec().unwrap();
+    /// let prepared_query = connection.prepare_query("SELECT ?s WHERE { ?s ?p ?o }", QueryOptions::default()).unwrap();
+    /// let results = prepared_query.exec(&BTreeMap::default()).unwrap();
     /// if let QueryResult::Bindings(results) = results {
     ///     assert_eq!(results.into_values_iter().next().unwrap().unwrap()[0], Some(ex.into()));
     /// }
     /// ```
-    fn prepare_query(&self, query: &str, base_iri: Option<&str>) -> Result<Self::PreparedQuery>;
+    fn prepare_query(&self, query: &str, options: QueryOptions<'_>) -> Result<Self::PreparedQuery>;
+
+    /// Prepares a query already parsed and planned with `QueryPlan::parse`.
+    ///
+    /// The base IRI of the `options` is ignored, the one given to `QueryPlan::parse` is used instead.
+    fn prepare_query_plan(
+        &self,
+        plan: &QueryPlan,
+        options: QueryOptions<'_>,
+    ) -> Result<Self::PreparedQuery>;
 
     /// Retrieves quads with a filter on each quad component
     ///
@@ -114,6 +137,8 @@ pub trait RepositoryConnection: Clone {
 
     /// Loads a graph file (i.e. triples) into the repository
     ///
+    /// The prefixes declared in Turtle files are added to the namespace table if they are not already bound.
+    ///
     /// Usage example:
     /// ```
     /// use rudf::model::*;
@@ -131,6 +156,30 @@ pub trait RepositoryConnection: Clone {
     /// let ex = NamedNode::parse("http://example.com").unwrap();
     /// assert_eq!(vec![Quad::new(ex.clone(), ex.clone(), ex.clone(), None)], results.unwrap());
     /// ```
+    ///
+    /// RDF-star quoted triples are loaded from Turtle-star and N-Triples-star files and may be queried using SPARQL-star:
+    /// ```
+    /// use rudf::model::*;
+    /// use rudf::{Repository, RepositoryConnection, MemoryRepository, GraphSyntax};
+    /// use rudf::sparql::{PreparedQuery, QueryOptions, QueryResult};
+    /// use std::collections::BTreeMap;
+    ///
+    /// let repository = MemoryRepository::default();
+    /// let mut connection = repository.connection().unwrap();
+    ///
+    /// let file = b"@prefix ex: <http://example.com/> . ex:alice ex:knows ex:bob {| ex:since 2010 |} .";
+    /// connection.load_graph(file.as_ref(), GraphSyntax::TurtleStar, None, None).unwrap();
+    ///
+    /// let prepared_query = connection.prepare_query(
+    ///     "PREFIX ex: <http://example.com/> SELECT (SUBJECT(?t) AS ?s) WHERE { ?t ex:since 2010 FILTER(isTRIPLE(?t)) }",
+    ///     QueryOptions::default()
+    /// ).unwrap();
+    /// let results = prepared_query.exec(&BTreeMap::default()).unwrap();
+    /// if let QueryResult::Bindings(results) = results {
+    ///     let alice = NamedNode::parse("http://example.com/alice").unwrap();
+    ///     assert_eq!(results.into_values_iter().next().unwrap().unwrap()[0], Some(alice.into()));
+    /// }
+    /// ```
     fn load_graph(
         &mut self,
         reader: impl BufRead,
@@ -141,6 +190,8 @@ pub trait RepositoryConnection: Clone {
 
     /// Loads a dataset file (i.e. quads) into the repository
     ///
+    /// The prefixes declared in TriG files are added to the namespace table if they are not already bound.
+    ///
     /// Usage example:
     /// ```
     /// use rudf::model::*;
@@ -173,4 +224,156 @@ pub trait RepositoryConnection: Clone {
 
     /// Removes a quad from this repository
     fn remove(&mut self, quad: &Quad) -> Result<()>;
+
+    /// Applies a patch to this repository: its deletions are removed then its insertions are added
+    ///
+    /// Usage example:
+    /// ```
+    /// use rudf::model::*;
+    /// use rudf::{Repository, RepositoryConnection, MemoryRepository};
+    ///
+    /// let repository = MemoryRepository::default();
+    /// let mut connection = repository.connection().unwrap();
+    /// let ex = NamedNode::parse("http://example.com").unwrap();
+    /// connection.insert(&Quad::new(ex.clone(), ex.clone(), Literal::from("old"), None)).unwrap();
+    ///
+    /// let patch = b"D <http://example.com> <http://example.com> \"old\" .
+    /// A <http://example.com> <http://example.com> \"new\" .";
+    /// connection.apply_patch(&DatasetPatch::parse_rdf_patch(patch.as_ref()).unwrap()).unwrap();
+    /// assert!(connection.contains(&Quad::new(ex.clone(), ex.clone(), Literal::from("new"), None)).unwrap());
+    /// assert!(!connection.contains(&Quad::new(ex.clone(), ex.clone(), Literal::from("old"), None)).unwrap());
+    /// ```
+    fn apply_patch(&mut self, patch: &DatasetPatch) -> Result<()> {
+        for quad in patch.deletions().iter() {
+            self.remove(&quad)?;
+        }
+        for quad in patch.insertions().iter() {
+            self.insert(&quad)?;
+        }
+        Ok(())
+    }
+
+    /// Enables a forward chaining reasoner implementing a subset of the [OWL 2 RL](https://www.w3.org/TR/owl2-profiles/#OWL_2_RL) rules
+    ///
+    /// The triples entailed from the union of all the graphs of the repository are materialized in the named graph `inferred_graph`.
+    /// Its previous content is replaced.
+    /// The reasoner is enabled on the repository: the inferred triples are then incrementally maintained on each insertion,
+    /// removal or load done with any of its connections, including the ones opened later.
+    /// The `RocksDbRepository` keeps it enabled when it is opened again.
+    ///
+    /// The supported rules are the ones about `owl:sameAs` (eq-sym, eq-trans, eq-rep-*), `owl:inverseOf` (prp-inv*),
+    /// `owl:SymmetricProperty` (prp-symp), `owl:TransitiveProperty` (prp-trp), `owl:propertyChainAxiom` (prp-spo2),
+    /// `owl:equivalentClass` and `owl:equivalentProperty` (scm-eqc1, scm-eqp1) and the RDFS ones (prp-dom, prp-rng, prp-spo1, cax-sco, scm-sco, scm-spo).
+    ///
+    /// Usage example:
+    /// ```
+    /// use rudf::model::*;
+    /// use rudf::{Repository, RepositoryConnection, MemoryRepository, GraphSyntax};
+    ///
+    /// let repository = MemoryRepository::default();
+    /// let mut connection = repository.connection().unwrap();
+    /// let inferred = NamedNode::parse("http://example.com/inferred").unwrap();
+    /// connection.enable_owl_rl_reasoning(&inferred.clone().into()).unwrap();
+    ///
+    /// // loading
+    /// let file = b"@prefix ex: <http://example.com/> .
+    ///     @prefix owl: <http://www.w3.org/2002/07/owl#> .
+    ///     ex:parentOf owl:inverseOf ex:childOf .
+    ///     ex:alice ex:parentOf ex:bob .";
+    /// connection.load_graph(file.as_ref(), GraphSyntax::Turtle, None, None).unwrap();
+    ///
+    /// let alice = NamedNode::parse("http://example.com/alice").unwrap();
+    /// let bob = NamedNode::parse("http://example.com/bob").unwrap();
+    /// let parent_of = NamedNode::parse("http://example.com/parentOf").unwrap();
+    /// let child_of = NamedNode::parse("http://example.com/childOf").unwrap();
+    /// let inferred_quad = Quad::new(bob.clone(), child_of, alice.clone(), Some(inferred.into()));
+    /// assert!(connection.contains(&inferred_quad).unwrap());
+    ///
+    /// // the inferred triples are removed with their premises
+    /// connection.remove(&Quad::new(alice, parent_of, bob, None)).unwrap();
+    /// assert!(!connection.contains(&inferred_quad).unwrap());
+    /// ```
+    fn enable_owl_rl_reasoning(&mut self, inferred_graph: &NamedOrBlankNode) -> Result<()>;
+
+    /// Enables the [skolemization](https://www.w3.org/TR/rdf11-concepts/#section-skolemization) of the blank nodes
+    ///
+    /// Skolemization is enabled on the repository: the blank nodes of the quads loaded, inserted, removed or looked up
+    /// with all its connections, including the ones opened later, are replaced by Skolem IRIs minted by `skolemizer`.
+    /// They could then be referenced in later queries.
+    /// `QueryResult::deskolemize` maps these IRIs back to blank nodes when serializing results.
+    /// `RocksDbRepository` keeps it enabled when reopened.
+    ///
+    /// Usage example:
+    /// ```
+    /// use rudf::model::*;
+    /// use rudf::{Repository, RepositoryConnection, MemoryRepository, GraphSyntax};
+    ///
+    /// let repository = MemoryRepository::default();
+    /// let mut connection = repository.connection().unwrap();
+    /// connection.enable_skolemization(&Skolemizer::new("http://example.com/").unwrap()).unwrap();
+    ///
+    /// let file = b"_:a <http://example.com/p> <http://example.com/o> .";
+    /// connection.load_graph(file.as_ref(), GraphSyntax::NTriples, None, None).unwrap();
+    ///
+    /// let quad = connection.quads_for_pattern(None, None, None, None).next().unwrap().unwrap();
+    /// assert!(quad.subject().to_string().starts_with("<http://example.com/.well-known/genid/"));
+    /// ```
+    fn enable_skolemization(&mut self, skolemizer: &Skolemizer) -> Result<()>;
+
+    /// Makes the loading of files fail on ill-typed literals and malformed language tags
+    ///
+    /// By default, a literal like `"abc"^^xsd:integer` is loaded as an opaque literal and
+    /// a language tag is only normalized to lowercase.
+    /// In strict mode, the lexical forms of the XML Schema datatypes supported by the stores are checked,
+    /// as the [BCP47](https://tools.ietf.org/html/bcp47) well-formedness of the language tags.
+    /// The returned `Error::Syntax` gives the position of the literal in the file except for RDF/XML.
+    ///
+    /// Usage example:
+    /// ```
+    /// use rudf::{Error, Repository, RepositoryConnection, MemoryRepository, GraphSyntax};
+    ///
+    /// let repository = MemoryRepository::default();
+    /// let mut connection = repository.connection().unwrap();
+    /// connection.enable_strict_loading();
+    ///
+    /// let file = b"<http://example.com/s> <http://example.com/p> \"1\" .\n<http://example.com/s> <http://example.com/p> \"abc\"^^<http://www.w3.org/2001/XMLSchema#integer> .";
+    /// match connection.load_graph(file.as_ref(), GraphSyntax::NTriples, None, None) {
+    ///     Err(Error::Syntax(error)) => {
+    ///         let position = error.position().unwrap();
+    ///         assert_eq!((position.line(), position.column()), (1, 47));
+    ///     }
+    ///     _ => panic!("the literal should be rejected"),
+    /// }
+    /// ```
+    fn enable_strict_loading(&mut self);
+
+    /// Returns the namespace table of this repository i.e. a map from prefixes to namespace IRIs
+    ///
+    /// Usage example:
+    /// ```
+    /// use rudf::{Repository, RepositoryConnection, MemoryRepository, GraphSyntax};
+    ///
+    /// let repository = MemoryRepository::default();
+    /// let mut connection = repository.connection().unwrap();
+    ///
+    /// // loading
+    /// let file = b"@prefix ex: <http://example.com/> . ex:s ex:p ex:o .";
+    /// connection.load_graph(file.as_ref(), GraphSyntax::Turtle, None, None).unwrap();
+    /// assert_eq!(Some("http://example.com/".to_owned()), connection.namespace("ex").unwrap());
+    ///
+    /// // edition
+    /// connection.set_namespace("foaf", "http://xmlns.com/foaf/0.1/").unwrap();
+    /// connection.remove_namespace("ex").unwrap();
+    /// assert_eq!(vec!["foaf"], connection.namespaces().unwrap().keys().collect::<Vec<_>>());
+    /// ```
+    fn namespaces(&self) -> Result<BTreeMap<String, String>>;
+
+    /// Returns the namespace IRI bound to a prefix if it exists
+    fn namespace(&self, prefix: &str) -> Result<Option<String>>;
+
+    /// Binds a prefix to a namespace IRI, replacing the previous binding if any
+    fn set_namespace(&mut self, prefix: &str, namespace: &str) -> Result<()>;
+
+    /// Removes the binding of a prefix
+    fn remove_namespace(&mut self, prefix: &str) -> Result<()>;
 }
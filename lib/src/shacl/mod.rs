@@ -0,0 +1,575 @@
+//! [SHACL](https://www.w3.org/TR/shacl/) validation.
+//!
+//! The [SHACL Core](https://www.w3.org/TR/shacl/#core-components) constraints `sh:class`, `sh:datatype`, `sh:nodeKind`,
+//! `sh:minCount`, `sh:maxCount`, `sh:minLength`, `sh:maxLength`, `sh:pattern`, `sh:in`, `sh:hasValue`, `sh:node`,
+//! `sh:property`, `sh:and`, `sh:or` and `sh:not` are supported with all the SHACL property paths and targets.
+//! [SPARQL-based constraints](https://www.w3.org/TR/shacl/#sparql-constraints) are evaluated using the SPARQL engine of this crate.
+//!
+//! Usage example:
+//! ```
+//! use rudf::model::*;
+//! use rudf::{Repository, RepositoryConnection, MemoryRepository, GraphSyntax};
+//! use rudf::shacl::ShapesGraph;
+//!
+//! // Reads the shapes from a named graph of a repository
+//! let repository = MemoryRepository::default();
+//! let mut connection = repository.connection().unwrap();
+//! let shapes = NamedNode::parse("http://example.com/shapes").unwrap();
+//! let file = b"@prefix ex: <http://example.com/> .
+//!     @prefix sh: <http://www.w3.org/ns/shacl#> .
+//!     ex:PersonShape a sh:NodeShape ;
+//!         sh:targetClass ex:Person ;
+//!         sh:property [ sh:path ex:name ; sh:minCount 1 ; sh:datatype <http://www.w3.org/2001/XMLSchema#string> ] .";
+//! connection.load_graph(file.as_ref(), GraphSyntax::Turtle, Some(&shapes.clone().into()), None).unwrap();
+//! let shapes = ShapesGraph::from_named_graph(&connection, &shapes.into()).unwrap();
+//!
+//! // Validates a data graph
+//! let ex = |name: &str| NamedNode::parse(format!("http://example.com/{}", name)).unwrap();
+//! let mut data = SimpleGraph::default();
+//! data.insert(Triple::new(ex("alice"), vocab::rdf::TYPE.clone(), ex("Person")));
+//! data.insert(Triple::new(ex("alice"), ex("name"), Literal::from("Alice")));
+//! data.insert(Triple::new(ex("bob"), vocab::rdf::TYPE.clone(), ex("Person")));
+//! let report = shapes.validate(&data).unwrap();
+//!
+//! assert!(!report.conforms());
+//! assert_eq!(report.results().len(), 1);
+//! assert_eq!(report.results()[0].focus_node(), &Term::from(ex("bob")));
+//! assert_eq!(report.results()[0].source_constraint_component(), &*vocab::sh::MIN_COUNT_CONSTRAINT_COMPONENT);
+//!
+//! // The report could be serialized as a standard sh:ValidationReport graph
+//! let report_graph = report.to_graph();
+//! ```
+
+mod shapes;
+mod validator;
+
+use crate::model::vocab::{rdf, sh};
+use crate::model::*;
+use crate::shacl::shapes::{read_shapes, Shape};
+use crate::shacl::validator::Validator;
+use crate::sparql::algebra::PropertyPath;
+use crate::{RepositoryConnection, Result};
+
+pub(crate) use crate::shacl::validator::is_well_formed;
+
+/// The shapes of a [SHACL shapes graph](https://www.w3.org/TR/shacl/#shapes-graph) ready to validate data graphs
+pub struct ShapesGraph {
+    shapes: Vec<Shape>,
+    /// The name and the content of the shapes graph if it has been read from a named graph
+    named_graph: Option<(NamedOrBlankNode, SimpleGraph)>,
+}
+
+impl ShapesGraph {
+    /// Reads the shapes defined in a graph
+    ///
+    /// Returns a syntax error if one of the shapes is ill-formed.
+    pub fn from_graph(graph: &SimpleGraph) -> Result<Self> {
+        Ok(Self {
+            shapes: read_shapes(graph)?,
+            named_graph: None,
+        })
+    }
+
+    /// Reads the shapes defined in a named graph of a repository
+    ///
+    /// The SPARQL-based constraints are then able to query the shapes graph using `$shapesGraph`.
+    pub fn from_named_graph(
+        connection: &impl RepositoryConnection,
+        graph_name: &NamedOrBlankNode,
+    ) -> Result<Self> {
+        let graph = connection
+            .quads_for_pattern(None, None, None, Some(Some(graph_name)))
+            .map(|quad| Ok(quad?.into_triple()))
+            .collect::<Result<SimpleGraph>>()?;
+        Ok(Self {
+            shapes: read_shapes(&graph)?,
+            named_graph: Some((graph_name.clone(), graph)),
+        })
+    }
+
+    /// Validates a data graph against the shapes
+    pub fn validate(&self, data: &SimpleGraph) -> Result<ValidationReport> {
+        let shapes_graph = self.named_graph.as_ref().map(|(name, graph)| (name, graph));
+        Ok(ValidationReport {
+            results: Validator::new(&self.shapes, shapes_graph, data)?.validate()?,
+        })
+    }
+}
+
+/// The result of the validation of a data graph
+#[derive(Debug, Clone)]
+pub struct ValidationReport {
+    results: Vec<ValidationResult>,
+}
+
+impl ValidationReport {
+    /// Checks if the data graph conforms to the shapes i.e. if the validation did not produce any result
+    pub fn conforms(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// The validation results
+    pub fn results(&self) -> &[ValidationResult] {
+        &self.results
+    }
+
+    /// Builds the [`sh:ValidationReport` graph](https://www.w3.org/TR/shacl/#validation-report) describing this report
+    pub fn to_graph(&self) -> SimpleGraph {
+        let mut graph = SimpleGraph::default();
+        let report = BlankNode::default();
+        graph.insert(Triple::new(
+            report.clone(),
+            rdf::TYPE.clone(),
+            sh::VALIDATION_REPORT.clone(),
+        ));
+        graph.insert(Triple::new(
+            report.clone(),
+            sh::CONFORMS.clone(),
+            Literal::from(self.conforms()),
+        ));
+        for result in &self.results {
+            let node = BlankNode::default();
+            graph.insert(Triple::new(
+                report.clone(),
+                sh::RESULT.clone(),
+                node.clone(),
+            ));
+            result.add_to_graph(node.into(), &mut graph);
+        }
+        graph
+    }
+}
+
+/// A [validation result](https://www.w3.org/TR/shacl/#results-validation-result)
+#[derive(Debug, Clone)]
+pub struct ValidationResult {
+    focus_node: Term,
+    path: Option<PropertyPath>,
+    value: Option<Term>,
+    source_shape: NamedOrBlankNode,
+    source_constraint_component: NamedNode,
+    source_constraint: Option<NamedOrBlankNode>,
+    severity: NamedNode,
+    messages: Vec<Term>,
+}
+
+impl ValidationResult {
+    /// The focus node that has caused the result
+    pub fn focus_node(&self) -> &Term {
+        &self.focus_node
+    }
+
+    /// The value node that has caused the result if the constraint applies to each value node
+    pub fn value(&self) -> Option<&Term> {
+        self.value.as_ref()
+    }
+
+    /// The shape that the focus node was validated against
+    pub fn source_shape(&self) -> &NamedOrBlankNode {
+        &self.source_shape
+    }
+
+    /// The constraint component that has produced the result e.g. `sh:MinCountConstraintComponent`
+    pub fn source_constraint_component(&self) -> &NamedNode {
+        &self.source_constraint_component
+    }
+
+    /// The severity of the result: `sh:Violation` by default or the `sh:severity` of the shape
+    pub fn severity(&self) -> &NamedNode {
+        &self.severity
+    }
+
+    /// The `sh:message`s of the shape or the messages returned by the SPARQL-based constraint
+    pub fn messages(&self) -> &[Term] {
+        &self.messages
+    }
+
+    fn add_to_graph(&self, node: NamedOrBlankNode, graph: &mut SimpleGraph) {
+        let mut add = |predicate: &NamedNode, object: Term| {
+            graph.insert(Triple::new(node.clone(), predicate.clone(), object));
+        };
+        add(&rdf::TYPE, sh::VALIDATION_RESULT.clone().into());
+        add(&sh::FOCUS_NODE, self.focus_node.clone());
+        if let Some(value) = &self.value {
+            add(&sh::VALUE, value.clone());
+        }
+        add(&sh::SOURCE_SHAPE, self.source_shape.clone().into());
+        add(
+            &sh::SOURCE_CONSTRAINT_COMPONENT,
+            self.source_constraint_component.clone().into(),
+        );
+        if let Some(source_constraint) = &self.source_constraint {
+            add(&sh::SOURCE_CONSTRAINT, source_constraint.clone().into());
+        }
+        add(&sh::RESULT_SEVERITY, self.severity.clone().into());
+        for message in &self.messages {
+            add(&sh::RESULT_MESSAGE, message.clone());
+        }
+        if let Some(path) = &self.path {
+            let path = write_path(path, graph);
+            graph.insert(Triple::new(node, sh::RESULT_PATH.clone(), path));
+        }
+    }
+}
+
+/// Writes a property path using the SHACL syntax and returns the node representing it
+fn write_path(path: &PropertyPath, graph: &mut SimpleGraph) -> Term {
+    match path {
+        PropertyPath::PredicatePath(predicate) => predicate.clone().into(),
+        PropertyPath::InversePath(path) => {
+            let path = write_path(path, graph);
+            write_path_node(&sh::INVERSE_PATH, path, graph)
+        }
+        PropertyPath::SequencePath(_, _) => {
+            let mut elements = Vec::default();
+            flatten_path(path, &mut elements, &|path| match path {
+                PropertyPath::SequencePath(a, b) => Some((a, b)),
+                _ => None,
+            });
+            write_list(&elements, graph)
+        }
+        PropertyPath::AlternativePath(_, _) => {
+            let mut elements = Vec::default();
+            flatten_path(path, &mut elements, &|path| match path {
+                PropertyPath::AlternativePath(a, b) => Some((a, b)),
+                _ => None,
+            });
+            let list = write_list(&elements, graph);
+            write_path_node(&sh::ALTERNATIVE_PATH, list, graph)
+        }
+        PropertyPath::ZeroOrMorePath(path) => {
+            let path = write_path(path, graph);
+            write_path_node(&sh::ZERO_OR_MORE_PATH, path, graph)
+        }
+        PropertyPath::OneOrMorePath(path) => {
+            let path = write_path(path, graph);
+            write_path_node(&sh::ONE_OR_MORE_PATH, path, graph)
+        }
+        PropertyPath::ZeroOrOnePath(path) => {
+            let path = write_path(path, graph);
+            write_path_node(&sh::ZERO_OR_ONE_PATH, path, graph)
+        }
+        PropertyPath::NegatedPropertySet(_) => {
+            // Not expressible in SHACL and never built from a shapes graph
+            BlankNode::default().into()
+        }
+    }
+}
+
+fn write_path_node(predicate: &NamedNode, object: Term, graph: &mut SimpleGraph) -> Term {
+    let node = BlankNode::default();
+    graph.insert(Triple::new(node.clone(), predicate.clone(), object));
+    node.into()
+}
+
+/// Lists the operands of nested binary sequences or alternatives
+fn flatten_path<'a>(
+    path: &'a PropertyPath,
+    elements: &mut Vec<&'a PropertyPath>,
+    split: &impl Fn(&'a PropertyPath) -> Option<(&'a PropertyPath, &'a PropertyPath)>,
+) {
+    if let Some((a, b)) = split(path) {
+        flatten_path(a, elements, split);
+        flatten_path(b, elements, split);
+    } else {
+        elements.push(path)
+    }
+}
+
+fn write_list(paths: &[&PropertyPath], graph: &mut SimpleGraph) -> Term {
+    let mut list: Term = rdf::NIL.clone().into();
+    for path in paths.iter().rev() {
+        let first = write_path(path, graph);
+        let node = BlankNode::default();
+        graph.insert(Triple::new(node.clone(), rdf::FIRST.clone(), first));
+        graph.insert(Triple::new(node.clone(), rdf::REST.clone(), list));
+        list = node.into();
+    }
+    list
+}
+
+#[cfg(test)]
+fn validate(shapes: &str, data: &str) -> Vec<(String, String, Option<String>)> {
+    use crate::{GraphSyntax, MemoryRepository, Repository};
+
+    let prefixes = "@prefix ex: <http://example.com/> .
+        @prefix sh: <http://www.w3.org/ns/shacl#> .
+        @prefix xsd: <http://www.w3.org/2001/XMLSchema#> .
+        @prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .\n";
+    let repository = MemoryRepository::default();
+    let mut connection = repository.connection().unwrap();
+    let shapes_graph: NamedOrBlankNode = NamedNode::parse("http://example.com/shapes")
+        .unwrap()
+        .into();
+    connection
+        .load_graph(
+            format!("{}{}", prefixes, shapes).as_bytes(),
+            GraphSyntax::Turtle,
+            Some(&shapes_graph),
+            None,
+        )
+        .unwrap();
+    let data_graph: NamedOrBlankNode = NamedNode::parse("http://example.com/data").unwrap().into();
+    connection
+        .load_graph(
+            format!("{}{}", prefixes, data).as_bytes(),
+            GraphSyntax::Turtle,
+            Some(&data_graph),
+            None,
+        )
+        .unwrap();
+    let data_graph = connection
+        .quads_for_pattern(None, None, None, Some(Some(&data_graph)))
+        .map(|quad| quad.unwrap().into_triple())
+        .collect();
+    let report = ShapesGraph::from_named_graph(&connection, &shapes_graph)
+        .unwrap()
+        .validate(&data_graph)
+        .unwrap();
+    let mut results: Vec<_> = report
+        .results()
+        .iter()
+        .map(|result| {
+            (
+                result.focus_node().to_string(),
+                result
+                    .source_constraint_component()
+                    .as_str()
+                    .trim_start_matches("http://www.w3.org/ns/shacl#")
+                    .to_owned(),
+                result.value().map(|value| value.to_string()),
+            )
+        })
+        .collect();
+    results.sort();
+    results
+}
+
+#[cfg(test)]
+fn result(
+    focus_node: &str,
+    component: &str,
+    value: Option<&str>,
+) -> (String, String, Option<String>) {
+    (
+        focus_node.to_owned(),
+        component.to_owned(),
+        value.map(|value| value.to_owned()),
+    )
+}
+
+#[test]
+fn test_value_type_constraints() {
+    let data = "ex:a a ex:Person ; ex:knows ex:b , ex:c , \"d\" ; ex:age 12 , \"12\" , \"foo\"^^xsd:integer .
+        ex:b a ex:Student . ex:Student rdfs:subClassOf ex:Person .
+        ex:c a ex:Animal .";
+    assert_eq!(
+        validate(
+            "ex:S sh:targetNode ex:a ; sh:property [ sh:path ex:knows ; sh:class ex:Person ] .",
+            data
+        ),
+        vec![
+            result(
+                "<http://example.com/a>",
+                "ClassConstraintComponent",
+                Some("\"d\"")
+            ),
+            result(
+                "<http://example.com/a>",
+                "ClassConstraintComponent",
+                Some("<http://example.com/c>")
+            ),
+        ]
+    );
+    assert_eq!(
+        validate(
+            "ex:S sh:targetNode ex:a ; sh:property [ sh:path ex:age ; sh:datatype xsd:integer ] .",
+            data
+        ),
+        vec![
+            result(
+                "<http://example.com/a>",
+                "DatatypeConstraintComponent",
+                Some("\"12\"")
+            ),
+            result(
+                "<http://example.com/a>",
+                "DatatypeConstraintComponent",
+                Some("\"foo\"^^<http://www.w3.org/2001/XMLSchema#integer>")
+            ),
+        ]
+    );
+    assert_eq!(
+        validate(
+            "ex:S sh:targetNode ex:a ; sh:property [ sh:path ex:knows ; sh:nodeKind sh:IRI ] .",
+            data
+        ),
+        vec![result(
+            "<http://example.com/a>",
+            "NodeKindConstraintComponent",
+            Some("\"d\"")
+        )]
+    );
+}
+
+#[test]
+fn test_cardinality_constraints() {
+    let shapes = "ex:S sh:targetClass ex:Person ; sh:property [ sh:path ex:name ; sh:minCount 1 ; sh:maxCount 2 ] .";
+    assert_eq!(
+        validate(
+            shapes,
+            "ex:a a ex:Person . ex:b a ex:Person ; ex:name \"b\" . ex:c a ex:Person ; ex:name \"c1\", \"c2\", \"c3\" ."
+        ),
+        vec![
+            result("<http://example.com/a>", "MinCountConstraintComponent", None),
+            result("<http://example.com/c>", "MaxCountConstraintComponent", None),
+        ]
+    );
+}
+
+#[test]
+fn test_string_constraints() {
+    let data = "ex:a ex:code \"a\" , \"abcd\" , \"ab12\" . ex:b ex:code _:c .";
+    assert_eq!(
+        validate(
+            "ex:S sh:targetNode ex:a ; sh:property [ sh:path ex:code ; sh:minLength 2 ; sh:maxLength 3 ] .",
+            data
+        ),
+        vec![
+            result("<http://example.com/a>", "MaxLengthConstraintComponent", Some("\"ab12\"")),
+            result("<http://example.com/a>", "MaxLengthConstraintComponent", Some("\"abcd\"")),
+            result("<http://example.com/a>", "MinLengthConstraintComponent", Some("\"a\"")),
+        ]
+    );
+    assert_eq!(
+        validate(
+            "ex:S sh:targetNode ex:a ; sh:property [ sh:path ex:code ; sh:pattern \"^[a-z]+$\" ] .",
+            data
+        ),
+        vec![result(
+            "<http://example.com/a>",
+            "PatternConstraintComponent",
+            Some("\"ab12\"")
+        )]
+    );
+    // The blank nodes have no string value
+    assert_eq!(
+        validate(
+            "ex:S sh:targetNode ex:b ; sh:property [ sh:path ex:code ; sh:maxLength 10 ] .",
+            data
+        )
+        .into_iter()
+        .map(|(focus_node, component, _)| (focus_node, component))
+        .collect::<Vec<_>>(),
+        vec![(
+            "<http://example.com/b>".to_owned(),
+            "MaxLengthConstraintComponent".to_owned()
+        )]
+    );
+}
+
+#[test]
+fn test_value_constraints() {
+    let data = "ex:a ex:color ex:red , ex:blue . ex:b ex:color ex:green .";
+    assert_eq!(
+        validate(
+            "ex:S sh:targetSubjectsOf ex:color ; sh:property [ sh:path ex:color ; sh:in ( ex:red ex:green ) ] .",
+            data
+        ),
+        vec![result("<http://example.com/a>", "InConstraintComponent", Some("<http://example.com/blue>"))]
+    );
+    assert_eq!(
+        validate(
+            "ex:S sh:targetSubjectsOf ex:color ; sh:property [ sh:path ex:color ; sh:hasValue ex:red ] .",
+            data
+        ),
+        vec![result("<http://example.com/b>", "HasValueConstraintComponent", None)]
+    );
+}
+
+#[test]
+fn test_shape_constraints() {
+    let data = "ex:a ex:p 1 . ex:b ex:q 2 . ex:c ex:p 3 ; ex:q 4 . ex:d ex:r 5 .";
+    let shapes = "ex:HasP sh:property [ sh:path ex:p ; sh:minCount 1 ] .
+        ex:HasQ sh:property [ sh:path ex:q ; sh:minCount 1 ] .";
+    let focus_nodes = |component: &str, constraint: &str| {
+        validate(
+            &format!(
+                "{} ex:S sh:targetNode ex:a , ex:b , ex:c , ex:d ; {} .",
+                shapes, constraint
+            ),
+            data,
+        )
+        .into_iter()
+        .map(|(focus_node, c, _)| {
+            assert_eq!(c, component);
+            focus_node
+                .trim_start_matches("<http://example.com/")
+                .trim_end_matches('>')
+                .to_owned()
+        })
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        focus_nodes("NodeConstraintComponent", "sh:node ex:HasP"),
+        vec!["b", "d"]
+    );
+    assert_eq!(
+        focus_nodes("AndConstraintComponent", "sh:and ( ex:HasP ex:HasQ )"),
+        vec!["a", "b", "d"]
+    );
+    assert_eq!(
+        focus_nodes("OrConstraintComponent", "sh:or ( ex:HasP ex:HasQ )"),
+        vec!["d"]
+    );
+    assert_eq!(
+        focus_nodes("NotConstraintComponent", "sh:not ex:HasP"),
+        vec!["a", "c"]
+    );
+    // sh:property reports the results of the property shape
+    assert_eq!(
+        focus_nodes(
+            "MinCountConstraintComponent",
+            "sh:property [ sh:path ex:r ; sh:minCount 1 ]"
+        ),
+        vec!["a", "b", "c"]
+    );
+}
+
+#[test]
+fn test_sparql_constraints() {
+    let data = "ex:a ex:p \"$PATH $this\" ; ex:q ex:a . ex:b ex:p \"b\" ; ex:q ex:a .";
+    // $this is pre-bound and $PATH is replaced in the query algebra and not in the strings
+    let shapes = "ex:S sh:targetSubjectsOf ex:p ; sh:property [
+            sh:path ex:q ;
+            sh:sparql [ sh:select \"SELECT $this ?value WHERE { $this $PATH ?value FILTER(?value != $this) FILTER NOT EXISTS { $this $PATH $this } FILTER(STR(?value) != '$PATH') }\" ]
+        ] .";
+    assert_eq!(
+        validate(shapes, data),
+        vec![result(
+            "<http://example.com/b>",
+            "SPARQLConstraintComponent",
+            Some("<http://example.com/a>")
+        )]
+    );
+    // The literals containing the variable names are kept
+    let shapes = "ex:S sh:targetSubjectsOf ex:p ; sh:sparql [ sh:select \"SELECT $this WHERE { $this <http://example.com/p> '$PATH $this' }\" ] .";
+    assert_eq!(
+        validate(shapes, data),
+        vec![result(
+            "<http://example.com/a>",
+            "SPARQLConstraintComponent",
+            Some("<http://example.com/a>")
+        )]
+    );
+    // $currentShape and $shapesGraph are pre-bound even if they are not projected
+    let shapes = "ex:S sh:targetSubjectsOf ex:p ; ex:forbidden \"b\" ; sh:sparql [ sh:select \"SELECT $this ?value WHERE { $this <http://example.com/p> ?value GRAPH $shapesGraph { $currentShape <http://example.com/forbidden> ?value } }\" ] .";
+    assert_eq!(
+        validate(shapes, data),
+        vec![result(
+            "<http://example.com/b>",
+            "SPARQLConstraintComponent",
+            Some("\"b\"")
+        )]
+    );
+}
@@ -0,0 +1,548 @@
+//! Reading of the shapes defined in a [shapes graph](https://www.w3.org/TR/shacl/#shapes-graph)
+
+use crate::model::vocab::{owl, rdf, rdfs, sh};
+use crate::model::*;
+use crate::sparql::algebra::PropertyPath;
+use crate::{Error, Result};
+use regex::{Regex, RegexBuilder};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+const REGEX_SIZE_LIMIT: usize = 1_000_000;
+
+/// Maximal nesting of the SHACL property paths
+const MAX_PATH_DEPTH: usize = 64;
+
+/// A node or property shape with its parameters read from the shapes graph
+pub struct Shape {
+    pub node: NamedOrBlankNode,
+    pub targets: Vec<Target>,
+    /// The path of property shapes
+    pub path: Option<PropertyPath>,
+    pub constraints: Vec<Constraint>,
+    pub severity: NamedNode,
+    pub messages: Vec<Term>,
+    pub deactivated: bool,
+}
+
+pub enum Target {
+    Class(Term),
+    Node(Term),
+    SubjectsOf(NamedNode),
+    ObjectsOf(NamedNode),
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum NodeKind {
+    Iri,
+    BlankNode,
+    Literal,
+    BlankNodeOrIri,
+    BlankNodeOrLiteral,
+    IriOrLiteral,
+}
+
+impl NodeKind {
+    pub fn matches(self, term: &Term) -> bool {
+        match term {
+            Term::NamedNode(_) => matches!(
+                self,
+                NodeKind::Iri | NodeKind::BlankNodeOrIri | NodeKind::IriOrLiteral
+            ),
+            Term::BlankNode(_) => matches!(
+                self,
+                NodeKind::BlankNode | NodeKind::BlankNodeOrIri | NodeKind::BlankNodeOrLiteral
+            ),
+            Term::Literal(_) => matches!(
+                self,
+                NodeKind::Literal | NodeKind::BlankNodeOrLiteral | NodeKind::IriOrLiteral
+            ),
+            Term::Triple(_) => false,
+        }
+    }
+}
+
+/// A constraint of a shape. The shapes are referred by their index in the shapes list
+pub enum Constraint {
+    Class(Term),
+    Datatype(NamedNode),
+    NodeKind(NodeKind),
+    MinCount(usize),
+    MaxCount(usize),
+    MinLength(usize),
+    MaxLength(usize),
+    Pattern(Regex),
+    In(Vec<Term>),
+    HasValue(Term),
+    Node(usize),
+    Property(usize),
+    And(Vec<usize>),
+    Or(Vec<usize>),
+    Not(usize),
+    Sparql(SparqlConstraint),
+}
+
+impl Constraint {
+    /// The constraint component reported in the validation results
+    pub fn component(&self) -> &'static NamedNode {
+        match self {
+            Constraint::Class(_) => &sh::CLASS_CONSTRAINT_COMPONENT,
+            Constraint::Datatype(_) => &sh::DATATYPE_CONSTRAINT_COMPONENT,
+            Constraint::NodeKind(_) => &sh::NODE_KIND_CONSTRAINT_COMPONENT,
+            Constraint::MinCount(_) => &sh::MIN_COUNT_CONSTRAINT_COMPONENT,
+            Constraint::MaxCount(_) => &sh::MAX_COUNT_CONSTRAINT_COMPONENT,
+            Constraint::MinLength(_) => &sh::MIN_LENGTH_CONSTRAINT_COMPONENT,
+            Constraint::MaxLength(_) => &sh::MAX_LENGTH_CONSTRAINT_COMPONENT,
+            Constraint::Pattern(_) => &sh::PATTERN_CONSTRAINT_COMPONENT,
+            Constraint::In(_) => &sh::IN_CONSTRAINT_COMPONENT,
+            Constraint::HasValue(_) => &sh::HAS_VALUE_CONSTRAINT_COMPONENT,
+            Constraint::Node(_) => &sh::NODE_CONSTRAINT_COMPONENT,
+            Constraint::Property(_) => &sh::PROPERTY_CONSTRAINT_COMPONENT,
+            Constraint::And(_) => &sh::AND_CONSTRAINT_COMPONENT,
+            Constraint::Or(_) => &sh::OR_CONSTRAINT_COMPONENT,
+            Constraint::Not(_) => &sh::NOT_CONSTRAINT_COMPONENT,
+            Constraint::Sparql(_) => &sh::SPARQL_CONSTRAINT_COMPONENT,
+        }
+    }
+}
+
+/// A [SPARQL-based constraint](https://www.w3.org/TR/shacl/#sparql-constraints)
+pub struct SparqlConstraint {
+    pub node: NamedOrBlankNode,
+    /// The `sh:select` query with the `sh:prefixes` declarations prepended
+    pub query: String,
+    pub messages: Vec<Term>,
+}
+
+/// Reads all the shapes of a shapes graph
+pub fn read_shapes(graph: &SimpleGraph) -> Result<Vec<Shape>> {
+    let mut nodes = BTreeSet::new();
+    for predicate in &[
+        &*sh::TARGET_CLASS,
+        &*sh::TARGET_NODE,
+        &*sh::TARGET_SUBJECTS_OF,
+        &*sh::TARGET_OBJECTS_OF,
+        &*sh::PATH,
+    ] {
+        nodes.extend(
+            graph
+                .triples_for_predicate(predicate)
+                .map(|t| t.subject().clone()),
+        );
+    }
+    for class in &[&*sh::NODE_SHAPE, &*sh::PROPERTY_SHAPE] {
+        nodes.extend(
+            graph
+                .subjects_for_predicate_object(&rdf::TYPE, &(*class).clone().into())
+                .cloned(),
+        );
+    }
+    for predicate in &[&*sh::PROPERTY, &*sh::NODE, &*sh::NOT] {
+        for triple in graph.triples_for_predicate(predicate) {
+            nodes.insert(to_shape_node(triple.object())?);
+        }
+    }
+    for predicate in &[&*sh::AND, &*sh::OR] {
+        for triple in graph.triples_for_predicate(predicate) {
+            for member in read_list(graph, triple.object())? {
+                nodes.insert(to_shape_node(&member)?);
+            }
+        }
+    }
+
+    let reader = ShapesReader {
+        graph,
+        ids: nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.clone(), i))
+            .collect(),
+    };
+    nodes
+        .into_iter()
+        .map(|node| reader.read_shape(node))
+        .collect()
+}
+
+struct ShapesReader<'a> {
+    graph: &'a SimpleGraph,
+    ids: HashMap<NamedOrBlankNode, usize>,
+}
+
+impl<'a> ShapesReader<'a> {
+    fn read_shape(&self, node: NamedOrBlankNode) -> Result<Shape> {
+        let graph = self.graph;
+        let mut targets = Vec::default();
+        for class in graph.objects_for_subject_predicate(&node, &sh::TARGET_CLASS) {
+            targets.push(Target::Class(class.clone()));
+        }
+        let node_term: Term = node.clone().into();
+        if is_class(graph, &node_term) {
+            targets.push(Target::Class(node_term));
+        }
+        for target in graph.objects_for_subject_predicate(&node, &sh::TARGET_NODE) {
+            targets.push(Target::Node(target.clone()));
+        }
+        for predicate in graph.objects_for_subject_predicate(&node, &sh::TARGET_SUBJECTS_OF) {
+            targets.push(Target::SubjectsOf(to_named_node(predicate)?));
+        }
+        for predicate in graph.objects_for_subject_predicate(&node, &sh::TARGET_OBJECTS_OF) {
+            targets.push(Target::ObjectsOf(to_named_node(predicate)?));
+        }
+
+        let path = match self.single_value(&node, &sh::PATH)? {
+            Some(path) => Some(read_path(graph, &path, 0)?),
+            None => None,
+        };
+
+        let severity = match self.single_value(&node, &sh::SEVERITY)? {
+            Some(severity) => to_named_node(&severity)?,
+            None => sh::VIOLATION.clone(),
+        };
+
+        let deactivated = match self.single_value(&node, &sh::DEACTIVATED)? {
+            Some(Term::Literal(value)) => value.value() == "true" || value.value() == "1",
+            Some(value) => {
+                return Err(Error::syntax(format!(
+                    "sh:deactivated should be a boolean, found {}",
+                    value
+                )))
+            }
+            None => false,
+        };
+
+        Ok(Shape {
+            constraints: self.read_constraints(&node)?,
+            messages: graph
+                .objects_for_subject_predicate(&node, &sh::MESSAGE)
+                .cloned()
+                .collect(),
+            node,
+            targets,
+            path,
+            severity,
+            deactivated,
+        })
+    }
+
+    fn read_constraints(&self, node: &NamedOrBlankNode) -> Result<Vec<Constraint>> {
+        let graph = self.graph;
+        let mut constraints = Vec::default();
+        for (predicate, value) in graph
+            .triples_for_subject(node)
+            .map(|t| (t.predicate(), t.object()))
+        {
+            constraints.push(if predicate == &*sh::CLASS {
+                Constraint::Class(value.clone())
+            } else if predicate == &*sh::DATATYPE {
+                Constraint::Datatype(to_named_node(value)?)
+            } else if predicate == &*sh::NODE_KIND {
+                Constraint::NodeKind(read_node_kind(value)?)
+            } else if predicate == &*sh::MIN_COUNT {
+                Constraint::MinCount(to_usize(value)?)
+            } else if predicate == &*sh::MAX_COUNT {
+                Constraint::MaxCount(to_usize(value)?)
+            } else if predicate == &*sh::MIN_LENGTH {
+                Constraint::MinLength(to_usize(value)?)
+            } else if predicate == &*sh::MAX_LENGTH {
+                Constraint::MaxLength(to_usize(value)?)
+            } else if predicate == &*sh::PATTERN {
+                Constraint::Pattern(self.read_pattern(node, value)?)
+            } else if predicate == &*sh::IN {
+                Constraint::In(read_list(graph, value)?)
+            } else if predicate == &*sh::HAS_VALUE {
+                Constraint::HasValue(value.clone())
+            } else if predicate == &*sh::NODE {
+                Constraint::Node(self.shape_id(value)?)
+            } else if predicate == &*sh::PROPERTY {
+                Constraint::Property(self.shape_id(value)?)
+            } else if predicate == &*sh::AND {
+                Constraint::And(self.shape_list(value)?)
+            } else if predicate == &*sh::OR {
+                Constraint::Or(self.shape_list(value)?)
+            } else if predicate == &*sh::NOT {
+                Constraint::Not(self.shape_id(value)?)
+            } else if predicate == &*sh::SPARQL {
+                match self.read_sparql_constraint(value)? {
+                    Some(constraint) => Constraint::Sparql(constraint),
+                    None => continue,
+                }
+            } else {
+                continue;
+            });
+        }
+        Ok(constraints)
+    }
+
+    fn read_pattern(&self, node: &NamedOrBlankNode, pattern: &Term) -> Result<Regex> {
+        let mut regex_builder = RegexBuilder::new(&to_string(pattern)?);
+        regex_builder.size_limit(REGEX_SIZE_LIMIT);
+        if let Some(flags) = self.single_value(node, &sh::FLAGS)? {
+            for flag in to_string(&flags)?.chars() {
+                match flag {
+                    's' => {
+                        regex_builder.dot_matches_new_line(true);
+                    }
+                    'm' => {
+                        regex_builder.multi_line(true);
+                    }
+                    'i' => {
+                        regex_builder.case_insensitive(true);
+                    }
+                    'x' => {
+                        regex_builder.ignore_whitespace(true);
+                    }
+                    _ => (),
+                }
+            }
+        }
+        regex_builder
+            .build()
+            .map_err(|e| Error::syntax(format!("Invalid sh:pattern {}: {}", pattern, e)))
+    }
+
+    /// Reads a SPARQL-based constraint or returns `None` if it is deactivated
+    fn read_sparql_constraint(&self, node: &Term) -> Result<Option<SparqlConstraint>> {
+        let node = to_shape_node(node)?;
+        if let Some(Term::Literal(deactivated)) = self.single_value(&node, &sh::DEACTIVATED)? {
+            if deactivated.value() == "true" || deactivated.value() == "1" {
+                return Ok(None);
+            }
+        }
+        let select = match self.single_value(&node, &sh::SELECT)? {
+            Some(select) => to_string(&select)?,
+            None => {
+                return Err(Error::syntax(format!(
+                    "The SPARQL constraint {} has no sh:select query",
+                    node
+                )))
+            }
+        };
+        let mut query = String::default();
+        for prefixes in self
+            .graph
+            .objects_for_subject_predicate(&node, &sh::PREFIXES)
+        {
+            for declaration in self
+                .graph
+                .objects_for_subject_predicate(&to_shape_node(prefixes)?, &sh::DECLARE)
+            {
+                let declaration = to_shape_node(declaration)?;
+                if let (Some(prefix), Some(namespace)) = (
+                    self.single_value(&declaration, &sh::PREFIX)?,
+                    self.single_value(&declaration, &sh::NAMESPACE)?,
+                ) {
+                    query.push_str(&format!(
+                        "PREFIX {}: <{}>\n",
+                        to_string(&prefix)?,
+                        to_string(&namespace)?
+                    ));
+                }
+            }
+        }
+        query.push_str(&select);
+        Ok(Some(SparqlConstraint {
+            messages: self
+                .graph
+                .objects_for_subject_predicate(&node, &sh::MESSAGE)
+                .cloned()
+                .collect(),
+            node,
+            query,
+        }))
+    }
+
+    fn shape_id(&self, node: &Term) -> Result<usize> {
+        Ok(self.ids[&to_shape_node(node)?])
+    }
+
+    fn shape_list(&self, list: &Term) -> Result<Vec<usize>> {
+        read_list(self.graph, list)?
+            .iter()
+            .map(|shape| self.shape_id(shape))
+            .collect()
+    }
+
+    /// Returns the value of a parameter that should have at most one value
+    fn single_value(&self, node: &NamedOrBlankNode, predicate: &NamedNode) -> Result<Option<Term>> {
+        let mut values = self.graph.objects_for_subject_predicate(node, predicate);
+        let value = values.next().cloned();
+        if values.next().is_some() {
+            Err(Error::syntax(format!(
+                "The shape {} should have at most one value for {}",
+                node, predicate
+            )))
+        } else {
+            Ok(value)
+        }
+    }
+}
+
+/// Reads a [SHACL property path](https://www.w3.org/TR/shacl/#property-paths)
+fn read_path(graph: &SimpleGraph, path: &Term, depth: usize) -> Result<PropertyPath> {
+    if depth > MAX_PATH_DEPTH {
+        return Err(Error::syntax(
+            "The SHACL property path is too deeply nested",
+        ));
+    }
+    let node = match path {
+        Term::NamedNode(predicate) => return Ok(PropertyPath::PredicatePath(predicate.clone())),
+        Term::BlankNode(node) => NamedOrBlankNode::from(node.clone()),
+        Term::Literal(_) | Term::Triple(_) => {
+            return Err(Error::syntax(format!(
+                "A SHACL property path should be an IRI or a blank node: {}",
+                path
+            )))
+        }
+    };
+    if graph
+        .object_for_subject_predicate(&node, &rdf::FIRST)
+        .is_some()
+    {
+        return fold_paths(graph, path, depth, |a, b| {
+            PropertyPath::SequencePath(Box::new(a), Box::new(b))
+        });
+    }
+    let single = |predicate: &NamedNode| -> Result<Option<PropertyPath>> {
+        graph
+            .object_for_subject_predicate(&node, predicate)
+            .map(|value| read_path(graph, value, depth + 1))
+            .transpose()
+    };
+    if let Some(list) = graph.object_for_subject_predicate(&node, &sh::ALTERNATIVE_PATH) {
+        fold_paths(graph, list, depth, |a, b| {
+            PropertyPath::AlternativePath(Box::new(a), Box::new(b))
+        })
+    } else if let Some(path) = single(&sh::INVERSE_PATH)? {
+        Ok(PropertyPath::InversePath(Box::new(path)))
+    } else if let Some(path) = single(&sh::ZERO_OR_MORE_PATH)? {
+        Ok(PropertyPath::ZeroOrMorePath(Box::new(path)))
+    } else if let Some(path) = single(&sh::ONE_OR_MORE_PATH)? {
+        Ok(PropertyPath::OneOrMorePath(Box::new(path)))
+    } else if let Some(path) = single(&sh::ZERO_OR_ONE_PATH)? {
+        Ok(PropertyPath::ZeroOrOnePath(Box::new(path)))
+    } else {
+        Err(Error::syntax(format!(
+            "{} is not a valid SHACL property path",
+            path
+        )))
+    }
+}
+
+/// Reads a list of at least two paths and combines them
+fn fold_paths(
+    graph: &SimpleGraph,
+    list: &Term,
+    depth: usize,
+    combine: impl Fn(PropertyPath, PropertyPath) -> PropertyPath,
+) -> Result<PropertyPath> {
+    let mut paths = read_list(graph, list)?
+        .iter()
+        .map(|path| read_path(graph, path, depth + 1))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter();
+    match (paths.next(), paths.next()) {
+        (Some(first), Some(second)) => Ok(paths.fold(combine(first, second), combine)),
+        _ => Err(Error::syntax(format!(
+            "The SHACL path list {} should have at least two elements",
+            list
+        ))),
+    }
+}
+
+/// Reads the elements of a RDF list
+pub fn read_list(graph: &SimpleGraph, list: &Term) -> Result<Vec<Term>> {
+    let mut elements = Vec::default();
+    let mut visited = HashSet::new();
+    let mut current = list.clone();
+    while current != Term::from(rdf::NIL.clone()) {
+        let node = match &current {
+            Term::BlankNode(node) => NamedOrBlankNode::from(node.clone()),
+            Term::NamedNode(node) => NamedOrBlankNode::from(node.clone()),
+            Term::Literal(_) | Term::Triple(_) => {
+                return Err(Error::syntax(format!("{} is not a RDF list", list)))
+            }
+        };
+        if !visited.insert(node.clone()) {
+            return Err(Error::syntax(format!("The RDF list {} is cyclic", list)));
+        }
+        match (
+            graph.object_for_subject_predicate(&node, &rdf::FIRST),
+            graph.object_for_subject_predicate(&node, &rdf::REST),
+        ) {
+            (Some(first), Some(rest)) => {
+                elements.push(first.clone());
+                current = rest.clone();
+            }
+            _ => return Err(Error::syntax(format!("{} is not a RDF list", list))),
+        }
+    }
+    Ok(elements)
+}
+
+/// Checks if the node is a class and a shape i.e. if it has an [implicit class target](https://www.w3.org/TR/shacl/#implicit-targetClass)
+fn is_class(graph: &SimpleGraph, node: &Term) -> bool {
+    if let Term::Literal(_) = node {
+        return false;
+    }
+    let node = to_shape_node(node).unwrap();
+    let has_type = |class: &NamedNode| {
+        graph.contains(&Triple::new(node.clone(), rdf::TYPE.clone(), class.clone()))
+    };
+    (has_type(&rdfs::CLASS) || has_type(&owl::CLASS))
+        && (has_type(&sh::NODE_SHAPE) || has_type(&sh::PROPERTY_SHAPE))
+}
+
+fn read_node_kind(value: &Term) -> Result<NodeKind> {
+    let node_kind = to_named_node(value)?;
+    Ok(if node_kind == *sh::IRI {
+        NodeKind::Iri
+    } else if node_kind == *sh::BLANK_NODE {
+        NodeKind::BlankNode
+    } else if node_kind == *sh::LITERAL {
+        NodeKind::Literal
+    } else if node_kind == *sh::BLANK_NODE_OR_IRI {
+        NodeKind::BlankNodeOrIri
+    } else if node_kind == *sh::BLANK_NODE_OR_LITERAL {
+        NodeKind::BlankNodeOrLiteral
+    } else if node_kind == *sh::IRI_OR_LITERAL {
+        NodeKind::IriOrLiteral
+    } else {
+        return Err(Error::syntax(format!("Unknown SHACL node kind {}", value)));
+    })
+}
+
+fn to_shape_node(term: &Term) -> Result<NamedOrBlankNode> {
+    match term {
+        Term::NamedNode(node) => Ok(node.clone().into()),
+        Term::BlankNode(node) => Ok(node.clone().into()),
+        Term::Literal(_) | Term::Triple(_) => Err(Error::syntax(format!(
+            "A SHACL shape should be an IRI or a blank node: {}",
+            term
+        ))),
+    }
+}
+
+fn to_named_node(term: &Term) -> Result<NamedNode> {
+    match term {
+        Term::NamedNode(node) => Ok(node.clone()),
+        _ => Err(Error::syntax(format!("{} should be an IRI", term))),
+    }
+}
+
+fn to_string(term: &Term) -> Result<String> {
+    match term {
+        Term::Literal(literal) => Ok(literal.value().to_owned()),
+        Term::NamedNode(node) => Ok(node.as_str().to_owned()),
+        Term::BlankNode(_) | Term::Triple(_) => {
+            Err(Error::syntax(format!("{} should be a literal", term)))
+        }
+    }
+}
+
+fn to_usize(term: &Term) -> Result<usize> {
+    match term {
+        Term::Literal(literal) => literal.value().parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| Error::syntax(format!("{} should be a non negative integer literal", term)))
+}
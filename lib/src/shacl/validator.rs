@@ -0,0 +1,539 @@
+use crate::model::vocab::{rdf, rdfs};
+use crate::model::*;
+use crate::shacl::shapes::*;
+use crate::shacl::ValidationResult;
+use crate::sparql::algebra::*;
+use crate::sparql::model::NamedNodeOrVariable;
+use crate::sparql::{PreparedQuery, Query, QueryOptions, QueryPlan, QueryResult, Variable};
+use crate::{Error, MemoryRepository, Repository, RepositoryConnection, Result};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Maximal nesting of the shapes validated through `sh:node`, `sh:property`, `sh:and`, `sh:or` and `sh:not`
+const MAX_SHAPE_DEPTH: usize = 64;
+
+/// A row of the results of a SPARQL-based constraint
+struct SparqlSolution {
+    value: Option<Term>,
+    path: Option<Term>,
+    message: Option<Term>,
+}
+
+pub struct Validator<'a> {
+    shapes: &'a [Shape],
+    data: &'a SimpleGraph,
+    shapes_graph_name: Option<&'a NamedOrBlankNode>,
+    /// The data graph, and the shapes graph if it is named, loaded in a repository for the evaluation of the SPARQL-based constraints
+    repository: MemoryRepository,
+    sparql_plans: RefCell<HashMap<(usize, usize), QueryPlan>>,
+}
+
+impl<'a> Validator<'a> {
+    pub fn new(
+        shapes: &'a [Shape],
+        shapes_graph: Option<(&'a NamedOrBlankNode, &SimpleGraph)>,
+        data: &'a SimpleGraph,
+    ) -> Result<Self> {
+        let repository = MemoryRepository::default();
+        let has_sparql_constraints = shapes.iter().any(|shape| {
+            shape
+                .constraints
+                .iter()
+                .any(|constraint| matches!(constraint, Constraint::Sparql(_)))
+        });
+        if has_sparql_constraints {
+            let mut connection = (&repository).connection()?;
+            for triple in data {
+                connection.insert(&triple.clone().in_graph(None))?;
+            }
+            if let Some((name, graph)) = shapes_graph {
+                for triple in graph {
+                    connection.insert(&triple.clone().in_graph(Some(name.clone())))?;
+                }
+            }
+        }
+        Ok(Self {
+            shapes,
+            data,
+            shapes_graph_name: shapes_graph.map(|(name, _)| name),
+            repository,
+            sparql_plans: RefCell::default(),
+        })
+    }
+
+    /// Validates all the focus nodes of all the shapes
+    pub fn validate(&self) -> Result<Vec<ValidationResult>> {
+        let mut results = Vec::default();
+        for (id, shape) in self.shapes.iter().enumerate() {
+            for focus_node in self.focus_nodes(shape) {
+                results.extend(self.validate_shape(id, &focus_node, 0)?);
+            }
+        }
+        Ok(results)
+    }
+
+    fn focus_nodes(&self, shape: &Shape) -> BTreeSet<Term> {
+        let mut focus_nodes = BTreeSet::new();
+        for target in &shape.targets {
+            match target {
+                Target::Class(class) => {
+                    for class in self.sub_classes(class) {
+                        focus_nodes.extend(
+                            self.data
+                                .subjects_for_predicate_object(&rdf::TYPE, &class)
+                                .map(|node| node.clone().into()),
+                        );
+                    }
+                }
+                Target::Node(node) => {
+                    focus_nodes.insert(node.clone());
+                }
+                Target::SubjectsOf(predicate) => focus_nodes.extend(
+                    self.data
+                        .triples_for_predicate(predicate)
+                        .map(|t| t.subject().clone().into()),
+                ),
+                Target::ObjectsOf(predicate) => focus_nodes.extend(
+                    self.data
+                        .triples_for_predicate(predicate)
+                        .map(|t| t.object().clone()),
+                ),
+            }
+        }
+        focus_nodes
+    }
+
+    fn validate_shape(
+        &self,
+        id: usize,
+        focus_node: &Term,
+        depth: usize,
+    ) -> Result<Vec<ValidationResult>> {
+        let shape = &self.shapes[id];
+        if shape.deactivated {
+            return Ok(Vec::default());
+        }
+        if depth > MAX_SHAPE_DEPTH {
+            return Err(Error::unsupported(format!(
+                "The validation of the shape {} requires too many nested validations. Recursive shapes are not supported.",
+                shape.node
+            )));
+        }
+        let value_nodes = if let Some(path) = &shape.path {
+            self.evaluate_path(path, focus_node, false)
+        } else {
+            Some(focus_node.clone()).into_iter().collect()
+        };
+
+        let mut results = Vec::default();
+        for (constraint_id, constraint) in shape.constraints.iter().enumerate() {
+            let result = |value: Option<&Term>| ValidationResult {
+                focus_node: focus_node.clone(),
+                path: shape.path.clone(),
+                value: value.cloned(),
+                source_shape: shape.node.clone(),
+                source_constraint_component: constraint.component().clone(),
+                source_constraint: None,
+                severity: shape.severity.clone(),
+                messages: shape.messages.clone(),
+            };
+            match constraint {
+                Constraint::MinCount(min) => {
+                    if value_nodes.len() < *min {
+                        results.push(result(None))
+                    }
+                }
+                Constraint::MaxCount(max) => {
+                    if value_nodes.len() > *max {
+                        results.push(result(None))
+                    }
+                }
+                Constraint::HasValue(expected) => {
+                    if !value_nodes.contains(expected) {
+                        results.push(result(None))
+                    }
+                }
+                Constraint::Property(property_shape) => {
+                    for value in &value_nodes {
+                        results.extend(self.validate_shape(*property_shape, value, depth + 1)?);
+                    }
+                }
+                Constraint::Sparql(sparql) => {
+                    for solution in self.sparql_solutions(id, constraint_id, sparql, focus_node)? {
+                        results.push(ValidationResult {
+                            focus_node: focus_node.clone(),
+                            path: match &solution.path {
+                                Some(Term::NamedNode(path)) => {
+                                    Some(PropertyPath::PredicatePath(path.clone()))
+                                }
+                                _ => shape.path.clone(),
+                            },
+                            value: solution.value.clone().or_else(|| {
+                                if shape.path.is_none() {
+                                    Some(focus_node.clone())
+                                } else {
+                                    None
+                                }
+                            }),
+                            source_shape: shape.node.clone(),
+                            source_constraint_component: constraint.component().clone(),
+                            source_constraint: Some(sparql.node.clone()),
+                            severity: shape.severity.clone(),
+                            messages: if let Some(message) = &solution.message {
+                                vec![message.clone()]
+                            } else if sparql.messages.is_empty() {
+                                shape.messages.clone()
+                            } else {
+                                sparql.messages.clone()
+                            },
+                        });
+                    }
+                }
+                _ => {
+                    for value in &value_nodes {
+                        if !self.is_valid_value(constraint, value, depth)? {
+                            results.push(result(Some(value)))
+                        }
+                    }
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    /// Checks the constraints that apply independently to each value node
+    fn is_valid_value(&self, constraint: &Constraint, value: &Term, depth: usize) -> Result<bool> {
+        Ok(match constraint {
+            Constraint::Class(class) => self.is_instance(value, class),
+            Constraint::Datatype(datatype) => match value {
+                Term::Literal(literal) => literal.datatype() == datatype && is_well_formed(literal),
+                _ => false,
+            },
+            Constraint::NodeKind(node_kind) => node_kind.matches(value),
+            Constraint::MinLength(min) => {
+                matches!(string_value(value), Some(value) if value.chars().count() >= *min)
+            }
+            Constraint::MaxLength(max) => {
+                matches!(string_value(value), Some(value) if value.chars().count() <= *max)
+            }
+            Constraint::Pattern(regex) => {
+                matches!(string_value(value), Some(value) if regex.is_match(value))
+            }
+            Constraint::In(allowed) => allowed.contains(value),
+            Constraint::Node(shape) => self.conforms(*shape, value, depth)?,
+            Constraint::And(shapes) => {
+                for shape in shapes {
+                    if !self.conforms(*shape, value, depth)? {
+                        return Ok(false);
+                    }
+                }
+                true
+            }
+            Constraint::Or(shapes) => {
+                for shape in shapes {
+                    if self.conforms(*shape, value, depth)? {
+                        return Ok(true);
+                    }
+                }
+                false
+            }
+            Constraint::Not(shape) => !self.conforms(*shape, value, depth)?,
+            Constraint::MinCount(_)
+            | Constraint::MaxCount(_)
+            | Constraint::HasValue(_)
+            | Constraint::Property(_)
+            | Constraint::Sparql(_) => true,
+        })
+    }
+
+    fn conforms(&self, shape: usize, focus_node: &Term, depth: usize) -> Result<bool> {
+        Ok(self
+            .validate_shape(shape, focus_node, depth + 1)?
+            .is_empty())
+    }
+
+    /// Checks if the term is a [SHACL instance](https://www.w3.org/TR/shacl/#dfn-shacl-instance) of the class
+    fn is_instance(&self, term: &Term, class: &Term) -> bool {
+        let node = match to_subject(term) {
+            Some(node) => node,
+            None => return false,
+        };
+        let sub_classes = self.sub_classes(class);
+        let is_instance = self
+            .data
+            .objects_for_subject_predicate(&node, &rdf::TYPE)
+            .any(|class| sub_classes.contains(class));
+        is_instance
+    }
+
+    /// The class and all its transitive subclasses
+    fn sub_classes(&self, class: &Term) -> BTreeSet<Term> {
+        let mut sub_classes = BTreeSet::new();
+        let mut stack = vec![class.clone()];
+        while let Some(class) = stack.pop() {
+            if sub_classes.insert(class.clone()) {
+                stack.extend(
+                    self.data
+                        .subjects_for_predicate_object(&rdfs::SUB_CLASS_OF, &class)
+                        .map(|class| class.clone().into()),
+                );
+            }
+        }
+        sub_classes
+    }
+
+    /// Returns the nodes reachable from `start` using the path, or backward if `inverse` is set
+    fn evaluate_path(&self, path: &PropertyPath, start: &Term, inverse: bool) -> BTreeSet<Term> {
+        match path {
+            PropertyPath::PredicatePath(predicate) => {
+                if inverse {
+                    self.data
+                        .subjects_for_predicate_object(predicate, start)
+                        .map(|node| node.clone().into())
+                        .collect()
+                } else if let Some(subject) = to_subject(start) {
+                    self.data
+                        .objects_for_subject_predicate(&subject, predicate)
+                        .cloned()
+                        .collect()
+                } else {
+                    BTreeSet::new()
+                }
+            }
+            PropertyPath::InversePath(path) => self.evaluate_path(path, start, !inverse),
+            PropertyPath::SequencePath(a, b) => {
+                let (first, second) = if inverse { (b, a) } else { (a, b) };
+                self.evaluate_path(first, start, inverse)
+                    .iter()
+                    .flat_map(|middle| self.evaluate_path(second, middle, inverse))
+                    .collect()
+            }
+            PropertyPath::AlternativePath(a, b) => {
+                let mut result = self.evaluate_path(a, start, inverse);
+                result.extend(self.evaluate_path(b, start, inverse));
+                result
+            }
+            PropertyPath::ZeroOrMorePath(path) => self.transitive_closure(path, start, inverse),
+            PropertyPath::OneOrMorePath(path) => self
+                .evaluate_path(path, start, inverse)
+                .iter()
+                .flat_map(|node| self.transitive_closure(path, node, inverse))
+                .collect(),
+            PropertyPath::ZeroOrOnePath(path) => {
+                let mut result = self.evaluate_path(path, start, inverse);
+                result.insert(start.clone());
+                result
+            }
+            PropertyPath::NegatedPropertySet(excluded) => {
+                if inverse {
+                    self.data
+                        .triples_for_object(start)
+                        .filter(|t| !excluded.contains(t.predicate()))
+                        .map(|t| t.subject().clone().into())
+                        .collect()
+                } else if let Some(subject) = to_subject(start) {
+                    self.data
+                        .triples_for_subject(&subject)
+                        .filter(|t| !excluded.contains(t.predicate()))
+                        .map(|t| t.object().clone())
+                        .collect()
+                } else {
+                    BTreeSet::new()
+                }
+            }
+        }
+    }
+
+    /// The nodes reachable from `start` using the path zero or more times
+    fn transitive_closure(
+        &self,
+        path: &PropertyPath,
+        start: &Term,
+        inverse: bool,
+    ) -> BTreeSet<Term> {
+        let mut reachable = BTreeSet::new();
+        let mut stack = vec![start.clone()];
+        while let Some(node) = stack.pop() {
+            if reachable.insert(node.clone()) {
+                stack.extend(self.evaluate_path(path, &node, inverse));
+            }
+        }
+        reachable
+    }
+
+    /// Evaluates the query of a SPARQL-based constraint for a focus node
+    ///
+    /// `$this`, `$currentShape` and `$shapesGraph` are pre-bound and `$PATH` is replaced by the path of the shape.
+    /// `$shapesGraph` is only bound if the shapes have been read from a named graph.
+    fn sparql_solutions(
+        &self,
+        shape_id: usize,
+        constraint_id: usize,
+        constraint: &SparqlConstraint,
+        focus_node: &Term,
+    ) -> Result<Vec<SparqlSolution>> {
+        let shape = &self.shapes[shape_id];
+        let key = (shape_id, constraint_id);
+        if !self.sparql_plans.borrow().contains_key(&key) {
+            let mut query = Query::parse(&constraint.query, None)?;
+            if let Some(path) = &shape.path {
+                substitute_path(query.algebra_mut(), path);
+            }
+            self.sparql_plans
+                .borrow_mut()
+                .insert(key, QueryPlan::from_query(query)?);
+        }
+        let connection = (&self.repository).connection()?;
+        let prepared_query = connection
+            .prepare_query_plan(&self.sparql_plans.borrow()[&key], QueryOptions::default())?;
+
+        let mut bindings = BTreeMap::new();
+        bindings.insert(Variable::new("this"), focus_node.clone());
+        bindings.insert(Variable::new("currentShape"), shape.node.clone().into());
+        if let Some(shapes_graph_name) = self.shapes_graph_name {
+            bindings.insert(
+                Variable::new("shapesGraph"),
+                shapes_graph_name.clone().into(),
+            );
+        }
+        let results = prepared_query.exec(&bindings)?;
+        let bindings = match results {
+            QueryResult::Bindings(bindings) => bindings,
+            _ => {
+                return Err(Error::syntax(format!(
+                    "The sh:select query of {} should be a SELECT query",
+                    constraint.node
+                )))
+            }
+        };
+        let (variables, iter) = bindings.destruct();
+        let position = |name: &str| {
+            variables
+                .iter()
+                .position(|variable| variable.name().ok() == Some(name))
+        };
+        let (value, path, message) = (position("value"), position("path"), position("message"));
+        iter.map(|solution| {
+            let solution = solution?;
+            let get = |position: Option<usize>| position.and_then(|i| solution[i].clone());
+            Ok(SparqlSolution {
+                value: get(value),
+                path: get(path),
+                message: get(message),
+            })
+        })
+        .collect()
+    }
+}
+
+/// Replaces the `$PATH` predicates of the triple patterns, including the ones of the `EXISTS` patterns, by the path
+fn substitute_path(pattern: &mut GraphPattern, path: &PropertyPath) {
+    match pattern {
+        GraphPattern::BGP(patterns) => {
+            for pattern in patterns {
+                if let TripleOrPathPattern::Triple(triple) = pattern {
+                    if let NamedNodeOrVariable::Variable(variable) = &triple.predicate {
+                        if variable.name().ok() == Some("PATH") {
+                            *pattern = PathPattern::new(
+                                triple.subject.clone(),
+                                path.clone(),
+                                triple.object.clone(),
+                            )
+                            .into();
+                        }
+                    }
+                }
+            }
+        }
+        GraphPattern::Join(a, b) | GraphPattern::Union(a, b) | GraphPattern::Minus(a, b) => {
+            substitute_path(a, path);
+            substitute_path(b, path);
+        }
+        GraphPattern::LeftJoin(a, b, e) => {
+            substitute_path(a, path);
+            substitute_path(b, path);
+            substitute_path_in_expression(e, path);
+        }
+        GraphPattern::Filter(e, p) | GraphPattern::Extend(p, _, e) => {
+            substitute_path(p, path);
+            substitute_path_in_expression(e, path);
+        }
+        GraphPattern::OrderBy(p, comparators) => {
+            substitute_path(p, path);
+            for comparator in comparators {
+                match comparator {
+                    OrderComparator::Asc(e) | OrderComparator::Desc(e) => {
+                        substitute_path_in_expression(e, path)
+                    }
+                }
+            }
+        }
+        GraphPattern::Graph(_, p)
+        | GraphPattern::Service(_, p, _)
+        | GraphPattern::AggregateJoin(GroupPattern(_, p), _)
+        | GraphPattern::Project(p, _)
+        | GraphPattern::Distinct(p)
+        | GraphPattern::Reduced(p)
+        | GraphPattern::Slice(p, _, _) => substitute_path(p, path),
+        GraphPattern::Data(_) => (),
+    }
+}
+
+fn substitute_path_in_expression(expression: &mut Expression, path: &PropertyPath) {
+    match expression {
+        Expression::Or(a, b)
+        | Expression::And(a, b)
+        | Expression::Equal(a, b)
+        | Expression::NotEqual(a, b)
+        | Expression::Greater(a, b)
+        | Expression::GreaterOrEq(a, b)
+        | Expression::Lower(a, b)
+        | Expression::LowerOrEq(a, b)
+        | Expression::Add(a, b)
+        | Expression::Sub(a, b)
+        | Expression::Mul(a, b)
+        | Expression::Div(a, b) => {
+            substitute_path_in_expression(a, path);
+            substitute_path_in_expression(b, path);
+        }
+        Expression::In(a, l) | Expression::NotIn(a, l) => {
+            substitute_path_in_expression(a, path);
+            for e in l {
+                substitute_path_in_expression(e, path);
+            }
+        }
+        Expression::UnaryPlus(e) | Expression::UnaryMinus(e) | Expression::UnaryNot(e) => {
+            substitute_path_in_expression(e, path)
+        }
+        Expression::FunctionCall(_, args) => {
+            for e in args {
+                substitute_path_in_expression(e, path);
+            }
+        }
+        Expression::Exists(p) => substitute_path(p, path),
+        Expression::Constant(_) | Expression::Bound(_) => (),
+    }
+}
+
+fn to_subject(term: &Term) -> Option<NamedOrBlankNode> {
+    match term {
+        Term::NamedNode(node) => Some(node.clone().into()),
+        Term::BlankNode(node) => Some(node.clone().into()),
+        Term::Literal(_) => None,
+        Term::Triple(triple) => Some(NamedOrBlankNode::Triple(triple.clone())),
+    }
+}
+
+/// The string used by the `sh:minLength`, `sh:maxLength` and `sh:pattern` constraints
+fn string_value(term: &Term) -> Option<&str> {
+    match term {
+        Term::NamedNode(node) => Some(node.as_str()),
+        Term::Literal(literal) => Some(literal.value()),
+        Term::BlankNode(_) | Term::Triple(_) => None,
+    }
+}
+
+/// Checks if the lexical form of the literal is valid for its datatype
+pub(crate) fn is_well_formed(literal: &Literal) -> bool {
+    check_literal(literal).is_ok()
+}
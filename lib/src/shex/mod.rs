@@ -0,0 +1,602 @@
+//! [ShEx](http://shex.io/shex-semantics/) validation.
+//!
+//! Schemas could be read from the [ShExC](http://shex.io/shex-semantics/#shexc) compact syntax or from the
+//! [ShExJ](http://shex.io/shex-semantics/#shexj) JSON syntax.
+//! Nodes are validated against the shapes associated to them by a [shape map](http://shex.io/shape-map/).
+//! `EXTERNAL` shapes, `IMPORT`s and stem ranges with exclusions are not supported and semantic actions are ignored.
+//!
+//! Usage example:
+//! ```
+//! use rudf::model::*;
+//! use rudf::shex::{Schema, ShapeMap};
+//!
+//! let schema = Schema::parse("PREFIX ex: <http://example.com/>
+//!     PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>
+//!     ex:Person { ex:name xsd:string ; ex:knows @ex:Person * }", None).unwrap();
+//!
+//! let ex = |name: &str| NamedNode::parse(format!("http://example.com/{}", name)).unwrap();
+//! let mut data = SimpleGraph::default();
+//! data.insert(Triple::new(ex("alice"), ex("name"), Literal::from("Alice")));
+//! data.insert(Triple::new(ex("alice"), ex("knows"), ex("bob")));
+//!
+//! let shape_map = ShapeMap::parse("ex:alice@ex:Person, ex:bob@ex:Person", &schema).unwrap();
+//! let results = schema.validate(&data, &shape_map).unwrap();
+//! assert!(!results[0].is_conformant()); // bob has no name
+//! assert!(!results[1].is_conformant());
+//! assert!(results[1].reason().is_some());
+//!
+//! data.insert(Triple::new(ex("bob"), ex("name"), Literal::from("Bob")));
+//! let results = schema.validate(&data, &shape_map).unwrap();
+//! assert!(results.iter().all(|result| result.is_conformant()));
+//! ```
+
+mod model;
+mod parser;
+mod shexj;
+mod validator;
+
+use crate::model::*;
+use crate::shex::model::*;
+use crate::shex::parser::{read_shape_map, read_shexc_schema};
+use crate::shex::shexj::read_shexj_schema;
+use crate::shex::validator::Validator;
+use crate::{Error, RepositoryConnection, Result};
+use std::collections::{BTreeMap, HashSet};
+
+pub use crate::shex::model::ShapeLabel;
+
+/// Maximal nesting of the inclusions of triple expressions
+const MAX_INCLUDE_DEPTH: usize = 64;
+
+/// A ShEx schema
+#[derive(Debug, Clone)]
+pub struct Schema {
+    prefixes: BTreeMap<String, String>,
+    start: Option<ShapeExpression>,
+    shapes: BTreeMap<ShapeLabel, ShapeExpression>,
+}
+
+impl Schema {
+    /// Parses a schema written using the [ShExC](http://shex.io/shex-semantics/#shexc) syntax
+    pub fn parse(schema: &str, base_iri: Option<&str>) -> Result<Self> {
+        read_shexc_schema(schema, base_iri)
+    }
+
+    /// Parses a schema written using the [ShExJ](http://shex.io/shex-semantics/#shexj) syntax
+    pub fn parse_json(schema: &str) -> Result<Self> {
+        read_shexj_schema(schema)
+    }
+
+    pub(crate) fn new(
+        prefixes: BTreeMap<String, String>,
+        start: Option<ShapeExpression>,
+        shapes: BTreeMap<ShapeLabel, ShapeExpression>,
+        triple_expressions: BTreeMap<ShapeLabel, TripleExpression>,
+    ) -> Result<Self> {
+        let builder = SchemaBuilder {
+            shapes: &shapes,
+            triple_expressions: &triple_expressions,
+        };
+        let start = match start {
+            Some(start) => Some(builder.build_shape_expression(start)?),
+            None => None,
+        };
+        let shapes = shapes
+            .iter()
+            .map(|(label, expression)| {
+                Ok((
+                    label.clone(),
+                    builder.build_shape_expression(expression.clone())?,
+                ))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            prefixes,
+            start,
+            shapes,
+        })
+    }
+
+    /// The prefixes declared in the schema
+    pub fn prefixes(&self) -> &BTreeMap<String, String> {
+        &self.prefixes
+    }
+
+    /// The labels of the shape expressions declared in the schema
+    pub fn labels(&self) -> impl Iterator<Item = &ShapeLabel> {
+        self.shapes.keys()
+    }
+
+    /// Validates the nodes of a data graph against the shapes given by a shape map
+    ///
+    /// Returns a result for each node and shape association, in the shape map order.
+    pub fn validate(
+        &self,
+        data: &impl DataGraph,
+        shape_map: &ShapeMap,
+    ) -> Result<Vec<ValidationResult>> {
+        let validator = Validator::new(self, data);
+        let mut results = Vec::default();
+        for (selector, shape) in &shape_map.associations {
+            for node in select_nodes(data, selector)? {
+                let reason = validator.validate(&node, shape.as_ref())?;
+                results.push(ValidationResult {
+                    node,
+                    shape: shape.clone(),
+                    reason,
+                })
+            }
+        }
+        Ok(results)
+    }
+
+    fn shape(&self, label: &ShapeLabel) -> Result<&ShapeExpression> {
+        self.shapes
+            .get(label)
+            .ok_or_else(|| Error::evaluation(format!("The shape {} does not exist", label)))
+    }
+
+    fn start(&self) -> Result<&ShapeExpression> {
+        self.start
+            .as_ref()
+            .ok_or_else(|| Error::evaluation("The schema does not define a START shape"))
+    }
+}
+
+/// Inlines the included triple expressions and numbers the triple constraints of each shape
+struct SchemaBuilder<'a> {
+    shapes: &'a BTreeMap<ShapeLabel, ShapeExpression>,
+    triple_expressions: &'a BTreeMap<ShapeLabel, TripleExpression>,
+}
+
+impl<'a> SchemaBuilder<'a> {
+    fn build_shape_expression(&self, expression: ShapeExpression) -> Result<ShapeExpression> {
+        Ok(match expression {
+            ShapeExpression::Or(e) => ShapeExpression::Or(self.build_shape_expressions(e)?),
+            ShapeExpression::And(e) => ShapeExpression::And(self.build_shape_expressions(e)?),
+            ShapeExpression::Not(e) => {
+                ShapeExpression::Not(Box::new(self.build_shape_expression(*e)?))
+            }
+            ShapeExpression::Shape(mut shape) => {
+                if let Some(expression) = shape.expression {
+                    shape.expression = Some(self.build_triple_expression(expression, &mut 0, 0)?);
+                }
+                ShapeExpression::Shape(shape)
+            }
+            ShapeExpression::Reference(label) => {
+                if !self.shapes.contains_key(&label) {
+                    return Err(Error::syntax(format!(
+                        "The shape {} is referenced but not defined",
+                        label
+                    )));
+                }
+                ShapeExpression::Reference(label)
+            }
+            e => e,
+        })
+    }
+
+    fn build_shape_expressions(
+        &self,
+        expressions: Vec<ShapeExpression>,
+    ) -> Result<Vec<ShapeExpression>> {
+        expressions
+            .into_iter()
+            .map(|e| self.build_shape_expression(e))
+            .collect()
+    }
+
+    fn build_triple_expression(
+        &self,
+        expression: TripleExpression,
+        next_id: &mut usize,
+        depth: usize,
+    ) -> Result<TripleExpression> {
+        Ok(match expression {
+            TripleExpression::EachOf(e, c) => {
+                TripleExpression::EachOf(self.build_triple_expressions(e, next_id, depth)?, c)
+            }
+            TripleExpression::OneOf(e, c) => {
+                TripleExpression::OneOf(self.build_triple_expressions(e, next_id, depth)?, c)
+            }
+            TripleExpression::TripleConstraint(mut constraint) => {
+                constraint.id = *next_id;
+                *next_id += 1;
+                if let Some(value) = constraint.value {
+                    constraint.value = Some(Box::new(self.build_shape_expression(*value)?));
+                }
+                TripleExpression::TripleConstraint(constraint)
+            }
+            TripleExpression::Include(label) => {
+                if depth > MAX_INCLUDE_DEPTH {
+                    return Err(Error::syntax(format!(
+                        "The inclusion of the triple expression {} is cyclic",
+                        label
+                    )));
+                }
+                let included = self.triple_expressions.get(&label).ok_or_else(|| {
+                    Error::syntax(format!(
+                        "The triple expression {} is included but not defined",
+                        label
+                    ))
+                })?;
+                self.build_triple_expression(included.clone(), next_id, depth + 1)?
+            }
+        })
+    }
+
+    fn build_triple_expressions(
+        &self,
+        expressions: Vec<TripleExpression>,
+        next_id: &mut usize,
+        depth: usize,
+    ) -> Result<Vec<TripleExpression>> {
+        expressions
+            .into_iter()
+            .map(|e| self.build_triple_expression(e, next_id, depth))
+            .collect()
+    }
+}
+
+/// A [shape map](http://shex.io/shape-map/) associating nodes to the shapes they should be validated against
+#[derive(Debug, Clone, Default)]
+pub struct ShapeMap {
+    associations: Vec<(NodeSelector, Option<ShapeLabel>)>,
+}
+
+impl ShapeMap {
+    /// Parses a [query shape map](http://shex.io/shape-map/#query-shape-map) like `<http://example.com/alice>@<http://example.com/Person>`
+    ///
+    /// The prefixes declared in the schema could be used.
+    /// The `{FOCUS p o}` and `{s p FOCUS}` triple patterns are supported with `_` as a wildcard.
+    pub fn parse(shape_map: &str, schema: &Schema) -> Result<Self> {
+        Ok(Self {
+            associations: read_shape_map(shape_map, &schema.prefixes)?,
+        })
+    }
+
+    /// Associates a node to a shape
+    pub fn insert(&mut self, node: impl Into<Term>, shape: impl Into<ShapeLabel>) {
+        self.associations
+            .push((NodeSelector::Node(node.into()), Some(shape.into())))
+    }
+
+    /// Associates a node to the `START` shape of the schema
+    pub fn insert_start(&mut self, node: impl Into<Term>) {
+        self.associations
+            .push((NodeSelector::Node(node.into()), None))
+    }
+}
+
+/// The result of the validation of a node against a shape
+#[derive(Debug, Clone)]
+pub struct ValidationResult {
+    node: Term,
+    shape: Option<ShapeLabel>,
+    reason: Option<String>,
+}
+
+impl ValidationResult {
+    /// The validated node
+    pub fn node(&self) -> &Term {
+        &self.node
+    }
+
+    /// The shape the node has been validated against or `None` for the `START` shape
+    pub fn shape(&self) -> Option<&ShapeLabel> {
+        self.shape.as_ref()
+    }
+
+    /// Checks if the node conforms to the shape
+    pub fn is_conformant(&self) -> bool {
+        self.reason.is_none()
+    }
+
+    /// The reason why the node does not conform to the shape
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+}
+
+/// A graph that could be validated against a ShEx schema
+///
+/// It is implemented by [`SimpleGraph`](../model/struct.SimpleGraph.html) and by the repository connections.
+/// The triples of all the graphs of a repository are validated together.
+pub trait DataGraph {
+    /// The triples with the given subject
+    fn triples_for_subject(&self, subject: &NamedOrBlankNode) -> Result<Vec<Triple>>;
+
+    /// The triples with the given object
+    fn triples_for_object(&self, object: &Term) -> Result<Vec<Triple>>;
+
+    /// The triples with the given predicate
+    fn triples_for_predicate(&self, predicate: &NamedNode) -> Result<Vec<Triple>>;
+}
+
+impl DataGraph for SimpleGraph {
+    fn triples_for_subject(&self, subject: &NamedOrBlankNode) -> Result<Vec<Triple>> {
+        Ok(SimpleGraph::triples_for_subject(self, subject)
+            .cloned()
+            .collect())
+    }
+
+    fn triples_for_object(&self, object: &Term) -> Result<Vec<Triple>> {
+        Ok(SimpleGraph::triples_for_object(self, object)
+            .cloned()
+            .collect())
+    }
+
+    fn triples_for_predicate(&self, predicate: &NamedNode) -> Result<Vec<Triple>> {
+        Ok(SimpleGraph::triples_for_predicate(self, predicate)
+            .cloned()
+            .collect())
+    }
+}
+
+impl<T: RepositoryConnection> DataGraph for T {
+    fn triples_for_subject(&self, subject: &NamedOrBlankNode) -> Result<Vec<Triple>> {
+        union_of_graphs(self.quads_for_pattern(Some(subject), None, None, None))
+    }
+
+    fn triples_for_object(&self, object: &Term) -> Result<Vec<Triple>> {
+        union_of_graphs(self.quads_for_pattern(None, None, Some(object), None))
+    }
+
+    fn triples_for_predicate(&self, predicate: &NamedNode) -> Result<Vec<Triple>> {
+        union_of_graphs(self.quads_for_pattern(None, Some(predicate), None, None))
+    }
+}
+
+fn union_of_graphs(quads: impl Iterator<Item = Result<Quad>>) -> Result<Vec<Triple>> {
+    let mut seen = HashSet::new();
+    let mut triples = Vec::default();
+    for quad in quads {
+        let triple = quad?.into_triple();
+        if seen.insert(triple.clone()) {
+            triples.push(triple);
+        }
+    }
+    Ok(triples)
+}
+
+fn select_nodes(data: &impl DataGraph, selector: &NodeSelector) -> Result<Vec<Term>> {
+    let mut nodes = match selector {
+        NodeSelector::Node(node) => return Ok(vec![node.clone()]),
+        NodeSelector::SubjectsOf(predicate, Some(object)) => data
+            .triples_for_object(object)?
+            .into_iter()
+            .filter(|t| t.predicate() == predicate)
+            .map(|t| t.subject_owned().into())
+            .collect::<Vec<_>>(),
+        NodeSelector::SubjectsOf(predicate, None) => data
+            .triples_for_predicate(predicate)?
+            .into_iter()
+            .map(|t| t.subject_owned().into())
+            .collect(),
+        NodeSelector::ObjectsOf(Some(subject), predicate) => data
+            .triples_for_subject(subject)?
+            .into_iter()
+            .filter(|t| t.predicate() == predicate)
+            .map(|t| t.object_owned())
+            .collect(),
+        NodeSelector::ObjectsOf(None, predicate) => data
+            .triples_for_predicate(predicate)?
+            .into_iter()
+            .map(|t| t.object_owned())
+            .collect(),
+    };
+    nodes.sort();
+    nodes.dedup();
+    Ok(nodes)
+}
+
+#[cfg(test)]
+const TEST_SCHEMA: &str = "PREFIX ex: <http://example.com/>
+PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>
+start = @ex:Person
+ex:Person CLOSED EXTRA a {
+    a [ex:Human ex:Robot~] ;
+    ex:name xsd:string MINLENGTH 2 ;
+    ex:nickname . ? ;
+    ex:age xsd:integer MININCLUSIVE 0 MAXEXCLUSIVE 150 ? ;
+    ex:email IRI /^mailto:/ * ;
+    ex:knows @ex:Person OR @ex:Organization {0,3} ;
+    ^ex:employs @ex:Organization ?
+}
+ex:Organization { ex:label LITERAL + ; ex:employs . * ; &ex:address }
+ex:Place { $ex:address ( ex:city . ; ex:zip /^[0-9]{5}$/ ? ) }
+ex:NotPerson NOT @ex:Person
+ex:NickNamed @ex:Person AND { ex:nickname . }";
+
+#[cfg(test)]
+const TEST_DATA: &str = "@prefix ex: <http://example.com/> .
+ex:alice a ex:Human, ex:Other ; ex:name \"Alice\" ; ex:age 30 ;
+    ex:email <mailto:alice@example.com> ; ex:knows ex:bob, ex:acme .
+ex:bob a <http://example.com/Robot/r2> ; ex:name \"Bob\" ; ex:nickname \"B\" .
+ex:acme ex:label \"ACME\" ; ex:city \"Paris\" ; ex:employs ex:alice .
+ex:carol a ex:Human ; ex:name \"C\" .
+ex:dave a ex:Human ; ex:name \"Dave\" ; ex:age 200 .
+ex:erin a ex:Human ; ex:name \"Erin\" ; ex:hobby \"chess\" .
+ex:frank a ex:Human ; ex:name \"Frank\" ; ex:email <http://example.com/frank> .
+ex:gina a ex:Alien ; ex:name \"Gina\" .
+ex:hal a ex:Human ; ex:name \"Hal\" ; ex:knows ex:carol .
+ex:initech ex:label \"Initech\" ; ex:city \"Austin\" ; ex:zip \"787\" .";
+
+#[cfg(test)]
+fn conformances(schema: &Schema, data: &impl DataGraph, shape_map: &str) -> Vec<(String, bool)> {
+    schema
+        .validate(data, &ShapeMap::parse(shape_map, schema).unwrap())
+        .unwrap()
+        .into_iter()
+        .map(|result| {
+            assert_eq!(result.is_conformant(), result.reason().is_none());
+            (
+                result.node().to_string().replace("http://example.com/", ""),
+                result.is_conformant(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_validation() {
+    use crate::{GraphSyntax, MemoryRepository, Repository};
+
+    let schema = Schema::parse(TEST_SCHEMA, None).unwrap();
+    let repository = MemoryRepository::default();
+    let mut connection = repository.connection().unwrap();
+    connection
+        .load_graph(TEST_DATA.as_bytes(), GraphSyntax::Turtle, None, None)
+        .unwrap();
+    let graph = connection
+        .quads_for_pattern(None, None, None, None)
+        .map(|quad| quad.map(Quad::into_triple))
+        .collect::<Result<SimpleGraph>>()
+        .unwrap();
+
+    let shape_map = "ex:alice@ex:Person, ex:bob@START, ex:carol@ex:Person, ex:dave@ex:Person, \
+         ex:erin@ex:Person, ex:frank@ex:Person, ex:gina@ex:Person, ex:hal@ex:Person, \
+         ex:acme@ex:Organization, ex:initech@ex:Organization, \
+         ex:alice@ex:NotPerson, ex:carol@ex:NotPerson, \
+         ex:alice@ex:NickNamed, ex:bob@ex:NickNamed";
+    let expected = vec![
+        ("<alice>", true),  // EXTRA types, optional values, inverse and reference to an OR
+        ("<bob>", true),    // IRI stem in a value set and START shape
+        ("<carol>", false), // MINLENGTH
+        ("<dave>", false),  // MAXEXCLUSIVE
+        ("<erin>", false),  // CLOSED
+        ("<frank>", false), // pattern on an IRI
+        ("<gina>", false),  // value set
+        ("<hal>", false),   // non conformant reference
+        ("<acme>", true),   // included triple expression
+        ("<initech>", false), // pattern in an included triple expression
+        ("<alice>", false), // NOT
+        ("<carol>", true),  // NOT
+        ("<alice>", false), // AND
+        ("<bob>", true),    // AND
+    ]
+    .into_iter()
+    .map(|(node, conformant)| (node.to_owned(), conformant))
+    .collect::<Vec<_>>();
+    assert_eq!(conformances(&schema, &graph, shape_map), expected);
+    assert_eq!(conformances(&schema, &connection, shape_map), expected);
+
+    // Triple pattern node selectors
+    assert_eq!(
+        conformances(&schema, &graph, "{FOCUS ex:name _}@ex:Person"),
+        vec![
+            ("<alice>".to_owned(), true),
+            ("<bob>".to_owned(), true),
+            ("<carol>".to_owned(), false),
+            ("<dave>".to_owned(), false),
+            ("<erin>".to_owned(), false),
+            ("<frank>".to_owned(), false),
+            ("<gina>".to_owned(), false),
+            ("<hal>".to_owned(), false),
+        ]
+    );
+    assert_eq!(
+        conformances(&schema, &connection, "{ex:alice ex:knows FOCUS}@ex:Person"),
+        vec![("<acme>".to_owned(), false), ("<bob>".to_owned(), true)]
+    );
+
+    // The reasons explain the failures
+    let results = schema
+        .validate(
+            &graph,
+            &ShapeMap::parse("ex:erin@ex:Person", &schema).unwrap(),
+        )
+        .unwrap();
+    assert!(results[0].reason().unwrap().contains("hobby"));
+}
+
+#[test]
+fn test_shexj() {
+    let schema = Schema::parse_json(
+        r#"{
+        "@context": "http://www.w3.org/ns/shex.jsonld",
+        "type": "Schema",
+        "start": "http://example.com/Person",
+        "shapes": [
+            {
+                "type": "Shape",
+                "id": "http://example.com/Person",
+                "closed": true,
+                "expression": {
+                    "type": "EachOf",
+                    "expressions": [
+                        {
+                            "type": "TripleConstraint",
+                            "predicate": "http://example.com/name",
+                            "valueExpr": {
+                                "type": "NodeConstraint",
+                                "datatype": "http://www.w3.org/2001/XMLSchema#string",
+                                "minlength": 2
+                            }
+                        },
+                        {
+                            "type": "TripleConstraint",
+                            "predicate": "http://example.com/knows",
+                            "valueExpr": "http://example.com/Person",
+                            "min": 0,
+                            "max": -1
+                        }
+                    ]
+                }
+            }
+        ]
+    }"#,
+    )
+    .unwrap();
+    let shexc = Schema::parse(
+        "PREFIX ex: <http://example.com/>
+        PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>
+        start = @ex:Person
+        ex:Person CLOSED { ex:name xsd:string MINLENGTH 2 ; ex:knows @ex:Person * }",
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        format!("{:?}", schema),
+        format!(
+            "{:?}",
+            Schema {
+                prefixes: BTreeMap::default(),
+                ..shexc
+            }
+        )
+    );
+
+    let ex = |name: &str| NamedNode::parse(format!("http://example.com/{}", name)).unwrap();
+    let mut data = SimpleGraph::default();
+    data.insert(Triple::new(ex("alice"), ex("name"), Literal::from("Alice")));
+    data.insert(Triple::new(ex("alice"), ex("knows"), ex("bob")));
+    data.insert(Triple::new(ex("bob"), ex("name"), Literal::from("B")));
+    let mut shape_map = ShapeMap::default();
+    shape_map.insert_start(ex("alice"));
+    shape_map.insert(ex("alice"), ex("Person"));
+    let results = schema.validate(&data, &shape_map).unwrap();
+    assert!(results.iter().all(|result| !result.is_conformant()));
+    assert_eq!(results[0].shape(), None);
+    assert_eq!(results[1].shape(), Some(&ShapeLabel::from(ex("Person"))));
+
+    data.remove(&Triple::new(ex("bob"), ex("name"), Literal::from("B")));
+    data.insert(Triple::new(ex("bob"), ex("name"), Literal::from("Bob")));
+    let results = schema.validate(&data, &shape_map).unwrap();
+    assert!(results.iter().all(|result| result.is_conformant()));
+}
+
+#[test]
+fn test_schema_errors() {
+    for schema in &[
+        "PREFIX ex: <http://example.com/> ex:S { ex:p @ex:Missing }",
+        "PREFIX ex: <http://example.com/> ex:S { &ex:missing }",
+        "PREFIX ex: <http://example.com/> ex:S { $ex:t ( ex:p . ; &ex:t ) }",
+        "ex:S { }",
+        "IMPORT <http://example.com/schema>",
+    ] {
+        assert!(Schema::parse(schema, None).is_err(), "{}", schema);
+    }
+    assert!(Schema::parse_json(r#"{"type": "Schema", "shapes": [{"type": "Shape"}]}"#).is_err());
+    assert!(Schema::parse_json(r#"{"type": "Other"}"#).is_err());
+}
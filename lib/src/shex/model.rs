@@ -0,0 +1,153 @@
+//! The ShEx abstract syntax, following the structure of [ShExJ](http://shex.io/shex-semantics/#shexj)
+
+use crate::model::*;
+use regex::Regex;
+use std::fmt;
+
+/// The label of a shape expression
+#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Clone, Hash)]
+pub enum ShapeLabel {
+    Iri(NamedNode),
+    BlankNode(String),
+}
+
+impl fmt::Display for ShapeLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShapeLabel::Iri(iri) => iri.fmt(f),
+            ShapeLabel::BlankNode(id) => write!(f, "_:{}", id),
+        }
+    }
+}
+
+impl From<NamedNode> for ShapeLabel {
+    fn from(iri: NamedNode) -> Self {
+        ShapeLabel::Iri(iri)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ShapeExpression {
+    Or(Vec<ShapeExpression>),
+    And(Vec<ShapeExpression>),
+    Not(Box<ShapeExpression>),
+    NodeConstraint(NodeConstraint),
+    Shape(Shape),
+    External,
+    Reference(ShapeLabel),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NodeConstraint {
+    pub node_kind: Option<NodeKind>,
+    pub datatype: Option<NamedNode>,
+    pub facets: Vec<Facet>,
+    pub values: Option<Vec<ValueSetValue>>,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum NodeKind {
+    Iri,
+    BlankNode,
+    NonLiteral,
+    Literal,
+}
+
+#[derive(Debug, Clone)]
+pub enum Facet {
+    Length(usize),
+    MinLength(usize),
+    MaxLength(usize),
+    Pattern(Regex),
+    MinInclusive(f64),
+    MinExclusive(f64),
+    MaxInclusive(f64),
+    MaxExclusive(f64),
+    TotalDigits(usize),
+    FractionDigits(usize),
+}
+
+#[derive(Debug, Clone)]
+pub enum ValueSetValue {
+    Iri(NamedNode),
+    IriStem(String),
+    Literal(Literal),
+    LiteralStem(String),
+    Language(String),
+    LanguageStem(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Shape {
+    pub closed: bool,
+    pub extra: Vec<NamedNode>,
+    pub expression: Option<TripleExpression>,
+}
+
+/// The cardinality of a triple expression: the maximum is `None` if unbounded
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub struct Cardinality {
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+impl Default for Cardinality {
+    fn default() -> Self {
+        Self {
+            min: 1,
+            max: Some(1),
+        }
+    }
+}
+
+impl Cardinality {
+    pub fn new(min: usize, max: Option<usize>) -> Self {
+        Self { min, max }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum TripleExpression {
+    EachOf(Vec<TripleExpression>, Cardinality),
+    OneOf(Vec<TripleExpression>, Cardinality),
+    TripleConstraint(TripleConstraint),
+    /// An inclusion of a labeled triple expression, removed when the schema is built
+    Include(ShapeLabel),
+}
+
+#[derive(Debug, Clone)]
+pub struct TripleConstraint {
+    /// Unique index of the triple constraint in its shape, set when the schema is built
+    pub id: usize,
+    pub inverse: bool,
+    pub predicate: NamedNode,
+    pub value: Option<Box<ShapeExpression>>,
+    pub cardinality: Cardinality,
+}
+
+impl TripleConstraint {
+    pub fn new(
+        inverse: bool,
+        predicate: NamedNode,
+        value: Option<ShapeExpression>,
+        cardinality: Cardinality,
+    ) -> Self {
+        Self {
+            id: 0,
+            inverse,
+            predicate,
+            value: value.map(Box::new),
+            cardinality,
+        }
+    }
+}
+
+/// A node selector of a [shape map](http://shex.io/shape-map/)
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub enum NodeSelector {
+    Node(Term),
+    /// The subjects of the triples with the given predicate and object, or any object if `None`
+    SubjectsOf(NamedNode, Option<Term>),
+    /// The objects of the triples with the given subject and predicate, or any subject if `None`
+    ObjectsOf(Option<NamedOrBlankNode>, NamedNode),
+}
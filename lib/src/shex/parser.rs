@@ -0,0 +1,142 @@
+mod grammar {
+    #![allow(
+        clippy::suspicious_else_formatting,
+        clippy::len_zero,
+        clippy::single_match,
+        clippy::unit_arg,
+        clippy::naive_bytecount,
+        clippy::cognitive_complexity,
+        clippy::many_single_char_names,
+        clippy::type_complexity,
+        ellipsis_inclusive_range_patterns
+    )]
+
+    use crate::model::*;
+    use crate::shex::model::*;
+    use crate::shex::Schema;
+    use crate::sparql::parser::{unescape_echars, unescape_pn_local, unescape_unicode_codepoints};
+    use crate::{SyntaxError, TextPosition};
+    use regex::{Regex, RegexBuilder};
+    use rio_api::iri::{Iri, IriParseError};
+    use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+    const REGEX_SIZE_LIMIT: usize = 1_000_000;
+
+    pub struct ParserState {
+        base_iri: Option<Iri<String>>,
+        namespaces: HashMap<String, String>,
+        start: Option<ShapeExpression>,
+        shapes: BTreeMap<ShapeLabel, ShapeExpression>,
+        triple_expressions: BTreeMap<ShapeLabel, TripleExpression>,
+    }
+
+    impl ParserState {
+        fn new(base_iri: Option<&str>) -> super::super::super::Result<Self> {
+            Ok(Self {
+                base_iri: if let Some(base_iri) = base_iri {
+                    Some(Iri::parse(base_iri.to_owned())?)
+                } else {
+                    None
+                },
+                namespaces: HashMap::default(),
+                start: None,
+                shapes: BTreeMap::default(),
+                triple_expressions: BTreeMap::default(),
+            })
+        }
+
+        fn parse_iri(&self, iri: &str) -> Result<Iri<String>, IriParseError> {
+            if let Some(base_iri) = &self.base_iri {
+                base_iri.resolve(iri)
+            } else {
+                Iri::parse(iri.to_owned())
+            }
+        }
+    }
+
+    /// Builds a regular expression from a ShEx pattern and its XPath flags
+    pub fn build_pattern(pattern: &str, flags: &str) -> Result<Regex, regex::Error> {
+        let mut regex_builder = RegexBuilder::new(pattern);
+        regex_builder.size_limit(REGEX_SIZE_LIMIT);
+        for flag in flags.chars() {
+            match flag {
+                's' => {
+                    regex_builder.dot_matches_new_line(true);
+                }
+                'm' => {
+                    regex_builder.multi_line(true);
+                }
+                'i' => {
+                    regex_builder.case_insensitive(true);
+                }
+                'x' => {
+                    regex_builder.ignore_whitespace(true);
+                }
+                _ => (),
+            }
+        }
+        regex_builder.build()
+    }
+
+    include!(concat!(env!("OUT_DIR"), "/shexc_grammar.rs"));
+
+    fn to_syntax_error(input: &str, error: &ParseError) -> SyntaxError {
+        let position = TextPosition::new(error.line - 1, error.column - 1);
+        let line_start = input[..error.offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = input[error.offset..]
+            .find('\n')
+            .map_or(input.len(), |i| error.offset + i);
+        let expected = error
+            .expected
+            .iter()
+            .map(|token| format!("`{}`", token))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let found = match input[error.offset..].chars().next() {
+            Some(c) => format!("`{}`", c),
+            None => "end of input".to_owned(),
+        };
+        let mut message = format!("Unexpected {} at {}", found, position);
+        if !expected.is_empty() {
+            message.push_str(", expected one of ");
+            message.push_str(&expected.join(", "));
+        }
+        SyntaxError::new(message, Some(position))
+            .with_expected(expected)
+            .with_snippet(input[line_start..line_end].trim_end_matches('\r'))
+    }
+
+    pub fn read_shexc_schema(
+        schema: &str,
+        base_iri: Option<&str>,
+    ) -> super::super::super::Result<Schema> {
+        let mut state = ParserState::new(base_iri)?;
+        let schema = unescape_unicode_codepoints(schema);
+        shexDoc(&schema, &mut state).map_err(|error| to_syntax_error(&schema, &error))?;
+        Schema::new(
+            state.namespaces.into_iter().collect(),
+            state.start,
+            state.shapes,
+            state.triple_expressions,
+        )
+    }
+
+    pub fn read_shape_map(
+        shape_map: &str,
+        prefixes: &BTreeMap<String, String>,
+    ) -> super::super::super::Result<Vec<(NodeSelector, Option<ShapeLabel>)>> {
+        let mut state = ParserState::new(None)?;
+        state.namespaces = prefixes
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        let shape_map = unescape_unicode_codepoints(shape_map);
+        Ok(
+            shapeMap(&shape_map, &mut state)
+                .map_err(|error| to_syntax_error(&shape_map, &error))?,
+        )
+    }
+}
+
+pub use self::grammar::{build_pattern, read_shape_map, read_shexc_schema};
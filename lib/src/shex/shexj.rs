@@ -0,0 +1,582 @@
+//! Reader of the [ShExJ](http://shex.io/shex-semantics/#shexj) JSON-LD serialization of ShEx schemas
+
+use crate::model::*;
+use crate::shex::model::*;
+use crate::shex::parser::build_pattern;
+use crate::shex::Schema;
+use crate::{Error, Result};
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// Maximal nesting of the JSON values
+const MAX_JSON_DEPTH: usize = 256;
+
+pub fn read_shexj_schema(schema: &str) -> Result<Schema> {
+    let json = JsonParser::new(schema).parse()?;
+    let root = as_object(&json, "schema")?;
+    if let Some(kind) = get(root, "type") {
+        if as_str(kind, "type")? != "Schema" {
+            return Err(Error::syntax("The ShExJ document should be of type Schema"));
+        }
+    }
+    let mut reader = ShexJReader::default();
+    let start = match get(root, "start") {
+        Some(start) => Some(reader.read_shape_expression(start)?),
+        None => None,
+    };
+    let mut shapes = BTreeMap::default();
+    if let Some(declarations) = get(root, "shapes") {
+        for declaration in as_array(declarations, "shapes")? {
+            let object = as_object(declaration, "shape declaration")?;
+            let label = read_label(
+                get(object, "id")
+                    .ok_or_else(|| Error::syntax("The shape declarations should have an id"))?,
+            )?;
+            // ShExJ 2.2 wraps the shape expressions into ShapeDecl objects
+            let expression = if get(object, "type").map(|t| as_str(t, "type")).transpose()?
+                == Some("ShapeDecl")
+            {
+                reader.read_shape_expression(get(object, "shapeExpr").ok_or_else(|| {
+                    Error::syntax("The ShapeDecl objects should have a shapeExpr")
+                })?)?
+            } else {
+                reader.read_shape_expression(declaration)?
+            };
+            shapes.insert(label, expression);
+        }
+    }
+    Schema::new(
+        BTreeMap::default(),
+        start,
+        shapes,
+        reader.triple_expressions,
+    )
+}
+
+#[derive(Default)]
+struct ShexJReader {
+    /// The triple expressions with an id that could be included
+    triple_expressions: BTreeMap<ShapeLabel, TripleExpression>,
+}
+
+impl ShexJReader {
+    fn read_shape_expression(&mut self, value: &JsonValue) -> Result<ShapeExpression> {
+        if let JsonValue::String(_) = value {
+            return Ok(ShapeExpression::Reference(read_label(value)?));
+        }
+        let object = as_object(value, "shape expression")?;
+        Ok(match get_type(object, "shape expressions")? {
+            "ShapeOr" => ShapeExpression::Or(self.read_shape_expressions(object)?),
+            "ShapeAnd" => ShapeExpression::And(self.read_shape_expressions(object)?),
+            "ShapeNot" => ShapeExpression::Not(Box::new(
+                self.read_shape_expression(get(object, "shapeExpr").ok_or_else(|| {
+                    Error::syntax("The ShapeNot objects should have a shapeExpr")
+                })?)?,
+            )),
+            "NodeConstraint" => ShapeExpression::NodeConstraint(read_node_constraint(object)?),
+            "Shape" => ShapeExpression::Shape(self.read_shape(object)?),
+            "ShapeExternal" => ShapeExpression::External,
+            kind => {
+                return Err(Error::unsupported(format!(
+                    "The ShExJ shape expression type {} is not supported",
+                    kind
+                )))
+            }
+        })
+    }
+
+    fn read_shape_expressions(
+        &mut self,
+        object: &[(String, JsonValue)],
+    ) -> Result<Vec<ShapeExpression>> {
+        as_array(
+            get(object, "shapeExprs").ok_or_else(|| {
+                Error::syntax("The ShapeOr and ShapeAnd objects should have shapeExprs")
+            })?,
+            "shapeExprs",
+        )?
+        .iter()
+        .map(|e| self.read_shape_expression(e))
+        .collect()
+    }
+
+    fn read_shape(&mut self, object: &[(String, JsonValue)]) -> Result<Shape> {
+        Ok(Shape {
+            closed: as_bool(get(object, "closed"), "closed")?,
+            extra: match get(object, "extra") {
+                Some(extra) => as_array(extra, "extra")?
+                    .iter()
+                    .map(|p| NamedNode::parse(as_str(p, "extra")?))
+                    .collect::<Result<_>>()?,
+                None => Vec::default(),
+            },
+            expression: match get(object, "expression") {
+                Some(expression) => Some(self.read_triple_expression(expression)?),
+                None => None,
+            },
+        })
+    }
+
+    fn read_triple_expression(&mut self, value: &JsonValue) -> Result<TripleExpression> {
+        if let JsonValue::String(_) = value {
+            return Ok(TripleExpression::Include(read_label(value)?));
+        }
+        let object = as_object(value, "triple expression")?;
+        let cardinality = read_cardinality(object)?;
+        let expression = match get_type(object, "triple expressions")? {
+            kind @ "EachOf" | kind @ "OneOf" => {
+                let expressions = as_array(
+                    get(object, "expressions").ok_or_else(|| {
+                        Error::syntax("The EachOf and OneOf objects should have expressions")
+                    })?,
+                    "expressions",
+                )?
+                .iter()
+                .map(|e| self.read_triple_expression(e))
+                .collect::<Result<_>>()?;
+                if kind == "EachOf" {
+                    TripleExpression::EachOf(expressions, cardinality)
+                } else {
+                    TripleExpression::OneOf(expressions, cardinality)
+                }
+            }
+            "TripleConstraint" => TripleExpression::TripleConstraint(TripleConstraint::new(
+                as_bool(get(object, "inverse"), "inverse")?,
+                NamedNode::parse(as_str(
+                    get(object, "predicate").ok_or_else(|| {
+                        Error::syntax("The TripleConstraint objects should have a predicate")
+                    })?,
+                    "predicate",
+                )?)?,
+                match get(object, "valueExpr") {
+                    Some(value) => Some(self.read_shape_expression(value)?),
+                    None => None,
+                },
+                cardinality,
+            )),
+            kind => {
+                return Err(Error::unsupported(format!(
+                    "The ShExJ triple expression type {} is not supported",
+                    kind
+                )))
+            }
+        };
+        if let Some(id) = get(object, "id") {
+            self.triple_expressions
+                .insert(read_label(id)?, expression.clone());
+        }
+        Ok(expression)
+    }
+}
+
+fn read_label(value: &JsonValue) -> Result<ShapeLabel> {
+    let label = as_str(value, "label")?;
+    Ok(if let Some(id) = label.strip_prefix("_:") {
+        ShapeLabel::BlankNode(id.to_owned())
+    } else {
+        ShapeLabel::Iri(NamedNode::parse(label)?)
+    })
+}
+
+fn read_node_constraint(object: &[(String, JsonValue)]) -> Result<NodeConstraint> {
+    let mut constraint = NodeConstraint::default();
+    let mut pattern = None;
+    let mut flags = "";
+    for (key, value) in object {
+        match key.as_str() {
+            "nodeKind" => {
+                constraint.node_kind = Some(match as_str(value, key)? {
+                    "iri" => NodeKind::Iri,
+                    "bnode" => NodeKind::BlankNode,
+                    "nonliteral" => NodeKind::NonLiteral,
+                    "literal" => NodeKind::Literal,
+                    kind => return Err(Error::syntax(format!("Unknown node kind {}", kind))),
+                })
+            }
+            "datatype" => constraint.datatype = Some(NamedNode::parse(as_str(value, key)?)?),
+            "values" => {
+                constraint.values = Some(
+                    as_array(value, key)?
+                        .iter()
+                        .map(read_value_set_value)
+                        .collect::<Result<_>>()?,
+                )
+            }
+            "length" => constraint.facets.push(Facet::Length(as_usize(value, key)?)),
+            "minlength" => constraint
+                .facets
+                .push(Facet::MinLength(as_usize(value, key)?)),
+            "maxlength" => constraint
+                .facets
+                .push(Facet::MaxLength(as_usize(value, key)?)),
+            "pattern" => pattern = Some(as_str(value, key)?),
+            "flags" => flags = as_str(value, key)?,
+            "mininclusive" => constraint
+                .facets
+                .push(Facet::MinInclusive(as_number(value, key)?)),
+            "minexclusive" => constraint
+                .facets
+                .push(Facet::MinExclusive(as_number(value, key)?)),
+            "maxinclusive" => constraint
+                .facets
+                .push(Facet::MaxInclusive(as_number(value, key)?)),
+            "maxexclusive" => constraint
+                .facets
+                .push(Facet::MaxExclusive(as_number(value, key)?)),
+            "totaldigits" => constraint
+                .facets
+                .push(Facet::TotalDigits(as_usize(value, key)?)),
+            "fractiondigits" => constraint
+                .facets
+                .push(Facet::FractionDigits(as_usize(value, key)?)),
+            _ => (),
+        }
+    }
+    if let Some(pattern) = pattern {
+        constraint
+            .facets
+            .push(Facet::Pattern(build_pattern(pattern, flags).map_err(
+                |_| Error::syntax(format!("Invalid pattern {}", pattern)),
+            )?))
+    }
+    Ok(constraint)
+}
+
+fn read_value_set_value(value: &JsonValue) -> Result<ValueSetValue> {
+    if let JsonValue::String(iri) = value {
+        return Ok(ValueSetValue::Iri(NamedNode::parse(iri.as_str())?));
+    }
+    let object = as_object(value, "value set value")?;
+    if let Some(lexical) = get(object, "value") {
+        let lexical = as_str(lexical, "value")?;
+        return Ok(ValueSetValue::Literal(
+            if let Some(language) = get(object, "language") {
+                Literal::new_language_tagged_literal(
+                    lexical,
+                    as_str(language, "language")?.to_ascii_lowercase(),
+                )
+            } else if let Some(datatype) = get(object, "type") {
+                Literal::new_typed_literal(lexical, NamedNode::parse(as_str(datatype, "type")?)?)
+            } else {
+                Literal::new_simple_literal(lexical)
+            },
+        ));
+    }
+    let kind = as_str(
+        get(object, "type")
+            .ok_or_else(|| Error::syntax("The value set values should have a type"))?,
+        "type",
+    )?;
+    let stem = || {
+        get(object, "stem")
+            .map(|stem| match stem {
+                JsonValue::Object(o) if get(o, "type").is_some() => Ok(String::default()),
+                stem => as_str(stem, "stem").map(str::to_owned),
+            })
+            .unwrap_or_else(|| Err(Error::syntax("The stems should have a stem value")))
+    };
+    Ok(match kind {
+        "IriStem" => ValueSetValue::IriStem(stem()?),
+        "LiteralStem" => ValueSetValue::LiteralStem(stem()?),
+        "Language" => ValueSetValue::Language(
+            as_str(
+                get(object, "languageTag").ok_or_else(|| {
+                    Error::syntax("The Language objects should have a languageTag")
+                })?,
+                "languageTag",
+            )?
+            .to_ascii_lowercase(),
+        ),
+        "LanguageStem" => ValueSetValue::LanguageStem(stem()?.to_ascii_lowercase()),
+        kind => {
+            return Err(Error::unsupported(format!(
+                "The ShExJ value set value type {} is not supported",
+                kind
+            )))
+        }
+    })
+}
+
+fn read_cardinality(object: &[(String, JsonValue)]) -> Result<Cardinality> {
+    let mut cardinality = Cardinality::default();
+    if let Some(min) = get(object, "min") {
+        cardinality.min = as_usize(min, "min")?;
+    }
+    if let Some(max) = get(object, "max") {
+        cardinality.max = if as_number(max, "max")? < 0. {
+            None
+        } else {
+            Some(as_usize(max, "max")?)
+        };
+    }
+    Ok(cardinality)
+}
+
+#[derive(Debug)]
+enum JsonValue {
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+fn get<'a>(object: &'a [(String, JsonValue)], key: &str) -> Option<&'a JsonValue> {
+    object
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v)
+        .filter(|v| !matches!(v, JsonValue::Null))
+}
+
+fn as_object<'a>(value: &'a JsonValue, name: &str) -> Result<&'a [(String, JsonValue)]> {
+    match value {
+        JsonValue::Object(object) => Ok(object),
+        _ => Err(Error::syntax(format!("{} should be a JSON object", name))),
+    }
+}
+
+fn as_array<'a>(value: &'a JsonValue, name: &str) -> Result<&'a [JsonValue]> {
+    match value {
+        JsonValue::Array(array) => Ok(array),
+        _ => Err(Error::syntax(format!("{} should be a JSON array", name))),
+    }
+}
+
+fn as_str<'a>(value: &'a JsonValue, name: &str) -> Result<&'a str> {
+    match value {
+        JsonValue::String(string) => Ok(string),
+        _ => Err(Error::syntax(format!("{} should be a JSON string", name))),
+    }
+}
+
+fn get_type<'a>(object: &'a [(String, JsonValue)], name: &str) -> Result<&'a str> {
+    as_str(
+        get(object, "type")
+            .ok_or_else(|| Error::syntax(format!("The {} should have a type", name)))?,
+        "type",
+    )
+}
+
+fn as_bool(value: Option<&JsonValue>, name: &str) -> Result<bool> {
+    match value {
+        Some(JsonValue::Boolean(value)) => Ok(*value),
+        Some(_) => Err(Error::syntax(format!("{} should be a JSON boolean", name))),
+        None => Ok(false),
+    }
+}
+
+fn as_number(value: &JsonValue, name: &str) -> Result<f64> {
+    match value {
+        JsonValue::Number(number) => Ok(*number),
+        _ => Err(Error::syntax(format!("{} should be a JSON number", name))),
+    }
+}
+
+fn as_usize(value: &JsonValue, name: &str) -> Result<usize> {
+    let number = as_number(value, name)?;
+    if number >= 0. && number.fract() == 0. {
+        Ok(number as usize)
+    } else {
+        Err(Error::syntax(format!(
+            "{} should be a non negative integer",
+            name
+        )))
+    }
+}
+
+/// A minimal [JSON](https://tools.ietf.org/html/rfc8259) parser
+struct JsonParser<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> JsonParser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            chars: input.char_indices().peekable(),
+        }
+    }
+
+    fn parse(mut self) -> Result<JsonValue> {
+        let value = self.parse_value(0)?;
+        self.skip_whitespaces();
+        if let Some((i, _)) = self.chars.next() {
+            return Err(self.error(i, "Unexpected content after the JSON value"));
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<JsonValue> {
+        if depth > MAX_JSON_DEPTH {
+            return Err(Error::syntax("Too deeply nested JSON value"));
+        }
+        self.skip_whitespaces();
+        match self.chars.peek().cloned() {
+            Some((_, '{')) => {
+                self.chars.next();
+                let mut object = Vec::default();
+                self.skip_whitespaces();
+                if self.eat('}') {
+                    return Ok(JsonValue::Object(object));
+                }
+                loop {
+                    self.skip_whitespaces();
+                    let key = self.parse_string()?;
+                    self.skip_whitespaces();
+                    self.expect(':')?;
+                    object.push((key, self.parse_value(depth + 1)?));
+                    self.skip_whitespaces();
+                    if self.eat('}') {
+                        return Ok(JsonValue::Object(object));
+                    }
+                    self.expect(',')?;
+                }
+            }
+            Some((_, '[')) => {
+                self.chars.next();
+                let mut array = Vec::default();
+                self.skip_whitespaces();
+                if self.eat(']') {
+                    return Ok(JsonValue::Array(array));
+                }
+                loop {
+                    array.push(self.parse_value(depth + 1)?);
+                    self.skip_whitespaces();
+                    if self.eat(']') {
+                        return Ok(JsonValue::Array(array));
+                    }
+                    self.expect(',')?;
+                }
+            }
+            Some((_, '"')) => Ok(JsonValue::String(self.parse_string()?)),
+            Some((i, c)) if c == '-' || c.is_ascii_digit() => {
+                let mut end = i;
+                while let Some((j, c)) = self.chars.peek().cloned() {
+                    if c.is_ascii_digit()
+                        || c == '-'
+                        || c == '+'
+                        || c == '.'
+                        || c == 'e'
+                        || c == 'E'
+                    {
+                        end = j + 1;
+                        self.chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                self.input[i..end]
+                    .parse()
+                    .map(JsonValue::Number)
+                    .map_err(|_| self.error(i, "Invalid number"))
+            }
+            Some((i, _)) => {
+                for (keyword, value) in &[
+                    ("true", JsonValue::Boolean(true)),
+                    ("false", JsonValue::Boolean(false)),
+                    ("null", JsonValue::Null),
+                ] {
+                    if self.input[i..].starts_with(keyword) {
+                        for _ in 0..keyword.len() {
+                            self.chars.next();
+                        }
+                        return Ok(match value {
+                            JsonValue::Boolean(b) => JsonValue::Boolean(*b),
+                            _ => JsonValue::Null,
+                        });
+                    }
+                }
+                Err(self.error(i, "Unexpected character"))
+            }
+            None => Err(Error::syntax("Unexpected end of the JSON document")),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut string = String::default();
+        loop {
+            match self.chars.next() {
+                Some((_, '"')) => return Ok(string),
+                Some((i, '\\')) => match self.chars.next() {
+                    Some((_, '"')) => string.push('"'),
+                    Some((_, '\\')) => string.push('\\'),
+                    Some((_, '/')) => string.push('/'),
+                    Some((_, 'b')) => string.push('\u{8}'),
+                    Some((_, 'f')) => string.push('\u{c}'),
+                    Some((_, 'n')) => string.push('\n'),
+                    Some((_, 'r')) => string.push('\r'),
+                    Some((_, 't')) => string.push('\t'),
+                    Some((_, 'u')) => {
+                        let mut code_point = self.parse_hex(i)?;
+                        if (0xD800..0xDC00).contains(&code_point) {
+                            // UTF-16 surrogate pair
+                            self.expect('\\')?;
+                            self.expect('u')?;
+                            let low = self.parse_hex(i)?;
+                            code_point = 0x10000 + ((code_point - 0xD800) << 10) + (low - 0xDC00);
+                        }
+                        string.push(
+                            std::char::from_u32(code_point)
+                                .ok_or_else(|| self.error(i, "Invalid unicode code point"))?,
+                        )
+                    }
+                    _ => return Err(self.error(i, "Invalid escape sequence")),
+                },
+                Some((_, c)) => string.push(c),
+                None => return Err(Error::syntax("Unexpected end of the JSON document")),
+            }
+        }
+    }
+
+    fn parse_hex(&mut self, position: usize) -> Result<u32> {
+        let mut value = 0;
+        for _ in 0..4 {
+            value = value * 16
+                + self
+                    .chars
+                    .next()
+                    .and_then(|(_, c)| c.to_digit(16))
+                    .ok_or_else(|| self.error(position, "Invalid unicode escape sequence"))?;
+        }
+        Ok(value)
+    }
+
+    fn skip_whitespaces(&mut self) {
+        while let Some((_, c)) = self.chars.peek() {
+            if c.is_whitespace() {
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.chars.peek().map(|(_, v)| *v) == Some(c) {
+            self.chars.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        match self.chars.next() {
+            Some((_, v)) if v == c => Ok(()),
+            Some((i, _)) => Err(self.error(i, &format!("Expected `{}`", c))),
+            None => Err(Error::syntax("Unexpected end of the JSON document")),
+        }
+    }
+
+    fn error(&self, position: usize, message: &str) -> Error {
+        Error::syntax(format!(
+            "{} at byte {} of the JSON document",
+            message, position
+        ))
+    }
+}
@@ -0,0 +1,514 @@
+use crate::model::vocab::xsd;
+use crate::model::*;
+use crate::shacl::is_well_formed;
+use crate::shex::model::*;
+use crate::shex::{DataGraph, Schema};
+use crate::{Error, Result};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// Maximal nesting of the shape references followed during the validation of a node
+const MAX_SHAPE_DEPTH: usize = 64;
+
+/// Maximal number of assignments of the triples of a neighbourhood to the triple constraints tried for a shape
+const MAX_ASSIGNMENTS: usize = 100_000;
+
+/// A range of repetitions `[min, max]` where `max` is `None` if unbounded
+type Interval = (usize, Option<usize>);
+
+/// The outcome of a check: `None` if it passes or the reason of the failure
+type Check = Option<String>;
+
+pub struct Validator<'a, G: DataGraph> {
+    schema: &'a Schema,
+    data: &'a G,
+    /// The (node, shape) pairs being validated, assumed to conform when reached again
+    in_progress: RefCell<HashSet<(Term, ShapeLabel)>>,
+}
+
+impl<'a, G: DataGraph> Validator<'a, G> {
+    pub fn new(schema: &'a Schema, data: &'a G) -> Self {
+        Self {
+            schema,
+            data,
+            in_progress: RefCell::default(),
+        }
+    }
+
+    /// Validates a node against a shape, or the `START` shape if `None`
+    pub fn validate(&self, node: &Term, shape: Option<&ShapeLabel>) -> Result<Check> {
+        match shape {
+            Some(label) => self.validate_reference(node, label, 0),
+            None => self.validate_expression(node, self.schema.start()?, 0),
+        }
+    }
+
+    fn validate_reference(&self, node: &Term, label: &ShapeLabel, depth: usize) -> Result<Check> {
+        let key = (node.clone(), label.clone());
+        if !self.in_progress.borrow_mut().insert(key.clone()) {
+            return Ok(None);
+        }
+        let result = self.validate_expression(node, self.schema.shape(label)?, depth + 1);
+        self.in_progress.borrow_mut().remove(&key);
+        Ok(result?.map(|reason| format!("{} does not match {}: {}", node, label, reason)))
+    }
+
+    fn validate_expression(
+        &self,
+        node: &Term,
+        expression: &ShapeExpression,
+        depth: usize,
+    ) -> Result<Check> {
+        if depth > MAX_SHAPE_DEPTH {
+            return Err(Error::unsupported(format!(
+                "The validation of {} requires too many nested shape validations",
+                node
+            )));
+        }
+        Ok(match expression {
+            ShapeExpression::Or(expressions) => {
+                let mut reasons = Vec::default();
+                for expression in expressions {
+                    match self.validate_expression(node, expression, depth)? {
+                        Some(reason) => reasons.push(reason),
+                        None => return Ok(None),
+                    }
+                }
+                Some(format!(
+                    "{} does not match any alternative ({})",
+                    node,
+                    reasons.join("; ")
+                ))
+            }
+            ShapeExpression::And(expressions) => {
+                for expression in expressions {
+                    if let Some(reason) = self.validate_expression(node, expression, depth)? {
+                        return Ok(Some(reason));
+                    }
+                }
+                None
+            }
+            ShapeExpression::Not(expression) => {
+                match self.validate_expression(node, expression, depth)? {
+                    Some(_) => None,
+                    None => Some(format!("{} matches a negated shape expression", node)),
+                }
+            }
+            ShapeExpression::NodeConstraint(constraint) => {
+                validate_node_constraint(node, constraint)
+            }
+            ShapeExpression::Shape(shape) => self.validate_shape(node, shape, depth)?,
+            ShapeExpression::External => {
+                return Err(Error::unsupported("EXTERNAL shapes are not supported"))
+            }
+            ShapeExpression::Reference(label) => self.validate_reference(node, label, depth)?,
+        })
+    }
+
+    fn validate_shape(&self, node: &Term, shape: &Shape, depth: usize) -> Result<Check> {
+        let mut constraints = Vec::default();
+        if let Some(expression) = &shape.expression {
+            triple_constraints(expression, &mut constraints);
+        }
+        let outgoing = match node {
+            Term::NamedNode(node) => self.data.triples_for_subject(&node.clone().into())?,
+            Term::BlankNode(node) => self.data.triples_for_subject(&node.clone().into())?,
+            Term::Triple(triple) => self
+                .data
+                .triples_for_subject(&NamedOrBlankNode::Triple(triple.clone()))?,
+            Term::Literal(_) => Vec::default(),
+        };
+        let incoming = if constraints.iter().any(|c| c.inverse) {
+            self.data.triples_for_object(node)?
+        } else {
+            Vec::default()
+        };
+
+        // The triple constraints each triple of the neighbourhood could be assigned to, None meaning no assignment
+        let mut choices = Vec::default();
+        for (triple, inverse) in outgoing
+            .iter()
+            .map(|t| (t, false))
+            .chain(incoming.iter().map(|t| (t, true)))
+        {
+            let value = if inverse {
+                triple.subject().clone().into()
+            } else {
+                triple.object().clone()
+            };
+            let mut mentioned = false;
+            let mut candidates = Vec::default();
+            let mut reasons = Vec::default();
+            for constraint in &constraints {
+                if constraint.inverse != inverse || &constraint.predicate != triple.predicate() {
+                    continue;
+                }
+                mentioned = true;
+                let check = match &constraint.value {
+                    Some(expression) => self.validate_expression(&value, expression, depth)?,
+                    None => None,
+                };
+                match check {
+                    Some(reason) => reasons.push(reason),
+                    None => candidates.push(Some(constraint.id)),
+                }
+            }
+            let is_extra = shape.extra.contains(triple.predicate());
+            if !mentioned {
+                if shape.closed && !inverse {
+                    return Ok(Some(format!(
+                        "the predicate {} is not allowed by the closed shape",
+                        triple.predicate()
+                    )));
+                }
+                continue;
+            }
+            if is_extra {
+                candidates.push(None);
+            }
+            if candidates.is_empty() {
+                return Ok(Some(format!(
+                    "the value {} of {} does not match: {}",
+                    value,
+                    triple.predicate(),
+                    reasons.join("; ")
+                )));
+            }
+            choices.push(candidates);
+        }
+
+        let expression = match &shape.expression {
+            Some(expression) => expression,
+            None => return Ok(None),
+        };
+        let assignments = choices
+            .iter()
+            .try_fold(1usize, |count, c| count.checked_mul(c.len()))
+            .filter(|count| *count <= MAX_ASSIGNMENTS)
+            .ok_or_else(|| {
+                Error::unsupported(format!(
+                    "The neighbourhood of {} has too many possible matches with the shape",
+                    node
+                ))
+            })?;
+        let mut counts = vec![0; constraints.len()];
+        for mut assignment in 0..assignments {
+            counts.iter_mut().for_each(|c| *c = 0);
+            for candidates in &choices {
+                if let Some(id) = candidates[assignment % candidates.len()] {
+                    counts[id] += 1;
+                }
+                assignment /= candidates.len();
+            }
+            if contains(interval(expression, &counts), 1) {
+                return Ok(None);
+            }
+        }
+
+        // We explain the failure using the first assignment
+        counts.iter_mut().for_each(|c| *c = 0);
+        for candidates in &choices {
+            if let Some(id) = candidates[0] {
+                counts[id] += 1;
+            }
+        }
+        for constraint in constraints {
+            let cardinality = constraint.cardinality;
+            if !contains((cardinality.min, cardinality.max), counts[constraint.id]) {
+                return Ok(Some(format!(
+                    "{} matching values for {}{} but expected {}",
+                    counts[constraint.id],
+                    if constraint.inverse { "^" } else { "" },
+                    constraint.predicate,
+                    describe_cardinality(cardinality)
+                )));
+            }
+        }
+        Ok(Some(
+            "the triples do not match the triple expression of the shape".to_owned(),
+        ))
+    }
+}
+
+fn triple_constraints<'a>(
+    expression: &'a TripleExpression,
+    constraints: &mut Vec<&'a TripleConstraint>,
+) {
+    match expression {
+        TripleExpression::EachOf(expressions, _) | TripleExpression::OneOf(expressions, _) => {
+            for expression in expressions {
+                triple_constraints(expression, constraints)
+            }
+        }
+        TripleExpression::TripleConstraint(constraint) => constraints.push(constraint),
+        TripleExpression::Include(_) => (),
+    }
+}
+
+/// Computes the numbers of repetitions of the expression that could match the triple counts
+///
+/// It is the interval algorithm for single occurrence regular bag expressions
+/// from [Complexity and Expressiveness of ShEx for RDF](https://hal.inria.fr/hal-01218552).
+fn interval(expression: &TripleExpression, counts: &[usize]) -> Interval {
+    match expression {
+        TripleExpression::EachOf(expressions, cardinality) => {
+            let inner = expressions
+                .iter()
+                .fold((0, None), |(min, max): Interval, e| {
+                    let (e_min, e_max) = interval(e, counts);
+                    (
+                        min.max(e_min),
+                        match (max, e_max) {
+                            (Some(a), Some(b)) => Some(a.min(b)),
+                            (a, b) => a.or(b),
+                        },
+                    )
+                });
+            repeat(inner, *cardinality)
+        }
+        TripleExpression::OneOf(expressions, cardinality) => {
+            let inner = expressions.iter().fold((0, Some(0)), |(min, max), e| {
+                let (e_min, e_max) = interval(e, counts);
+                (
+                    min + e_min,
+                    match (max, e_max) {
+                        (Some(a), Some(b)) => Some(a + b),
+                        _ => None,
+                    },
+                )
+            });
+            repeat(inner, *cardinality)
+        }
+        TripleExpression::TripleConstraint(constraint) => {
+            let count = counts[constraint.id];
+            repeat((count, Some(count)), constraint.cardinality)
+        }
+        TripleExpression::Include(_) => (0, None),
+    }
+}
+
+/// The numbers of repetitions `k` of `e{min,max}` such that `e` repeated a number of times in `inner` is covered
+#[allow(clippy::manual_div_ceil)] // usize::div_ceil is not available with the supported Rust versions
+fn repeat((min, max): Interval, cardinality: Cardinality) -> Interval {
+    if let Some(max) = max {
+        if min > max {
+            return (1, Some(0)); // Empty interval
+        }
+    }
+    let lower = match cardinality.max {
+        Some(0) => {
+            if min == 0 {
+                0
+            } else {
+                return (1, Some(0));
+            }
+        }
+        Some(c_max) => (min + c_max - 1) / c_max,
+        None => {
+            if min > 0 {
+                1
+            } else {
+                0
+            }
+        }
+    };
+    let upper = match (max, cardinality.min) {
+        (_, 0) | (None, _) => None,
+        (Some(max), c_min) => Some(max / c_min),
+    };
+    (lower, upper)
+}
+
+fn contains((min, max): Interval, value: usize) -> bool {
+    min <= value
+        && match max {
+            Some(max) => value <= max,
+            None => true,
+        }
+}
+
+fn describe_cardinality(cardinality: Cardinality) -> String {
+    match cardinality.max {
+        Some(max) if max == cardinality.min => format!("{}", max),
+        Some(max) => format!("between {} and {}", cardinality.min, max),
+        None => format!("at least {}", cardinality.min),
+    }
+}
+
+fn validate_node_constraint(node: &Term, constraint: &NodeConstraint) -> Check {
+    if let Some(kind) = constraint.node_kind {
+        let matches = match kind {
+            NodeKind::Iri => node.is_named_node(),
+            NodeKind::BlankNode => node.is_blank_node(),
+            NodeKind::NonLiteral => !node.is_literal(),
+            NodeKind::Literal => node.is_literal(),
+        };
+        if !matches {
+            return Some(format!("{} does not have the node kind {:?}", node, kind));
+        }
+    }
+    if let Some(datatype) = &constraint.datatype {
+        let matches = match node {
+            Term::Literal(literal) => literal.datatype() == datatype && is_well_formed(literal),
+            _ => false,
+        };
+        if !matches {
+            return Some(format!(
+                "{} is not a valid literal of datatype {}",
+                node, datatype
+            ));
+        }
+    }
+    for facet in &constraint.facets {
+        if !matches_facet(node, facet) {
+            return Some(format!("{} does not match the facet {:?}", node, facet));
+        }
+    }
+    if let Some(values) = &constraint.values {
+        if !values.iter().any(|value| matches_value(node, value)) {
+            return Some(format!("{} is not in the value set", node));
+        }
+    }
+    None
+}
+
+fn matches_facet(node: &Term, facet: &Facet) -> bool {
+    match facet {
+        Facet::Length(length) => {
+            matches!(lexical_form(node), Some(v) if v.chars().count() == *length)
+        }
+        Facet::MinLength(length) => {
+            matches!(lexical_form(node), Some(v) if v.chars().count() >= *length)
+        }
+        Facet::MaxLength(length) => {
+            matches!(lexical_form(node), Some(v) if v.chars().count() <= *length)
+        }
+        Facet::Pattern(regex) => matches!(lexical_form(node), Some(v) if regex.is_match(v)),
+        Facet::MinInclusive(bound) => matches!(numeric_value(node), Some(v) if v >= *bound),
+        Facet::MinExclusive(bound) => matches!(numeric_value(node), Some(v) if v > *bound),
+        Facet::MaxInclusive(bound) => matches!(numeric_value(node), Some(v) if v <= *bound),
+        Facet::MaxExclusive(bound) => matches!(numeric_value(node), Some(v) if v < *bound),
+        Facet::TotalDigits(digits) => {
+            matches!(decimal_digits(node), Some((total, _)) if total <= *digits)
+        }
+        Facet::FractionDigits(digits) => {
+            matches!(decimal_digits(node), Some((_, fraction)) if fraction <= *digits)
+        }
+    }
+}
+
+/// The lexical form of a literal or the string of an IRI
+fn lexical_form(node: &Term) -> Option<&str> {
+    match node {
+        Term::NamedNode(node) => Some(node.as_str()),
+        Term::BlankNode(_) | Term::Triple(_) => None,
+        Term::Literal(literal) => Some(literal.value()),
+    }
+}
+
+fn numeric_value(node: &Term) -> Option<f64> {
+    match node {
+        Term::Literal(literal) if is_numeric(literal) && is_well_formed(literal) => {
+            f64::from_str(literal.value()).ok()
+        }
+        _ => None,
+    }
+}
+
+fn is_numeric(literal: &Literal) -> bool {
+    let datatype = literal.datatype();
+    is_decimal(literal) || datatype == &*xsd::FLOAT || datatype == &*xsd::DOUBLE
+}
+
+fn is_decimal(literal: &Literal) -> bool {
+    let datatype = literal.datatype();
+    datatype == &*xsd::DECIMAL
+        || datatype == &*xsd::INTEGER
+        || datatype == &*xsd::BYTE
+        || datatype == &*xsd::SHORT
+        || datatype == &*xsd::INT
+        || datatype == &*xsd::LONG
+        || datatype == &*xsd::UNSIGNED_BYTE
+        || datatype == &*xsd::UNSIGNED_SHORT
+        || datatype == &*xsd::UNSIGNED_INT
+        || datatype == &*xsd::UNSIGNED_LONG
+        || datatype == &*xsd::POSITIVE_INTEGER
+        || datatype == &*xsd::NEGATIVE_INTEGER
+        || datatype == &*xsd::NON_POSITIVE_INTEGER
+        || datatype == &*xsd::NON_NEGATIVE_INTEGER
+}
+
+/// The total number of digits and the number of fraction digits of a decimal literal
+fn decimal_digits(node: &Term) -> Option<(usize, usize)> {
+    match node {
+        Term::Literal(literal) if is_decimal(literal) && is_well_formed(literal) => {
+            let value = literal.value().trim_start_matches(['+', '-']);
+            let (integer, fraction) = match value.find('.') {
+                Some(i) => (&value[..i], value[i + 1..].trim_end_matches('0')),
+                None => (value, ""),
+            };
+            let integer = integer.trim_start_matches('0');
+            Some((integer.len() + fraction.len(), fraction.len()))
+        }
+        _ => None,
+    }
+}
+
+fn matches_value(node: &Term, value: &ValueSetValue) -> bool {
+    match (value, node) {
+        (ValueSetValue::Iri(iri), Term::NamedNode(node)) => iri == node,
+        (ValueSetValue::IriStem(stem), Term::NamedNode(node)) => node.as_str().starts_with(stem),
+        (ValueSetValue::Literal(expected), Term::Literal(literal)) => expected == literal,
+        (ValueSetValue::LiteralStem(stem), Term::Literal(literal)) => {
+            literal.value().starts_with(stem)
+        }
+        (ValueSetValue::Language(tag), Term::Literal(literal)) => {
+            matches!(literal.language(), Some(language) if language.eq_ignore_ascii_case(tag))
+        }
+        (ValueSetValue::LanguageStem(stem), Term::Literal(literal)) => match literal.language() {
+            Some(language) => {
+                let language = language.to_ascii_lowercase();
+                stem.is_empty()
+                    || language == *stem
+                    || (language.starts_with(stem.as_str())
+                        && language[stem.len()..].starts_with('-'))
+            }
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+#[test]
+fn test_interval() {
+    use crate::model::vocab::rdf;
+
+    let constraint = |id, min, max| {
+        let mut constraint =
+            TripleConstraint::new(false, rdf::TYPE.clone(), None, Cardinality::new(min, max));
+        constraint.id = id;
+        TripleExpression::TripleConstraint(constraint)
+    };
+    // a ; b*
+    let each_of = TripleExpression::EachOf(
+        vec![constraint(0, 1, Some(1)), constraint(1, 0, None)],
+        Cardinality::default(),
+    );
+    assert!(contains(interval(&each_of, &[1, 0]), 1));
+    assert!(contains(interval(&each_of, &[1, 3]), 1));
+    assert!(!contains(interval(&each_of, &[0, 3]), 1));
+    assert!(!contains(interval(&each_of, &[2, 0]), 1));
+
+    // (a | b){2}
+    let one_of = TripleExpression::OneOf(
+        vec![constraint(0, 1, Some(1)), constraint(1, 1, Some(1))],
+        Cardinality::new(2, Some(2)),
+    );
+    assert!(contains(interval(&one_of, &[1, 1]), 1));
+    assert!(contains(interval(&one_of, &[2, 0]), 1));
+    assert!(!contains(interval(&one_of, &[1, 0]), 1));
+    assert!(!contains(interval(&one_of, &[2, 1]), 1));
+
+    assert_eq!(interval(&constraint(0, 0, Some(0)), &[0]), (0, None));
+}
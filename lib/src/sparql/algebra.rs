@@ -108,7 +108,7 @@ impl fmt::Display for PropertyPath {
     }
 }
 
-struct SparqlPropertyPath<'a>(&'a PropertyPath);
+pub(crate) struct SparqlPropertyPath<'a>(pub &'a PropertyPath);
 
 impl<'a> fmt::Display for SparqlPropertyPath<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -491,6 +491,11 @@ pub enum Function {
     IsLiteral,
     IsNumeric,
     Regex,
+    Triple,
+    Subject,
+    Predicate,
+    Object,
+    IsTriple,
     Custom(NamedNode),
 }
 
@@ -546,6 +551,11 @@ impl fmt::Display for Function {
             Function::IsLiteral => write!(f, "isLITERAL"),
             Function::IsNumeric => write!(f, "isNUMERIC"),
             Function::Regex => write!(f, "REGEX"),
+            Function::Triple => write!(f, "TRIPLE"),
+            Function::Subject => write!(f, "SUBJECT"),
+            Function::Predicate => write!(f, "PREDICATE"),
+            Function::Object => write!(f, "OBJECT"),
+            Function::IsTriple => write!(f, "isTRIPLE"),
             Function::Custom(iri) => iri.fmt(f),
         }
     }
@@ -670,24 +680,10 @@ impl GraphPattern {
             GraphPattern::BGP(p) => {
                 for pattern in p {
                     match pattern {
-                        TripleOrPathPattern::Triple(tp) => {
-                            if let TermOrVariable::Variable(ref s) = tp.subject {
-                                adds_if_has_name(vars, s);
-                            }
-                            if let NamedNodeOrVariable::Variable(ref p) = tp.predicate {
-                                adds_if_has_name(vars, p);
-                            }
-                            if let TermOrVariable::Variable(ref o) = tp.object {
-                                adds_if_has_name(vars, o);
-                            }
-                        }
+                        TripleOrPathPattern::Triple(tp) => add_triple_pattern_variables(vars, tp),
                         TripleOrPathPattern::Path(ppp) => {
-                            if let TermOrVariable::Variable(ref s) = ppp.subject {
-                                adds_if_has_name(vars, s);
-                            }
-                            if let TermOrVariable::Variable(ref o) = ppp.object {
-                                adds_if_has_name(vars, o);
-                            }
+                            add_term_or_variable_variables(vars, &ppp.subject);
+                            add_term_or_variable_variables(vars, &ppp.object);
                         }
                     }
                 }
@@ -734,6 +730,22 @@ fn adds_if_has_name<'a>(vars: &mut BTreeSet<&'a Variable>, var: &'a Variable) {
     }
 }
 
+fn add_triple_pattern_variables<'a>(vars: &mut BTreeSet<&'a Variable>, pattern: &'a TriplePattern) {
+    add_term_or_variable_variables(vars, &pattern.subject);
+    if let NamedNodeOrVariable::Variable(ref p) = pattern.predicate {
+        adds_if_has_name(vars, p);
+    }
+    add_term_or_variable_variables(vars, &pattern.object);
+}
+
+fn add_term_or_variable_variables<'a>(vars: &mut BTreeSet<&'a Variable>, term: &'a TermOrVariable) {
+    match term {
+        TermOrVariable::Variable(var) => adds_if_has_name(vars, var),
+        TermOrVariable::Triple(pattern) => add_triple_pattern_variables(vars, pattern),
+        TermOrVariable::Term(_) => (),
+    }
+}
+
 struct SparqlGraphPattern<'a>(&'a GraphPattern);
 
 impl<'a> fmt::Display for SparqlGraphPattern<'a> {
@@ -770,7 +782,9 @@ impl<'a> fmt::Display for SparqlGraphPattern<'a> {
                 SparqlGraphPattern(&*a),
                 SparqlGraphPattern(&*b),
             ),
-            GraphPattern::Graph(g, p) => write!(f, "GRAPH {} {{ {} }}", g, SparqlGraphPattern(&*p),),
+            GraphPattern::Graph(g, p) => {
+                write!(f, "GRAPH {} {{ {} }}", g, SparqlGraphPattern(&*p),)
+            }
             GraphPattern::Extend(p, v, e) => write!(
                 f,
                 "{} BIND({} AS {})",
@@ -1228,6 +1242,26 @@ pub enum QueryVariants {
     },
 }
 
+impl QueryVariants {
+    pub fn base_iri(&self) -> Option<&Iri<String>> {
+        match self {
+            QueryVariants::Select { base_iri, .. }
+            | QueryVariants::Construct { base_iri, .. }
+            | QueryVariants::Describe { base_iri, .. }
+            | QueryVariants::Ask { base_iri, .. } => base_iri.as_ref(),
+        }
+    }
+
+    pub fn algebra_mut(&mut self) -> &mut GraphPattern {
+        match self {
+            QueryVariants::Select { algebra, .. }
+            | QueryVariants::Construct { algebra, .. }
+            | QueryVariants::Describe { algebra, .. }
+            | QueryVariants::Ask { algebra, .. } => algebra,
+        }
+    }
+}
+
 impl fmt::Display for QueryVariants {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -0,0 +1,402 @@
+//! Implementation of the [RDFS entailment regime](https://www.w3.org/TR/sparql11-entailment/#RDFSEntRegime)
+//!
+//! The entailed quads are not materialized: each quad pattern lookup is rewritten into lookups of the explicit quads
+//! that could entail a matching quad, and the consequences of these quads are computed on the fly.
+//!
+//! Entailment is done graph by graph, the RDFS vocabulary of a graph only applying to the quads of the same graph.
+//! The axiomatic triples and the rules that only produce trivial consequences (rdfs4, rdfs6, rdfs8, rdfs10...) are not applied.
+
+use crate::store::numeric_encoder::*;
+use crate::store::StoreConnection;
+use crate::Result;
+use std::collections::{HashMap, HashSet};
+use std::iter::once;
+use std::rc::Rc;
+
+type TriplePattern = (
+    Option<EncodedTerm>,
+    Option<EncodedTerm>,
+    Option<EncodedTerm>,
+);
+
+/// The RDFS vocabulary of all the graphs of a store
+#[derive(Default)]
+pub struct RdfsSchema {
+    graphs: HashMap<EncodedTerm, GraphSchema>,
+    /// For each class, its subclasses in any of the graphs
+    sub_classes: HashMap<EncodedTerm, HashSet<EncodedTerm>>,
+    /// For each property, its subproperties in any of the graphs
+    sub_properties: HashMap<EncodedTerm, HashSet<EncodedTerm>>,
+    /// For each class, the properties whose subjects are instances of it in any of the graphs
+    domain_properties: HashMap<EncodedTerm, HashSet<EncodedTerm>>,
+    /// For each class, the properties whose objects are instances of it in any of the graphs
+    range_properties: HashMap<EncodedTerm, HashSet<EncodedTerm>>,
+}
+
+/// The RDFS vocabulary of a graph with its hierarchies transitively closed
+#[derive(Default)]
+struct GraphSchema {
+    super_classes: HashMap<EncodedTerm, HashSet<EncodedTerm>>,
+    super_properties: HashMap<EncodedTerm, HashSet<EncodedTerm>>,
+    /// The classes of the subjects of each property, inherited from its super properties and closed by subclass
+    domains: HashMap<EncodedTerm, HashSet<EncodedTerm>>,
+    /// The classes of the objects of each property, inherited from its super properties and closed by subclass
+    ranges: HashMap<EncodedTerm, HashSet<EncodedTerm>>,
+}
+
+impl RdfsSchema {
+    /// Reads the RDFS vocabulary of all the graphs of the store
+    pub fn load(store: &impl StoreConnection) -> Result<Self> {
+        let sub_class_of = direct_relations(store, ENCODED_RDFS_SUB_CLASS_OF_NAMED_NODE)?;
+        let sub_property_of = direct_relations(store, ENCODED_RDFS_SUB_PROPERTY_OF_NAMED_NODE)?;
+        let domain = direct_relations(store, ENCODED_RDFS_DOMAIN_NAMED_NODE)?;
+        let range = direct_relations(store, ENCODED_RDFS_RANGE_NAMED_NODE)?;
+
+        let mut schema = RdfsSchema::default();
+        let graph_names: HashSet<EncodedTerm> = sub_class_of
+            .keys()
+            .chain(sub_property_of.keys())
+            .chain(domain.keys())
+            .chain(range.keys())
+            .cloned()
+            .collect();
+        let empty = HashMap::default();
+        for graph_name in graph_names {
+            let super_classes = transitive_closure(sub_class_of.get(&graph_name).unwrap_or(&empty));
+            let super_properties =
+                transitive_closure(sub_property_of.get(&graph_name).unwrap_or(&empty));
+            let domains = property_classes(
+                domain.get(&graph_name).unwrap_or(&empty),
+                &super_properties,
+                &super_classes,
+            );
+            let ranges = property_classes(
+                range.get(&graph_name).unwrap_or(&empty),
+                &super_properties,
+                &super_classes,
+            );
+            add_inverse(&mut schema.sub_classes, &super_classes);
+            add_inverse(&mut schema.sub_properties, &super_properties);
+            add_inverse(&mut schema.domain_properties, &domains);
+            add_inverse(&mut schema.range_properties, &ranges);
+            schema.graphs.insert(
+                graph_name,
+                GraphSchema {
+                    super_classes,
+                    super_properties,
+                    domains,
+                    ranges,
+                },
+            );
+        }
+        Ok(schema)
+    }
+
+    /// Returns the explicit and entailed quads matching the pattern
+    pub fn quads_for_pattern<'a, S: StoreConnection>(
+        self: Rc<Self>,
+        store: &'a S,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
+        object: Option<EncodedTerm>,
+        graph_name: Option<EncodedTerm>,
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'a> {
+        let lookups = self.lookups(subject, predicate, object);
+        let mut seen = HashSet::new();
+        Box::new(
+            lookups
+                .into_iter()
+                .flat_map(move |(subject, predicate, object)| {
+                    store.quads_for_pattern(subject, predicate, object, graph_name)
+                })
+                .flat_map(
+                    move |quad| -> Box<dyn Iterator<Item = Result<EncodedQuad>>> {
+                        match quad {
+                            Ok(quad) => Box::new(self.entailed_quads(quad).into_iter().map(Ok)),
+                            Err(error) => Box::new(once(Err(error))),
+                        }
+                    },
+                )
+                .filter(move |quad| match quad {
+                    Ok(quad) => {
+                        matches(subject, quad.subject)
+                            && matches(predicate, quad.predicate)
+                            && matches(object, quad.object)
+                            && seen.insert(quad.clone())
+                    }
+                    Err(_) => true,
+                }),
+        )
+    }
+
+    /// The patterns of the explicit quads that could entail a quad matching the given pattern
+    fn lookups(
+        &self,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
+        object: Option<EncodedTerm>,
+    ) -> Vec<TriplePattern> {
+        let predicate = if let Some(predicate) = predicate {
+            predicate
+        } else if subject.is_some() {
+            // The only entailed quads whose subject is not the subject of the entailing quad come from rdfs:range
+            let mut lookups = vec![(subject, None, None)];
+            for property in properties_for_class(&self.range_properties, None) {
+                lookups.push((None, Some(property), subject));
+            }
+            return lookups;
+        } else {
+            return vec![(None, None, None)];
+        };
+
+        let mut objects = vec![object];
+        if let Some(object) = object {
+            let sub_terms = if predicate == ENCODED_RDF_TYPE_NAMED_NODE
+                || predicate == ENCODED_RDFS_SUB_CLASS_OF_NAMED_NODE
+            {
+                self.sub_classes.get(&object)
+            } else if predicate == ENCODED_RDFS_SUB_PROPERTY_OF_NAMED_NODE {
+                self.sub_properties.get(&object)
+            } else {
+                None
+            };
+            objects.extend(sub_terms.into_iter().flatten().map(|term| Some(*term)));
+        }
+        let mut lookups = Vec::default();
+        for property in
+            once(&predicate).chain(self.sub_properties.get(&predicate).into_iter().flatten())
+        {
+            for object in &objects {
+                lookups.push((subject, Some(*property), *object));
+            }
+        }
+
+        if predicate == ENCODED_RDF_TYPE_NAMED_NODE {
+            // rdfs2 and rdfs3
+            for property in properties_for_class(&self.domain_properties, object) {
+                lookups.push((subject, Some(property), None));
+            }
+            for property in properties_for_class(&self.range_properties, object) {
+                lookups.push((None, Some(property), subject));
+            }
+        }
+        lookups
+    }
+
+    /// The quad and the quads it entails
+    fn entailed_quads(&self, quad: EncodedQuad) -> Vec<EncodedQuad> {
+        let schema = if let Some(schema) = self.graphs.get(&quad.graph_name) {
+            schema
+        } else {
+            return vec![quad];
+        };
+        let mut entailed = Vec::default();
+        let typed = |subject, class| {
+            EncodedQuad::new(subject, ENCODED_RDF_TYPE_NAMED_NODE, class, quad.graph_name)
+        };
+        // rdfs7
+        for property in schema
+            .super_properties
+            .get(&quad.predicate)
+            .into_iter()
+            .flatten()
+        {
+            entailed.push(EncodedQuad::new(
+                quad.subject,
+                *property,
+                quad.object,
+                quad.graph_name,
+            ));
+        }
+        // rdfs2
+        for class in schema.domains.get(&quad.predicate).into_iter().flatten() {
+            entailed.push(typed(quad.subject, *class));
+        }
+        // rdfs3
+        if !quad.object.is_literal() {
+            for class in schema.ranges.get(&quad.predicate).into_iter().flatten() {
+                entailed.push(typed(quad.object, *class));
+            }
+        }
+        // rdfs9, rdfs11 and rdfs5
+        let super_terms = if quad.predicate == ENCODED_RDF_TYPE_NAMED_NODE
+            || quad.predicate == ENCODED_RDFS_SUB_CLASS_OF_NAMED_NODE
+        {
+            schema.super_classes.get(&quad.object)
+        } else if quad.predicate == ENCODED_RDFS_SUB_PROPERTY_OF_NAMED_NODE {
+            schema.super_properties.get(&quad.object)
+        } else {
+            None
+        };
+        for term in super_terms.into_iter().flatten() {
+            entailed.push(EncodedQuad::new(
+                quad.subject,
+                quad.predicate,
+                *term,
+                quad.graph_name,
+            ));
+        }
+        entailed.push(quad);
+        entailed
+    }
+}
+
+/// Checks if the term matches the pattern term, `None` matching everything
+fn matches(pattern: Option<EncodedTerm>, term: EncodedTerm) -> bool {
+    match pattern {
+        Some(pattern) => pattern == term,
+        None => true,
+    }
+}
+
+/// Reads the (subject, object) pairs of the quads with the given predicate, grouped by graph
+fn direct_relations(
+    store: &impl StoreConnection,
+    predicate: EncodedTerm,
+) -> Result<HashMap<EncodedTerm, HashMap<EncodedTerm, HashSet<EncodedTerm>>>> {
+    let mut relations: HashMap<EncodedTerm, HashMap<EncodedTerm, HashSet<EncodedTerm>>> =
+        HashMap::default();
+    for quad in store.quads_for_pattern(None, Some(predicate), None, None) {
+        let quad = quad?;
+        relations
+            .entry(quad.graph_name)
+            .or_default()
+            .entry(quad.subject)
+            .or_default()
+            .insert(quad.object);
+    }
+    Ok(relations)
+}
+
+/// Computes for each term all the terms reachable from it
+fn transitive_closure(
+    relation: &HashMap<EncodedTerm, HashSet<EncodedTerm>>,
+) -> HashMap<EncodedTerm, HashSet<EncodedTerm>> {
+    relation
+        .keys()
+        .map(|start| {
+            let mut reachable = HashSet::new();
+            let mut stack: Vec<_> = relation[start].iter().cloned().collect();
+            while let Some(term) = stack.pop() {
+                if reachable.insert(term) {
+                    stack.extend(relation.get(&term).into_iter().flatten().cloned());
+                }
+            }
+            (*start, reachable)
+        })
+        .collect()
+}
+
+/// Computes the classes implied by the rdfs:domain or rdfs:range statements for each property
+fn property_classes(
+    direct: &HashMap<EncodedTerm, HashSet<EncodedTerm>>,
+    super_properties: &HashMap<EncodedTerm, HashSet<EncodedTerm>>,
+    super_classes: &HashMap<EncodedTerm, HashSet<EncodedTerm>>,
+) -> HashMap<EncodedTerm, HashSet<EncodedTerm>> {
+    let mut classes: HashMap<EncodedTerm, HashSet<EncodedTerm>> = HashMap::default();
+    let properties = direct.keys().chain(super_properties.keys());
+    for property in properties {
+        let inherited = once(property).chain(super_properties.get(property).into_iter().flatten());
+        for class in inherited.filter_map(|p| direct.get(p)).flatten() {
+            let property_classes = classes.entry(*property).or_default();
+            property_classes.insert(*class);
+            property_classes.extend(super_classes.get(class).into_iter().flatten());
+        }
+    }
+    classes
+}
+
+/// The properties related to the class in the index or all the properties of the index if no class is given
+fn properties_for_class(
+    index: &HashMap<EncodedTerm, HashSet<EncodedTerm>>,
+    class: Option<EncodedTerm>,
+) -> HashSet<EncodedTerm> {
+    if let Some(class) = class {
+        index.get(&class).cloned().unwrap_or_default()
+    } else {
+        index.values().flatten().cloned().collect()
+    }
+}
+
+fn add_inverse(
+    inverse: &mut HashMap<EncodedTerm, HashSet<EncodedTerm>>,
+    relation: &HashMap<EncodedTerm, HashSet<EncodedTerm>>,
+) {
+    for (subject, objects) in relation {
+        for object in objects {
+            inverse.entry(*object).or_default().insert(*subject);
+        }
+    }
+}
+
+#[test]
+fn test_rdfs_rules() {
+    use crate::sparql::eval::query_values;
+    use crate::sparql::QueryOptions;
+    use crate::{DatasetSyntax, Repository, RepositoryConnection};
+
+    let repository = crate::MemoryRepository::default();
+    repository
+        .connection()
+        .unwrap()
+        .load_dataset(
+            b"@prefix ex: <http://example.com/> .
+            @prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .
+            ex:A rdfs:subClassOf ex:B . ex:B rdfs:subClassOf ex:C .
+            ex:p rdfs:subPropertyOf ex:q . ex:q rdfs:subPropertyOf ex:r .
+            ex:r rdfs:domain ex:A . ex:q rdfs:range ex:B .
+            ex:x a ex:A . ex:s1 ex:p ex:o1 . ex:s2 ex:p \"o2\" .
+            ex:g { ex:y a ex:A . ex:s3 ex:p ex:o3 }"
+                .as_ref(),
+            DatasetSyntax::TriG,
+            None,
+        )
+        .unwrap();
+    let select = |query: &str, options: QueryOptions<'_>| {
+        let query = format!(
+            "PREFIX ex: <http://example.com/> PREFIX rdfs: <http://www.w3.org/2000/01/rdf-schema#> SELECT ?r WHERE {{ {} }}",
+            query
+        );
+        let mut results = query_values(&repository, &query, options)
+            .into_iter()
+            .map(|values| {
+                values[0]
+                    .as_ref()
+                    .unwrap()
+                    .to_string()
+                    .replace("http://example.com/", "")
+            })
+            .collect::<Vec<_>>();
+        results.sort();
+        results
+    };
+    let entailed = |query: &str| select(query, QueryOptions::default().with_rdfs_entailment());
+
+    // rdfs9 and rdfs11 along the subClassOf chain
+    assert_eq!(entailed("ex:x a ?r"), vec!["<A>", "<B>", "<C>"]);
+    assert_eq!(entailed("ex:A rdfs:subClassOf ?r"), vec!["<B>", "<C>"]);
+    assert_eq!(entailed("?r rdfs:subClassOf ex:C"), vec!["<A>", "<B>"]);
+
+    // rdfs5 and rdfs7 along the subPropertyOf chain
+    assert_eq!(entailed("ex:s1 ?r ex:o1"), vec!["<p>", "<q>", "<r>"]);
+    assert_eq!(entailed("?r ex:r ex:o1"), vec!["<s1>"]);
+    assert_eq!(entailed("ex:p rdfs:subPropertyOf ?r"), vec!["<q>", "<r>"]);
+
+    // rdfs2 and rdfs3 with the domain and range inherited from the super properties and closed by subclass
+    assert_eq!(entailed("ex:s1 a ?r"), vec!["<A>", "<B>", "<C>"]);
+    assert_eq!(entailed("ex:o1 a ?r"), vec!["<B>", "<C>"]);
+    assert_eq!(entailed("?r a ex:A"), vec!["<s1>", "<s2>", "<x>"]);
+    assert_eq!(entailed("?r a ex:C"), vec!["<o1>", "<s1>", "<s2>", "<x>"]);
+    assert_eq!(entailed("?r ex:q \"o2\""), vec!["<s2>"]);
+
+    // The schema of a graph does not apply to the other graphs
+    assert_eq!(entailed("GRAPH ex:g { ex:y a ?r }"), vec!["<A>"]);
+    assert_eq!(entailed("GRAPH ex:g { ex:s3 ?r ex:o3 }"), vec!["<p>"]);
+
+    // Nothing is entailed by default
+    assert_eq!(select("ex:x a ?r", QueryOptions::default()), vec!["<A>"]);
+    assert_eq!(
+        select("ex:s1 ?r ex:o1", QueryOptions::default()),
+        vec!["<p>"]
+    );
+}
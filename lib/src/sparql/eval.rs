@@ -1,78 +1,154 @@
 use crate::model::BlankNode;
+use crate::model::Term;
 use crate::model::Triple;
 use crate::sparql::model::*;
 use crate::sparql::plan::*;
+use crate::sparql::spill;
+use crate::sparql::spill::{tuple_memory_size, SpillPartitions};
 use crate::store::numeric_encoder::*;
 use crate::store::StoreConnection;
+use crate::Error;
 use crate::Result;
 use chrono::prelude::*;
 use digest::Digest;
-use failure::format_err;
 use md5::Md5;
 use num_traits::identities::Zero;
 use num_traits::FromPrimitive;
 use num_traits::One;
 use num_traits::ToPrimitive;
 use rand::random;
+use rayon::prelude::*;
+use rayon::ThreadPool;
 use regex::{Regex, RegexBuilder};
 use rio_api::iri::Iri;
 use rio_api::model as rio;
 use rust_decimal::{Decimal, RoundingStrategy};
 use sha1::Sha1;
 use sha2::{Sha256, Sha384, Sha512};
-use std::cmp::min;
 use std::cmp::Ordering;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::cmp::{max, min};
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque};
 use std::convert::TryInto;
 use std::fmt::Write;
-use std::hash::Hash;
+use std::hash::{Hash, Hasher};
 use std::iter::Iterator;
-use std::iter::{empty, once};
+use std::iter::{empty, from_fn, once};
 use std::ops::Deref;
+use std::rc::Rc;
 use std::str;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 
 const REGEX_SIZE_LIMIT: usize = 1_000_000;
+/// Rough estimation of the number of bytes used by an aggregate accumulator
+const ACCUMULATOR_MEMORY_SIZE: usize = 64;
+/// Maximal number of tuples allocated in advance by a top-K heap
+const MAX_TOP_K_INITIAL_CAPACITY: usize = 1024;
 
 type EncodedTuplesIterator<'a> = Box<dyn Iterator<Item = Result<EncodedTuple>> + 'a>;
 
+/// Maximal number of path ends kept by a `PathCache`
+const MAX_PATH_CACHE_SIZE: usize = 1_000_000;
+
 pub struct SimpleEvaluator<S: StoreConnection> {
     dataset: DatasetView<S>,
-    bnodes_map: Mutex<BTreeMap<u128, u128>>,
+    bnodes_map: Arc<Mutex<BTreeMap<u128, u128>>>,
     base_iri: Option<Iri<String>>,
     now: DateTime<FixedOffset>,
+    thread_pool: Option<Arc<ThreadPool>>,
+    memory_limit: Option<usize>,
+}
+
+/// What is sent to a thread of the pool to build there an evaluator of the same query
+struct EvaluatorSeed<S: StoreConnection> {
+    dataset: DatasetViewSeed<S>,
+    bnodes_map: Arc<Mutex<BTreeMap<u128, u128>>>,
+    base_iri: Option<Iri<String>>,
+    now: DateTime<FixedOffset>,
+    memory_limit: Option<usize>,
+}
+
+impl<S: StoreConnection> EvaluatorSeed<S> {
+    fn into_evaluator(self) -> SimpleEvaluator<S> {
+        SimpleEvaluator {
+            dataset: self.dataset.into_view(),
+            bnodes_map: self.bnodes_map,
+            base_iri: self.base_iri,
+            now: self.now,
+            thread_pool: None,
+            memory_limit: self.memory_limit,
+        }
+    }
 }
 
 impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
-    pub fn new(dataset: DatasetView<S>, base_iri: Option<Iri<String>>) -> Self {
+    pub fn new(
+        dataset: DatasetView<S>,
+        base_iri: Option<Iri<String>>,
+        thread_pool: Option<Arc<ThreadPool>>,
+        memory_limit: Option<usize>,
+    ) -> Self {
         Self {
             dataset,
-            bnodes_map: Mutex::new(BTreeMap::default()),
+            bnodes_map: Arc::new(Mutex::new(BTreeMap::default())),
             base_iri,
             now: Utc::now().with_timezone(&FixedOffset::east(0)),
+            thread_pool,
+            memory_limit,
         }
     }
 
+    /// Encodes the values given to the variables before the evaluation into the tuple to evaluate the plan from
+    ///
+    /// The keys of the `pre_bound_variables`, the variables of the WHERE clause that are not returned, follow the ones of `variables`.
+    /// The values of the variables that are not used by the query are ignored.
+    pub fn encode_bindings(
+        &self,
+        variables: &[Variable],
+        pre_bound_variables: &[Variable],
+        bindings: &BTreeMap<Variable, Term>,
+    ) -> Result<EncodedTuple> {
+        let mut encoder = self.dataset.encoder();
+        let mut tuple = vec![None; variables.len() + pre_bound_variables.len()];
+        for (variable, value) in bindings {
+            if let Some(position) = variables
+                .iter()
+                .chain(pre_bound_variables)
+                .position(|v| v == variable)
+            {
+                tuple[position] = Some(encoder.encode_term(value)?);
+            }
+        }
+        Ok(tuple)
+    }
+
     pub fn evaluate_select_plan<'b>(
         &'b self,
         plan: &'b PlanNode,
         variables: &[Variable],
+        from: EncodedTuple,
     ) -> Result<QueryResult<'b>>
     where
         'a: 'b,
     {
-        let iter = self.eval_plan(plan, vec![None; variables.len()]);
+        self.dataset.clear_entailment_cache();
+        let iter = self.eval_plan_from_bindings(plan, from);
         Ok(QueryResult::Bindings(
             self.decode_bindings(iter, variables.to_vec()),
         ))
     }
 
-    pub fn evaluate_ask_plan<'b>(&'b self, plan: &'b PlanNode) -> Result<QueryResult<'b>>
+    pub fn evaluate_ask_plan<'b>(
+        &'b self,
+        plan: &'b PlanNode,
+        from: EncodedTuple,
+    ) -> Result<QueryResult<'b>>
     where
         'a: 'b,
     {
-        match self.eval_plan(plan, vec![]).next() {
+        self.dataset.clear_entailment_cache();
+        match self.eval_plan_from_bindings(plan, from).next() {
             Some(Ok(_)) => Ok(QueryResult::Boolean(true)),
             Some(Err(error)) => Err(error),
             None => Ok(QueryResult::Boolean(false)),
@@ -83,37 +159,70 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
         &'b self,
         plan: &'b PlanNode,
         construct: &'b [TripleTemplate],
+        from: EncodedTuple,
     ) -> Result<QueryResult<'b>>
     where
         'a: 'b,
     {
+        self.dataset.clear_entailment_cache();
         Ok(QueryResult::Graph(Box::new(ConstructIterator {
             eval: self,
-            iter: self.eval_plan(plan, vec![]),
+            iter: self.eval_plan_from_bindings(plan, from),
             template: construct,
             buffered_results: Vec::default(),
             bnodes: Vec::default(),
         })))
     }
 
-    pub fn evaluate_describe_plan<'b>(&'b self, plan: &'b PlanNode) -> Result<QueryResult<'b>>
+    pub fn evaluate_describe_plan<'b>(
+        &'b self,
+        plan: &'b PlanNode,
+        from: EncodedTuple,
+    ) -> Result<QueryResult<'b>>
     where
         'a: 'b,
     {
+        self.dataset.clear_entailment_cache();
         Ok(QueryResult::Graph(Box::new(DescribeIterator {
             eval: self,
-            iter: self.eval_plan(plan, vec![]),
+            iter: self.eval_plan_from_bindings(plan, from),
             quads: Box::new(empty()),
         })))
     }
 
+    /// Evaluates the plan from the bound values, dropping the results where an expression replaced one of them
+    fn eval_plan_from_bindings<'b>(
+        &'b self,
+        plan: &'b PlanNode,
+        from: EncodedTuple,
+    ) -> EncodedTuplesIterator<'b>
+    where
+        'a: 'b,
+    {
+        if from.iter().all(Option::is_none) {
+            return self.eval_plan(plan, from);
+        }
+        Box::new(
+            self.eval_plan(plan, from.clone())
+                .filter(move |tuple| match tuple {
+                    Ok(tuple) => are_tuples_compatible(tuple, &from),
+                    Err(_) => true,
+                }),
+        )
+    }
+
     fn eval_plan<'b>(&'b self, node: &'b PlanNode, from: EncodedTuple) -> EncodedTuplesIterator<'b>
     where
         'a: 'b,
     {
         match node {
             PlanNode::Init => Box::new(once(Ok(from))),
-            PlanNode::StaticBindings { tuples } => Box::new(tuples.iter().cloned().map(Ok)),
+            PlanNode::StaticBindings { tuples } => Box::new(
+                tuples
+                    .iter()
+                    .filter_map(move |tuple| combine_tuples(&from, tuple))
+                    .map(Ok),
+            ),
             PlanNode::QuadPatternJoin {
                 child,
                 subject,
@@ -169,14 +278,40 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                         }))
                     }
                 }
-                let iter: EncodedTuplesIterator<'_> = Box::new(iter.map(move |quad| {
-                    let quad = quad?;
+                let iter: EncodedTuplesIterator<'_> = Box::new(iter.filter_map(move |quad| {
+                    let quad = match quad {
+                        Ok(quad) => quad,
+                        Err(error) => return Some(Err(error)),
+                    };
                     let mut new_tuple = tuple.clone();
-                    put_pattern_value(&subject, quad.subject, &mut new_tuple);
-                    put_pattern_value(&predicate, quad.predicate, &mut new_tuple);
-                    put_pattern_value(&object, quad.object, &mut new_tuple);
-                    put_pattern_value(&graph_name, quad.graph_name, &mut new_tuple);
-                    Ok(new_tuple)
+                    let matches =
+                        put_pattern_value(&subject, quad.subject, &mut new_tuple, &self.dataset)
+                            .and_then(|matches| {
+                                Ok(matches
+                                    && put_pattern_value(
+                                        &predicate,
+                                        quad.predicate,
+                                        &mut new_tuple,
+                                        &self.dataset,
+                                    )?
+                                    && put_pattern_value(
+                                        &object,
+                                        quad.object,
+                                        &mut new_tuple,
+                                        &self.dataset,
+                                    )?
+                                    && put_pattern_value(
+                                        &graph_name,
+                                        quad.graph_name,
+                                        &mut new_tuple,
+                                        &self.dataset,
+                                    )?)
+                            });
+                    match matches {
+                        Ok(true) => Some(Ok(new_tuple)),
+                        Ok(false) => None,
+                        Err(error) => Some(Err(error)),
+                    }
                 }));
                 iter
             })),
@@ -186,65 +321,110 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                 path,
                 object,
                 graph_name,
-            } => Box::new(self.eval_plan(&*child, from).flat_map_ok(move |tuple| {
-                let input_subject = get_pattern_value(&subject, &tuple);
-                let input_object = get_pattern_value(&object, &tuple);
-                let input_graph_name =
-                    if let Some(graph_name) = get_pattern_value(&graph_name, &tuple) {
-                        graph_name
-                    } else {
-                        return Box::new(once(Err(format_err!(
-                            "Unknown graph name is not allowed when evaluating property path"
-                        )))) as EncodedTuplesIterator<'_>;
-                    };
-                match (input_subject, input_object) {
-                    (Some(input_subject), Some(input_object)) => Box::new(
-                        self.eval_path_from(path, input_subject, input_graph_name)
-                            .filter_map(move |o| match o {
-                                Ok(o) => {
-                                    if o == input_object {
-                                        Some(Ok(tuple.clone()))
-                                    } else {
-                                        None
+            } => {
+                let mut from_cache = PathCache::new(MAX_PATH_CACHE_SIZE);
+                let mut to_cache = PathCache::new(MAX_PATH_CACHE_SIZE);
+                Box::new(self.eval_plan(&*child, from).flat_map_ok(move |tuple| {
+                    let input_subject = get_pattern_value(&subject, &tuple);
+                    let input_object = get_pattern_value(&object, &tuple);
+                    let input_graph_name =
+                        if let Some(graph_name) = get_pattern_value(&graph_name, &tuple) {
+                            graph_name
+                        } else {
+                            return Box::new(once(Err(Error::evaluation(
+                                "Unknown graph name is not allowed when evaluating property path",
+                            )))) as EncodedTuplesIterator<'_>;
+                        };
+                    match (input_subject, input_object) {
+                        (Some(input_subject), Some(input_object)) if path.is_transitive() => {
+                            match self.is_path_connected(
+                                path,
+                                input_subject,
+                                input_object,
+                                input_graph_name,
+                            ) {
+                                Ok(true) => Box::new(once(Ok(tuple))),
+                                Ok(false) => Box::new(empty()),
+                                Err(error) => Box::new(once(Err(error))),
+                            }
+                        }
+                        (Some(input_subject), Some(input_object)) => Box::new(
+                            self.eval_path_from(path, input_subject, input_graph_name)
+                                .filter_map(move |o| match o {
+                                    Ok(o) => {
+                                        if o == input_object {
+                                            Some(Ok(tuple.clone()))
+                                        } else {
+                                            None
+                                        }
                                     }
-                                }
-                                Err(error) => Some(Err(error)),
-                            }),
-                    )
-                        as EncodedTuplesIterator<'_>,
-                    (Some(input_subject), None) => Box::new(
-                        self.eval_path_from(path, input_subject, input_graph_name)
-                            .map(move |o| {
+                                    Err(error) => Some(Err(error)),
+                                }),
+                        )
+                            as EncodedTuplesIterator<'_>,
+                        (Some(input_subject), None) => Box::new(
+                            self.eval_cached_path(
+                                &mut from_cache,
+                                path,
+                                input_subject,
+                                input_graph_name,
+                                |start| self.eval_path_from(path, start, input_graph_name),
+                            )
+                            .filter_map(move |o| {
                                 let mut new_tuple = tuple.clone();
-                                put_pattern_value(&object, o?, &mut new_tuple);
-                                Ok(new_tuple)
+                                o.and_then(|o| {
+                                    put_pattern_value(&object, o, &mut new_tuple, &self.dataset)
+                                })
+                                .map(|matches| if matches { Some(new_tuple) } else { None })
+                                .transpose()
                             }),
-                    ),
-                    (None, Some(input_object)) => Box::new(
-                        self.eval_path_to(path, input_object, input_graph_name)
-                            .map(move |s| {
+                        ),
+                        (None, Some(input_object)) => Box::new(
+                            self.eval_cached_path(
+                                &mut to_cache,
+                                path,
+                                input_object,
+                                input_graph_name,
+                                |end| self.eval_path_to(path, end, input_graph_name),
+                            )
+                            .filter_map(move |s| {
                                 let mut new_tuple = tuple.clone();
-                                put_pattern_value(&subject, s?, &mut new_tuple);
-                                Ok(new_tuple)
+                                s.and_then(|s| {
+                                    put_pattern_value(&subject, s, &mut new_tuple, &self.dataset)
+                                })
+                                .map(|matches| if matches { Some(new_tuple) } else { None })
+                                .transpose()
                             }),
-                    ),
-                    (None, None) => {
-                        Box::new(self.eval_open_path(path, input_graph_name).map(move |so| {
-                            let mut new_tuple = tuple.clone();
-                            so.map(move |(s, o)| {
-                                put_pattern_value(&subject, s, &mut new_tuple);
-                                put_pattern_value(&object, o, &mut new_tuple);
-                                new_tuple
-                            })
-                        }))
+                        ),
+                        (None, None) => Box::new(
+                            self.eval_open_path(path, input_graph_name)
+                                .filter_map(move |so| {
+                                    let mut new_tuple = tuple.clone();
+                                    so.and_then(|(s, o)| {
+                                        Ok(put_pattern_value(
+                                            &subject,
+                                            s,
+                                            &mut new_tuple,
+                                            &self.dataset,
+                                        )? && put_pattern_value(
+                                            &object,
+                                            o,
+                                            &mut new_tuple,
+                                            &self.dataset,
+                                        )?)
+                                    })
+                                    .map(|matches| if matches { Some(new_tuple) } else { None })
+                                    .transpose()
+                                }),
+                        ),
                     }
-                }
-            })),
+                }))
+            }
             PlanNode::Join { left, right } => {
-                //TODO: very dumb implementation
                 let mut errors = Vec::default();
                 let left_values = self
-                    .eval_plan(&*left, from.clone())
+                    .collect_plan(&*left, from.clone())
+                    .into_iter()
                     .filter_map(|result| match result {
                         Ok(result) => Some(result),
                         Err(error) => {
@@ -254,22 +434,32 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                     })
                     .collect::<Vec<_>>();
                 Box::new(JoinIterator {
-                    left: left_values,
+                    left: JoinHashTable::new(left_values, self.thread_pool.as_deref()),
                     right_iter: self.eval_plan(&*right, from),
                     buffered_results: errors,
                 })
             }
             PlanNode::AntiJoin { left, right } => {
-                //TODO: dumb implementation
                 let right: Vec<_> = self
-                    .eval_plan(&*right, from.clone())
+                    .collect_plan(&*right, from.clone())
+                    .into_iter()
                     .filter_map(|result| result.ok())
                     .collect();
                 Box::new(AntiJoinIterator {
                     left_iter: self.eval_plan(&*left, from),
-                    right,
+                    right: JoinHashTable::new(right, self.thread_pool.as_deref()),
                 })
             }
+            PlanNode::SemiJoin {
+                left,
+                right,
+                correlated,
+            } => self.eval_semi_join(&*left, &*right, *correlated, false, from),
+            PlanNode::AntiSemiJoin {
+                left,
+                right,
+                correlated,
+            } => self.eval_semi_join(&*left, &*right, *correlated, true, from),
             PlanNode::LeftJoin {
                 left,
                 right,
@@ -328,9 +518,16 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                 }))
             }
             PlanNode::Sort { child, by } => {
+                let compare = move |a: &EncodedTuple, b: &EncodedTuple| {
+                    self.cmp_according_to_comparators(a, b, by)
+                };
+                if let Some(memory_limit) = self.memory_limit {
+                    return spill::sort(self.eval_plan(&*child, from), memory_limit, compare);
+                }
                 let mut errors = Vec::default();
                 let mut values = self
-                    .eval_plan(&*child, from)
+                    .collect_plan(&*child, from)
+                    .into_iter()
                     .filter_map(|result| match result {
                         Ok(result) => Some(result),
                         Err(error) => {
@@ -339,43 +536,65 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                         }
                     })
                     .collect::<Vec<_>>();
-                values.sort_unstable_by(|a, b| {
-                    for comp in by {
-                        match comp {
-                            Comparator::Asc(expression) => {
-                                match self.cmp_according_to_expression(a, b, &expression) {
-                                    Ordering::Greater => return Ordering::Greater,
-                                    Ordering::Less => return Ordering::Less,
-                                    Ordering::Equal => (),
-                                }
-                            }
-                            Comparator::Desc(expression) => {
-                                match self.cmp_according_to_expression(a, b, &expression) {
-                                    Ordering::Greater => return Ordering::Less,
-                                    Ordering::Less => return Ordering::Greater,
-                                    Ordering::Equal => (),
-                                }
-                            }
-                        }
-                    }
-                    Ordering::Equal
-                });
+                values.sort_unstable_by(compare);
                 Box::new(errors.into_iter().chain(values.into_iter().map(Ok)))
             }
+            PlanNode::TopK { child, by, count } => {
+                let compare = |a: &EncodedTuple, b: &EncodedTuple| {
+                    self.cmp_according_to_comparators(a, b, by)
+                };
+                Box::new(top_k(self.eval_plan(&*child, from), *count, &compare).into_iter())
+            }
             PlanNode::HashDeduplicate { child } => {
-                Box::new(hash_deduplicate(self.eval_plan(&*child, from)))
+                if let Some(memory_limit) = self.memory_limit {
+                    if is_sorted(child) {
+                        spill::deduplicate_keeping_order(
+                            self.eval_plan(&*child, from),
+                            memory_limit,
+                        )
+                    } else {
+                        spill::deduplicate(self.eval_plan(&*child, from), memory_limit)
+                    }
+                } else {
+                    Box::new(hash_deduplicate(self.eval_plan(&*child, from)))
+                }
             }
             PlanNode::Skip { child, count } => Box::new(self.eval_plan(&*child, from).skip(*count)),
             PlanNode::Limit { child, count } => {
                 Box::new(self.eval_plan(&*child, from).take(*count))
             }
-            PlanNode::Project { child, mapping } => {
-                //TODO: use from somewhere?
+            PlanNode::Project {
+                child,
+                mapping,
+                pre_bound,
+            } => {
+                // The values of the projected and pre-bound variables are given to the child
+                let mut input_tuple = vec![None; mapping.len()];
+                for (input_key, output_key) in mapping.iter().chain(pre_bound) {
+                    if let Some(value) = get_tuple_value(*output_key, &from) {
+                        put_value(*input_key, value, &mut input_tuple)
+                    }
+                }
+                let tuple_size = from.len();
                 Box::new(
-                    self.eval_plan(&*child, vec![None; mapping.len()])
+                    self.eval_plan(&*child, input_tuple)
+                        .filter(move |tuple| match tuple {
+                            // The results where a BIND gave a pre-bound variable another value are dropped
+                            // here because the non projected variables are not returned
+                            Ok(tuple) => pre_bound.iter().all(|(input_key, output_key)| {
+                                match (
+                                    get_tuple_value(*input_key, tuple),
+                                    get_tuple_value(*output_key, &from),
+                                ) {
+                                    (Some(value), Some(bound_value)) => value == bound_value,
+                                    _ => true,
+                                }
+                            }),
+                            Err(_) => true,
+                        })
                         .map(move |tuple| {
                             let tuple = tuple?;
-                            let mut output_tuple = vec![None; from.len()];
+                            let mut output_tuple = vec![None; tuple_size];
                             for (input_key, output_key) in mapping.iter() {
                                 if let Some(value) = tuple[*input_key] {
                                     put_value(*output_key, value, &mut output_tuple)
@@ -391,72 +610,292 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                 aggregates,
             } => {
                 let tuple_size = from.len(); //TODO: not nice
-                let mut errors = Vec::default();
-                let mut accumulators_for_group =
-                    HashMap::<Vec<Option<EncodedTerm>>, Vec<Box<dyn Accumulator>>>::default();
-                self.eval_plan(child, from)
-                    .filter_map(|result| match result {
-                        Ok(result) => Some(result),
-                        Err(error) => {
-                            errors.push(error);
-                            None
+                                             // The values of the group keys are given to the child
+                let mut from_keys = vec![None; key_mapping.len()];
+                for (input_key, output_key) in key_mapping.iter().enumerate() {
+                    from_keys[input_key] = get_tuple_value(*output_key, &from);
+                }
+                let from = from_keys;
+                match (&self.thread_pool, self.memory_limit) {
+                    (Some(thread_pool), None) => {
+                        // The groups are split between the threads
+                        let mut errors = Vec::default();
+                        let mut partitions =
+                            vec![Vec::default(); thread_pool.current_num_threads()];
+                        for result in self.collect_plan(child, from) {
+                            match result {
+                                Ok(tuple) => {
+                                    let mut hasher = DefaultHasher::new();
+                                    group_key(&tuple, key_mapping.len()).hash(&mut hasher);
+                                    let partition = (hasher.finish() as usize) % partitions.len();
+                                    partitions[partition].push(tuple)
+                                }
+                                Err(error) => errors.push(Err(error)),
+                            }
                         }
-                    })
-                    .for_each(|tuple| {
-                        //TODO avoid copy for key?
-                        let key = (0..key_mapping.len())
-                            .map(|v| get_tuple_value(v, &tuple))
-                            .collect();
-
-                        let key_accumulators =
-                            accumulators_for_group.entry(key).or_insert_with(|| {
-                                aggregates
-                                    .iter()
-                                    .map(|(aggregate, _)| {
-                                        self.accumulator_for_aggregate(
-                                            &aggregate.function,
-                                            aggregate.distinct,
-                                        )
-                                    })
-                                    .collect::<Vec<_>>()
-                            });
-                        for (i, accumulator) in key_accumulators.iter_mut().enumerate() {
-                            let (aggregate, _) = &aggregates[i];
-                            accumulator.add(
-                                aggregate
-                                    .parameter
-                                    .as_ref()
-                                    .and_then(|parameter| self.eval_expression(&parameter, &tuple)),
-                            );
+                        partitions.retain(|partition| !partition.is_empty());
+                        let mut results = self
+                            .run_in_parallel(thread_pool, partitions, |eval, partition| {
+                                eval.aggregate(
+                                    partition.into_iter().map(Ok),
+                                    key_mapping,
+                                    aggregates,
+                                    tuple_size,
+                                    0,
+                                )
+                                .collect::<Vec<_>>()
+                            })
+                            .into_iter()
+                            .flatten()
+                            .collect::<Vec<_>>();
+                        if results.is_empty() {
+                            // There is always at least one group
+                            results.push(Ok(vec![None; tuple_size]));
                         }
-                    });
-                if accumulators_for_group.is_empty() {
-                    // There is always at least one group
-                    accumulators_for_group.insert(vec![None; key_mapping.len()], Vec::default());
+                        Box::new(errors.into_iter().chain(results))
+                    }
+                    _ => self.aggregate(
+                        self.eval_plan(child, from),
+                        key_mapping,
+                        aggregates,
+                        tuple_size,
+                        0,
+                    ),
                 }
-                Box::new(
-                    errors
-                        .into_iter()
-                        .map(Err)
-                        .chain(accumulators_for_group.into_iter().map(
-                            move |(key, accumulators)| {
-                                let mut result = vec![None; tuple_size];
-                                for (from_position, to_position) in key_mapping.iter().enumerate() {
-                                    if let Some(value) = key[from_position] {
-                                        put_value(*to_position, value, &mut result);
-                                    }
-                                }
-                                for (i, accumulator) in accumulators.into_iter().enumerate() {
-                                    if let Some(value) = accumulator.state() {
-                                        put_value(aggregates[i].1, value, &mut result);
-                                    }
+            }
+        }
+    }
+
+    /// Groups the tuples by their `key_mapping.len()` first values and returns a tuple with the aggregates for each group
+    ///
+    /// If a memory limit is set, the tuples of the groups that do not fit in memory are written to hash partitions
+    /// that are aggregated after the groups kept in memory.
+    /// `partitions_level` is the number of times the tuples have already been partitioned.
+    fn aggregate<'b>(
+        &'b self,
+        tuples: impl Iterator<Item = Result<EncodedTuple>>,
+        key_mapping: &'b [usize],
+        aggregates: &'b [(PlanAggregation, usize)],
+        tuple_size: usize,
+        partitions_level: usize,
+    ) -> EncodedTuplesIterator<'b>
+    where
+        'a: 'b,
+    {
+        let mut errors = Vec::default();
+        let mut accumulators_for_group =
+            HashMap::<Vec<Option<EncodedTerm>>, Vec<Box<dyn Accumulator>>>::default();
+        let mut groups_size = 0;
+        let mut partitions = SpillPartitions::new(partitions_level);
+        for tuple in tuples {
+            let tuple = match tuple {
+                Ok(tuple) => tuple,
+                Err(error) => {
+                    errors.push(Err(error));
+                    continue;
+                }
+            };
+            //TODO avoid copy for key?
+            let key_accumulators =
+                match accumulators_for_group.entry(group_key(&tuple, key_mapping.len())) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        if let Some(memory_limit) = self.memory_limit {
+                            if groups_size >= memory_limit && groups_size > 0 {
+                                // The group is aggregated later from its partition
+                                if let Err(error) = partitions.write(entry.key(), &tuple) {
+                                    errors.push(Err(error));
                                 }
-                                Ok(result)
-                            },
-                        )),
-                )
+                                continue;
+                            }
+                            groups_size += tuple_memory_size(entry.key())
+                                + aggregates.len() * ACCUMULATOR_MEMORY_SIZE;
+                        }
+                        entry.insert(
+                            aggregates
+                                .iter()
+                                .map(|(aggregate, _)| {
+                                    self.accumulator_for_aggregate(
+                                        &aggregate.function,
+                                        aggregate.distinct,
+                                    )
+                                })
+                                .collect::<Vec<_>>(),
+                        )
+                    }
+                };
+            for (i, accumulator) in key_accumulators.iter_mut().enumerate() {
+                let (aggregate, _) = &aggregates[i];
+                accumulator.add(
+                    aggregate
+                        .parameter
+                        .as_ref()
+                        .and_then(|parameter| self.eval_expression(&parameter, &tuple)),
+                );
             }
         }
+        if accumulators_for_group.is_empty() && partitions_level == 0 {
+            // There is always at least one group
+            accumulators_for_group.insert(vec![None; key_mapping.len()], Vec::default());
+        }
+        let results = accumulators_for_group
+            .into_iter()
+            .map(move |(key, accumulators)| {
+                let mut result = vec![None; tuple_size];
+                for (from_position, to_position) in key_mapping.iter().enumerate() {
+                    if let Some(value) = key[from_position] {
+                        put_value(*to_position, value, &mut result);
+                    }
+                }
+                for (i, accumulator) in accumulators.into_iter().enumerate() {
+                    if let Some(value) = accumulator.state() {
+                        put_value(aggregates[i].1, value, &mut result);
+                    }
+                }
+                Ok(result)
+            });
+        let partitions_results = partitions
+            .read()
+            .flat_map(move |partition| match partition {
+                Ok(tuples) => self.aggregate(
+                    tuples,
+                    key_mapping,
+                    aggregates,
+                    tuple_size,
+                    partitions_level + 1,
+                ),
+                Err(error) => Box::new(once(Err(error))),
+            });
+        Box::new(errors.into_iter().chain(results).chain(partitions_results))
+    }
+
+    /// Keeps the tuples of `left` having a compatible tuple in `right`, or not having one if `negated`
+    fn eval_semi_join<'b>(
+        &'b self,
+        left: &'b PlanNode,
+        right: &'b PlanNode,
+        correlated: bool,
+        negated: bool,
+        from: EncodedTuple,
+    ) -> EncodedTuplesIterator<'b>
+    where
+        'a: 'b,
+    {
+        if correlated {
+            let eval = self;
+            return Box::new(self.eval_plan(left, from).filter(move |tuple| match tuple {
+                Ok(tuple) => eval.eval_plan(right, tuple.clone()).next().is_some() != negated,
+                Err(_) => true,
+            }));
+        }
+        let mut errors = Vec::default();
+        let right_values = self
+            .collect_plan(right, from.clone())
+            .into_iter()
+            .filter_map(|result| match result {
+                Ok(result) => Some(result),
+                Err(error) => {
+                    errors.push(Err(error));
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        let right = JoinHashTable::new(right_values, self.thread_pool.as_deref());
+        Box::new(
+            errors
+                .into_iter()
+                .chain(self.eval_plan(left, from).filter(move |tuple| {
+                    match tuple {
+                        Ok(tuple) => {
+                            right
+                                .candidates(tuple)
+                                .any(|right_tuple| are_tuples_compatible(tuple, right_tuple))
+                                != negated
+                        }
+                        Err(_) => true,
+                    }
+                })),
+        )
+    }
+
+    /// Evaluates the plan and collects its results
+    ///
+    /// If a thread pool is set, the results of the first pattern of the plan are partitioned between the threads
+    /// that evaluate the rest of the plan on each partition.
+    fn collect_plan<'b>(
+        &'b self,
+        node: &'b PlanNode,
+        from: EncodedTuple,
+    ) -> Vec<Result<EncodedTuple>>
+    where
+        'a: 'b,
+    {
+        if let Some(thread_pool) = &self.thread_pool {
+            if let Some((first, rest)) = split_first_pattern(node) {
+                let mut results = Vec::default();
+                let mut inputs = Vec::default();
+                for result in self.eval_plan(first, from) {
+                    match result {
+                        Ok(tuple) => inputs.push(tuple),
+                        Err(error) => results.push(Err(error)),
+                    }
+                }
+                let partitions = partition_tuples(inputs, thread_pool.current_num_threads());
+                results.extend(
+                    self.run_in_parallel(thread_pool, partitions, |eval, partition| {
+                        partition
+                            .into_iter()
+                            .flat_map(|tuple| eval.eval_plan(&rest, tuple))
+                            .collect::<Vec<_>>()
+                    })
+                    .into_iter()
+                    .flatten(),
+                );
+                return results;
+            }
+        }
+        self.eval_plan(node, from).collect()
+    }
+
+    /// Runs the task on each partition in the threads of the pool, each with its own evaluator of the same query
+    fn run_in_parallel<I: Send, O: Send>(
+        &self,
+        thread_pool: &ThreadPool,
+        partitions: Vec<I>,
+        task: impl Fn(&SimpleEvaluator<S>, I) -> O + Sync,
+    ) -> Vec<O> {
+        let seeded_partitions = partitions
+            .into_iter()
+            .map(|partition| (self.seed(), partition))
+            .collect::<Vec<_>>();
+        let outputs = thread_pool.install(|| {
+            seeded_partitions
+                .into_par_iter()
+                .map(|(seed, partition)| {
+                    let eval = seed.into_evaluator();
+                    let output = task(&eval, partition);
+                    (output, eval.dataset.into_extra_strings())
+                })
+                .collect::<Vec<_>>()
+        });
+        outputs
+            .into_iter()
+            .map(|(output, strings)| {
+                // The strings built by the threads are needed to decode their outputs
+                self.dataset.add_extra_strings(strings);
+                output
+            })
+            .collect()
+    }
+
+    fn seed(&self) -> EvaluatorSeed<S> {
+        EvaluatorSeed {
+            dataset: self.dataset.seed(),
+            bnodes_map: self.bnodes_map.clone(),
+            base_iri: self.base_iri.clone(),
+            now: self.now,
+            memory_limit: self.memory_limit,
+        }
     }
 
     fn accumulator_for_aggregate<'b>(
@@ -473,19 +912,21 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                 }
             }
             PlanAggregationFunction::Sum => {
+                let sum = ValueAccumulator::new(self, SumAccumulator::default());
                 if distinct {
-                    Box::new(DistinctAccumulator::new(SumAccumulator::default()))
+                    Box::new(DistinctAccumulator::new(sum))
                 } else {
-                    Box::new(SumAccumulator::default())
+                    Box::new(sum)
                 }
             }
             PlanAggregationFunction::Min => Box::new(MinAccumulator::new(self)), // DISTINCT does not make sense with min
             PlanAggregationFunction::Max => Box::new(MaxAccumulator::new(self)), // DISTINCT does not make sense with max
             PlanAggregationFunction::Avg => {
+                let avg = ValueAccumulator::new(self, AvgAccumulator::default());
                 if distinct {
-                    Box::new(DistinctAccumulator::new(AvgAccumulator::default()))
+                    Box::new(DistinctAccumulator::new(avg))
                 } else {
-                    Box::new(AvgAccumulator::default())
+                    Box::new(avg)
                 }
             }
             PlanAggregationFunction::Sample => Box::new(SampleAccumulator::default()), // DISTINCT does not make sense with sample
@@ -626,22 +1067,43 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                 self.eval_open_path(&a, graph_name)
                     .chain(self.eval_open_path(&b, graph_name)),
             ),
-            PlanPropertyPath::ZeroOrMorePath(p) => Box::new(transitive_closure(
-                self.get_subject_or_object_identity_pairs(graph_name), //TODO: avoid to inject everything
-                move |(start, middle)| {
-                    self.eval_path_from(p, middle, graph_name)
-                        .map(move |end| Ok((start, end?)))
-                },
-            )),
-            PlanPropertyPath::OneOrMorePath(p) => Box::new(transitive_closure(
-                self.eval_open_path(p, graph_name),
-                move |(start, middle)| {
-                    self.eval_path_from(p, middle, graph_name)
+            // The step relation is evaluated only once and kept in memory.
+            // The closures are computed from it one start node at a time so that the results are streamed.
+            PlanPropertyPath::ZeroOrMorePath(p) | PlanPropertyPath::OneOrMorePath(p) => {
+                let mut successors = HashMap::<EncodedTerm, Vec<EncodedTerm>>::default();
+                for step in self.eval_open_path(p, graph_name) {
+                    match step {
+                        Ok((start, end)) => successors.entry(start).or_default().push(end),
+                        Err(error) => return Box::new(once(Err(error))),
+                    }
+                }
+                let successors = Rc::new(successors);
+                let zero_length = matches!(path, PlanPropertyPath::ZeroOrMorePath(_));
+                let starts: Box<dyn Iterator<Item = Result<EncodedTerm>>> = if zero_length {
+                    Box::new(self.get_subject_or_object_nodes(graph_name))
+                } else {
+                    Box::new(
+                        successors
+                            .keys()
+                            .map(|start| Ok(*start))
+                            .collect::<Vec<_>>()
+                            .into_iter(),
+                    )
+                };
+                Box::new(starts.flat_map_ok(move |start| {
+                    let next = successors.clone();
+                    let first: Box<dyn Iterator<Item = Result<EncodedTerm>>> = if zero_length {
+                        Box::new(once(Ok(start)))
+                    } else {
+                        Box::new(successors_in_memory(&successors, start))
+                    };
+                    transitive_closure(first, move |e| successors_in_memory(&next, e))
                         .map(move |end| Ok((start, end?)))
-                },
-            )),
+                }))
+            }
             PlanPropertyPath::ZeroOrOnePath(p) => Box::new(hash_deduplicate(
-                self.get_subject_or_object_identity_pairs(graph_name)
+                self.get_subject_or_object_nodes(graph_name)
+                    .map(|e| e.map(|e| (e, e)))
                     .chain(self.eval_open_path(&p, graph_name)),
             )),
             PlanPropertyPath::NegatedPropertySet(ps) => Box::new(
@@ -656,14 +1118,104 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
         }
     }
 
-    fn get_subject_or_object_identity_pairs<'b>(
+    fn get_subject_or_object_nodes<'b>(
         &'b self,
         graph_name: EncodedTerm,
-    ) -> impl Iterator<Item = Result<(EncodedTerm, EncodedTerm)>> + 'b {
-        self.dataset
-            .quads_for_pattern(None, None, None, Some(graph_name))
-            .flat_map_ok(|t| once(Ok(t.subject)).chain(once(Ok(t.object))))
-            .map(|e| e.map(|e| (e, e)))
+    ) -> impl Iterator<Item = Result<EncodedTerm>> + 'b {
+        hash_deduplicate(
+            self.dataset
+                .quads_for_pattern(None, None, None, Some(graph_name))
+                .flat_map_ok(|t| once(Ok(t.subject)).chain(once(Ok(t.object)))),
+        )
+    }
+
+    /// Evaluates a path from one of its ends, reusing the transitive closures already computed for the same node
+    fn eval_cached_path<'b>(
+        &'b self,
+        cache: &mut PathCache,
+        path: &PlanPropertyPath,
+        node: EncodedTerm,
+        graph_name: EncodedTerm,
+        eval: impl FnOnce(EncodedTerm) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'b>,
+    ) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'b> {
+        if !path.is_transitive() {
+            return eval(node);
+        }
+        let ends = match cache.get(node, graph_name) {
+            Some(ends) => ends,
+            None => match eval(node).collect::<Result<Vec<_>>>() {
+                Ok(ends) => cache.insert(node, graph_name, ends),
+                Err(error) => return Box::new(once(Err(error))),
+            },
+        };
+        Box::new((0..ends.len()).map(move |i| Ok(ends[i])))
+    }
+
+    /// Checks if `end` is reachable from `start` using a transitive path
+    ///
+    /// It is a bidirectional breadth-first search that always expands the smallest frontier.
+    fn is_path_connected(
+        &self,
+        path: &PlanPropertyPath,
+        start: EncodedTerm,
+        end: EncodedTerm,
+        graph_name: EncodedTerm,
+    ) -> Result<bool> {
+        let (step, mut forward) = match path {
+            PlanPropertyPath::ZeroOrMorePath(p) => (p, vec![start]),
+            PlanPropertyPath::OneOrMorePath(p) => (
+                p,
+                self.eval_path_from(p, start, graph_name)
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            _ => {
+                for e in self.eval_path_from(path, start, graph_name) {
+                    if e? == end {
+                        return Ok(true);
+                    }
+                }
+                return Ok(false);
+            }
+        };
+        let mut backward = vec![end];
+        let mut forward_visited = HashSet::new();
+        forward.retain(|e| forward_visited.insert(*e));
+        let mut backward_visited = HashSet::new();
+        backward_visited.insert(end);
+        if forward_visited.contains(&end) {
+            return Ok(true);
+        }
+        while !forward.is_empty() && !backward.is_empty() {
+            let mut next = Vec::default();
+            if forward.len() <= backward.len() {
+                for node in forward {
+                    for e in self.eval_path_from(step, node, graph_name) {
+                        let e = e?;
+                        if backward_visited.contains(&e) {
+                            return Ok(true);
+                        }
+                        if forward_visited.insert(e) {
+                            next.push(e);
+                        }
+                    }
+                }
+                forward = next;
+            } else {
+                for node in backward {
+                    for e in self.eval_path_to(step, node, graph_name) {
+                        let e = e?;
+                        if forward_visited.contains(&e) {
+                            return Ok(true);
+                        }
+                        if backward_visited.insert(e) {
+                            next.push(e);
+                        }
+                    }
+                }
+                backward = next;
+            }
+        }
+        Ok(false)
     }
 
     fn eval_expression(
@@ -794,14 +1346,14 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                     .into(),
                 NumericBinaryOperands::Decimal(v1, v2) => v1.checked_div(v2)?.into(),
             }),
-            PlanExpression::UnaryPlus(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::UnaryPlus(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::FloatLiteral(value) => Some((*value).into()),
                 EncodedTerm::DoubleLiteral(value) => Some((*value).into()),
                 EncodedTerm::IntegerLiteral(value) => Some((value).into()),
                 EncodedTerm::DecimalLiteral(value) => Some((value).into()),
                 _ => None,
             },
-            PlanExpression::UnaryMinus(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::UnaryMinus(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::FloatLiteral(value) => Some((-*value).into()),
                 EncodedTerm::DoubleLiteral(value) => Some((-*value).into()),
                 EncodedTerm::IntegerLiteral(value) => Some((-value).into()),
@@ -883,28 +1435,28 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                 }),
             },
             PlanExpression::Rand => Some(random::<f64>().into()),
-            PlanExpression::Abs(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::Abs(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::IntegerLiteral(value) => Some(value.checked_abs()?.into()),
                 EncodedTerm::DecimalLiteral(value) => Some(value.abs().into()),
                 EncodedTerm::FloatLiteral(value) => Some(value.abs().into()),
                 EncodedTerm::DoubleLiteral(value) => Some(value.abs().into()),
                 _ => None,
             },
-            PlanExpression::Ceil(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::Ceil(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::IntegerLiteral(value) => Some(value.into()),
                 EncodedTerm::DecimalLiteral(value) => Some(value.ceil().into()),
                 EncodedTerm::FloatLiteral(value) => Some(value.ceil().into()),
                 EncodedTerm::DoubleLiteral(value) => Some(value.ceil().into()),
                 _ => None,
             },
-            PlanExpression::Floor(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::Floor(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::IntegerLiteral(value) => Some(value.into()),
                 EncodedTerm::DecimalLiteral(value) => Some(value.floor().into()),
                 EncodedTerm::FloatLiteral(value) => Some(value.floor().into()),
                 EncodedTerm::DoubleLiteral(value) => Some(value.floor().into()),
                 _ => None,
             },
-            PlanExpression::Round(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::Round(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::IntegerLiteral(value) => Some(value.into()),
                 EncodedTerm::DecimalLiteral(value) => Some(
                     value
@@ -936,15 +1488,14 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                 let (source, language) =
                     self.to_string_and_language(self.eval_expression(source, tuple)?)?;
 
-                let starting_location: usize = if let EncodedTerm::IntegerLiteral(v) =
-                    self.eval_expression(starting_loc, tuple)?
-                {
-                    v.try_into().ok()?
-                } else {
-                    return None;
-                };
+                let starting_location: usize =
+                    if let EncodedTerm::IntegerLiteral(v) = self.eval_value(starting_loc, tuple)? {
+                        v.try_into().ok()?
+                    } else {
+                        return None;
+                    };
                 let length: Option<usize> = if let Some(length) = length {
-                    if let EncodedTerm::IntegerLiteral(v) = self.eval_expression(length, tuple)? {
+                    if let EncodedTerm::IntegerLiteral(v) = self.eval_value(length, tuple)? {
                         Some(v.try_into().ok()?)
                     } else {
                         return None;
@@ -1076,40 +1627,40 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                     Some(ENCODED_EMPTY_STRING_LITERAL)
                 }
             }
-            PlanExpression::Year(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::Year(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::DateLiteral(date) => Some(date.year().into()),
                 EncodedTerm::NaiveDateLiteral(date) => Some(date.year().into()),
                 EncodedTerm::DateTimeLiteral(date_time) => Some(date_time.year().into()),
                 EncodedTerm::NaiveDateTimeLiteral(date_time) => Some(date_time.year().into()),
                 _ => None,
             },
-            PlanExpression::Month(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::Month(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::DateLiteral(date) => Some(date.year().into()),
                 EncodedTerm::NaiveDateLiteral(date) => Some(date.month().into()),
                 EncodedTerm::DateTimeLiteral(date_time) => Some(date_time.month().into()),
                 EncodedTerm::NaiveDateTimeLiteral(date_time) => Some(date_time.month().into()),
                 _ => None,
             },
-            PlanExpression::Day(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::Day(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::DateLiteral(date) => Some(date.year().into()),
                 EncodedTerm::NaiveDateLiteral(date) => Some(date.day().into()),
                 EncodedTerm::DateTimeLiteral(date_time) => Some(date_time.day().into()),
                 EncodedTerm::NaiveDateTimeLiteral(date_time) => Some(date_time.day().into()),
                 _ => None,
             },
-            PlanExpression::Hours(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::Hours(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::NaiveTimeLiteral(time) => Some(time.hour().into()),
                 EncodedTerm::DateTimeLiteral(date_time) => Some(date_time.hour().into()),
                 EncodedTerm::NaiveDateTimeLiteral(date_time) => Some(date_time.hour().into()),
                 _ => None,
             },
-            PlanExpression::Minutes(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::Minutes(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::NaiveTimeLiteral(time) => Some(time.minute().into()),
                 EncodedTerm::DateTimeLiteral(date_time) => Some(date_time.minute().into()),
                 EncodedTerm::NaiveDateTimeLiteral(date_time) => Some(date_time.minute().into()),
                 _ => None,
             },
-            PlanExpression::Seconds(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::Seconds(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::NaiveTimeLiteral(time) => Some(
                     (Decimal::new(time.nanosecond().into(), 9) + Decimal::from(time.second()))
                         .into(),
@@ -1127,7 +1678,7 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                 _ => None,
             },
             PlanExpression::Timezone(e) => {
-                let timezone = match self.eval_expression(e, tuple)? {
+                let timezone = match self.eval_value(e, tuple)? {
                     EncodedTerm::DateLiteral(date) => date.timezone(),
                     EncodedTerm::DateTimeLiteral(date_time) => date_time.timezone(),
                     _ => return None,
@@ -1161,7 +1712,7 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                 })
             }
             PlanExpression::Tz(e) => {
-                let timezone = match self.eval_expression(e, tuple)? {
+                let timezone = match self.eval_value(e, tuple)? {
                     EncodedTerm::DateLiteral(date) => Some(date.timezone()),
                     EncodedTerm::DateTimeLiteral(date_time) => Some(date_time.timezone()),
                     EncodedTerm::NaiveDateLiteral(_)
@@ -1216,8 +1767,11 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                 Some(EncodedTerm::LangStringLiteral {
                     value_id: self
                         .to_simple_string_id(self.eval_expression(lexical_form, tuple)?)?,
-                    language_id: self
-                        .to_simple_string_id(self.eval_expression(lang_tag, tuple)?)?,
+                    language_id: self.build_string_id(
+                        &self
+                            .to_simple_string(self.eval_expression(lang_tag, tuple)?)?
+                            .to_ascii_lowercase(),
+                    )?,
                 })
             }
             PlanExpression::StrDT(lexical_form, datatype) => {
@@ -1250,7 +1804,7 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                 Some(self.eval_expression(e, tuple)?.is_literal().into())
             }
             PlanExpression::IsNumeric(e) => Some(
-                match self.eval_expression(e, tuple)? {
+                match self.eval_value(e, tuple)? {
                     EncodedTerm::FloatLiteral(_)
                     | EncodedTerm::DoubleLiteral(_)
                     | EncodedTerm::IntegerLiteral(_)
@@ -1271,14 +1825,14 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                 let text = self.to_string(self.eval_expression(text, tuple)?)?;
                 Some(regex.is_match(&text).into())
             }
-            PlanExpression::BooleanCast(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::BooleanCast(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::BooleanLiteral(value) => Some(value.into()),
                 EncodedTerm::StringLiteral { value_id } => {
                     parse_boolean_str(&*self.dataset.get_str(value_id).ok()??)
                 }
                 _ => None,
             },
-            PlanExpression::DoubleCast(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::DoubleCast(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::FloatLiteral(value) => Some(value.to_f64()?.into()),
                 EncodedTerm::DoubleLiteral(value) => Some(value.to_f64()?.into()),
                 EncodedTerm::IntegerLiteral(value) => Some(value.to_f64()?.into()),
@@ -1291,7 +1845,7 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                 }
                 _ => None,
             },
-            PlanExpression::FloatCast(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::FloatCast(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::FloatLiteral(value) => Some(value.to_f32()?.into()),
                 EncodedTerm::DoubleLiteral(value) => Some(value.to_f32()?.into()),
                 EncodedTerm::IntegerLiteral(value) => Some(value.to_f32()?.into()),
@@ -1304,7 +1858,7 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                 }
                 _ => None,
             },
-            PlanExpression::IntegerCast(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::IntegerCast(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::FloatLiteral(value) => Some(value.to_i128()?.into()),
                 EncodedTerm::DoubleLiteral(value) => Some(value.to_i128()?.into()),
                 EncodedTerm::IntegerLiteral(value) => Some(value.to_i128()?.into()),
@@ -1315,7 +1869,7 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                 }
                 _ => None,
             },
-            PlanExpression::DecimalCast(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::DecimalCast(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::FloatLiteral(value) => Some(Decimal::from_f32(*value)?.into()),
                 EncodedTerm::DoubleLiteral(value) => Some(Decimal::from_f64(*value)?.into()),
                 EncodedTerm::IntegerLiteral(value) => Some(Decimal::from_i128(value)?.into()),
@@ -1333,7 +1887,7 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                 }
                 _ => None,
             },
-            PlanExpression::DateCast(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::DateCast(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::DateLiteral(value) => Some(value.into()),
                 EncodedTerm::NaiveDateLiteral(value) => Some(value.into()),
                 EncodedTerm::DateTimeLiteral(value) => Some(value.date().into()),
@@ -1343,7 +1897,7 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                 }
                 _ => None,
             },
-            PlanExpression::TimeCast(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::TimeCast(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::NaiveTimeLiteral(value) => Some(value.into()),
                 EncodedTerm::DateTimeLiteral(value) => Some(value.time().into()),
                 EncodedTerm::NaiveDateTimeLiteral(value) => Some(value.time().into()),
@@ -1352,7 +1906,7 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                 }
                 _ => None,
             },
-            PlanExpression::DateTimeCast(e) => match self.eval_expression(e, tuple)? {
+            PlanExpression::DateTimeCast(e) => match self.eval_value(e, tuple)? {
                 EncodedTerm::DateTimeLiteral(value) => Some(value.into()),
                 EncodedTerm::NaiveDateTimeLiteral(value) => Some(value.into()),
                 EncodedTerm::StringLiteral { value_id } => {
@@ -1363,13 +1917,57 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
             PlanExpression::StringCast(e) => Some(EncodedTerm::StringLiteral {
                 value_id: self.to_string_id(self.eval_expression(e, tuple)?)?,
             }),
+            PlanExpression::Triple(subject, predicate, object) => self.build_quoted_triple(
+                self.eval_expression(subject, tuple)?,
+                self.eval_expression(predicate, tuple)?,
+                self.eval_expression(object, tuple)?,
+            ),
+            PlanExpression::Subject(e) => {
+                Some(self.quoted_triple_terms(self.eval_expression(e, tuple)?)?.0)
+            }
+            PlanExpression::Predicate(e) => {
+                Some(self.quoted_triple_terms(self.eval_expression(e, tuple)?)?.1)
+            }
+            PlanExpression::Object(e) => {
+                Some(self.quoted_triple_terms(self.eval_expression(e, tuple)?)?.2)
+            }
+            PlanExpression::IsTriple(e) => Some(self.eval_expression(e, tuple)?.is_triple().into()),
+        }
+    }
+
+    fn build_quoted_triple(
+        &self,
+        subject: EncodedTerm,
+        predicate: EncodedTerm,
+        object: EncodedTerm,
+    ) -> Option<EncodedTerm> {
+        if !(subject.is_named_node() || subject.is_blank_node() || subject.is_triple())
+            || !predicate.is_named_node()
+            || object == ENCODED_DEFAULT_GRAPH
+        {
+            return None;
+        }
+        self.dataset
+            .encoder()
+            .encode_quoted_triple_terms(subject, predicate, object)
+            .ok()
+    }
+
+    fn quoted_triple_terms(
+        &self,
+        term: EncodedTerm,
+    ) -> Option<(EncodedTerm, EncodedTerm, EncodedTerm)> {
+        if let EncodedTerm::Triple { id } = term {
+            quoted_triple_terms(&self.dataset, id).ok()
+        } else {
+            None
         }
     }
 
     fn to_bool(&self, term: EncodedTerm) -> Option<bool> {
-        match term {
+        match self.to_value(term) {
             EncodedTerm::BooleanLiteral(value) => Some(value),
-            EncodedTerm::StringLiteral { .. } => Some(term != ENCODED_EMPTY_STRING_LITERAL),
+            term @ EncodedTerm::StringLiteral { .. } => Some(term != ENCODED_EMPTY_STRING_LITERAL),
             EncodedTerm::FloatLiteral(value) => Some(!value.is_zero()),
             EncodedTerm::DoubleLiteral(value) => Some(!value.is_zero()),
             EncodedTerm::IntegerLiteral(value) => Some(!value.is_zero()),
@@ -1382,7 +1980,7 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
         match term {
             EncodedTerm::DefaultGraph => None,
             EncodedTerm::NamedNode { iri_id } => Some(iri_id),
-            EncodedTerm::BlankNode { .. } => None,
+            EncodedTerm::BlankNode { .. } | EncodedTerm::Triple { .. } => None,
             EncodedTerm::StringLiteral { value_id }
             | EncodedTerm::LangStringLiteral { value_id, .. }
             | EncodedTerm::TypedLiteral { value_id, .. } => Some(value_id),
@@ -1532,10 +2130,37 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
         e2: &PlanExpression,
         tuple: &[Option<EncodedTerm>],
     ) -> Option<NumericBinaryOperands> {
-        NumericBinaryOperands::new(
-            self.eval_expression(&e1, tuple)?,
-            self.eval_expression(&e2, tuple)?,
-        )
+        NumericBinaryOperands::new(self.eval_value(&e1, tuple)?, self.eval_value(&e2, tuple)?)
+    }
+
+    /// Evaluates an expression and returns the value of its result if it is a literal
+    fn eval_value(
+        &self,
+        expression: &PlanExpression,
+        tuple: &[Option<EncodedTerm>],
+    ) -> Option<EncodedTerm> {
+        Some(self.to_value(self.eval_expression(expression, tuple)?))
+    }
+
+    /// Maps the literals kept with their original lexical form to the encoding of their value
+    ///
+    /// The other terms are returned unchanged.
+    fn to_value(&self, term: EncodedTerm) -> EncodedTerm {
+        if let EncodedTerm::TypedLiteral {
+            value_id,
+            datatype_id,
+        } = term
+        {
+            if let (Ok(Some(value)), Ok(Some(datatype))) = (
+                self.dataset.get_str(value_id),
+                self.dataset.get_str(datatype_id),
+            ) {
+                if let Some(value) = parse_typed_literal_value(&value, &datatype) {
+                    return value;
+                }
+            }
+        }
+        term
     }
 
     fn decode_bindings<'b>(
@@ -1552,7 +2177,8 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
             variables,
             Box::new(iter.map(move |values| {
                 let mut result = vec![None; tuple_size];
-                for (i, value) in values?.into_iter().enumerate() {
+                // The tuples might also contain the pre-bound variables
+                for (i, value) in values?.into_iter().take(tuple_size).enumerate() {
                     if let Some(term) = value {
                         result[i] = Some(eval.dataset.decode_term(term)?)
                     }
@@ -1564,11 +2190,14 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
 
     #[allow(clippy::float_cmp)]
     fn equals(&self, a: EncodedTerm, b: EncodedTerm) -> Option<bool> {
+        let a = self.to_value(a);
+        let b = self.to_value(b);
         match a {
             EncodedTerm::DefaultGraph
             | EncodedTerm::NamedNode { .. }
             | EncodedTerm::BlankNode { .. }
-            | EncodedTerm::LangStringLiteral { .. } => Some(a == b),
+            | EncodedTerm::LangStringLiteral { .. }
+            | EncodedTerm::Triple { .. } => Some(a == b),
             EncodedTerm::StringLiteral { value_id: a } => match b {
                 EncodedTerm::StringLiteral { value_id: b } => Some(a == b),
                 EncodedTerm::TypedLiteral { .. } => None,
@@ -1615,7 +2244,8 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                 EncodedTerm::TypedLiteral { .. } if a == b => Some(true),
                 EncodedTerm::NamedNode { .. }
                 | EncodedTerm::BlankNode { .. }
-                | EncodedTerm::LangStringLiteral { .. } => Some(false),
+                | EncodedTerm::LangStringLiteral { .. }
+                | EncodedTerm::Triple { .. } => Some(false),
                 _ => None,
             },
             EncodedTerm::DateLiteral(a) => match b {
@@ -1674,6 +2304,33 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
         }
     }
 
+    fn cmp_according_to_comparators(
+        &self,
+        tuple_a: &[Option<EncodedTerm>],
+        tuple_b: &[Option<EncodedTerm>],
+        by: &[Comparator],
+    ) -> Ordering {
+        for comp in by {
+            match comp {
+                Comparator::Asc(expression) => {
+                    match self.cmp_according_to_expression(tuple_a, tuple_b, &expression) {
+                        Ordering::Greater => return Ordering::Greater,
+                        Ordering::Less => return Ordering::Less,
+                        Ordering::Equal => (),
+                    }
+                }
+                Comparator::Desc(expression) => {
+                    match self.cmp_according_to_expression(tuple_a, tuple_b, &expression) {
+                        Ordering::Greater => return Ordering::Less,
+                        Ordering::Less => return Ordering::Greater,
+                        Ordering::Equal => (),
+                    }
+                }
+            }
+        }
+        Ordering::Equal
+    }
+
     fn cmp_according_to_expression(
         &self,
         tuple_a: &[Option<EncodedTerm>],
@@ -1703,10 +2360,18 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
                     EncodedTerm::BlankNode { .. } => Ordering::Greater,
                     _ => Ordering::Less,
                 },
+                EncodedTerm::Triple { .. } => {
+                    if b.is_triple() {
+                        self.cmp_quoted_triples(a, b).unwrap_or(Ordering::Equal)
+                    } else {
+                        Ordering::Greater
+                    }
+                }
                 a => match b {
                     EncodedTerm::NamedNode { .. } | EncodedTerm::BlankNode { .. } => {
                         Ordering::Greater
                     }
+                    EncodedTerm::Triple { .. } => Ordering::Less,
                     b => self.partial_cmp_literals(a, b).unwrap_or(Ordering::Equal),
                 },
             },
@@ -1716,8 +2381,20 @@ impl<'a, S: StoreConnection + 'a> SimpleEvaluator<S> {
         }
     }
 
+    /// Compares quoted triples by subject, then predicate, then object
+    fn cmp_quoted_triples(&self, a: EncodedTerm, b: EncodedTerm) -> Option<Ordering> {
+        let (a_subject, a_predicate, a_object) = self.quoted_triple_terms(a)?;
+        let (b_subject, b_predicate, b_object) = self.quoted_triple_terms(b)?;
+        Some(
+            self.cmp_terms(Some(a_subject), Some(b_subject))
+                .then_with(|| self.cmp_terms(Some(a_predicate), Some(b_predicate)))
+                .then_with(|| self.cmp_terms(Some(a_object), Some(b_object))),
+        )
+    }
+
     fn partial_cmp_literals(&self, a: EncodedTerm, b: EncodedTerm) -> Option<Ordering> {
-        match a {
+        let b = self.to_value(b);
+        match self.to_value(a) {
             EncodedTerm::StringLiteral { value_id: a } => {
                 if let EncodedTerm::StringLiteral { value_id: b } = b {
                     self.compare_str_ids(a, b)
@@ -1924,14 +2601,43 @@ fn get_pattern_value(
     match selector {
         PatternValue::Constant(term) => Some(*term),
         PatternValue::Variable(v) => get_tuple_value(*v, tuple),
+        PatternValue::Triple(triple) => Some(EncodedTerm::quoted_triple(
+            get_pattern_value(&triple.subject, tuple)?,
+            get_pattern_value(&triple.predicate, tuple)?,
+            get_pattern_value(&triple.object, tuple)?,
+        )),
     }
 }
 
-fn put_pattern_value(selector: &PatternValue, value: EncodedTerm, tuple: &mut EncodedTuple) {
-    match selector {
-        PatternValue::Constant(_) => (),
-        PatternValue::Variable(v) => put_value(*v, value, tuple),
-    }
+/// Binds the variables of `selector` to `value`
+///
+/// Returns `false` if `value` does not match the constants and the already bound variables of a quoted triple pattern.
+fn put_pattern_value(
+    selector: &PatternValue,
+    value: EncodedTerm,
+    tuple: &mut EncodedTuple,
+    lookup: &impl StrLookup,
+) -> Result<bool> {
+    Ok(match selector {
+        PatternValue::Constant(term) => *term == value,
+        PatternValue::Variable(v) => match get_tuple_value(*v, tuple) {
+            Some(old) => old == value,
+            None => {
+                put_value(*v, value, tuple);
+                true
+            }
+        },
+        PatternValue::Triple(triple) => {
+            if let EncodedTerm::Triple { id } = value {
+                let (subject, predicate, object) = quoted_triple_terms(lookup, id)?;
+                put_pattern_value(&triple.subject, subject, tuple, lookup)?
+                    && put_pattern_value(&triple.predicate, predicate, tuple, lookup)?
+                    && put_pattern_value(&triple.object, object, tuple, lookup)?
+            } else {
+                false
+            }
+        }
+    })
 }
 
 fn put_value(position: usize, value: EncodedTerm, tuple: &mut EncodedTuple) {
@@ -1960,6 +2666,89 @@ fn unbind_variables(binding: &mut [Option<EncodedTerm>], variables: &[usize]) {
     }
 }
 
+fn group_key(tuple: &[Option<EncodedTerm>], key_size: usize) -> Vec<Option<EncodedTerm>> {
+    (0..key_size).map(|v| get_tuple_value(v, tuple)).collect()
+}
+
+/// Splits a chain of operators evaluated tuple by tuple into its first pattern and the rest of the chain
+///
+/// In the rest of the chain the first pattern is replaced by `PlanNode::Init`
+/// so that evaluating it from each result of the first pattern gives the results of the whole chain.
+/// Returns `None` if there is nothing after the first pattern.
+fn split_first_pattern(node: &PlanNode) -> Option<(&PlanNode, PlanNode)> {
+    let child = chained_child(node)?;
+    if let Some((first, rest)) = split_first_pattern(child) {
+        Some((first, with_chained_child(node, rest)))
+    } else if chained_child(child).is_some() {
+        Some((child, with_chained_child(node, PlanNode::Init)))
+    } else {
+        None
+    }
+}
+
+fn chained_child(node: &PlanNode) -> Option<&PlanNode> {
+    match node {
+        PlanNode::QuadPatternJoin { child, .. }
+        | PlanNode::PathPatternJoin { child, .. }
+        | PlanNode::Filter { child, .. }
+        | PlanNode::Extend { child, .. } => Some(child),
+        PlanNode::SemiJoin {
+            left,
+            correlated: true,
+            ..
+        }
+        | PlanNode::AntiSemiJoin {
+            left,
+            correlated: true,
+            ..
+        } => Some(left),
+        _ => None,
+    }
+}
+
+fn with_chained_child(node: &PlanNode, new_child: PlanNode) -> PlanNode {
+    let mut node = node.clone();
+    match &mut node {
+        PlanNode::QuadPatternJoin { child, .. }
+        | PlanNode::PathPatternJoin { child, .. }
+        | PlanNode::Filter { child, .. }
+        | PlanNode::Extend { child, .. } => **child = new_child,
+        PlanNode::SemiJoin { left, .. } | PlanNode::AntiSemiJoin { left, .. } => **left = new_child,
+        _ => (),
+    }
+    node
+}
+
+/// Returns if the tuples returned by the node are in the order given by an `ORDER BY`
+fn is_sorted(node: &PlanNode) -> bool {
+    match node {
+        PlanNode::Sort { .. } | PlanNode::TopK { .. } => true,
+        PlanNode::Project { child, .. }
+        | PlanNode::HashDeduplicate { child }
+        | PlanNode::Skip { child, .. }
+        | PlanNode::Limit { child, .. }
+        | PlanNode::Filter { child, .. }
+        | PlanNode::Extend { child, .. } => is_sorted(child),
+        PlanNode::SemiJoin { left, .. } | PlanNode::AntiSemiJoin { left, .. } => is_sorted(left),
+        _ => false,
+    }
+}
+
+/// Splits the tuples in at most `count` partitions of the same size
+#[allow(clippy::manual_div_ceil)] // usize::div_ceil is not available with the supported Rust versions
+fn partition_tuples(tuples: Vec<EncodedTuple>, count: usize) -> Vec<Vec<EncodedTuple>> {
+    let size = max(1, (tuples.len() + count - 1) / count);
+    let mut tuples = tuples.into_iter();
+    let mut partitions = Vec::default();
+    loop {
+        let partition = tuples.by_ref().take(size).collect::<Vec<_>>();
+        if partition.is_empty() {
+            return partitions;
+        }
+        partitions.push(partition);
+    }
+}
+
 fn combine_tuples(a: &[Option<EncodedTerm>], b: &[Option<EncodedTerm>]) -> Option<EncodedTuple> {
     if a.len() < b.len() {
         let mut result = b.to_owned();
@@ -1994,6 +2783,15 @@ fn combine_tuples(a: &[Option<EncodedTerm>], b: &[Option<EncodedTerm>]) -> Optio
     }
 }
 
+fn are_tuples_compatible(a: &[Option<EncodedTerm>], b: &[Option<EncodedTerm>]) -> bool {
+    a.iter()
+        .zip(b)
+        .all(|(a_value, b_value)| match (a_value, b_value) {
+            (Some(a_value), Some(b_value)) => a_value == b_value,
+            _ => true,
+        })
+}
+
 fn are_tuples_compatible_and_not_disjointed(
     a: &[Option<EncodedTerm>],
     b: &[Option<EncodedTerm>],
@@ -2010,8 +2808,82 @@ fn are_tuples_compatible_and_not_disjointed(
     found_intersection
 }
 
+/// The tuples of one side of a join indexed by the values of the variables they all bind
+struct JoinHashTable {
+    key: Vec<usize>,
+    buckets: HashMap<Vec<EncodedTerm>, Vec<EncodedTuple>>,
+}
+
+impl JoinHashTable {
+    fn new(tuples: Vec<EncodedTuple>, thread_pool: Option<&ThreadPool>) -> Self {
+        let mut key = tuples.first().map_or_else(Vec::default, |tuple| {
+            (0..tuple.len())
+                .filter(|i| tuple[*i].is_some())
+                .collect::<Vec<_>>()
+        });
+        for tuple in tuples.iter().skip(1) {
+            key.retain(|i| get_tuple_value(*i, tuple).is_some());
+        }
+        let buckets = if let Some(thread_pool) = thread_pool {
+            thread_pool.install(|| {
+                tuples
+                    .into_par_iter()
+                    .fold(HashMap::default, |buckets, tuple| {
+                        Self::insert(buckets, &key, tuple)
+                    })
+                    .reduce(HashMap::default, |mut buckets, other| {
+                        for (key, mut tuples) in other {
+                            buckets.entry(key).or_default().append(&mut tuples);
+                        }
+                        buckets
+                    })
+            })
+        } else {
+            tuples
+                .into_iter()
+                .fold(HashMap::default(), |buckets, tuple| {
+                    Self::insert(buckets, &key, tuple)
+                })
+        };
+        Self { key, buckets }
+    }
+
+    fn insert(
+        mut buckets: HashMap<Vec<EncodedTerm>, Vec<EncodedTuple>>,
+        key: &[usize],
+        tuple: EncodedTuple,
+    ) -> HashMap<Vec<EncodedTerm>, Vec<EncodedTuple>> {
+        buckets
+            .entry(
+                key.iter()
+                    .filter_map(|i| get_tuple_value(*i, &tuple))
+                    .collect(),
+            )
+            .or_default()
+            .push(tuple);
+        buckets
+    }
+
+    /// Returns the tuples that may be compatible with the given one
+    fn candidates<'a>(
+        &'a self,
+        tuple: &[Option<EncodedTerm>],
+    ) -> Box<dyn Iterator<Item = &'a EncodedTuple> + 'a> {
+        let key = self
+            .key
+            .iter()
+            .map(|i| get_tuple_value(*i, tuple))
+            .collect::<Option<Vec<_>>>();
+        if let Some(key) = key {
+            Box::new(self.buckets.get(&key).into_iter().flatten())
+        } else {
+            Box::new(self.buckets.values().flatten())
+        }
+    }
+}
+
 struct JoinIterator<'a> {
-    left: Vec<EncodedTuple>,
+    left: JoinHashTable,
     right_iter: EncodedTuplesIterator<'a>,
     buffered_results: Vec<Result<EncodedTuple>>,
 }
@@ -2028,7 +2900,7 @@ impl<'a> Iterator for JoinIterator<'a> {
                 Ok(right_tuple) => right_tuple,
                 Err(error) => return Some(Err(error)),
             };
-            for left_tuple in &self.left {
+            for left_tuple in self.left.candidates(&right_tuple) {
                 if let Some(result_tuple) = combine_tuples(left_tuple, &right_tuple) {
                     self.buffered_results.push(Ok(result_tuple))
                 }
@@ -2039,7 +2911,7 @@ impl<'a> Iterator for JoinIterator<'a> {
 
 struct AntiJoinIterator<'a> {
     left_iter: EncodedTuplesIterator<'a>,
-    right: Vec<EncodedTuple>,
+    right: JoinHashTable,
 }
 
 impl<'a> Iterator for AntiJoinIterator<'a> {
@@ -2049,9 +2921,10 @@ impl<'a> Iterator for AntiJoinIterator<'a> {
         loop {
             match self.left_iter.next()? {
                 Ok(left_tuple) => {
-                    let exists_compatible_right = self.right.iter().any(|right_tuple| {
-                        are_tuples_compatible_and_not_disjointed(&left_tuple, right_tuple)
-                    });
+                    let exists_compatible_right =
+                        self.right.candidates(&left_tuple).any(|right_tuple| {
+                            are_tuples_compatible_and_not_disjointed(&left_tuple, right_tuple)
+                        });
                     if !exists_compatible_right {
                         return Some(Ok(left_tuple));
                     }
@@ -2176,9 +3049,24 @@ impl<'a, S: StoreConnection> Iterator for ConstructIterator<'a, S> {
                 };
                 for template in self.template {
                     if let (Some(subject), Some(predicate), Some(object)) = (
-                        get_triple_template_value(&template.subject, &tuple, &mut self.bnodes),
-                        get_triple_template_value(&template.predicate, &tuple, &mut self.bnodes),
-                        get_triple_template_value(&template.object, &tuple, &mut self.bnodes),
+                        get_triple_template_value(
+                            self.eval,
+                            &template.subject,
+                            &tuple,
+                            &mut self.bnodes,
+                        ),
+                        get_triple_template_value(
+                            self.eval,
+                            &template.predicate,
+                            &tuple,
+                            &mut self.bnodes,
+                        ),
+                        get_triple_template_value(
+                            self.eval,
+                            &template.object,
+                            &tuple,
+                            &mut self.bnodes,
+                        ),
                     ) {
                         self.buffered_results.push(decode_triple(
                             &self.eval.dataset,
@@ -2194,12 +3082,18 @@ impl<'a, S: StoreConnection> Iterator for ConstructIterator<'a, S> {
     }
 }
 
-fn get_triple_template_value(
+fn get_triple_template_value<S: StoreConnection>(
+    eval: &SimpleEvaluator<S>,
     selector: &TripleTemplateValue,
     tuple: &[Option<EncodedTerm>],
     bnodes: &mut Vec<BlankNode>,
 ) -> Option<EncodedTerm> {
     match selector {
+        TripleTemplateValue::Triple(triple) => eval.build_quoted_triple(
+            get_triple_template_value(eval, &triple.subject, tuple, bnodes)?,
+            get_triple_template_value(eval, &triple.predicate, tuple, bnodes)?,
+            get_triple_template_value(eval, &triple.object, tuple, bnodes)?,
+        ),
         TripleTemplateValue::Constant(term) => Some(*term),
         TripleTemplateValue::Variable(v) => get_tuple_value(*v, tuple),
         TripleTemplateValue::BlankNode(id) => {
@@ -2285,48 +3179,139 @@ impl<T1, T2, I1: Iterator<Item = T1>, I2: Iterator<Item = T2>> Iterator
     }
 }
 
+/// The ends of the transitive paths already evaluated from a given node in a given graph
+///
+/// It keeps at most `capacity` ends: it is emptied when a new closure does not fit in it.
+struct PathCache {
+    ends: HashMap<(EncodedTerm, EncodedTerm), Rc<Vec<EncodedTerm>>>,
+    size: usize,
+    capacity: usize,
+}
+
+impl PathCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            ends: HashMap::default(),
+            size: 0,
+            capacity,
+        }
+    }
+
+    fn get(&self, node: EncodedTerm, graph_name: EncodedTerm) -> Option<Rc<Vec<EncodedTerm>>> {
+        self.ends.get(&(node, graph_name)).cloned()
+    }
+
+    fn insert(
+        &mut self,
+        node: EncodedTerm,
+        graph_name: EncodedTerm,
+        ends: Vec<EncodedTerm>,
+    ) -> Rc<Vec<EncodedTerm>> {
+        let ends = Rc::new(ends);
+        if ends.len() > self.capacity {
+            return ends;
+        }
+        if self.size + ends.len() > self.capacity {
+            self.ends.clear();
+            self.size = 0;
+        }
+        self.size += ends.len();
+        if let Some(previous) = self.ends.insert((node, graph_name), ends.clone()) {
+            self.size -= previous.len();
+        }
+        ends
+    }
+}
+
+/// Lazily computes the transitive closure of `start` by the `next` relation using a breadth-first search
 fn transitive_closure<'a, T: 'a + Copy + Eq + Hash, NI: Iterator<Item = Result<T>> + 'a>(
-    start: impl IntoIterator<Item = Result<T>>,
-    next: impl Fn(T) -> NI,
+    start: impl IntoIterator<Item = Result<T>> + 'a,
+    next: impl Fn(T) -> NI + 'a,
 ) -> impl Iterator<Item = Result<T>> + 'a {
-    //TODO: optimize
-    let mut all = HashSet::<T>::default();
-    let mut errors = Vec::default();
-    let mut current = start
-        .into_iter()
-        .filter_map(|e| match e {
-            Ok(e) => {
-                all.insert(e);
-                Some(e)
-            }
-            Err(error) => {
-                errors.push(error);
-                None
+    let mut visited = HashSet::<T>::default();
+    let mut todo = VecDeque::<T>::default();
+    let mut current: Box<dyn Iterator<Item = Result<T>> + 'a> = Box::new(start.into_iter());
+    from_fn(move || loop {
+        match current.next() {
+            Some(Ok(e)) => {
+                if visited.insert(e) {
+                    todo.push_back(e);
+                    return Some(Ok(e));
+                }
             }
-        })
-        .collect::<Vec<_>>();
+            Some(Err(error)) => return Some(Err(error)),
+            None => current = Box::new(next(todo.pop_front()?)),
+        }
+    })
+}
 
-    while !current.is_empty() {
-        current = current
-            .into_iter()
-            .flat_map(|e| next(e))
-            .filter_map(|e| match e {
-                Ok(e) => {
-                    if all.contains(&e) {
-                        None
-                    } else {
-                        all.insert(e);
-                        Some(e)
+/// Returns the successors of `node` in a relation loaded in memory
+fn successors_in_memory<T: Copy + Eq + Hash>(
+    successors: &Rc<HashMap<T, Vec<T>>>,
+    node: T,
+) -> impl Iterator<Item = Result<T>> {
+    let successors = successors.clone();
+    let len = successors.get(&node).map_or(0, Vec::len);
+    (0..len).map(move |i| Ok(successors[&node][i]))
+}
+
+/// Returns the errors and then the `count` smallest tuples according to `compare` in increasing order
+///
+/// Only `count` tuples are kept in memory, in a heap whose root is the greatest of them.
+fn top_k(
+    tuples: impl Iterator<Item = Result<EncodedTuple>>,
+    count: usize,
+    compare: &dyn Fn(&EncodedTuple, &EncodedTuple) -> Ordering,
+) -> Vec<Result<EncodedTuple>> {
+    let mut errors = Vec::default();
+    // The capacity is bounded because `count` comes from the query LIMIT
+    let mut heap = BinaryHeap::with_capacity(min(count, MAX_TOP_K_INITIAL_CAPACITY));
+    for tuple in tuples {
+        match tuple {
+            Ok(tuple) => {
+                if heap.len() < count {
+                    heap.push(HeapTuple { tuple, compare });
+                } else if let Some(mut greatest) = heap.peek_mut() {
+                    if compare(&tuple, &greatest.tuple) == Ordering::Less {
+                        greatest.tuple = tuple;
                     }
                 }
-                Err(error) => {
-                    errors.push(error);
-                    None
-                }
-            })
-            .collect();
+            }
+            Err(error) => errors.push(Err(error)),
+        }
+    }
+    errors.extend(
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|tuple| Ok(tuple.tuple)),
+    );
+    errors
+}
+
+/// A tuple ordered by a comparison function in order to be stored in a `BinaryHeap`
+struct HeapTuple<'a> {
+    tuple: EncodedTuple,
+    compare: &'a dyn Fn(&EncodedTuple, &EncodedTuple) -> Ordering,
+}
+
+impl<'a> PartialEq for HeapTuple<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<'a> Eq for HeapTuple<'a> {}
+
+impl<'a> PartialOrd for HeapTuple<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> Ord for HeapTuple<'a> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.compare)(&self.tuple, &other.tuple)
     }
-    errors.into_iter().map(Err).chain(all.into_iter().map(Ok))
 }
 
 fn hash_deduplicate<T: Eq + Hash + Clone>(
@@ -2433,6 +3418,28 @@ impl<T: Accumulator> Accumulator for DistinctAccumulator<T> {
     }
 }
 
+/// Feeds the inner accumulator with the values of the literals instead of their lexical forms
+struct ValueAccumulator<'a, S: StoreConnection + 'a, T: Accumulator> {
+    eval: &'a SimpleEvaluator<S>,
+    inner: T,
+}
+
+impl<'a, S: StoreConnection + 'a, T: Accumulator> ValueAccumulator<'a, S, T> {
+    fn new(eval: &'a SimpleEvaluator<S>, inner: T) -> Self {
+        Self { eval, inner }
+    }
+}
+
+impl<'a, S: StoreConnection + 'a, T: Accumulator> Accumulator for ValueAccumulator<'a, S, T> {
+    fn add(&mut self, element: Option<EncodedTerm>) {
+        self.inner.add(element.map(|e| self.eval.to_value(e)))
+    }
+
+    fn state(&self) -> Option<EncodedTerm> {
+        self.inner.state()
+    }
+}
+
 #[derive(Default, Debug)]
 struct CountAccumulator {
     count: u64,
@@ -2634,3 +3641,408 @@ impl<'a, S: StoreConnection + 'a> Accumulator for GroupConcatAccumulator<'a, S>
         })
     }
 }
+
+#[cfg(test)]
+pub fn query_values(
+    repository: &crate::MemoryRepository,
+    query: &str,
+    options: crate::sparql::QueryOptions<'_>,
+) -> Vec<Vec<Option<Term>>> {
+    use crate::sparql::PreparedQuery;
+    use crate::{Repository, RepositoryConnection};
+
+    let connection = repository.connection().unwrap();
+    let prepared_query = connection.prepare_query(query, options).unwrap();
+    let results = prepared_query.exec(&BTreeMap::default()).unwrap();
+    if let QueryResult::Bindings(bindings) = results {
+        bindings
+            .into_values_iter()
+            .collect::<Result<Vec<_>>>()
+            .unwrap()
+    } else {
+        panic!("Bindings were expected for {}", query)
+    }
+}
+
+#[cfg(test)]
+pub fn numbers_repository(count: i64) -> crate::MemoryRepository {
+    use crate::model::{Literal, NamedNode, Quad};
+    use crate::{Repository, RepositoryConnection};
+
+    let repository = crate::MemoryRepository::default();
+    let mut connection = repository.connection().unwrap();
+    let p = NamedNode::parse("http://example.com/p").unwrap();
+    for i in 0..count {
+        // The values are not inserted in order and contain duplicates
+        let s = NamedNode::parse(format!("http://example.com/{}", i)).unwrap();
+        connection
+            .insert(&Quad::new(s, p.clone(), Literal::from((i * 7) % 13), None))
+            .unwrap();
+    }
+    repository
+}
+
+#[test]
+fn test_top_k() {
+    use crate::sparql::QueryOptions;
+
+    let repository = numbers_repository(100);
+    let sorted = query_values(
+        &repository,
+        "SELECT ?o ?s WHERE { ?s <http://example.com/p> ?o } ORDER BY DESC(?o) ?s",
+        QueryOptions::default(),
+    );
+    assert_eq!(sorted.len(), 100);
+    for (offset, limit) in &[
+        (0, 0),
+        (0, 1),
+        (0, 10),
+        (5, 10),
+        (95, 10),
+        (150, 10),
+        (0, 1_000_000_000_000_000),
+        (3, usize::MAX),
+        (usize::MAX, 1),
+    ] {
+        let query = format!(
+            "SELECT ?o ?s WHERE {{ ?s <http://example.com/p> ?o }} ORDER BY DESC(?o) ?s OFFSET {} LIMIT {}",
+            offset, limit
+        );
+        let expected: Vec<_> = sorted.iter().skip(*offset).take(*limit).cloned().collect();
+        assert_eq!(
+            query_values(&repository, &query, QueryOptions::default()),
+            expected,
+            "{}",
+            query
+        );
+    }
+}
+
+#[test]
+fn test_non_canonical_literals() {
+    use crate::model::vocab::xsd;
+    use crate::model::{Literal, NamedNode};
+    use crate::sparql::QueryOptions;
+    use crate::{GraphSyntax, Repository, RepositoryConnection};
+
+    let repository = crate::MemoryRepository::default();
+    repository
+        .connection()
+        .unwrap()
+        .load_graph(
+            b"@prefix xsd: <http://www.w3.org/2001/XMLSchema#> .
+            <http://example.com/a> <http://example.com/p> \"01\"^^xsd:integer .
+            <http://example.com/b> <http://example.com/p> \"1\"^^xsd:integer .
+            <http://example.com/c> <http://example.com/p> \"1.0E0\"^^xsd:double .
+            <http://example.com/d> <http://example.com/p> \"2\"^^xsd:byte .
+            <http://example.com/e> <http://example.com/p> \"+3.00\"^^xsd:decimal .
+            <http://example.com/f> <http://example.com/p> \"1\"^^xsd:boolean ."
+                .as_ref(),
+            GraphSyntax::Turtle,
+            None,
+            None,
+        )
+        .unwrap();
+    let select = |query: &str| {
+        query_values(&repository, query, QueryOptions::default())
+            .into_iter()
+            .map(|values| {
+                values
+                    .into_iter()
+                    .map(|value| value.unwrap().to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>()
+    };
+    let typed = |value: &str, datatype: &NamedNode| {
+        Literal::new_typed_literal(value, datatype.clone()).to_string()
+    };
+
+    // The lexical forms are kept
+    assert_eq!(
+        select("SELECT ?o WHERE { <http://example.com/a> ?p ?o }"),
+        vec![typed("01", &xsd::INTEGER)]
+    );
+    assert_eq!(
+        select("SELECT ?o WHERE { ?s ?p ?o FILTER(?s = <http://example.com/f>) }"),
+        vec![typed("1", &xsd::BOOLEAN)]
+    );
+
+    // The values are compared across the lexical forms and the datatypes
+    assert_eq!(
+        select("SELECT ?s WHERE { ?s ?p ?o FILTER(?o = 1) } ORDER BY ?s"),
+        vec![
+            "<http://example.com/a>",
+            "<http://example.com/b>",
+            "<http://example.com/c>"
+        ]
+    );
+    assert_eq!(
+        select("SELECT ?s WHERE { ?s ?p ?o FILTER(?o > 1 && ?o <= 3.0) } ORDER BY ?s"),
+        vec!["<http://example.com/d>", "<http://example.com/e>"]
+    );
+    assert_eq!(
+        select("SELECT ?s WHERE { ?s ?p ?o FILTER(?o = true) }"),
+        vec!["<http://example.com/f>"]
+    );
+
+    // sameTerm and DISTINCT distinguish the lexical forms
+    assert_eq!(
+        select("SELECT ?s WHERE { ?s ?p ?o FILTER(sameTerm(?o, 1)) }"),
+        vec!["<http://example.com/b>"]
+    );
+    assert_eq!(
+        select("SELECT (COUNT(DISTINCT ?o) AS ?c) WHERE { ?s ?p ?o FILTER(isNumeric(?o)) }"),
+        vec![typed("5", &xsd::INTEGER)]
+    );
+
+    // The numbers are ordered by value
+    assert_eq!(
+        select("SELECT ?s WHERE { ?s ?p ?o FILTER(isNumeric(?o)) } ORDER BY DESC(?o) ?s"),
+        vec![
+            "<http://example.com/e>",
+            "<http://example.com/d>",
+            "<http://example.com/a>",
+            "<http://example.com/b>",
+            "<http://example.com/c>"
+        ]
+    );
+}
+
+#[test]
+fn test_open_transitive_paths() {
+    use crate::sparql::QueryOptions;
+    use crate::{GraphSyntax, Repository, RepositoryConnection};
+
+    let repository = crate::MemoryRepository::default();
+    repository
+        .connection()
+        .unwrap()
+        .load_graph(
+            b"@prefix ex: <http://example.com/> .
+            ex:a ex:p ex:b . ex:b ex:p ex:c . ex:c ex:p ex:a . ex:c ex:p ex:d .
+            ex:e ex:q ex:e ."
+                .as_ref(),
+            GraphSyntax::Turtle,
+            None,
+            None,
+        )
+        .unwrap();
+    let select = |query: &str| {
+        let mut pairs = query_values(&repository, query, QueryOptions::default())
+            .into_iter()
+            .map(|values| {
+                values
+                    .into_iter()
+                    .map(|value| {
+                        value
+                            .unwrap()
+                            .to_string()
+                            .replace("http://example.com/", "")
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>();
+        pairs.sort();
+        pairs
+    };
+
+    // The open closures are the union of the closures from each node
+    for path in &["<http://example.com/p>*", "<http://example.com/p>+"] {
+        let open = select(&format!("SELECT ?s ?o WHERE {{ ?s {} ?o }}", path));
+        let mut from_each_node = Vec::default();
+        for s in &["a", "b", "c", "d", "e"] {
+            from_each_node.extend(
+                select(&format!(
+                    "SELECT ?o WHERE {{ <http://example.com/{}> {} ?o }}",
+                    s, path
+                ))
+                .into_iter()
+                .map(|o| format!("<{}> {}", s, o)),
+            );
+        }
+        from_each_node.sort();
+        assert_eq!(open, from_each_node, "{}", path);
+    }
+    assert_eq!(
+        select("SELECT ?s ?o WHERE { ?s <http://example.com/p>+ ?o FILTER(?o = <http://example.com/d>) }"),
+        vec!["<a> <d>", "<b> <d>", "<c> <d>"]
+    );
+    assert_eq!(
+        select("SELECT ?s WHERE { ?s <http://example.com/p>* ?s }"),
+        vec!["<a>", "<b>", "<c>", "<d>", "<e>"]
+    );
+    assert_eq!(
+        select("SELECT ?s WHERE { ?s <http://example.com/q>+ ?s }"),
+        vec!["<e>"]
+    );
+}
+
+#[test]
+fn test_path_cache() {
+    let ends = |count: u128| {
+        (0..count)
+            .map(|id| EncodedTerm::BlankNode { id })
+            .collect::<Vec<_>>()
+    };
+    let graph_name = EncodedTerm::DefaultGraph;
+    let node = |id| EncodedTerm::BlankNode { id };
+
+    let mut cache = PathCache::new(5);
+    cache.insert(node(0), graph_name, ends(2));
+    cache.insert(node(1), graph_name, ends(3));
+    assert_eq!(cache.get(node(0), graph_name), Some(Rc::new(ends(2))));
+    assert_eq!(cache.size, 5);
+
+    // The cache is emptied when it is full
+    assert_eq!(*cache.insert(node(2), graph_name, ends(1)), ends(1));
+    assert_eq!(cache.get(node(0), graph_name), None);
+    assert_eq!(cache.get(node(2), graph_name), Some(Rc::new(ends(1))));
+    assert_eq!(cache.size, 1);
+
+    // The closures greater than the capacity are not cached
+    assert_eq!(*cache.insert(node(3), graph_name, ends(6)), ends(6));
+    assert_eq!(cache.get(node(3), graph_name), None);
+    assert_eq!(cache.size, 1);
+}
+
+#[test]
+fn test_semi_joins() {
+    use crate::sparql::QueryOptions;
+    use crate::{GraphSyntax, Repository, RepositoryConnection};
+
+    let repository = crate::MemoryRepository::default();
+    repository
+        .connection()
+        .unwrap()
+        .load_graph(
+            b"@prefix ex: <http://example.com/> .
+            ex:a ex:p ex:x . ex:b ex:p ex:y . ex:c ex:p ex:z .
+            ex:x ex:q 1 . ex:y ex:r 2 ."
+                .as_ref(),
+            GraphSyntax::Turtle,
+            None,
+            None,
+        )
+        .unwrap();
+    let select = |pattern: &str| {
+        let query = format!(
+            "PREFIX ex: <http://example.com/> SELECT ?s WHERE {{ ?s ex:p ?o {} }}",
+            pattern
+        );
+        let mut subjects = query_values(&repository, &query, QueryOptions::default())
+            .into_iter()
+            .map(|values| {
+                values[0]
+                    .as_ref()
+                    .unwrap()
+                    .to_string()
+                    .replace("http://example.com/", "")
+            })
+            .collect::<Vec<_>>();
+        subjects.sort();
+        subjects
+    };
+
+    // Shared variables
+    assert_eq!(select("FILTER EXISTS { ?o ex:q ?v }"), vec!["<a>"]);
+    assert_eq!(
+        select("FILTER NOT EXISTS { ?o ex:q ?v }"),
+        vec!["<b>", "<c>"]
+    );
+    assert_eq!(select("MINUS { ?o ex:q ?v }"), vec!["<b>", "<c>"]);
+
+    // Disjoint variables
+    assert_eq!(
+        select("FILTER EXISTS { ?x ex:q ?v }"),
+        vec!["<a>", "<b>", "<c>"]
+    );
+    assert!(select("FILTER NOT EXISTS { ?x ex:q ?v }").is_empty());
+    assert_eq!(
+        select("FILTER NOT EXISTS { ?x ex:s ?v }"),
+        vec!["<a>", "<b>", "<c>"]
+    );
+    assert_eq!(select("MINUS { ?x ex:q ?v }"), vec!["<a>", "<b>", "<c>"]);
+
+    // Inner OPTIONAL
+    assert_eq!(
+        select("FILTER EXISTS { ?o ?r ?v OPTIONAL { ?o ex:q ?w } FILTER(!BOUND(?w)) }"),
+        vec!["<b>"]
+    );
+    assert_eq!(
+        select("FILTER NOT EXISTS { ?o ?r ?v OPTIONAL { ?o ex:q ?w } FILTER(!BOUND(?w)) }"),
+        vec!["<a>", "<c>"]
+    );
+    assert_eq!(
+        select("MINUS { ?s ex:p ?o OPTIONAL { ?o ex:q ?v } FILTER(BOUND(?v)) }"),
+        vec!["<b>", "<c>"]
+    );
+    assert_eq!(
+        select("MINUS { ?o ex:r ?v OPTIONAL { ?o ex:q ?s } }"),
+        vec!["<a>", "<c>"]
+    );
+}
+
+#[test]
+fn test_parallel_evaluation() {
+    use crate::sparql::QueryOptions;
+    use rayon::ThreadPoolBuilder;
+
+    let repository = numbers_repository(200);
+    let thread_pool = Arc::new(ThreadPoolBuilder::new().num_threads(4).build().unwrap());
+    for (query, is_ordered) in &[
+        (
+            "SELECT ?s ?s2 WHERE { ?s <http://example.com/p> ?o . ?s2 <http://example.com/p> ?o }",
+            false,
+        ),
+        (
+            "SELECT ?s ?s2 WHERE { ?s <http://example.com/p> ?o OPTIONAL { ?s2 <http://example.com/p> ?o FILTER(STR(?s2) < STR(?s)) } }",
+            false,
+        ),
+        (
+            "SELECT ?o (COUNT(?s) AS ?c) (SUM(?o) AS ?sum) (SAMPLE(?o) AS ?sample) WHERE { ?s <http://example.com/p> ?o } GROUP BY ?o",
+            false,
+        ),
+        (
+            "SELECT (COUNT(DISTINCT ?s2) AS ?c) (MAX(?o) AS ?m) WHERE { ?s <http://example.com/p> ?o . ?s2 <http://example.com/p> ?o }",
+            true,
+        ),
+        (
+            "SELECT ?o ?s WHERE { ?s <http://example.com/p> ?o } ORDER BY DESC(?o) ?s",
+            true,
+        ),
+        (
+            "SELECT ?s ?s2 WHERE { ?s <http://example.com/p> ?o . ?s2 <http://example.com/p> ?o } ORDER BY ?s2 ?s LIMIT 50",
+            true,
+        ),
+    ] {
+        let mut expected = query_values(&repository, query, QueryOptions::default());
+        let mut actual = query_values(
+            &repository,
+            query,
+            QueryOptions::default().with_thread_pool(&thread_pool),
+        );
+        if !is_ordered {
+            expected.sort_by_key(|values| format!("{:?}", values));
+            actual.sort_by_key(|values| format!("{:?}", values));
+        }
+        assert!(!expected.is_empty(), "{}", query);
+        assert_eq!(actual, expected, "{}", query);
+    }
+}
+
+#[test]
+fn test_partition_tuples() {
+    let tuples = (0..10)
+        .map(|i| vec![Some(EncodedTerm::IntegerLiteral(i))])
+        .collect::<Vec<_>>();
+    let partitions = partition_tuples(tuples.clone(), 4);
+    assert_eq!(
+        partitions.iter().map(Vec::len).collect::<Vec<_>>(),
+        vec![3, 3, 3, 1]
+    );
+    assert_eq!(partitions.concat(), tuples);
+    assert!(partition_tuples(Vec::default(), 4).is_empty());
+}
@@ -2,8 +2,8 @@
 
 use crate::model::*;
 use crate::sparql::model::*;
+use crate::Error;
 use crate::Result;
-use failure::format_err;
 use std::io::Write;
 
 pub fn write_json_results<W: Write>(results: QueryResult<'_>, mut sink: W) -> Result<W> {
@@ -45,33 +45,8 @@ pub fn write_json_results<W: Write>(results: QueryResult<'_>, mut sink: W) -> Re
                             sink.write_all(b",")?;
                         }
                         write_escaped_json_string(variables[i].name()?, &mut sink)?;
-                        match term {
-                            Term::NamedNode(uri) => {
-                                sink.write_all(b":{\"type\":\"uri\",\"value\":")?;
-                                write_escaped_json_string(uri.as_str(), &mut sink)?;
-                                sink.write_all(b"}")?;
-                            }
-                            Term::BlankNode(bnode) => {
-                                sink.write_all(b":{\"type\":\"bnode\",\"value\":")?;
-                                write!(sink, "{}", bnode.as_str())?;
-                                sink.write_all(b"}")?;
-                            }
-                            Term::Literal(literal) => {
-                                sink.write_all(b":{\"type\":\"literal\",\"value\":")?;
-                                write_escaped_json_string(literal.value(), &mut sink)?;
-                                if let Some(language) = literal.language() {
-                                    sink.write_all(b",\"xml:lang\":")?;
-                                    write_escaped_json_string(language, &mut sink)?;
-                                } else if !literal.is_plain() {
-                                    sink.write_all(b",\"datatype\":")?;
-                                    write_escaped_json_string(
-                                        literal.datatype().as_str(),
-                                        &mut sink,
-                                    )?;
-                                }
-                                sink.write_all(b"}")?;
-                            }
-                        }
+                        sink.write_all(b":")?;
+                        write_json_term(&term, &mut sink)?;
                     }
                 }
                 sink.write_all(b"}")?;
@@ -79,14 +54,51 @@ pub fn write_json_results<W: Write>(results: QueryResult<'_>, mut sink: W) -> Re
             sink.write_all(b"]}}")?;
         }
         QueryResult::Graph(_) => {
-            return Err(format_err!(
-                "Graphs could not be formatted to SPARQL query results XML format"
+            return Err(Error::unsupported(
+                "Graphs could not be formatted to SPARQL query results XML format",
             ));
         }
     }
     Ok(sink)
 }
 
+fn write_json_term(term: &Term, sink: &mut impl Write) -> Result<()> {
+    match term {
+        Term::NamedNode(uri) => {
+            sink.write_all(b"{\"type\":\"uri\",\"value\":")?;
+            write_escaped_json_string(uri.as_str(), sink)?;
+            sink.write_all(b"}")?;
+        }
+        Term::BlankNode(bnode) => {
+            sink.write_all(b"{\"type\":\"bnode\",\"value\":")?;
+            write!(sink, "{}", bnode.as_str())?;
+            sink.write_all(b"}")?;
+        }
+        Term::Literal(literal) => {
+            sink.write_all(b"{\"type\":\"literal\",\"value\":")?;
+            write_escaped_json_string(literal.value(), sink)?;
+            if let Some(language) = literal.language() {
+                sink.write_all(b",\"xml:lang\":")?;
+                write_escaped_json_string(language, sink)?;
+            } else if !literal.is_plain() {
+                sink.write_all(b",\"datatype\":")?;
+                write_escaped_json_string(literal.datatype().as_str(), sink)?;
+            }
+            sink.write_all(b"}")?;
+        }
+        Term::Triple(triple) => {
+            sink.write_all(b"{\"type\":\"triple\",\"value\":{\"subject\":")?;
+            write_json_term(&triple.subject().clone().into(), sink)?;
+            sink.write_all(b",\"predicate\":")?;
+            write_json_term(&triple.predicate().clone().into(), sink)?;
+            sink.write_all(b",\"object\":")?;
+            write_json_term(triple.object(), sink)?;
+            sink.write_all(b"}}")?;
+        }
+    }
+    Ok(())
+}
+
 fn write_escaped_json_string(s: &str, sink: &mut impl Write) -> Result<()> {
     sink.write_all(b"\"")?;
     for c in s.chars() {
@@ -1,23 +1,33 @@
 //! [SPARQL](https://www.w3.org/TR/sparql11-overview/) implementation.
 
-mod algebra;
+pub(crate) mod algebra;
+mod entailment;
 mod eval;
 mod json_results;
-mod model;
-mod parser;
+pub(crate) mod model;
+mod optimizer;
+pub(crate) mod parser;
 mod plan;
 mod plan_builder;
+mod spill;
 mod xml_results;
 
-use crate::sparql::algebra::QueryVariants;
+use crate::model::Term;
+use crate::sparql::algebra::{GraphPattern, QueryVariants};
 use crate::sparql::eval::SimpleEvaluator;
+use crate::sparql::optimizer::{all_variables, optimize};
 use crate::sparql::parser::read_sparql_query;
 use crate::sparql::plan::TripleTemplate;
 use crate::sparql::plan::{DatasetView, PlanNode};
 use crate::sparql::plan_builder::PlanBuilder;
+use crate::store::numeric_encoder::MemoryStrStore;
 use crate::store::StoreConnection;
 use crate::Result;
+use rayon::ThreadPool;
+use rio_api::iri::Iri;
+use std::collections::BTreeMap;
 use std::fmt;
+use std::sync::Arc;
 
 pub use crate::sparql::model::BindingsIterator;
 pub use crate::sparql::model::QueryResult;
@@ -27,129 +37,636 @@ pub use crate::sparql::model::Variable;
 /// A prepared [SPARQL query](https://www.w3.org/TR/sparql11-query/)
 pub trait PreparedQuery {
     /// Evaluates the query and returns its results
-    fn exec(&self) -> Result<QueryResult>;
+    ///
+    /// The variables of `bindings` are bound to the given terms before the evaluation of the query WHERE clause,
+    /// even if they are not returned by the query.
+    /// The variables that are not used by the query are ignored.
+    /// The variables of the subqueries are not bound.
+    ///
+    /// ```
+    /// use rudf::model::*;
+    /// use rudf::{Repository, RepositoryConnection, MemoryRepository};
+    /// use rudf::sparql::{PreparedQuery, QueryOptions, QueryResult, Variable};
+    /// use std::collections::BTreeMap;
+    ///
+    /// let repository = MemoryRepository::default();
+    /// let mut connection = repository.connection().unwrap();
+    /// let name = NamedNode::parse("http://example.com/name").unwrap();
+    /// let alice = NamedNode::parse("http://example.com/alice").unwrap();
+    /// let bob = NamedNode::parse("http://example.com/bob").unwrap();
+    /// connection.insert(&Quad::new(alice.clone(), name.clone(), Literal::from("Alice"), None)).unwrap();
+    /// connection.insert(&Quad::new(bob.clone(), name.clone(), Literal::from("Bob"), None)).unwrap();
+    ///
+    /// // The same prepared query is evaluated for each person
+    /// let prepared_query = connection.prepare_query(
+    ///     "SELECT ?person ?name WHERE { ?person <http://example.com/name> ?name }",
+    ///     QueryOptions::default()
+    /// ).unwrap();
+    /// for (person, expected) in vec![(alice, "Alice"), (bob, "Bob")] {
+    ///     let mut bindings = BTreeMap::default();
+    ///     bindings.insert(Variable::new("person"), person.into());
+    ///     if let QueryResult::Bindings(results) = prepared_query.exec(&bindings).unwrap() {
+    ///         let solutions = results.into_values_iter().collect::<Result<Vec<_>, _>>().unwrap();
+    ///         assert_eq!(solutions.len(), 1);
+    ///         assert_eq!(solutions[0][1], Some(Literal::from(expected).into()));
+    ///     }
+    /// }
+    /// ```
+    fn exec(&self, bindings: &BTreeMap<Variable, Term>) -> Result<QueryResult>;
+}
+
+/// Options for the preparation of a SPARQL query
+///
+/// ```
+/// use rudf::sparql::QueryOptions;
+///
+/// let options = QueryOptions::default()
+///     .with_base_iri("http://example.com/")
+///     .with_rdfs_entailment();
+/// ```
+#[derive(Default, Clone, Copy, Debug)]
+pub struct QueryOptions<'a> {
+    base_iri: Option<&'a str>,
+    rdfs_entailment: bool,
+    thread_pool: Option<&'a Arc<ThreadPool>>,
+    memory_limit: Option<usize>,
+}
+
+impl<'a> QueryOptions<'a> {
+    /// Sets the IRI used to resolve the relative IRIs of the query
+    pub fn with_base_iri(mut self, base_iri: &'a str) -> Self {
+        self.base_iri = Some(base_iri);
+        self
+    }
+
+    /// Evaluates the query using the [RDFS entailment regime](https://www.w3.org/TR/sparql11-entailment/#RDFSEntRegime)
+    ///
+    /// The `rdfs:subClassOf`, `rdfs:subPropertyOf`, `rdfs:domain` and `rdfs:range` statements of a graph are used to
+    /// entail new triples in the same graph.
+    /// Nothing is materialized: the entailed triples are computed during the evaluation.
+    ///
+    /// ```
+    /// use rudf::model::*;
+    /// use rudf::model::vocab::{rdf, rdfs};
+    /// use rudf::{Repository, RepositoryConnection, MemoryRepository};
+    /// use rudf::sparql::{PreparedQuery, QueryOptions, QueryResult};
+    /// use std::collections::BTreeMap;
+    ///
+    /// let repository = MemoryRepository::default();
+    /// let mut connection = repository.connection().unwrap();
+    /// let cat = NamedNode::parse("http://example.com/Cat").unwrap();
+    /// let animal = NamedNode::parse("http://example.com/Animal").unwrap();
+    /// let tom = NamedNode::parse("http://example.com/tom").unwrap();
+    /// connection.insert(&Quad::new(cat.clone(), rdfs::SUB_CLASS_OF.clone(), animal, None)).unwrap();
+    /// connection.insert(&Quad::new(tom.clone(), rdf::TYPE.clone(), cat, None)).unwrap();
+    ///
+    /// let prepared_query = connection.prepare_query(
+    ///     "ASK { <http://example.com/tom> a <http://example.com/Animal> }",
+    ///     QueryOptions::default().with_rdfs_entailment()
+    /// ).unwrap();
+    /// let results = prepared_query.exec(&BTreeMap::default()).unwrap();
+    /// if let QueryResult::Boolean(result) = results {
+    ///     assert!(result);
+    /// }
+    /// ```
+    pub fn with_rdfs_entailment(mut self) -> Self {
+        self.rdfs_entailment = true;
+        self
+    }
+
+    /// Evaluates the query using the threads of the given pool
+    ///
+    /// The parts of the query that are materialized anyway are evaluated in parallel:
+    /// the left side of the joins, the right side of `MINUS`, the input of `ORDER BY` and the aggregates.
+    /// The matches of their first triple pattern are partitioned between the threads
+    /// and each thread evaluates the rest of the basic graph pattern on its partition.
+    /// The hash tables of the joins are also built in parallel and the groups of the aggregates are split between the threads.
+    /// The pool is meant to be shared by all the queries so that the number of threads stays bounded.
+    ///
+    /// ```
+    /// use rudf::model::*;
+    /// use rudf::{Repository, RepositoryConnection, MemoryRepository};
+    /// use rudf::sparql::{PreparedQuery, QueryOptions, QueryResult};
+    /// use rayon::ThreadPoolBuilder;
+    /// use std::collections::BTreeMap;
+    /// use std::sync::Arc;
+    ///
+    /// let repository = MemoryRepository::default();
+    /// let mut connection = repository.connection().unwrap();
+    /// let p = NamedNode::parse("http://example.com/p").unwrap();
+    /// for i in 0..100 {
+    ///     let s = NamedNode::parse(format!("http://example.com/{}", i)).unwrap();
+    ///     connection.insert(&Quad::new(s, p.clone(), Literal::from(i % 10), None)).unwrap();
+    /// }
+    ///
+    /// let pool = Arc::new(ThreadPoolBuilder::new().num_threads(4).build().unwrap());
+    /// let prepared_query = connection.prepare_query(
+    ///     "SELECT (COUNT(DISTINCT ?o) AS ?c) WHERE { ?s <http://example.com/p> ?o . ?s2 <http://example.com/p> ?o }",
+    ///     QueryOptions::default().with_thread_pool(&pool)
+    /// ).unwrap();
+    /// let results = prepared_query.exec(&BTreeMap::default()).unwrap();
+    /// if let QueryResult::Bindings(results) = results {
+    ///     let solution = results.into_values_iter().next().unwrap().unwrap();
+    ///     assert_eq!(solution[0], Some(Literal::from(10).into()));
+    /// }
+    /// ```
+    pub fn with_thread_pool(mut self, thread_pool: &'a Arc<ThreadPool>) -> Self {
+        self.thread_pool = Some(thread_pool);
+        self
+    }
+
+    /// Limits to about `memory_limit` bytes the memory used by each `ORDER BY`, `DISTINCT` and `GROUP BY` of the query
+    ///
+    /// Beyond this limit the tuples are written to temporary files:
+    /// `ORDER BY` writes sorted runs that are merged at the end,
+    /// `DISTINCT` and `GROUP BY` write the tuples they have no room for to hash partitions that are evaluated one after the other.
+    /// The input of these operators is then streamed even if a thread pool is set, and the aggregates are computed on the current thread.
+    ///
+    /// ```
+    /// use rudf::model::*;
+    /// use rudf::{Repository, RepositoryConnection, MemoryRepository};
+    /// use rudf::sparql::{PreparedQuery, QueryOptions, QueryResult};
+    /// use std::collections::BTreeMap;
+    ///
+    /// let repository = MemoryRepository::default();
+    /// let mut connection = repository.connection().unwrap();
+    /// let p = NamedNode::parse("http://example.com/p").unwrap();
+    /// for i in 0..1000 {
+    ///     let s = NamedNode::parse(format!("http://example.com/{}", i)).unwrap();
+    ///     connection.insert(&Quad::new(s, p.clone(), Literal::from(i % 100), None)).unwrap();
+    /// }
+    ///
+    /// // Only a few groups fit in 1kB, the other ones are aggregated from temporary files
+    /// let prepared_query = connection.prepare_query(
+    ///     "SELECT ?o (COUNT(?s) AS ?c) WHERE { ?s <http://example.com/p> ?o } GROUP BY ?o",
+    ///     QueryOptions::default().with_memory_limit(1024)
+    /// ).unwrap();
+    /// let results = prepared_query.exec(&BTreeMap::default()).unwrap();
+    /// if let QueryResult::Bindings(results) = results {
+    ///     let solutions = results.into_values_iter().collect::<Result<Vec<_>, _>>().unwrap();
+    ///     assert_eq!(solutions.len(), 100);
+    ///     assert!(solutions.iter().all(|solution| solution[1] == Some(Literal::from(10).into())));
+    /// }
+    /// ```
+    pub fn with_memory_limit(mut self, memory_limit: usize) -> Self {
+        self.memory_limit = Some(memory_limit);
+        self
+    }
 }
 
 /// An implementation of `PreparedQuery` for internal use
-pub struct SimplePreparedQuery<S: StoreConnection>(SimplePreparedQueryOptions<S>);
+pub struct SimplePreparedQuery<S: StoreConnection> {
+    plan: Arc<QueryPlanVariants>,
+    evaluator: SimpleEvaluator<S>,
+}
+
+impl<S: StoreConnection> SimplePreparedQuery<S> {
+    pub(crate) fn new(connection: S, query: &str, options: QueryOptions<'_>) -> Result<Self> {
+        Ok(Self::from_plan(
+            connection,
+            &QueryPlan::parse(query, options.base_iri)?,
+            options,
+        ))
+    }
+
+    pub(crate) fn from_plan(connection: S, plan: &QueryPlan, options: QueryOptions<'_>) -> Self {
+        let dataset = DatasetView::new(connection, options.rdfs_entailment);
+        dataset.add_extra_strings(plan.strings.clone());
+        Self {
+            plan: plan.variants.clone(),
+            evaluator: SimpleEvaluator::new(
+                dataset,
+                plan.base_iri.clone(),
+                options.thread_pool.cloned(),
+                options.memory_limit,
+            ),
+        }
+    }
+}
+
+impl<S: StoreConnection> PreparedQuery for SimplePreparedQuery<S> {
+    fn exec(&self, bindings: &BTreeMap<Variable, Term>) -> Result<QueryResult<'_>> {
+        let evaluator = &self.evaluator;
+        match &*self.plan {
+            QueryPlanVariants::Select {
+                plan,
+                variables,
+                pre_bound_variables,
+            } => evaluator.evaluate_select_plan(
+                &plan,
+                &variables,
+                evaluator.encode_bindings(variables, pre_bound_variables, bindings)?,
+            ),
+            QueryPlanVariants::Ask {
+                plan,
+                variables,
+                pre_bound_variables,
+            } => evaluator.evaluate_ask_plan(
+                &plan,
+                evaluator.encode_bindings(variables, pre_bound_variables, bindings)?,
+            ),
+            QueryPlanVariants::Construct {
+                plan,
+                variables,
+                pre_bound_variables,
+                construct,
+            } => evaluator.evaluate_construct_plan(
+                &plan,
+                &construct,
+                evaluator.encode_bindings(variables, pre_bound_variables, bindings)?,
+            ),
+            QueryPlanVariants::Describe {
+                plan,
+                variables,
+                pre_bound_variables,
+            } => evaluator.evaluate_describe_plan(
+                &plan,
+                evaluator.encode_bindings(variables, pre_bound_variables, bindings)?,
+            ),
+        }
+    }
+}
+
+/// A parsed [SPARQL query](https://www.w3.org/TR/sparql11-query/)
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct Query {
+    variants: QueryVariants,
+    prefixes: BTreeMap<String, String>,
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (prefix, namespace) in &self.prefixes {
+            writeln!(f, "PREFIX {}: <{}>", prefix, namespace)?;
+        }
+        self.variants.fmt(f)
+    }
+}
+
+impl Query {
+    /// Parses a SPARQL query
+    ///
+    /// Syntax errors provide the position of the error and the tokens that were expected there:
+    /// ```
+    /// use rudf::sparql::Query;
+    /// use rudf::Error;
+    ///
+    /// if let Err(Error::Syntax(error)) = Query::parse("SELECT * WHERE {\n  ?s ?p\n}", None) {
+    ///     assert_eq!(error.position().map(|p| (p.line(), p.column())), Some((2, 0)));
+    ///     assert_eq!(error.snippet(), Some("}"));
+    ///     assert!(error.expected().contains(&"a variable".to_owned()));
+    /// } else {
+    ///     panic!("a syntax error was expected")
+    /// }
+    /// ```
+    pub fn parse(query: &str, base_iri: Option<&str>) -> Result<Self> {
+        read_sparql_query(query, base_iri)
+    }
 
-enum SimplePreparedQueryOptions<S: StoreConnection> {
+    /// Returns the base IRI of the query i.e. the one declared with `BASE` or the one given to `parse`
+    pub fn base_iri(&self) -> Option<&str> {
+        self.variants.base_iri().map(|iri| iri.as_str())
+    }
+
+    /// Returns the prefixes declared with `PREFIX` in the query prologue
+    ///
+    /// ```
+    /// use rudf::sparql::Query;
+    ///
+    /// let query = Query::parse("PREFIX ex: <http://example.com/> SELECT * WHERE { ?s ex:p ?o }", None).unwrap();
+    /// assert_eq!(Some(&"http://example.com/".to_owned()), query.prefixes().get("ex"));
+    /// ```
+    pub fn prefixes(&self) -> &BTreeMap<String, String> {
+        &self.prefixes
+    }
+
+    /// The pattern of the query WHERE clause
+    pub(crate) fn algebra_mut(&mut self) -> &mut GraphPattern {
+        self.variants.algebra_mut()
+    }
+}
+
+/// A [SPARQL query](https://www.w3.org/TR/sparql11-query/) parsed and planned independently of any connection
+///
+/// It allows to prepare the same query many times with `RepositoryConnection::prepare_query_plan`
+/// without parsing and planning it again:
+/// ```
+/// use rudf::model::*;
+/// use rudf::{Repository, RepositoryConnection, MemoryRepository};
+/// use rudf::sparql::{PreparedQuery, QueryOptions, QueryPlan, QueryResult};
+/// use std::collections::BTreeMap;
+///
+/// let repository = MemoryRepository::default();
+/// let mut connection = repository.connection().unwrap();
+/// let ex = NamedNode::parse("http://example.com").unwrap();
+/// connection.insert(&Quad::new(ex.clone(), ex.clone(), ex.clone(), None)).unwrap();
+///
+/// let plan = QueryPlan::parse("ASK { ?s <http://example.com> ?o }", None).unwrap();
+/// for _ in 0..2 {
+///     let prepared_query = repository.connection().unwrap().prepare_query_plan(&plan, QueryOptions::default()).unwrap();
+///     let results = prepared_query.exec(&BTreeMap::default()).unwrap();
+///     if let QueryResult::Boolean(result) = results {
+///         assert!(result);
+///     }
+/// }
+/// ```
+#[derive(Clone)]
+pub struct QueryPlan {
+    variants: Arc<QueryPlanVariants>,
+    base_iri: Option<Iri<String>>,
+    strings: MemoryStrStore,
+}
+
+enum QueryPlanVariants {
     Select {
         plan: PlanNode,
         variables: Vec<Variable>,
-        evaluator: SimpleEvaluator<S>,
+        pre_bound_variables: Vec<Variable>,
     },
     Ask {
         plan: PlanNode,
-        evaluator: SimpleEvaluator<S>,
+        variables: Vec<Variable>,
+        pre_bound_variables: Vec<Variable>,
     },
     Construct {
         plan: PlanNode,
+        variables: Vec<Variable>,
+        pre_bound_variables: Vec<Variable>,
         construct: Vec<TripleTemplate>,
-        evaluator: SimpleEvaluator<S>,
     },
     Describe {
         plan: PlanNode,
-        evaluator: SimpleEvaluator<S>,
+        variables: Vec<Variable>,
+        pre_bound_variables: Vec<Variable>,
     },
 }
 
-impl<S: StoreConnection> SimplePreparedQuery<S> {
-    pub(crate) fn new(connection: S, query: &str, base_iri: Option<&str>) -> Result<Self> {
-        let dataset = DatasetView::new(connection);
-        //TODO avoid inserting terms in the Repository StringStore
-        Ok(Self(match read_sparql_query(query, base_iri)? {
-            QueryVariants::Select {
-                algebra,
-                dataset: _,
-                base_iri,
-            } => {
-                let (plan, variables) = PlanBuilder::build(dataset.encoder(), &algebra)?;
-                SimplePreparedQueryOptions::Select {
+impl QueryPlan {
+    /// Parses and plans a SPARQL query
+    ///
+    /// The strings of the query are kept in the plan so that it does not write anything in the repositories it is prepared with.
+    pub fn parse(query: &str, base_iri: Option<&str>) -> Result<Self> {
+        Self::from_query(read_sparql_query(query, base_iri)?)
+    }
+
+    /// Plans an already parsed SPARQL query
+    pub fn from_query(query: Query) -> Result<Self> {
+        let mut strings = MemoryStrStore::default();
+        let base_iri = query.variants.base_iri().cloned();
+        let variants = match query.variants {
+            QueryVariants::Select { algebra, .. } => {
+                let (plan, variables, pre_bound_variables) =
+                    PlanBuilder::build(&mut strings, &optimize_where_clause(algebra))?;
+                QueryPlanVariants::Select {
                     plan,
                     variables,
-                    evaluator: SimpleEvaluator::new(dataset, base_iri),
+                    pre_bound_variables,
                 }
             }
-            QueryVariants::Ask {
-                algebra,
-                dataset: _,
-                base_iri,
-            } => {
-                let (plan, _) = PlanBuilder::build(dataset.encoder(), &algebra)?;
-                SimplePreparedQueryOptions::Ask {
+            QueryVariants::Ask { algebra, .. } => {
+                let (plan, variables, pre_bound_variables) =
+                    PlanBuilder::build(&mut strings, &optimize_where_clause(algebra))?;
+                QueryPlanVariants::Ask {
                     plan,
-                    evaluator: SimpleEvaluator::new(dataset, base_iri),
+                    variables,
+                    pre_bound_variables,
                 }
             }
             QueryVariants::Construct {
-                construct,
-                algebra,
-                dataset: _,
-                base_iri,
+                construct, algebra, ..
             } => {
-                let (plan, variables) = PlanBuilder::build(dataset.encoder(), &algebra)?;
-                SimplePreparedQueryOptions::Construct {
+                let (plan, variables, pre_bound_variables) =
+                    PlanBuilder::build(&mut strings, &optimize_where_clause(algebra))?;
+                QueryPlanVariants::Construct {
                     plan,
+                    // The keys of the variables only used in the template follow the ones of the pre-bound variables
                     construct: PlanBuilder::build_graph_template(
-                        dataset.encoder(),
+                        &mut strings,
                         &construct,
-                        variables,
+                        variables
+                            .iter()
+                            .chain(&pre_bound_variables)
+                            .cloned()
+                            .collect(),
                     )?,
-                    evaluator: SimpleEvaluator::new(dataset, base_iri),
+                    variables,
+                    pre_bound_variables,
                 }
             }
-            QueryVariants::Describe {
-                algebra,
-                dataset: _,
-                base_iri,
-            } => {
-                let (plan, _) = PlanBuilder::build(dataset.encoder(), &algebra)?;
-                SimplePreparedQueryOptions::Describe {
+            QueryVariants::Describe { algebra, .. } => {
+                let (plan, variables, pre_bound_variables) =
+                    PlanBuilder::build(&mut strings, &optimize_where_clause(algebra))?;
+                QueryPlanVariants::Describe {
                     plan,
-                    evaluator: SimpleEvaluator::new(dataset, base_iri),
+                    variables,
+                    pre_bound_variables,
                 }
             }
-        }))
+        };
+        Ok(Self {
+            variants: Arc::new(variants),
+            base_iri,
+            strings,
+        })
     }
 }
 
-impl<S: StoreConnection> PreparedQuery for SimplePreparedQuery<S> {
-    fn exec(&self) -> Result<QueryResult<'_>> {
-        match &self.0 {
-            SimplePreparedQueryOptions::Select {
-                plan,
-                variables,
-                evaluator,
-            } => evaluator.evaluate_select_plan(&plan, &variables),
-            SimplePreparedQueryOptions::Ask { plan, evaluator } => {
-                evaluator.evaluate_ask_plan(&plan)
-            }
-            SimplePreparedQueryOptions::Construct {
-                plan,
-                construct,
-                evaluator,
-            } => evaluator.evaluate_construct_plan(&plan, &construct),
-            SimplePreparedQueryOptions::Describe { plan, evaluator } => {
-                evaluator.evaluate_describe_plan(&plan)
-            }
-        }
+/// Optimizes the pattern of a query WHERE clause whose variables may all be bound by `PreparedQuery::exec`
+fn optimize_where_clause(pattern: GraphPattern) -> GraphPattern {
+    let bound_variables = all_variables(&pattern);
+    optimize(pattern, &bound_variables)
+}
+
+#[test]
+fn test_exec_with_bindings() {
+    use crate::model::{Literal, NamedNode, Quad};
+    use crate::{MemoryRepository, Repository, RepositoryConnection};
+
+    let repository = MemoryRepository::default();
+    let mut connection = repository.connection().unwrap();
+    let ex = |name: &str| NamedNode::parse(format!("http://example.com/{}", name)).unwrap();
+    for (s, o) in &[("a", 1), ("a", 2), ("b", 3)] {
+        connection
+            .insert(&Quad::new(ex(s), ex("p"), Literal::from(*o), None))
+            .unwrap();
     }
+    let solutions = |query: &str, bindings: &[(&str, Term)]| {
+        let prepared_query = connection
+            .prepare_query(query, QueryOptions::default())
+            .unwrap();
+        let bindings = bindings
+            .iter()
+            .map(|(name, value)| (Variable::new(*name), value.clone()))
+            .collect();
+        let results = prepared_query.exec(&bindings).unwrap();
+        let mut solutions = match results {
+            QueryResult::Bindings(results) => results
+                .into_values_iter()
+                .collect::<Result<Vec<_>>>()
+                .unwrap(),
+            QueryResult::Boolean(result) => vec![vec![Some(Literal::from(result).into())]],
+            QueryResult::Graph(_) => panic!("No graph expected"),
+        };
+        solutions.sort();
+        solutions
+    };
+    let a: Term = ex("a").into();
+    let one: Term = Literal::from(1).into();
+    let two: Term = Literal::from(2).into();
+
+    // Projected variable
+    assert_eq!(
+        solutions(
+            "SELECT ?s ?o WHERE { ?s <http://example.com/p> ?o }",
+            &[("s", a.clone())]
+        ),
+        vec![
+            vec![Some(a.clone()), Some(one.clone())],
+            vec![Some(a.clone()), Some(two.clone())]
+        ]
+    );
+    // Variable of the WHERE clause that is not projected
+    assert_eq!(
+        solutions(
+            "SELECT ?o WHERE { ?s <http://example.com/p> ?o }",
+            &[("s", a.clone())]
+        ),
+        vec![vec![Some(one.clone())], vec![Some(two.clone())]]
+    );
+    // Unused variable
+    assert_eq!(
+        solutions(
+            "SELECT ?o WHERE { ?s <http://example.com/p> ?o }",
+            &[("unused", a.clone())]
+        )
+        .len(),
+        3
+    );
+    // Values conflicting with the pattern or with a BIND
+    assert_eq!(
+        solutions(
+            "SELECT ?s WHERE { ?s <http://example.com/p> 3 }",
+            &[("s", a.clone())]
+        ),
+        Vec::<Vec<Option<Term>>>::new()
+    );
+    assert_eq!(
+        solutions(
+            "SELECT ?o ?x WHERE { ?s <http://example.com/p> ?o BIND(1 AS ?x) }",
+            &[("x", two.clone())]
+        ),
+        Vec::<Vec<Option<Term>>>::new()
+    );
+    // The variables of the subqueries are not bound
+    assert_eq!(
+        solutions(
+            "SELECT ?o WHERE { { SELECT ?o WHERE { ?s <http://example.com/p> ?o } } }",
+            &[("s", a.clone())]
+        )
+        .len(),
+        3
+    );
+    // ASK queries
+    assert_eq!(
+        solutions("ASK { ?s <http://example.com/p> 3 }", &[("s", a.clone())]),
+        vec![vec![Some(Literal::from(false).into())]]
+    );
+    assert_eq!(
+        solutions("ASK { ?s <http://example.com/p> 2 }", &[("s", a)]),
+        vec![vec![Some(Literal::from(true).into())]]
+    );
 }
 
-/// A parsed [SPARQL query](https://www.w3.org/TR/sparql11-query/)
-#[derive(Eq, PartialEq, Debug, Clone, Hash)]
-pub struct Query(QueryVariants);
+#[test]
+fn test_exec_with_bindings_of_rewritten_variables() {
+    use crate::model::{NamedNode, Quad};
+    use crate::{MemoryRepository, Repository, RepositoryConnection};
 
-impl fmt::Display for Query {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0.fmt(f)
+    let repository = MemoryRepository::default();
+    let mut connection = repository.connection().unwrap();
+    let ex = |name: &str| NamedNode::parse(format!("http://e/{}", name)).unwrap();
+    for (s, o) in &[("a", "b"), ("b", "c"), ("c", "d")] {
+        connection
+            .insert(&Quad::new(ex(s), ex("p"), ex(o), None))
+            .unwrap();
     }
+    let count = |query: &str, variable: &str, value: &str| {
+        let prepared_query = connection
+            .prepare_query(query, QueryOptions::default())
+            .unwrap();
+        let bindings = [(Variable::new(variable), ex(value).into())]
+            .iter()
+            .cloned()
+            .collect();
+        let count = match prepared_query.exec(&bindings).unwrap() {
+            QueryResult::Bindings(results) => results.into_values_iter().count(),
+            _ => panic!("Bindings expected for {}", query),
+        };
+        count
+    };
+
+    // The filters on a pre-bound variable are not replaced by a value ignoring the binding, even if it is not projected
+    for query in &[
+        "SELECT ?s WHERE { ?s <http://e/p> ?o FILTER(?o = <http://e/c>) }",
+        "SELECT ?s WHERE { ?s <http://e/p> ?o FILTER(sameTerm(?o, <http://e/c>)) }",
+        "SELECT ?s ?o WHERE { ?s <http://e/p> ?o FILTER(?o = <http://e/c>) }",
+        "SELECT ?s WHERE { ?s <http://e/p> ?o FILTER(STR(?o) = \"http://e/c\") }",
+    ] {
+        assert_eq!(count(query, "o", "b"), 0, "{}", query);
+        assert_eq!(count(query, "o", "c"), 1, "{}", query);
+    }
+    // The BINDs of pre-bound variables are kept even if they are not projected
+    assert_eq!(
+        count(
+            "SELECT ?s WHERE { ?s <http://e/p> ?o BIND(?o AS ?o2) }",
+            "o2",
+            "zz"
+        ),
+        0
+    );
+    assert_eq!(
+        count(
+            "SELECT ?s WHERE { ?s <http://e/p> ?o BIND(?o AS ?o2) }",
+            "o2",
+            "c"
+        ),
+        1
+    );
+    // An optional pattern is not required to match a pre-bound variable
+    assert_eq!(
+        count(
+            "SELECT ?s WHERE { ?s <http://e/p> ?o OPTIONAL { ?o <http://e/q> ?r } FILTER(BOUND(?r)) }",
+            "r",
+            "zz"
+        ),
+        3
+    );
 }
 
-impl Query {
-    /// Parses a SPARQL query
-    pub fn parse(query: &str, base_iri: Option<&str>) -> Result<Self> {
-        Ok(Query(read_sparql_query(query, base_iri)?))
+#[test]
+fn test_variables_of_empty_results() {
+    use crate::{MemoryRepository, Repository, RepositoryConnection};
+
+    let repository = MemoryRepository::default();
+    let connection = repository.connection().unwrap();
+    for query in &[
+        "SELECT ?s ?o WHERE { ?s <http://example.com/p> ?o } LIMIT 0",
+        "SELECT ?s ?o WHERE { ?s <http://example.com/p> ?o FILTER(false) }",
+        "SELECT DISTINCT ?s ?o WHERE { ?s <http://example.com/p> ?o FILTER(1 = 2) } ORDER BY ?o",
+    ] {
+        let prepared_query = connection
+            .prepare_query(query, QueryOptions::default())
+            .unwrap();
+        let results = prepared_query.exec(&Default::default()).unwrap();
+        match results {
+            QueryResult::Bindings(results) => {
+                assert_eq!(
+                    results.variables(),
+                    &[Variable::new("s"), Variable::new("o")]
+                );
+                assert_eq!(results.into_values_iter().count(), 0);
+            }
+            _ => panic!("Bindings expected for {}", query),
+        }
     }
 }
@@ -1,12 +1,15 @@
 use crate::model::*;
+use crate::sparql::algebra::TriplePattern;
 use crate::sparql::json_results::write_json_results;
 use crate::sparql::xml_results::{read_xml_results, write_xml_results};
+use crate::Error;
 use crate::{FileSyntax, GraphSyntax, Result};
-use failure::format_err;
 use rand::random;
 use rio_api::formatter::TriplesFormatter;
-use rio_turtle::{NTriplesFormatter, TurtleFormatter};
+use rio_api::model as rio;
+use rio_turtle::TurtleFormatter;
 use rio_xml::RdfXmlFormatter;
+use std::convert::TryFrom;
 use std::fmt;
 use std::io::{BufRead, Write};
 
@@ -35,34 +38,78 @@ impl<'a> QueryResult<'a> {
         }
     }
 
-    pub fn write_graph<W: Write>(self, write: W, syntax: GraphSyntax) -> Result<W> {
+    /// Maps the Skolem IRIs minted by `skolemizer` back to blank nodes
+    ///
+    /// Usage example:
+    /// ```
+    /// use rudf::model::*;
+    /// use rudf::{Repository, RepositoryConnection, MemoryRepository};
+    /// use rudf::sparql::{PreparedQuery, QueryOptions, QueryResult};
+    /// use std::collections::BTreeMap;
+    ///
+    /// let repository = MemoryRepository::default();
+    /// let mut connection = repository.connection().unwrap();
+    /// let skolemizer = Skolemizer::new("http://example.com/").unwrap();
+    /// connection.enable_skolemization(&skolemizer).unwrap();
+    /// let ex = NamedNode::parse("http://example.com").unwrap();
+    /// let blank_node = BlankNode::default();
+    /// connection.insert(&Quad::new(blank_node.clone(), ex.clone(), ex, None)).unwrap();
+    ///
+    /// let prepared_query = connection.prepare_query("SELECT ?s WHERE { ?s ?p ?o }", QueryOptions::default()).unwrap();
+    /// let results = prepared_query.exec(&BTreeMap::default()).unwrap().deskolemize(skolemizer);
+    /// if let QueryResult::Bindings(results) = results {
+    ///     assert_eq!(results.into_values_iter().next().unwrap().unwrap()[0], Some(blank_node.into()));
+    /// }
+    /// ```
+    pub fn deskolemize(self, skolemizer: Skolemizer) -> Self {
+        match self {
+            QueryResult::Bindings(bindings) => {
+                let (variables, iter) = bindings.destruct();
+                QueryResult::Bindings(BindingsIterator::new(
+                    variables,
+                    Box::new(iter.map(move |values| {
+                        Ok(values?
+                            .into_iter()
+                            .map(|value| value.map(|term| skolemizer.deskolemize_term(&term)))
+                            .collect())
+                    })),
+                ))
+            }
+            QueryResult::Boolean(value) => QueryResult::Boolean(value),
+            QueryResult::Graph(triples) => QueryResult::Graph(Box::new(
+                triples.map(move |triple| Ok(skolemizer.deskolemize_triple(&triple?))),
+            )),
+        }
+    }
+
+    pub fn write_graph<W: Write>(self, mut write: W, syntax: GraphSyntax) -> Result<W> {
         if let QueryResult::Graph(triples) = self {
             Ok(match syntax {
-                GraphSyntax::NTriples => {
-                    let mut formatter = NTriplesFormatter::new(write);
+                // The N-Triples-star lines are also valid Turtle-star
+                GraphSyntax::NTriples | GraphSyntax::NTriplesStar | GraphSyntax::TurtleStar => {
                     for triple in triples {
-                        formatter.format(&(&triple?).into())?;
+                        writeln!(write, "{}", triple?)?;
                     }
-                    formatter.finish()
+                    write
                 }
                 GraphSyntax::Turtle => {
                     let mut formatter = TurtleFormatter::new(write);
                     for triple in triples {
-                        formatter.format(&(&triple?).into())?;
+                        formatter.format(&rio::Triple::try_from(&triple?)?)?;
                     }
                     formatter.finish()?
                 }
                 GraphSyntax::RdfXml => {
                     let mut formatter = RdfXmlFormatter::new(write)?;
                     for triple in triples {
-                        formatter.format(&(&triple?).into())?;
+                        formatter.format(&rio::Triple::try_from(&triple?)?)?;
                     }
                     formatter.finish()?
                 }
             })
         } else {
-            Err(format_err!(
-                "Bindings or booleans could not be formatted as an RDF graph"
+            Err(Error::unsupported(
+                "Bindings or booleans could not be formatted as an RDF graph",
             ))
         }
     }
@@ -164,7 +211,10 @@ impl Variable {
     pub fn name(&self) -> Result<&str> {
         match self {
             Variable::Variable { name } => Ok(name),
-            _ => Err(format_err!("The variable {} has no name", self)),
+            _ => Err(Error::evaluation(format!(
+                "The variable {} has no name",
+                self
+            ))),
         }
     }
 }
@@ -226,6 +276,8 @@ impl From<Variable> for NamedNodeOrVariable {
 pub enum TermOrVariable {
     Term(Term),
     Variable(Variable),
+    /// A [quoted triple pattern](https://w3c.github.io/rdf-star/cg-spec/#dfn-quoted-triple-pattern)
+    Triple(Box<TriplePattern>),
 }
 
 impl fmt::Display for TermOrVariable {
@@ -233,6 +285,7 @@ impl fmt::Display for TermOrVariable {
         match self {
             TermOrVariable::Term(term) => write!(f, "{}", term),
             TermOrVariable::Variable(var) => write!(f, "{}", var),
+            TermOrVariable::Triple(triple) => write!(f, "<< {} >>", triple),
         }
     }
 }
@@ -267,6 +320,14 @@ impl From<Term> for TermOrVariable {
             Term::NamedNode(node) => TermOrVariable::Term(node.into()),
             Term::BlankNode(node) => TermOrVariable::Variable(node.into()),
             Term::Literal(literal) => TermOrVariable::Term(literal.into()),
+            Term::Triple(triple) => {
+                let triple = *triple;
+                TermOrVariable::Triple(Box::new(TriplePattern::new(
+                    Term::from(triple.subject().clone()),
+                    triple.predicate().clone(),
+                    triple.object_owned(),
+                )))
+            }
         }
     }
 }
@@ -0,0 +1,1341 @@
+//! Rewriting of the SPARQL algebra before its translation into a plan
+//!
+//! Each rule is a function returning a pattern equivalent to the given one.
+//! `optimize` applies all of them.
+//!
+//! The pattern may be evaluated with some of its variables already bound, like the ones given to `PreparedQuery::exec`.
+//! The rules depending on which variables are bound take the set of these variables as input
+//! and do not assume that the only bindings are the ones of the query text.
+
+use crate::model::vocab::xsd;
+use crate::model::*;
+use crate::sparql::algebra::*;
+use crate::sparql::model::*;
+use crate::sparql::plan_builder::sort_bgp;
+use std::collections::{BTreeSet, HashSet};
+
+/// Applies all the rewriting rules to the pattern
+///
+/// `bound_variables` are the variables that may be bound before the evaluation of the pattern.
+pub fn optimize(pattern: GraphPattern, bound_variables: &BTreeSet<Variable>) -> GraphPattern {
+    let pattern = fold_constants(pattern);
+    let pattern = eliminate_empty_patterns(pattern);
+    let pattern = optional_to_join(pattern, bound_variables);
+    let pattern = push_filters(pattern);
+    let pattern = substitute_bound_values(pattern);
+    push_projections(pattern, bound_variables)
+}
+
+/// Evaluates the expressions, or the parts of them, that do not depend on the solutions
+///
+/// The filters that are always true are removed and the ones that are always false are replaced by an empty pattern.
+pub fn fold_constants(pattern: GraphPattern) -> GraphPattern {
+    match map_children(pattern, fold_constants) {
+        GraphPattern::Filter(e, p) => match fold_expression(e) {
+            e if to_boolean(&e) == Some(true) => *p,
+            e if to_boolean(&e) == Some(false) => empty_pattern(),
+            e => GraphPattern::Filter(e, p),
+        },
+        GraphPattern::LeftJoin(a, b, e) => match fold_expression(e) {
+            e if to_boolean(&e) == Some(false) => *a,
+            e => GraphPattern::LeftJoin(a, b, e),
+        },
+        GraphPattern::Extend(p, v, e) => GraphPattern::Extend(p, v, fold_expression(e)),
+        GraphPattern::OrderBy(p, comparators) => GraphPattern::OrderBy(
+            p,
+            comparators
+                .into_iter()
+                .map(|comparator| match comparator {
+                    OrderComparator::Asc(e) => OrderComparator::Asc(fold_expression(e)),
+                    OrderComparator::Desc(e) => OrderComparator::Desc(fold_expression(e)),
+                })
+                .collect(),
+        ),
+        p => p,
+    }
+}
+
+/// Removes the patterns without any solution and the empty group patterns
+pub fn eliminate_empty_patterns(pattern: GraphPattern) -> GraphPattern {
+    match map_children(pattern, eliminate_empty_patterns) {
+        GraphPattern::Join(a, b) => {
+            if is_empty(&a) || is_empty(&b) {
+                empty_pattern()
+            } else if is_unit(&a) {
+                *b
+            } else if is_unit(&b) {
+                *a
+            } else {
+                GraphPattern::Join(a, b)
+            }
+        }
+        GraphPattern::LeftJoin(a, b, e) => {
+            if is_empty(&a) {
+                empty_pattern()
+            } else if is_empty(&b) || is_unit(&b) {
+                *a
+            } else {
+                GraphPattern::LeftJoin(a, b, e)
+            }
+        }
+        GraphPattern::Union(a, b) => {
+            if is_empty(&a) {
+                *b
+            } else if is_empty(&b) {
+                *a
+            } else {
+                GraphPattern::Union(a, b)
+            }
+        }
+        GraphPattern::Minus(a, b) => {
+            if is_empty(&a) {
+                empty_pattern()
+            } else if is_empty(&b) || is_unit(&b) {
+                *a
+            } else {
+                GraphPattern::Minus(a, b)
+            }
+        }
+        GraphPattern::Slice(p, _, Some(0)) => without_solutions(*p),
+        GraphPattern::Filter(_, p) | GraphPattern::Graph(_, p) | GraphPattern::Extend(p, _, _)
+            if is_empty(&p) =>
+        {
+            empty_pattern()
+        }
+        GraphPattern::OrderBy(p, _)
+        | GraphPattern::Distinct(p)
+        | GraphPattern::Reduced(p)
+        | GraphPattern::Slice(p, _, _)
+            if is_empty(&p) =>
+        {
+            without_solutions(*p)
+        }
+        GraphPattern::Project(p, projection) if is_empty(&p) => {
+            GraphPattern::Project(Box::new(empty_pattern()), projection)
+        }
+        p => p,
+    }
+}
+
+/// Replaces the `OPTIONAL`s by joins when a filter above them rejects all the solutions without the optional part
+///
+/// It is the case if the filter rejects the solutions where a variable always bound by the optional pattern,
+/// and never by the other side, is unbound, like `FILTER(BOUND(?x))` or `FILTER(?x > 2)`.
+/// The variables of `bound_variables` may be bound before the evaluation so they are never considered as unbound.
+pub fn optional_to_join(
+    pattern: GraphPattern,
+    bound_variables: &BTreeSet<Variable>,
+) -> GraphPattern {
+    match pattern {
+        GraphPattern::Filter(e, p) => {
+            let mut filters = vec![e];
+            let mut child = *p;
+            while let GraphPattern::Filter(e, p) = child {
+                filters.push(e);
+                child = *p;
+            }
+            let child = match optional_to_join(child, bound_variables) {
+                GraphPattern::LeftJoin(a, b, e) => {
+                    let left_variables = all_variables(&a);
+                    let only_right_variables = certainly_bound_variables(&b)
+                        .into_iter()
+                        .filter(|v| !left_variables.contains(v) && !bound_variables.contains(v))
+                        .collect::<Vec<_>>();
+                    if filters.iter().any(|filter| {
+                        only_right_variables
+                            .iter()
+                            .any(|v| rejects_unbound(filter, v))
+                    }) {
+                        let join = GraphPattern::Join(a, b);
+                        if to_boolean(&e) == Some(true) {
+                            join
+                        } else {
+                            GraphPattern::Filter(e, Box::new(join))
+                        }
+                    } else {
+                        GraphPattern::LeftJoin(a, b, e)
+                    }
+                }
+                child => child,
+            };
+            filters
+                .into_iter()
+                .rev()
+                .fold(child, |p, e| GraphPattern::Filter(e, Box::new(p)))
+        }
+        p => map_children(p, |p| optional_to_join(p, bound_variables)),
+    }
+}
+
+/// Moves the filters as close as possible to the patterns binding their variables
+///
+/// The conjunctions are split and each part is evaluated just after the patterns of the BGPs, joins, `OPTIONAL`s,
+/// `MINUS`s, unions, `BIND`s and `GRAPH`s providing its variables.
+/// The filters using `EXISTS` or functions returning a different value at each call are not moved.
+pub fn push_filters(pattern: GraphPattern) -> GraphPattern {
+    match pattern {
+        GraphPattern::Filter(e, p) => {
+            let mut filters = Vec::default();
+            add_conjuncts(e, &mut filters);
+            let mut child = *p;
+            while let GraphPattern::Filter(e, p) = child {
+                add_conjuncts(e, &mut filters);
+                child = *p;
+            }
+            push_filters_into(filters, push_filters(child))
+        }
+        p => map_children(p, push_filters),
+    }
+}
+
+/// Replaces the variables of the BGPs that are compared to an IRI by a filter by this IRI
+///
+/// `FILTER(?x = <iri>)` or `FILTER(sameTerm(?x, <iri>))` just above a BGP containing `?x` is replaced
+/// by the BGP with `?x` replaced by `<iri>` and a `BIND(<iri> AS ?x)`.
+/// If `?x` is bound before the evaluation, the `BIND` drops the solutions where it has another value
+/// so it should not be removed by `push_projections`.
+pub fn substitute_bound_values(pattern: GraphPattern) -> GraphPattern {
+    match map_children(pattern, substitute_bound_values) {
+        GraphPattern::Filter(e, p) => match (bound_value(&e), *p) {
+            (Some((variable, value)), GraphPattern::BGP(patterns))
+                if patterns
+                    .iter()
+                    .any(|pattern| bgp_pattern_contains(pattern, variable)) =>
+            {
+                let variable = variable.clone();
+                let value = value.clone();
+                GraphPattern::Extend(
+                    Box::new(GraphPattern::BGP(
+                        patterns
+                            .into_iter()
+                            .map(|pattern| substitute_in_bgp_pattern(pattern, &variable, &value))
+                            .collect(),
+                    )),
+                    variable,
+                    Expression::Constant(TermOrVariable::Term(value.into())),
+                )
+            }
+            (_, p) => GraphPattern::Filter(e, Box::new(p)),
+        },
+        p => p,
+    }
+}
+
+/// Removes the `BIND`s whose variables are not used by the projections above them
+///
+/// The `BIND`s of the variables of `bound_variables` are kept below the outermost projection even if they are not projected:
+/// these variables may be bound before the evaluation and the `BIND`s drop the solutions where they have another value.
+/// The variables of the subqueries are only bound by their projection.
+pub fn push_projections(
+    pattern: GraphPattern,
+    bound_variables: &BTreeSet<Variable>,
+) -> GraphPattern {
+    match pattern {
+        GraphPattern::Project(p, projection) => {
+            let mut needed = bound_variables.clone();
+            needed.extend(projection.iter().cloned());
+            GraphPattern::Project(
+                Box::new(push_projections(
+                    remove_unused_bindings(*p, needed),
+                    &BTreeSet::default(),
+                )),
+                projection,
+            )
+        }
+        p => map_children(p, |p| push_projections(p, bound_variables)),
+    }
+}
+
+/// Applies the rule to all the direct children of the pattern
+fn map_children(
+    pattern: GraphPattern,
+    rule: impl Fn(GraphPattern) -> GraphPattern,
+) -> GraphPattern {
+    match pattern {
+        GraphPattern::BGP(p) => GraphPattern::BGP(p),
+        GraphPattern::Join(a, b) => GraphPattern::Join(Box::new(rule(*a)), Box::new(rule(*b))),
+        GraphPattern::LeftJoin(a, b, e) => {
+            GraphPattern::LeftJoin(Box::new(rule(*a)), Box::new(rule(*b)), e)
+        }
+        GraphPattern::Filter(e, p) => GraphPattern::Filter(e, Box::new(rule(*p))),
+        GraphPattern::Union(a, b) => GraphPattern::Union(Box::new(rule(*a)), Box::new(rule(*b))),
+        GraphPattern::Graph(g, p) => GraphPattern::Graph(g, Box::new(rule(*p))),
+        GraphPattern::Extend(p, v, e) => GraphPattern::Extend(Box::new(rule(*p)), v, e),
+        GraphPattern::Minus(a, b) => GraphPattern::Minus(Box::new(rule(*a)), Box::new(rule(*b))),
+        GraphPattern::Service(n, p, s) => GraphPattern::Service(n, Box::new(rule(*p)), s),
+        GraphPattern::AggregateJoin(GroupPattern(key, p), aggregates) => {
+            GraphPattern::AggregateJoin(GroupPattern(key, Box::new(rule(*p))), aggregates)
+        }
+        GraphPattern::Data(bs) => GraphPattern::Data(bs),
+        GraphPattern::OrderBy(p, o) => GraphPattern::OrderBy(Box::new(rule(*p)), o),
+        GraphPattern::Project(p, v) => GraphPattern::Project(Box::new(rule(*p)), v),
+        GraphPattern::Distinct(p) => GraphPattern::Distinct(Box::new(rule(*p))),
+        GraphPattern::Reduced(p) => GraphPattern::Reduced(Box::new(rule(*p))),
+        GraphPattern::Slice(p, start, length) => {
+            GraphPattern::Slice(Box::new(rule(*p)), start, length)
+        }
+    }
+}
+
+/// The pattern without any solution
+fn empty_pattern() -> GraphPattern {
+    GraphPattern::Data(StaticBindings::default())
+}
+
+/// The pattern without any solution keeping the projection of the given pattern
+///
+/// The projection gives the variables of the results so it should not be removed.
+fn without_solutions(pattern: GraphPattern) -> GraphPattern {
+    match pattern {
+        GraphPattern::Project(_, projection) => {
+            GraphPattern::Project(Box::new(empty_pattern()), projection)
+        }
+        GraphPattern::OrderBy(p, _)
+        | GraphPattern::Distinct(p)
+        | GraphPattern::Reduced(p)
+        | GraphPattern::Slice(p, _, _) => without_solutions(*p),
+        _ => empty_pattern(),
+    }
+}
+
+fn is_empty(pattern: &GraphPattern) -> bool {
+    match pattern {
+        GraphPattern::Data(bs) => bs.is_empty(),
+        GraphPattern::Project(p, _) => is_empty(p),
+        _ => false,
+    }
+}
+
+/// Checks if the pattern is the empty group pattern `{}` that has a single empty solution
+fn is_unit(pattern: &GraphPattern) -> bool {
+    if let GraphPattern::BGP(p) = pattern {
+        p.is_empty()
+    } else {
+        false
+    }
+}
+
+fn fold_expression(expression: Expression) -> Expression {
+    match expression {
+        Expression::Or(a, b) => {
+            let a = fold_expression(*a);
+            let b = fold_expression(*b);
+            match (to_boolean(&a), to_boolean(&b)) {
+                (Some(true), _) | (_, Some(true)) => boolean_expression(true),
+                (Some(false), _) if is_boolean(&b) => b,
+                (_, Some(false)) if is_boolean(&a) => a,
+                _ => Expression::Or(Box::new(a), Box::new(b)),
+            }
+        }
+        Expression::And(a, b) => {
+            let a = fold_expression(*a);
+            let b = fold_expression(*b);
+            match (to_boolean(&a), to_boolean(&b)) {
+                (Some(false), _) | (_, Some(false)) => boolean_expression(false),
+                (Some(true), _) if is_boolean(&b) => b,
+                (_, Some(true)) if is_boolean(&a) => a,
+                _ => Expression::And(Box::new(a), Box::new(b)),
+            }
+        }
+        Expression::UnaryNot(e) => {
+            let e = fold_expression(*e);
+            match to_boolean(&e) {
+                Some(value) => boolean_expression(!value),
+                None => Expression::UnaryNot(Box::new(e)),
+            }
+        }
+        Expression::Equal(a, b) => {
+            let a = fold_expression(*a);
+            let b = fold_expression(*b);
+            match are_equal(&a, &b) {
+                Some(value) => boolean_expression(value),
+                None => Expression::Equal(Box::new(a), Box::new(b)),
+            }
+        }
+        Expression::NotEqual(a, b) => {
+            let a = fold_expression(*a);
+            let b = fold_expression(*b);
+            match are_equal(&a, &b) {
+                Some(value) => boolean_expression(!value),
+                None => Expression::NotEqual(Box::new(a), Box::new(b)),
+            }
+        }
+        Expression::Greater(a, b) => {
+            fold_integer_comparison(*a, *b, Expression::Greater, |a, b| a > b)
+        }
+        Expression::GreaterOrEq(a, b) => {
+            fold_integer_comparison(*a, *b, Expression::GreaterOrEq, |a, b| a >= b)
+        }
+        Expression::Lower(a, b) => fold_integer_comparison(*a, *b, Expression::Lower, |a, b| a < b),
+        Expression::LowerOrEq(a, b) => {
+            fold_integer_comparison(*a, *b, Expression::LowerOrEq, |a, b| a <= b)
+        }
+        Expression::Add(a, b) => {
+            fold_integer_operation(*a, *b, Expression::Add, |a, b| a.checked_add(b))
+        }
+        Expression::Sub(a, b) => {
+            fold_integer_operation(*a, *b, Expression::Sub, |a, b| a.checked_sub(b))
+        }
+        Expression::Mul(a, b) => {
+            fold_integer_operation(*a, *b, Expression::Mul, |a, b| a.checked_mul(b))
+        }
+        Expression::UnaryPlus(e) => {
+            let e = fold_expression(*e);
+            match to_integer(&e) {
+                Some(value) => integer_expression(value),
+                None => Expression::UnaryPlus(Box::new(e)),
+            }
+        }
+        Expression::UnaryMinus(e) => {
+            let e = fold_expression(*e);
+            match to_integer(&e).and_then(i128::checked_neg) {
+                Some(value) => integer_expression(value),
+                None => Expression::UnaryMinus(Box::new(e)),
+            }
+        }
+        Expression::Div(a, b) => {
+            Expression::Div(Box::new(fold_expression(*a)), Box::new(fold_expression(*b)))
+        }
+        Expression::In(a, l) => Expression::In(
+            Box::new(fold_expression(*a)),
+            l.into_iter().map(fold_expression).collect(),
+        ),
+        Expression::NotIn(a, l) => Expression::NotIn(
+            Box::new(fold_expression(*a)),
+            l.into_iter().map(fold_expression).collect(),
+        ),
+        Expression::FunctionCall(f, args) => {
+            Expression::FunctionCall(f, args.into_iter().map(fold_expression).collect())
+        }
+        e => e,
+    }
+}
+
+fn fold_integer_comparison(
+    a: Expression,
+    b: Expression,
+    build: fn(Box<Expression>, Box<Expression>) -> Expression,
+    compare: fn(i128, i128) -> bool,
+) -> Expression {
+    let a = fold_expression(a);
+    let b = fold_expression(b);
+    match (to_integer(&a), to_integer(&b)) {
+        (Some(a), Some(b)) => boolean_expression(compare(a, b)),
+        _ => build(Box::new(a), Box::new(b)),
+    }
+}
+
+fn fold_integer_operation(
+    a: Expression,
+    b: Expression,
+    build: fn(Box<Expression>, Box<Expression>) -> Expression,
+    operation: fn(i128, i128) -> Option<i128>,
+) -> Expression {
+    let a = fold_expression(a);
+    let b = fold_expression(b);
+    match (to_integer(&a), to_integer(&b)) {
+        (Some(a_value), Some(b_value)) => match operation(a_value, b_value) {
+            Some(value) => integer_expression(value),
+            None => build(Box::new(a), Box::new(b)),
+        },
+        _ => build(Box::new(a), Box::new(b)),
+    }
+}
+
+/// Returns if the two constants are equal, or `None` if it could not be known without evaluating the expressions
+fn are_equal(a: &Expression, b: &Expression) -> Option<bool> {
+    match (to_term(a)?, to_term(b)?) {
+        (Term::NamedNode(a), Term::NamedNode(b)) => Some(a == b),
+        (Term::Literal(a), Term::Literal(b)) => {
+            if let (Some(a), Some(b)) =
+                (to_integer(&a.clone().into()), to_integer(&b.clone().into()))
+            {
+                Some(a == b)
+            } else if a.datatype() == &*xsd::STRING && b.datatype() == &*xsd::STRING {
+                Some(a.value() == b.value())
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+fn to_term(expression: &Expression) -> Option<&Term> {
+    if let Expression::Constant(TermOrVariable::Term(term)) = expression {
+        Some(term)
+    } else {
+        None
+    }
+}
+
+fn to_literal(expression: &Expression) -> Option<&Literal> {
+    if let Term::Literal(literal) = to_term(expression)? {
+        Some(literal)
+    } else {
+        None
+    }
+}
+
+fn to_boolean(expression: &Expression) -> Option<bool> {
+    let literal = to_literal(expression)?;
+    if literal.datatype() != &*xsd::BOOLEAN {
+        return None;
+    }
+    match literal.value() {
+        "true" | "1" => Some(true),
+        "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn to_integer(expression: &Expression) -> Option<i128> {
+    let literal = to_literal(expression)?;
+    if literal.datatype() == &*xsd::INTEGER {
+        literal.value().parse().ok()
+    } else {
+        None
+    }
+}
+
+fn boolean_expression(value: bool) -> Expression {
+    Literal::from(value).into()
+}
+
+fn integer_expression(value: i128) -> Expression {
+    Literal::new_typed_literal(value.to_string(), xsd::INTEGER.clone()).into()
+}
+
+/// Checks if the expression always returns a boolean or an error
+fn is_boolean(expression: &Expression) -> bool {
+    match expression {
+        Expression::Or(..)
+        | Expression::And(..)
+        | Expression::Equal(..)
+        | Expression::NotEqual(..)
+        | Expression::Greater(..)
+        | Expression::GreaterOrEq(..)
+        | Expression::Lower(..)
+        | Expression::LowerOrEq(..)
+        | Expression::In(..)
+        | Expression::NotIn(..)
+        | Expression::UnaryNot(..)
+        | Expression::Exists(..)
+        | Expression::Bound(..) => true,
+        e => to_boolean(e).is_some(),
+    }
+}
+
+/// Checks if the filter rejects all the solutions where the variable is unbound
+fn rejects_unbound(expression: &Expression, variable: &Variable) -> bool {
+    match expression {
+        Expression::Bound(v) => v == variable,
+        Expression::And(a, b) => rejects_unbound(a, variable) || rejects_unbound(b, variable),
+        Expression::Or(a, b) => rejects_unbound(a, variable) && rejects_unbound(b, variable),
+        e => fails_if_unbound(e, variable),
+    }
+}
+
+/// Checks if the evaluation of the expression is an error when the variable is unbound
+fn fails_if_unbound(expression: &Expression, variable: &Variable) -> bool {
+    match expression {
+        Expression::Constant(TermOrVariable::Variable(v)) => v == variable,
+        Expression::Equal(a, b)
+        | Expression::NotEqual(a, b)
+        | Expression::Greater(a, b)
+        | Expression::GreaterOrEq(a, b)
+        | Expression::Lower(a, b)
+        | Expression::LowerOrEq(a, b)
+        | Expression::Add(a, b)
+        | Expression::Sub(a, b)
+        | Expression::Mul(a, b)
+        | Expression::Div(a, b) => fails_if_unbound(a, variable) || fails_if_unbound(b, variable),
+        Expression::UnaryPlus(e) | Expression::UnaryMinus(e) | Expression::UnaryNot(e) => {
+            fails_if_unbound(e, variable)
+        }
+        Expression::In(e, _) | Expression::NotIn(e, _) => fails_if_unbound(e, variable),
+        _ => false,
+    }
+}
+
+/// Returns the variable and the IRI it should be equal to if the expression is `?x = <iri>` or `sameTerm(?x, <iri>)`
+fn bound_value(expression: &Expression) -> Option<(&Variable, &NamedNode)> {
+    let (a, b) = match expression {
+        Expression::Equal(a, b) => (&**a, &**b),
+        Expression::FunctionCall(Function::SameTerm, args) if args.len() == 2 => {
+            (&args[0], &args[1])
+        }
+        _ => return None,
+    };
+    match (a, b) {
+        (
+            Expression::Constant(TermOrVariable::Variable(v)),
+            Expression::Constant(TermOrVariable::Term(Term::NamedNode(n))),
+        )
+        | (
+            Expression::Constant(TermOrVariable::Term(Term::NamedNode(n))),
+            Expression::Constant(TermOrVariable::Variable(v)),
+        ) => Some((v, n)),
+        _ => None,
+    }
+}
+
+fn bgp_pattern_contains(pattern: &TripleOrPathPattern, variable: &Variable) -> bool {
+    match pattern {
+        TripleOrPathPattern::Triple(t) => triple_pattern_contains(t, variable),
+        TripleOrPathPattern::Path(p) => {
+            term_or_variable_contains(&p.subject, variable)
+                || term_or_variable_contains(&p.object, variable)
+        }
+    }
+}
+
+fn triple_pattern_contains(pattern: &TriplePattern, variable: &Variable) -> bool {
+    term_or_variable_contains(&pattern.subject, variable)
+        || pattern.predicate == NamedNodeOrVariable::Variable(variable.clone())
+        || term_or_variable_contains(&pattern.object, variable)
+}
+
+fn term_or_variable_contains(term: &TermOrVariable, variable: &Variable) -> bool {
+    match term {
+        TermOrVariable::Variable(v) => v == variable,
+        TermOrVariable::Triple(t) => triple_pattern_contains(t, variable),
+        TermOrVariable::Term(_) => false,
+    }
+}
+
+fn substitute_in_bgp_pattern(
+    pattern: TripleOrPathPattern,
+    variable: &Variable,
+    value: &NamedNode,
+) -> TripleOrPathPattern {
+    match pattern {
+        TripleOrPathPattern::Triple(t) => {
+            TripleOrPathPattern::Triple(substitute_in_triple_pattern(t, variable, value))
+        }
+        TripleOrPathPattern::Path(p) => TripleOrPathPattern::Path(PathPattern {
+            subject: substitute_in_term_or_variable(p.subject, variable, value),
+            path: p.path,
+            object: substitute_in_term_or_variable(p.object, variable, value),
+        }),
+    }
+}
+
+fn substitute_in_triple_pattern(
+    pattern: TriplePattern,
+    variable: &Variable,
+    value: &NamedNode,
+) -> TriplePattern {
+    TriplePattern {
+        subject: substitute_in_term_or_variable(pattern.subject, variable, value),
+        predicate: match pattern.predicate {
+            NamedNodeOrVariable::Variable(ref v) if v == variable => value.clone().into(),
+            p => p,
+        },
+        object: substitute_in_term_or_variable(pattern.object, variable, value),
+    }
+}
+
+fn substitute_in_term_or_variable(
+    term: TermOrVariable,
+    variable: &Variable,
+    value: &NamedNode,
+) -> TermOrVariable {
+    match term {
+        TermOrVariable::Variable(ref v) if v == variable => value.clone().into(),
+        TermOrVariable::Triple(t) => {
+            TermOrVariable::Triple(Box::new(substitute_in_triple_pattern(*t, variable, value)))
+        }
+        t => t,
+    }
+}
+
+fn add_conjuncts(expression: Expression, conjuncts: &mut Vec<Expression>) {
+    if let Expression::And(a, b) = expression {
+        add_conjuncts(*a, conjuncts);
+        add_conjuncts(*b, conjuncts);
+    } else {
+        conjuncts.push(expression)
+    }
+}
+
+/// Adds the filters to the pattern, its own filters having already been pushed
+fn push_filters_into(filters: Vec<Expression>, pattern: GraphPattern) -> GraphPattern {
+    let (filters, kept): (Vec<_>, Vec<_>) = filters.into_iter().partition(is_movable);
+    let pattern = match pattern {
+        GraphPattern::Join(a, b) => {
+            let a_variables = certainly_bound_variables(&a);
+            let b_variables = certainly_bound_variables(&b);
+            let (a_filters, filters): (Vec<_>, Vec<_>) = filters
+                .into_iter()
+                .partition(|e| is_subset(&expression_variables(e), &a_variables));
+            let (b_filters, filters): (Vec<_>, Vec<_>) = filters
+                .into_iter()
+                .partition(|e| is_subset(&expression_variables(e), &b_variables));
+            add_filters(
+                filters,
+                GraphPattern::Join(
+                    Box::new(push_filters_into(a_filters, *a)),
+                    Box::new(push_filters_into(b_filters, *b)),
+                ),
+            )
+        }
+        GraphPattern::LeftJoin(a, b, e) => {
+            let a_variables = certainly_bound_variables(&a);
+            let (a_filters, filters): (Vec<_>, Vec<_>) = filters
+                .into_iter()
+                .partition(|e| is_subset(&expression_variables(e), &a_variables));
+            add_filters(
+                filters,
+                GraphPattern::LeftJoin(Box::new(push_filters_into(a_filters, *a)), b, e),
+            )
+        }
+        GraphPattern::Minus(a, b) => {
+            let a_variables = certainly_bound_variables(&a);
+            let (a_filters, filters): (Vec<_>, Vec<_>) = filters
+                .into_iter()
+                .partition(|e| is_subset(&expression_variables(e), &a_variables));
+            add_filters(
+                filters,
+                GraphPattern::Minus(Box::new(push_filters_into(a_filters, *a)), b),
+            )
+        }
+        GraphPattern::Union(a, b) => GraphPattern::Union(
+            Box::new(push_filters_into(filters.clone(), *a)),
+            Box::new(push_filters_into(filters, *b)),
+        ),
+        GraphPattern::Extend(p, v, e) => {
+            let (p_filters, filters): (Vec<_>, Vec<_>) = filters
+                .into_iter()
+                .partition(|e| !expression_variables(e).contains(&v));
+            add_filters(
+                filters,
+                GraphPattern::Extend(Box::new(push_filters_into(p_filters, *p)), v, e),
+            )
+        }
+        GraphPattern::Graph(g, p) => {
+            let (p_filters, filters): (Vec<_>, Vec<_>) =
+                filters.into_iter().partition(|e| match &g {
+                    NamedNodeOrVariable::Variable(g) => !expression_variables(e).contains(g),
+                    NamedNodeOrVariable::NamedNode(_) => true,
+                });
+            add_filters(
+                filters,
+                GraphPattern::Graph(g, Box::new(push_filters_into(p_filters, *p))),
+            )
+        }
+        GraphPattern::Filter(e, p) => {
+            let mut filters = filters;
+            add_conjuncts(e, &mut filters);
+            push_filters_into(filters, *p)
+        }
+        GraphPattern::BGP(p) => push_filters_into_bgp(filters, p),
+        p => add_filters(filters, p),
+    };
+    add_filters(kept, pattern)
+}
+
+/// Evaluates each filter just after the patterns binding its variables
+///
+/// The variables compared to an IRI are first replaced by this IRI like in `substitute_bound_values`.
+/// The BGP is then split in parts following the order in which its patterns are evaluated.
+fn push_filters_into_bgp(
+    filters: Vec<Expression>,
+    mut patterns: Vec<TripleOrPathPattern>,
+) -> GraphPattern {
+    let mut bindings = Vec::default();
+    let mut other_filters = Vec::default();
+    for e in filters {
+        match bound_value(&e) {
+            Some((variable, value))
+                if patterns
+                    .iter()
+                    .any(|pattern| bgp_pattern_contains(pattern, variable)) =>
+            {
+                patterns = patterns
+                    .into_iter()
+                    .map(|pattern| substitute_in_bgp_pattern(pattern, variable, value))
+                    .collect();
+                bindings.push((variable.clone(), value.clone()));
+            }
+            _ => other_filters.push(e),
+        }
+    }
+    // The filters on the substituted variables are evaluated after the bindings
+    let (outer_filters, filters): (Vec<_>, Vec<_>) = other_filters.into_iter().partition(|e| {
+        let variables = expression_variables(e);
+        bindings.iter().any(|(v, _)| variables.contains(v))
+    });
+
+    let sorted = sort_bgp(&patterns, HashSet::default());
+    let mut bound = BTreeSet::default();
+    let mut ends = Vec::with_capacity(sorted.len());
+    for pattern in &sorted {
+        add_bgp_pattern_variables(pattern, &mut bound);
+        ends.push(bound.clone());
+    }
+    // For each filter, the number of patterns to evaluate before it
+    let mut filters_by_end: Vec<(usize, Expression)> = filters
+        .into_iter()
+        .map(|e| {
+            let variables = expression_variables(&e);
+            let end = ends
+                .iter()
+                .position(|bound| is_subset(&variables, bound))
+                .map_or(sorted.len(), |i| i + 1);
+            (end, e)
+        })
+        .collect();
+    filters_by_end.sort_by_key(|(end, _)| *end);
+
+    let mut result: Option<GraphPattern> = None;
+    let mut start = 0;
+    for (end, e) in filters_by_end {
+        if end > start || result.is_none() {
+            let part = GraphPattern::BGP(sorted[start..end].iter().map(|p| (*p).clone()).collect());
+            result = Some(match result {
+                Some(result) => GraphPattern::Join(Box::new(result), Box::new(part)),
+                None => part,
+            });
+            start = end;
+        }
+        result = result.map(|p| GraphPattern::Filter(e, Box::new(p)));
+    }
+    let result = match result {
+        Some(result) if start < sorted.len() => GraphPattern::Join(
+            Box::new(result),
+            Box::new(GraphPattern::BGP(
+                sorted[start..].iter().map(|p| (*p).clone()).collect(),
+            )),
+        ),
+        Some(result) => result,
+        None => GraphPattern::BGP(patterns.clone()),
+    };
+    let result = bindings.into_iter().fold(result, |p, (variable, value)| {
+        GraphPattern::Extend(
+            Box::new(p),
+            variable,
+            Expression::Constant(TermOrVariable::Term(value.into())),
+        )
+    });
+    add_filters(outer_filters, result)
+}
+
+fn add_filters(filters: Vec<Expression>, pattern: GraphPattern) -> GraphPattern {
+    filters
+        .into_iter()
+        .rev()
+        .fold(pattern, |p, e| GraphPattern::Filter(e, Box::new(p)))
+}
+
+/// Checks if the filter could be evaluated on a part of the solutions without changing its result
+fn is_movable(expression: &Expression) -> bool {
+    match expression {
+        Expression::Constant(_) | Expression::Bound(_) => true,
+        Expression::Or(a, b)
+        | Expression::And(a, b)
+        | Expression::Equal(a, b)
+        | Expression::NotEqual(a, b)
+        | Expression::Greater(a, b)
+        | Expression::GreaterOrEq(a, b)
+        | Expression::Lower(a, b)
+        | Expression::LowerOrEq(a, b)
+        | Expression::Add(a, b)
+        | Expression::Sub(a, b)
+        | Expression::Mul(a, b)
+        | Expression::Div(a, b) => is_movable(a) && is_movable(b),
+        Expression::UnaryPlus(e) | Expression::UnaryMinus(e) | Expression::UnaryNot(e) => {
+            is_movable(e)
+        }
+        Expression::In(a, l) | Expression::NotIn(a, l) => is_movable(a) && l.iter().all(is_movable),
+        Expression::FunctionCall(f, args) => match f {
+            Function::BNode
+            | Function::Rand
+            | Function::Now
+            | Function::UUID
+            | Function::StrUUID => false,
+            _ => args.iter().all(is_movable),
+        },
+        Expression::Exists(_) => false,
+    }
+}
+
+fn is_subset(a: &BTreeSet<Variable>, b: &BTreeSet<Variable>) -> bool {
+    a.iter().all(|v| b.contains(v))
+}
+
+/// Returns the variables bound in all the solutions of the pattern
+fn certainly_bound_variables(pattern: &GraphPattern) -> BTreeSet<Variable> {
+    match pattern {
+        GraphPattern::BGP(p) => {
+            let mut variables = BTreeSet::default();
+            for pattern in p {
+                add_bgp_pattern_variables(pattern, &mut variables);
+            }
+            variables
+        }
+        GraphPattern::Join(a, b) => {
+            let mut variables = certainly_bound_variables(a);
+            variables.extend(certainly_bound_variables(b));
+            variables
+        }
+        GraphPattern::LeftJoin(p, _, _)
+        | GraphPattern::Filter(_, p)
+        | GraphPattern::Extend(p, _, _)
+        | GraphPattern::Minus(p, _)
+        | GraphPattern::OrderBy(p, _)
+        | GraphPattern::Distinct(p)
+        | GraphPattern::Reduced(p)
+        | GraphPattern::Slice(p, _, _) => certainly_bound_variables(p),
+        GraphPattern::Union(a, b) => certainly_bound_variables(a)
+            .intersection(&certainly_bound_variables(b))
+            .cloned()
+            .collect(),
+        GraphPattern::Graph(g, p) => {
+            let mut variables = certainly_bound_variables(p);
+            if let NamedNodeOrVariable::Variable(g) = g {
+                variables.insert(g.clone());
+            }
+            variables
+        }
+        GraphPattern::Project(p, projection) => certainly_bound_variables(p)
+            .into_iter()
+            .filter(|v| projection.contains(v))
+            .collect(),
+        GraphPattern::AggregateJoin(GroupPattern(key, p), _) => certainly_bound_variables(p)
+            .into_iter()
+            .filter(|v| key.contains(v))
+            .collect(),
+        GraphPattern::Data(bs) => bs
+            .variables_iter()
+            .enumerate()
+            .filter(|(i, _)| bs.values_iter().all(|values| values[*i].is_some()))
+            .map(|(_, v)| v.clone())
+            .collect(),
+        GraphPattern::Service(..) => BTreeSet::default(),
+    }
+}
+
+/// Returns all the variables used in the pattern, including the ones of its expressions
+pub fn all_variables(pattern: &GraphPattern) -> BTreeSet<Variable> {
+    let mut variables = BTreeSet::default();
+    add_all_variables(pattern, &mut variables);
+    variables
+}
+
+fn add_all_variables(pattern: &GraphPattern, variables: &mut BTreeSet<Variable>) {
+    match pattern {
+        GraphPattern::BGP(p) => {
+            for pattern in p {
+                add_bgp_pattern_variables(pattern, variables);
+            }
+        }
+        GraphPattern::Join(a, b) | GraphPattern::Union(a, b) | GraphPattern::Minus(a, b) => {
+            add_all_variables(a, variables);
+            add_all_variables(b, variables);
+        }
+        GraphPattern::LeftJoin(a, b, e) => {
+            add_all_variables(a, variables);
+            add_all_variables(b, variables);
+            add_expression_variables(e, variables);
+        }
+        GraphPattern::Filter(e, p) => {
+            add_expression_variables(e, variables);
+            add_all_variables(p, variables);
+        }
+        GraphPattern::Graph(g, p) | GraphPattern::Service(g, p, _) => {
+            if let NamedNodeOrVariable::Variable(g) = g {
+                variables.insert(g.clone());
+            }
+            add_all_variables(p, variables);
+        }
+        GraphPattern::Extend(p, v, e) => {
+            add_all_variables(p, variables);
+            variables.insert(v.clone());
+            add_expression_variables(e, variables);
+        }
+        GraphPattern::AggregateJoin(GroupPattern(key, p), aggregates) => {
+            variables.extend(key.iter().cloned());
+            add_all_variables(p, variables);
+            for (aggregate, v) in aggregates {
+                variables.insert(v.clone());
+                match aggregate {
+                    Aggregation::Count(Some(e), _)
+                    | Aggregation::Sum(e, _)
+                    | Aggregation::Min(e, _)
+                    | Aggregation::Max(e, _)
+                    | Aggregation::Avg(e, _)
+                    | Aggregation::Sample(e, _)
+                    | Aggregation::GroupConcat(e, _, _) => add_expression_variables(e, variables),
+                    Aggregation::Count(None, _) => (),
+                }
+            }
+        }
+        GraphPattern::Data(bs) => variables.extend(bs.variables_iter().cloned()),
+        GraphPattern::OrderBy(p, comparators) => {
+            add_all_variables(p, variables);
+            for comparator in comparators {
+                match comparator {
+                    OrderComparator::Asc(e) | OrderComparator::Desc(e) => {
+                        add_expression_variables(e, variables)
+                    }
+                }
+            }
+        }
+        GraphPattern::Project(p, projection) => {
+            add_all_variables(p, variables);
+            variables.extend(projection.iter().cloned());
+        }
+        GraphPattern::Distinct(p) | GraphPattern::Reduced(p) | GraphPattern::Slice(p, _, _) => {
+            add_all_variables(p, variables)
+        }
+    }
+}
+
+fn expression_variables(expression: &Expression) -> BTreeSet<Variable> {
+    let mut variables = BTreeSet::default();
+    add_expression_variables(expression, &mut variables);
+    variables
+}
+
+fn add_expression_variables(expression: &Expression, variables: &mut BTreeSet<Variable>) {
+    match expression {
+        Expression::Constant(TermOrVariable::Variable(v)) | Expression::Bound(v) => {
+            variables.insert(v.clone());
+        }
+        Expression::Constant(TermOrVariable::Triple(t)) => {
+            add_triple_pattern_variables(t, variables)
+        }
+        Expression::Constant(TermOrVariable::Term(_)) => (),
+        Expression::Or(a, b)
+        | Expression::And(a, b)
+        | Expression::Equal(a, b)
+        | Expression::NotEqual(a, b)
+        | Expression::Greater(a, b)
+        | Expression::GreaterOrEq(a, b)
+        | Expression::Lower(a, b)
+        | Expression::LowerOrEq(a, b)
+        | Expression::Add(a, b)
+        | Expression::Sub(a, b)
+        | Expression::Mul(a, b)
+        | Expression::Div(a, b) => {
+            add_expression_variables(a, variables);
+            add_expression_variables(b, variables);
+        }
+        Expression::UnaryPlus(e) | Expression::UnaryMinus(e) | Expression::UnaryNot(e) => {
+            add_expression_variables(e, variables)
+        }
+        Expression::In(a, l) | Expression::NotIn(a, l) => {
+            add_expression_variables(a, variables);
+            for e in l {
+                add_expression_variables(e, variables);
+            }
+        }
+        Expression::FunctionCall(_, args) => {
+            for e in args {
+                add_expression_variables(e, variables);
+            }
+        }
+        Expression::Exists(p) => add_all_variables(p, variables),
+    }
+}
+
+fn add_bgp_pattern_variables(pattern: &TripleOrPathPattern, variables: &mut BTreeSet<Variable>) {
+    match pattern {
+        TripleOrPathPattern::Triple(t) => add_triple_pattern_variables(t, variables),
+        TripleOrPathPattern::Path(p) => {
+            add_term_or_variable_variables(&p.subject, variables);
+            add_term_or_variable_variables(&p.object, variables);
+        }
+    }
+}
+
+fn add_triple_pattern_variables(pattern: &TriplePattern, variables: &mut BTreeSet<Variable>) {
+    add_term_or_variable_variables(&pattern.subject, variables);
+    if let NamedNodeOrVariable::Variable(v) = &pattern.predicate {
+        variables.insert(v.clone());
+    }
+    add_term_or_variable_variables(&pattern.object, variables);
+}
+
+fn add_term_or_variable_variables(term: &TermOrVariable, variables: &mut BTreeSet<Variable>) {
+    match term {
+        TermOrVariable::Variable(v) => {
+            variables.insert(v.clone());
+        }
+        TermOrVariable::Triple(t) => add_triple_pattern_variables(t, variables),
+        TermOrVariable::Term(_) => (),
+    }
+}
+
+/// Removes the `BIND`s of variables that are not in `needed` and not used by the patterns between them and the projection
+fn remove_unused_bindings(pattern: GraphPattern, mut needed: BTreeSet<Variable>) -> GraphPattern {
+    match pattern {
+        GraphPattern::Extend(p, v, e) => {
+            if needed.contains(&v) {
+                add_expression_variables(&e, &mut needed);
+                GraphPattern::Extend(Box::new(remove_unused_bindings(*p, needed)), v, e)
+            } else {
+                remove_unused_bindings(*p, needed)
+            }
+        }
+        GraphPattern::Filter(e, p) => {
+            add_expression_variables(&e, &mut needed);
+            GraphPattern::Filter(e, Box::new(remove_unused_bindings(*p, needed)))
+        }
+        GraphPattern::OrderBy(p, comparators) => {
+            for comparator in &comparators {
+                match comparator {
+                    OrderComparator::Asc(e) | OrderComparator::Desc(e) => {
+                        add_expression_variables(e, &mut needed)
+                    }
+                }
+            }
+            GraphPattern::OrderBy(Box::new(remove_unused_bindings(*p, needed)), comparators)
+        }
+        GraphPattern::Join(a, b) => {
+            let mut a_needed = needed.clone();
+            a_needed.extend(all_variables(&b));
+            let mut b_needed = needed;
+            b_needed.extend(all_variables(&a));
+            GraphPattern::Join(
+                Box::new(remove_unused_bindings(*a, a_needed)),
+                Box::new(remove_unused_bindings(*b, b_needed)),
+            )
+        }
+        GraphPattern::LeftJoin(a, b, e) => {
+            add_expression_variables(&e, &mut needed);
+            let mut a_needed = needed.clone();
+            a_needed.extend(all_variables(&b));
+            let mut b_needed = needed;
+            b_needed.extend(all_variables(&a));
+            GraphPattern::LeftJoin(
+                Box::new(remove_unused_bindings(*a, a_needed)),
+                Box::new(remove_unused_bindings(*b, b_needed)),
+                e,
+            )
+        }
+        GraphPattern::Union(a, b) => GraphPattern::Union(
+            Box::new(remove_unused_bindings(*a, needed.clone())),
+            Box::new(remove_unused_bindings(*b, needed)),
+        ),
+        GraphPattern::Minus(a, b) => {
+            needed.extend(all_variables(&b));
+            GraphPattern::Minus(Box::new(remove_unused_bindings(*a, needed)), b)
+        }
+        p => p,
+    }
+}
+
+#[test]
+fn test_optimize() {
+    use crate::sparql::parser::read_sparql_query;
+
+    let algebra = |query| match read_sparql_query(query, None).unwrap().variants {
+        QueryVariants::Select { algebra, .. } => algebra,
+        _ => unreachable!(),
+    };
+    let examples = [
+        (
+            "SELECT ?s ?o WHERE { ?s <http://e/p> ?o FILTER(1 + 2 = 3 && ?o = 1) }",
+            "SELECT ?s ?o WHERE { ?s <http://e/p> ?o FILTER(?o = 1) }",
+        ),
+        (
+            "SELECT ?s ?o ?r WHERE { ?s <http://e/p> ?o OPTIONAL { ?o <http://e/q> ?r } FILTER(?o > 1) }",
+            "SELECT ?s ?o ?r WHERE { { ?s <http://e/p> ?o FILTER(?o > 1) } OPTIONAL { ?o <http://e/q> ?r } }",
+        ),
+        (
+            "SELECT ?s ?o WHERE { ?s <http://e/p> ?o FILTER(?s = <http://e/a>) }",
+            "SELECT ?s ?o WHERE { <http://e/a> <http://e/p> ?o BIND(<http://e/a> AS ?s) }",
+        ),
+        (
+            "SELECT ?s WHERE { ?s <http://e/p> ?o BIND(?o + 1 AS ?x) }",
+            "SELECT ?s WHERE { ?s <http://e/p> ?o }",
+        ),
+    ];
+    for (query, expected) in examples.iter() {
+        assert_eq!(
+            optimize(algebra(query), &BTreeSet::default()),
+            algebra(expected)
+        );
+    }
+}
+
+#[cfg(test)]
+fn select_algebra(query: &str) -> GraphPattern {
+    match crate::sparql::parser::read_sparql_query(query, None)
+        .unwrap()
+        .variants
+    {
+        QueryVariants::Select { algebra, .. } => algebra,
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+fn assert_rewrites(rule: impl Fn(GraphPattern) -> GraphPattern, examples: &[(&str, &str)]) {
+    for (query, expected) in examples {
+        assert_eq!(
+            rule(select_algebra(query)),
+            select_algebra(expected),
+            "{}",
+            query
+        );
+    }
+}
+
+#[test]
+fn test_fold_constants() {
+    assert_rewrites(
+        fold_constants,
+        &[
+            (
+                "SELECT ?s WHERE { ?s <http://e/p> ?o FILTER(1 < 2) }",
+                "SELECT ?s WHERE { ?s <http://e/p> ?o }",
+            ),
+            (
+                "SELECT ?s WHERE { ?s <http://e/p> ?o FILTER(?o = 2 * 3 || false) }",
+                "SELECT ?s WHERE { ?s <http://e/p> ?o FILTER(?o = 6) }",
+            ),
+            (
+                "SELECT ?s WHERE { ?s <http://e/p> ?o OPTIONAL { ?o <http://e/q> ?r FILTER(false) } }",
+                "SELECT ?s WHERE { ?s <http://e/p> ?o }",
+            ),
+        ],
+    );
+}
+
+#[test]
+fn test_eliminate_empty_patterns() {
+    assert_rewrites(
+        |p| eliminate_empty_patterns(fold_constants(p)),
+        &[
+            (
+                "SELECT ?s WHERE { { ?s <http://e/p> ?o } UNION { ?s <http://e/q> ?o FILTER(false) } }",
+                "SELECT ?s WHERE { ?s <http://e/p> ?o }",
+            ),
+            (
+                "SELECT ?s WHERE { ?s <http://e/p> ?o OPTIONAL { ?o <http://e/q> ?r FILTER(1 = 2) } }",
+                "SELECT ?s WHERE { ?s <http://e/p> ?o }",
+            ),
+            (
+                "SELECT ?s WHERE { ?s <http://e/p> ?o MINUS { ?o <http://e/q> ?r FILTER(false) } }",
+                "SELECT ?s WHERE { ?s <http://e/p> ?o }",
+            ),
+        ],
+    );
+
+    // The projection is kept to return the variables of the query
+    let projection = vec![Variable::new("s"), Variable::new("o")];
+    for query in &[
+        "SELECT ?s ?o WHERE { ?s <http://e/p> ?o FILTER(false) }",
+        "SELECT ?s ?o WHERE { ?s <http://e/p> ?o { ?o <http://e/q> ?r FILTER(false) } }",
+        "SELECT ?s ?o WHERE { ?s <http://e/p> ?o } LIMIT 0",
+        "SELECT DISTINCT ?s ?o WHERE { ?s <http://e/p> ?o FILTER(false) } ORDER BY ?o LIMIT 10",
+    ] {
+        assert_eq!(
+            eliminate_empty_patterns(fold_constants(select_algebra(query))),
+            GraphPattern::Project(Box::new(empty_pattern()), projection.clone()),
+            "{}",
+            query
+        );
+    }
+}
+
+#[test]
+fn test_optional_to_join() {
+    assert_rewrites(
+        |p| optional_to_join(p, &BTreeSet::default()),
+        &[
+            (
+                "SELECT * WHERE { ?s <http://e/p> ?o OPTIONAL { GRAPH ?g { ?o <http://e/q> ?r } } FILTER(BOUND(?r)) }",
+                "SELECT * WHERE { ?s <http://e/p> ?o GRAPH ?g { ?o <http://e/q> ?r } FILTER(BOUND(?r)) }",
+            ),
+            (
+                "SELECT * WHERE { ?s <http://e/p> ?o OPTIONAL { ?o <http://e/q> ?r } FILTER(BOUND(?o)) }",
+                "SELECT * WHERE { ?s <http://e/p> ?o OPTIONAL { ?o <http://e/q> ?r } FILTER(BOUND(?o)) }",
+            ),
+        ],
+    );
+
+    // ?r may be bound before the evaluation even if the optional pattern does not match
+    let bound_variables = [Variable::new("r")].iter().cloned().collect();
+    assert_rewrites(
+        |p| optional_to_join(p, &bound_variables),
+        &[(
+            "SELECT * WHERE { ?s <http://e/p> ?o OPTIONAL { ?o <http://e/q> ?r } FILTER(BOUND(?r)) }",
+            "SELECT * WHERE { ?s <http://e/p> ?o OPTIONAL { ?o <http://e/q> ?r } FILTER(BOUND(?r)) }",
+        )],
+    );
+}
+
+#[test]
+fn test_push_filters() {
+    assert_rewrites(
+        push_filters,
+        &[
+            (
+                "SELECT * WHERE { { ?s <http://e/p> ?o } UNION { ?s <http://e/q> ?o } FILTER(?o > 1) }",
+                "SELECT * WHERE { { ?s <http://e/p> ?o FILTER(?o > 1) } UNION { ?s <http://e/q> ?o FILTER(?o > 1) } }",
+            ),
+            (
+                "SELECT * WHERE { ?s <http://e/p> ?o OPTIONAL { ?o <http://e/q> ?r } FILTER(?o > 1 && RAND() > 0.5) }",
+                "SELECT * WHERE { { ?s <http://e/p> ?o FILTER(?o > 1) } OPTIONAL { ?o <http://e/q> ?r } FILTER(RAND() > 0.5) }",
+            ),
+        ],
+    );
+}
+
+#[test]
+fn test_substitute_bound_values() {
+    assert_rewrites(
+        substitute_bound_values,
+        &[
+            (
+                "SELECT ?s ?o WHERE { ?s <http://e/p> ?o FILTER(sameTerm(?s, <http://e/a>)) }",
+                "SELECT ?s ?o WHERE { <http://e/a> <http://e/p> ?o BIND(<http://e/a> AS ?s) }",
+            ),
+            (
+                "SELECT ?s ?o WHERE { ?s <http://e/p> ?o FILTER(?x = <http://e/a>) }",
+                "SELECT ?s ?o WHERE { ?s <http://e/p> ?o FILTER(?x = <http://e/a>) }",
+            ),
+        ],
+    );
+}
+
+#[test]
+fn test_push_projections() {
+    assert_rewrites(
+        |p| push_projections(p, &BTreeSet::default()),
+        &[
+            (
+                "SELECT ?s WHERE { ?s <http://e/p> ?o BIND(?o + 1 AS ?x) }",
+                "SELECT ?s WHERE { ?s <http://e/p> ?o }",
+            ),
+            (
+                "SELECT ?s ?x WHERE { ?s <http://e/p> ?o BIND(?o + 1 AS ?x) }",
+                "SELECT ?s ?x WHERE { ?s <http://e/p> ?o BIND(?o + 1 AS ?x) }",
+            ),
+            (
+                "SELECT ?s WHERE { ?s <http://e/p> ?o { SELECT ?o WHERE { ?o <http://e/q> ?r BIND(?r AS ?x) } } }",
+                "SELECT ?s WHERE { ?s <http://e/p> ?o { SELECT ?o WHERE { ?o <http://e/q> ?r } } }",
+            ),
+        ],
+    );
+
+    // The BINDs of the variables bound before the evaluation are kept, but not the ones of the subqueries
+    let bound_variables = [Variable::new("x")].iter().cloned().collect();
+    assert_rewrites(
+        |p| push_projections(p, &bound_variables),
+        &[
+            (
+                "SELECT ?s WHERE { ?s <http://e/p> ?o BIND(?o + 1 AS ?x) }",
+                "SELECT ?s WHERE { ?s <http://e/p> ?o BIND(?o + 1 AS ?x) }",
+            ),
+            (
+                "SELECT ?s WHERE { ?s <http://e/p> ?o { SELECT ?o WHERE { ?o <http://e/q> ?r BIND(?r AS ?x) } } }",
+                "SELECT ?s WHERE { ?s <http://e/p> ?o { SELECT ?o WHERE { ?o <http://e/q> ?r } } }",
+            ),
+        ],
+    );
+}
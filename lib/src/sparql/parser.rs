@@ -14,6 +14,8 @@ mod grammar {
     use crate::model::*;
     use crate::sparql::algebra::*;
     use crate::sparql::model::*;
+    use crate::sparql::Query;
+    use crate::{SyntaxError, TextPosition};
     use lazy_static::lazy_static;
     use rio_api::iri::{Iri, IriParseError};
     use std::borrow::Cow;
@@ -95,6 +97,105 @@ mod grammar {
         }
     }
 
+    /// An object with the predicates and objects annotating the triple it is the object of
+    struct AnnotatedTerm<P> {
+        term: TermOrVariable,
+        annotations: Vec<(P, Vec<AnnotatedTerm<P>>)>,
+    }
+
+    impl From<NamedNodeOrVariable> for VariableOrPropertyPath {
+        fn from(predicate: NamedNodeOrVariable) -> Self {
+            match predicate {
+                NamedNodeOrVariable::NamedNode(predicate) => {
+                    VariableOrPropertyPath::PropertyPath(predicate.into())
+                }
+                NamedNodeOrVariable::Variable(predicate) => {
+                    VariableOrPropertyPath::Variable(predicate)
+                }
+            }
+        }
+    }
+
+    impl From<AnnotatedTerm<NamedNodeOrVariable>> for AnnotatedTerm<VariableOrPropertyPath> {
+        fn from(term: AnnotatedTerm<NamedNodeOrVariable>) -> Self {
+            Self {
+                term: term.term,
+                annotations: term
+                    .annotations
+                    .into_iter()
+                    .map(|(p, os)| (p.into(), os.into_iter().map(|o| o.into()).collect()))
+                    .collect(),
+            }
+        }
+    }
+
+    /// Adds the triple pattern and the patterns annotating it
+    fn add_annotated_triple_patterns(
+        s: TermOrVariable,
+        p: NamedNodeOrVariable,
+        o: AnnotatedTerm<NamedNodeOrVariable>,
+        patterns: &mut Vec<TriplePattern>,
+    ) {
+        let triple = TriplePattern::new(s, p, o.term);
+        if !o.annotations.is_empty() {
+            let quoted = TermOrVariable::Triple(Box::new(triple.clone()));
+            for (p, os) in o.annotations {
+                for o in os {
+                    add_annotated_triple_patterns(quoted.clone(), p.clone(), o, patterns);
+                }
+            }
+        }
+        patterns.push(triple);
+    }
+
+    /// Adds the triple or path patterns and the patterns annotating them
+    ///
+    /// Only the triples with a predicate or a variable in the predicate position could be annotated.
+    fn add_annotated_triple_or_path_patterns(
+        s: TermOrVariable,
+        p: VariableOrPropertyPath,
+        o: AnnotatedTerm<VariableOrPropertyPath>,
+        patterns: &mut Vec<TripleOrPathPattern>,
+    ) -> Result<(), &'static str> {
+        if o.annotations.is_empty() {
+            add_to_triple_or_path_patterns(s, p, o.term, patterns);
+            return Ok(());
+        }
+        let triple = match p {
+            VariableOrPropertyPath::Variable(p) => TriplePattern::new(s, p, o.term),
+            VariableOrPropertyPath::PropertyPath(PropertyPath::PredicatePath(p)) => {
+                TriplePattern::new(s, p, o.term)
+            }
+            VariableOrPropertyPath::PropertyPath(_) => {
+                return Err("Only the triples with a simple predicate could be annotated")
+            }
+        };
+        add_property_list_path_patterns(
+            TermOrVariable::Triple(Box::new(triple.clone())),
+            o.annotations,
+            patterns,
+        )?;
+        patterns.push(triple.into());
+        Ok(())
+    }
+
+    /// Adds the patterns of the predicates and objects of the subject `s`
+    fn add_property_list_path_patterns(
+        s: TermOrVariable,
+        property_list: Vec<(
+            VariableOrPropertyPath,
+            Vec<AnnotatedTerm<VariableOrPropertyPath>>,
+        )>,
+        patterns: &mut Vec<TripleOrPathPattern>,
+    ) -> Result<(), &'static str> {
+        for (p, os) in property_list {
+            for o in os {
+                add_annotated_triple_or_path_patterns(s.clone(), p.clone(), o, patterns)?;
+            }
+        }
+        Ok(())
+    }
+
     struct FocusedTripleOrPathPattern<F> {
         focus: F,
         patterns: Vec<TripleOrPathPattern>,
@@ -511,7 +612,7 @@ mod grammar {
         );
     }
 
-    fn unescape_echars(input: &str) -> Cow<'_, str> {
+    pub fn unescape_echars(input: &str) -> Cow<'_, str> {
         unescape_characters(input, &UNESCAPE_CHARACTERS, &UNESCAPE_REPLACEMENT)
     }
 
@@ -538,10 +639,121 @@ mod grammar {
 
     include!(concat!(env!("OUT_DIR"), "/sparql_grammar.rs"));
 
+    const MAX_EXPECTED_TOKENS_IN_MESSAGE: usize = 16;
+
+    /// Builds the error from the original query, the parser error being located in the query with its code points unescaped
+    fn to_syntax_error(query: &str, error: &ParseError) -> SyntaxError {
+        let offset = original_offset(query, error.offset);
+        let line_start = query[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = query[offset..]
+            .find('\n')
+            .map_or(query.len(), |i| offset + i);
+        let position = TextPosition::new(
+            query[..line_start].matches('\n').count(),
+            query[line_start..offset].chars().count(),
+        );
+        // Descriptions like "a variable" go before the literal tokens
+        let mut expected = error
+            .expected
+            .iter()
+            .map(|token| describe_expected_token(token))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        expected.sort_by_key(|token| token.starts_with('`'));
+        let mut message = format!(
+            "Unexpected {} at {}",
+            describe_found_token(&query[offset..]),
+            position
+        );
+        if !expected.is_empty() {
+            message.push_str(", expected ");
+            if expected.len() > 1 {
+                message.push_str("one of ");
+            }
+            if expected.len() > MAX_EXPECTED_TOKENS_IN_MESSAGE {
+                message.push_str(&expected[..MAX_EXPECTED_TOKENS_IN_MESSAGE].join(", "));
+                message.push_str(&format!(
+                    " or {} other tokens",
+                    expected.len() - MAX_EXPECTED_TOKENS_IN_MESSAGE
+                ));
+            } else {
+                message.push_str(&expected.join(", "));
+            }
+        }
+        SyntaxError::new(message, Some(position))
+            .with_expected(expected)
+            .with_snippet(query[line_start..line_end].trim_end_matches('\r'))
+    }
+
+    /// Maps an offset in the query with its code points unescaped to the offset in the original query
+    fn original_offset(query: &str, unescaped_offset: usize) -> usize {
+        let mut offset = 0;
+        let mut unescaped = 0;
+        while unescaped < unescaped_offset && offset < query.len() {
+            let (consumed, produced) = unescape_step(&query[offset..]);
+            offset += consumed;
+            unescaped += produced;
+        }
+        offset
+    }
+
+    /// Returns the number of bytes read and written by `UnescapeUnicodeCharIterator` for the start of the input
+    fn unescape_step(input: &str) -> (usize, usize) {
+        let mut chars = input.chars();
+        match chars.next() {
+            Some('\\') => match chars.next() {
+                Some(c) if c == 'u' || c == 'U' => {
+                    let len = if c == 'u' { 4 } else { 8 };
+                    let digits = chars.take(len).collect::<String>();
+                    let consumed = 2 + digits.len();
+                    match u32::from_str_radix(&digits, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                    {
+                        Some(c) if digits.chars().count() == len => (consumed, c.len_utf8()),
+                        _ => (consumed, consumed),
+                    }
+                }
+                Some(c) => (1 + c.len_utf8(), 1 + c.len_utf8()),
+                None => (1, 1),
+            },
+            Some(c) => (c.len_utf8(), c.len_utf8()),
+            None => (0, 0),
+        }
+    }
+
+    fn describe_found_token(input: &str) -> String {
+        match input.chars().next() {
+            None => "end of query".to_owned(),
+            Some(c) if c.is_alphanumeric() || c == '_' => {
+                let len = input
+                    .find(|c: char| !c.is_alphanumeric() && c != '_')
+                    .unwrap_or(input.len());
+                format!("`{}`", &input[..len])
+            }
+            Some(c) if c.is_whitespace() => "whitespace".to_owned(),
+            Some(c) => format!("`{}`", c),
+        }
+    }
+
+    /// Converts the token descriptions of the PEG parser into user facing ones
+    fn describe_expected_token(token: &str) -> String {
+        match token {
+            "?" | "$" => "a variable".to_owned(),
+            "_:" => "a blank node".to_owned(),
+            "[0-9]" => "a number".to_owned(),
+            "'" | "\"" | "'''" | "\"\"\"" => "a string".to_owned(),
+            // The first character of a prefix or of a prefixed name
+            token if token.starts_with("[A-Za-z") => "a prefixed name".to_owned(),
+            token => format!("`{}`", token),
+        }
+    }
+
     pub fn read_sparql_query(
         query: &str,
         base_iri: Option<&str>,
-    ) -> super::super::super::Result<QueryVariants> {
+    ) -> super::super::super::Result<Query> {
         let mut state = ParserState {
             base_iri: if let Some(base_iri) = base_iri {
                 Some(Iri::parse(base_iri.to_owned())?)
@@ -554,8 +766,87 @@ mod grammar {
             aggregations: Vec::default(),
         };
 
-        Ok(QueryUnit(&unescape_unicode_codepoints(query), &mut state)?)
+        let unescaped_query = unescape_unicode_codepoints(query);
+        let variants = QueryUnit(&unescaped_query, &mut state)
+            .map_err(|error| to_syntax_error(query, &error))?;
+        Ok(Query {
+            variants,
+            prefixes: state.namespaces.into_iter().collect(),
+        })
     }
 }
 
 pub use self::grammar::read_sparql_query;
+pub(crate) use self::grammar::{unescape_echars, unescape_pn_local, unescape_unicode_codepoints};
+
+#[test]
+fn test_sparql_star_annotations() {
+    use crate::sparql::algebra::QueryVariants;
+
+    let algebra = |query| match read_sparql_query(query, None).unwrap().variants {
+        QueryVariants::Select { algebra, .. } => algebra,
+        QueryVariants::Construct {
+            construct, algebra, ..
+        } => crate::sparql::algebra::GraphPattern::Join(
+            Box::new(algebra),
+            Box::new(crate::sparql::algebra::GraphPattern::BGP(
+                construct.into_iter().map(|t| t.into()).collect(),
+            )),
+        ),
+        _ => unreachable!(),
+    };
+    let examples = [
+        (
+            "SELECT * WHERE { ?s ?p ?o {| ?q ?v {| ?r ?w |} |} }",
+            "SELECT * WHERE { << << ?s ?p ?o >> ?q ?v >> ?r ?w . << ?s ?p ?o >> ?q ?v . ?s ?p ?o }",
+        ),
+        (
+            "SELECT * WHERE { ?s <http://e/p> ?o {| <http://e/q>* ?v |}, ?o2 ; <http://e/r> ?o3 {| ?q ?v |} }",
+            "SELECT * WHERE { << ?s <http://e/p> ?o >> <http://e/q>* ?v . ?s <http://e/p> ?o , ?o2 . << ?s <http://e/r> ?o3 >> ?q ?v . ?s <http://e/r> ?o3 }",
+        ),
+        (
+            "CONSTRUCT { ?s ?p ?o {| ?q << ?s ?p ?o >> |} } WHERE { ?s ?p ?o }",
+            "CONSTRUCT { << ?s ?p ?o >> ?q << ?s ?p ?o >> . ?s ?p ?o } WHERE { ?s ?p ?o }",
+        ),
+    ];
+    for (query, expected) in examples.iter() {
+        assert_eq!(algebra(query), algebra(expected), "{}", query);
+    }
+
+    // Only the triples with a simple predicate could be annotated
+    assert!(read_sparql_query("SELECT * WHERE { ?s <http://e/p>* ?o {| ?q ?v |} }", None).is_err());
+}
+
+#[test]
+fn test_syntax_error_position_with_escaped_code_points() {
+    use crate::Error;
+
+    let syntax_error = |query| match read_sparql_query(query, None) {
+        Err(Error::Syntax(error)) => {
+            let position = error.position().unwrap();
+            (
+                position.line(),
+                position.column(),
+                error.snippet().unwrap().to_owned(),
+                error.to_string(),
+            )
+        }
+        _ => panic!("A syntax error was expected for {}", query),
+    };
+
+    let query = "SELECT * WHERE {\n  ?s ?p \"\\u00E9t\\U000000E9\\\\u0041\" ; ?p2 ?o2 ?x .\n}";
+    let (line, column, snippet, message) = syntax_error(query);
+    assert_eq!((line, column), (1, 45));
+    assert_eq!(snippet, query.lines().nth(1).unwrap());
+    assert!(
+        message.contains("Unexpected `?` at line 2 column 46"),
+        "{}",
+        message
+    );
+
+    // The invalid escapes are kept as they are
+    let query = "SELECT * WHERE { # \\u12\\u0041\n?s ?p ?o ?x }";
+    let (line, column, snippet, _) = syntax_error(query);
+    assert_eq!((line, column), (1, 9));
+    assert_eq!(snippet, "?s ?p ?o ?x }");
+}
@@ -1,3 +1,4 @@
+use crate::sparql::entailment::RdfsSchema;
 use crate::sparql::eval::StringOrStoreString;
 use crate::store::numeric_encoder::{
     EncodedQuad, EncodedTerm, Encoder, MemoryStrStore, StrContainer, StrLookup,
@@ -6,6 +7,8 @@ use crate::store::StoreConnection;
 use crate::Result;
 use std::cell::{RefCell, RefMut};
 use std::collections::BTreeSet;
+use std::iter::once;
+use std::rc::Rc;
 
 pub type EncodedTuple = Vec<Option<EncodedTerm>>;
 
@@ -37,6 +40,21 @@ pub enum PlanNode {
         left: Box<PlanNode>,
         right: Box<PlanNode>,
     },
+    /// The tuples of `left` compatible with at least one tuple of `right`, used for `FILTER EXISTS`
+    ///
+    /// If `right` is not `correlated` with `left` it is evaluated once and hashed,
+    /// else it is evaluated from each tuple of `left` until its first result.
+    SemiJoin {
+        left: Box<PlanNode>,
+        right: Box<PlanNode>,
+        correlated: bool,
+    },
+    /// The tuples of `left` compatible with no tuple of `right`, used for `FILTER NOT EXISTS`
+    AntiSemiJoin {
+        left: Box<PlanNode>,
+        right: Box<PlanNode>,
+        correlated: bool,
+    },
     Filter {
         child: Box<PlanNode>,
         expression: PlanExpression,
@@ -58,6 +76,12 @@ pub enum PlanNode {
         child: Box<PlanNode>,
         by: Vec<Comparator>,
     },
+    /// The `count` first tuples of the child according to `by`, used for `ORDER BY` with a `LIMIT`
+    TopK {
+        child: Box<PlanNode>,
+        by: Vec<Comparator>,
+        count: usize,
+    },
     HashDeduplicate {
         child: Box<PlanNode>,
     },
@@ -72,6 +96,9 @@ pub enum PlanNode {
     Project {
         child: Box<PlanNode>,
         mapping: Vec<(usize, usize)>, // pairs of (variable key in child, variable key in output)
+        /// Pairs of (variable key in child, variable key in input) of the variables that are not projected
+        /// but could be bound before the evaluation of the query WHERE clause
+        pre_bound: Vec<(usize, usize)>,
     },
     Aggregate {
         // By definition the group by key are the range 0..key_mapping.len()
@@ -88,6 +115,29 @@ impl PlanNode {
         set
     }
 
+    /// Checks if evaluating the node from tuples binding the given variables
+    /// is the same as evaluating it once without them and joining the results with these tuples
+    pub fn is_independent_of(&self, variables: &BTreeSet<usize>) -> bool {
+        match self {
+            PlanNode::Init | PlanNode::StaticBindings { .. } => true,
+            PlanNode::QuadPatternJoin { child, .. } | PlanNode::PathPatternJoin { child, .. } => {
+                child.is_independent_of(variables)
+            }
+            PlanNode::Join { left, right } => {
+                left.is_independent_of(variables) && right.is_independent_of(variables)
+            }
+            PlanNode::Union { children } => children
+                .iter()
+                .all(|child| child.is_independent_of(variables)),
+            PlanNode::Filter { child, expression } => {
+                let mut expression_variables = BTreeSet::default();
+                expression.add_variables(&mut expression_variables);
+                expression_variables.is_disjoint(variables) && child.is_independent_of(variables)
+            }
+            _ => false,
+        }
+    }
+
     fn add_variables(&self, set: &mut BTreeSet<usize>) {
         match self {
             PlanNode::Init => (),
@@ -107,18 +157,10 @@ impl PlanNode {
                 object,
                 graph_name,
             } => {
-                if let PatternValue::Variable(var) = subject {
-                    set.insert(*var);
-                }
-                if let PatternValue::Variable(var) = predicate {
-                    set.insert(*var);
-                }
-                if let PatternValue::Variable(var) = object {
-                    set.insert(*var);
-                }
-                if let PatternValue::Variable(var) = graph_name {
-                    set.insert(*var);
-                }
+                subject.add_variables(set);
+                predicate.add_variables(set);
+                object.add_variables(set);
+                graph_name.add_variables(set);
                 child.add_variables(set);
             }
             PlanNode::PathPatternJoin {
@@ -128,15 +170,9 @@ impl PlanNode {
                 graph_name,
                 ..
             } => {
-                if let PatternValue::Variable(var) = subject {
-                    set.insert(*var);
-                }
-                if let PatternValue::Variable(var) = object {
-                    set.insert(*var);
-                }
-                if let PatternValue::Variable(var) = graph_name {
-                    set.insert(*var);
-                }
+                subject.add_variables(set);
+                object.add_variables(set);
+                graph_name.add_variables(set);
                 child.add_variables(set);
             }
             PlanNode::Filter { child, expression } => {
@@ -150,6 +186,8 @@ impl PlanNode {
             }
             PlanNode::Join { left, right }
             | PlanNode::AntiJoin { left, right }
+            | PlanNode::SemiJoin { left, right, .. }
+            | PlanNode::AntiSemiJoin { left, right, .. }
             | PlanNode::LeftJoin { left, right, .. } => {
                 left.add_variables(set);
                 right.add_variables(set);
@@ -161,10 +199,11 @@ impl PlanNode {
                 child.add_variables(set);
             }
             PlanNode::Sort { child, .. } => child.add_variables(set),
+            PlanNode::TopK { child, .. } => child.add_variables(set),
             PlanNode::HashDeduplicate { child } => child.add_variables(set),
             PlanNode::Skip { child, .. } => child.add_variables(set),
             PlanNode::Limit { child, .. } => child.add_variables(set),
-            PlanNode::Project { child: _, mapping } => {
+            PlanNode::Project { mapping, .. } => {
                 for i in 0..mapping.len() {
                     set.insert(i);
                 }
@@ -183,19 +222,42 @@ impl PlanNode {
     }
 }
 
-#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub enum PatternValue {
     Constant(EncodedTerm),
     Variable(usize),
+    /// A quoted triple pattern containing at least one variable
+    Triple(Box<TriplePatternValue>),
 }
 
 impl PatternValue {
     pub fn is_var(&self) -> bool {
         match self {
-            PatternValue::Constant(_) => false,
+            PatternValue::Constant(_) | PatternValue::Triple(_) => false,
             PatternValue::Variable(_) => true,
         }
     }
+
+    fn add_variables(&self, set: &mut BTreeSet<usize>) {
+        match self {
+            PatternValue::Constant(_) => (),
+            PatternValue::Variable(var) => {
+                set.insert(*var);
+            }
+            PatternValue::Triple(triple) => {
+                triple.subject.add_variables(set);
+                triple.predicate.add_variables(set);
+                triple.object.add_variables(set);
+            }
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct TriplePatternValue {
+    pub subject: PatternValue,
+    pub predicate: PatternValue,
+    pub object: PatternValue,
 }
 
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
@@ -295,6 +357,15 @@ pub enum PlanExpression {
     TimeCast(Box<PlanExpression>),
     DateTimeCast(Box<PlanExpression>),
     StringCast(Box<PlanExpression>),
+    Triple(
+        Box<PlanExpression>,
+        Box<PlanExpression>,
+        Box<PlanExpression>,
+    ),
+    Subject(Box<PlanExpression>),
+    Predicate(Box<PlanExpression>),
+    Object(Box<PlanExpression>),
+    IsTriple(Box<PlanExpression>),
 }
 
 impl PlanExpression {
@@ -350,7 +421,11 @@ impl PlanExpression {
             | PlanExpression::SHA1(e)
             | PlanExpression::SHA256(e)
             | PlanExpression::SHA384(e)
-            | PlanExpression::SHA512(e) => {
+            | PlanExpression::SHA512(e)
+            | PlanExpression::Subject(e)
+            | PlanExpression::Predicate(e)
+            | PlanExpression::Object(e)
+            | PlanExpression::IsTriple(e) => {
                 e.add_variables(set);
             }
             PlanExpression::Or(a, b)
@@ -380,6 +455,7 @@ impl PlanExpression {
                 b.add_variables(set);
             }
             PlanExpression::If(a, b, c)
+            | PlanExpression::Triple(a, b, c)
             | PlanExpression::SubStr(a, b, Some(c))
             | PlanExpression::Replace(a, b, c, None)
             | PlanExpression::Regex(a, b, Some(c)) => {
@@ -439,36 +515,56 @@ pub enum PlanPropertyPath {
     NegatedPropertySet(Vec<EncodedTerm>),
 }
 
+impl PlanPropertyPath {
+    /// Returns if the path is a `*` or `+` closure, whose evaluation recursively walks the graph
+    pub fn is_transitive(&self) -> bool {
+        match self {
+            PlanPropertyPath::ZeroOrMorePath(_) | PlanPropertyPath::OneOrMorePath(_) => true,
+            PlanPropertyPath::PredicatePath(_)
+            | PlanPropertyPath::InversePath(_)
+            | PlanPropertyPath::SequencePath(_, _)
+            | PlanPropertyPath::AlternativePath(_, _)
+            | PlanPropertyPath::ZeroOrOnePath(_)
+            | PlanPropertyPath::NegatedPropertySet(_) => false,
+        }
+    }
+}
+
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub enum Comparator {
     Asc(PlanExpression),
     Desc(PlanExpression),
 }
 
-#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub struct TripleTemplate {
     pub subject: TripleTemplateValue,
     pub predicate: TripleTemplateValue,
     pub object: TripleTemplateValue,
 }
 
-#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub enum TripleTemplateValue {
     Constant(EncodedTerm),
     BlankNode(usize),
     Variable(usize),
+    Triple(Box<TripleTemplate>),
 }
 
 pub struct DatasetView<S: StoreConnection> {
     store: S,
     extra: RefCell<MemoryStrStore>,
+    rdfs_entailment: bool,
+    rdfs_schema: RefCell<Option<Rc<RdfsSchema>>>,
 }
 
 impl<S: StoreConnection> DatasetView<S> {
-    pub fn new(store: S) -> Self {
+    pub fn new(store: S, rdfs_entailment: bool) -> Self {
         Self {
             store,
             extra: RefCell::new(MemoryStrStore::default()),
+            rdfs_entailment,
+            rdfs_schema: RefCell::new(None),
         }
     }
 
@@ -479,8 +575,32 @@ impl<S: StoreConnection> DatasetView<S> {
         object: Option<EncodedTerm>,
         graph_name: Option<EncodedTerm>,
     ) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'a> {
-        self.store
-            .quads_for_pattern(subject, predicate, object, graph_name)
+        if !self.rdfs_entailment {
+            return self
+                .store
+                .quads_for_pattern(subject, predicate, object, graph_name);
+        }
+        match self.rdfs_schema() {
+            Ok(schema) => {
+                schema.quads_for_pattern(&self.store, subject, predicate, object, graph_name)
+            }
+            Err(error) => Box::new(once(Err(error))),
+        }
+    }
+
+    /// Forgets the RDFS vocabulary read from the store so that the next evaluation sees its changes
+    pub fn clear_entailment_cache(&self) {
+        self.rdfs_schema.replace(None);
+    }
+
+    fn rdfs_schema(&self) -> Result<Rc<RdfsSchema>> {
+        let mut cache = self.rdfs_schema.borrow_mut();
+        if let Some(schema) = &*cache {
+            return Ok(schema.clone());
+        }
+        let schema = Rc::new(RdfsSchema::load(&self.store)?);
+        *cache = Some(schema.clone());
+        Ok(schema)
     }
 
     pub fn encoder<'a>(&'a self) -> impl Encoder + StrContainer + 'a {
@@ -489,6 +609,43 @@ impl<S: StoreConnection> DatasetView<S> {
             extra: self.extra.borrow_mut(),
         }
     }
+
+    /// Returns what is needed to build the same view in another thread
+    pub fn seed(&self) -> DatasetViewSeed<S> {
+        DatasetViewSeed {
+            store: self.store.clone(),
+            extra: self.extra.borrow().clone(),
+            rdfs_entailment: self.rdfs_entailment,
+        }
+    }
+
+    /// Returns the strings built during the evaluation that are not in the store
+    pub fn into_extra_strings(self) -> MemoryStrStore {
+        self.extra.into_inner()
+    }
+
+    /// Adds the strings built by another view of the same store
+    pub fn add_extra_strings(&self, strings: MemoryStrStore) {
+        self.extra.borrow_mut().merge(strings)
+    }
+}
+
+/// A `DatasetView` without its caches that could be sent to another thread
+pub struct DatasetViewSeed<S: StoreConnection> {
+    store: S,
+    extra: MemoryStrStore,
+    rdfs_entailment: bool,
+}
+
+impl<S: StoreConnection> DatasetViewSeed<S> {
+    pub fn into_view(self) -> DatasetView<S> {
+        DatasetView {
+            store: self.store,
+            extra: RefCell::new(self.extra),
+            rdfs_entailment: self.rdfs_entailment,
+            rdfs_schema: RefCell::new(None),
+        }
+    }
 }
 
 impl<S: StoreConnection> StrLookup for DatasetView<S> {
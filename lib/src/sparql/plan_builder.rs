@@ -5,23 +5,37 @@ use crate::sparql::model::*;
 use crate::sparql::plan::PlanPropertyPath;
 use crate::sparql::plan::*;
 use crate::store::numeric_encoder::{Encoder, ENCODED_DEFAULT_GRAPH};
+use crate::Error;
 use crate::Result;
-use failure::format_err;
 use std::collections::HashSet;
 
 pub struct PlanBuilder<E: Encoder> {
     encoder: E,
+    /// If the pattern built is still above the query WHERE clause
+    at_root: bool,
+    pre_bound_variables: Vec<Variable>,
 }
 
 impl<E: Encoder> PlanBuilder<E> {
-    pub fn build(encoder: E, pattern: &GraphPattern) -> Result<(PlanNode, Vec<Variable>)> {
+    /// Returns the plan with its variables and then the variables of the WHERE clause that are not projected
+    ///
+    /// The keys of the non projected variables follow the ones of the projected variables.
+    pub fn build(
+        encoder: E,
+        pattern: &GraphPattern,
+    ) -> Result<(PlanNode, Vec<Variable>, Vec<Variable>)> {
         let mut variables = Vec::default();
-        let plan = PlanBuilder { encoder }.build_for_graph_pattern(
+        let mut builder = PlanBuilder {
+            encoder,
+            at_root: true,
+            pre_bound_variables: Vec::default(),
+        };
+        let plan = builder.build_for_graph_pattern(
             pattern,
             &mut variables,
-            PatternValue::Constant(ENCODED_DEFAULT_GRAPH),
+            &PatternValue::Constant(ENCODED_DEFAULT_GRAPH),
         )?;
-        Ok((plan, variables))
+        Ok((plan, variables, builder.pre_bound_variables))
     }
 
     pub fn build_graph_template(
@@ -29,21 +43,49 @@ impl<E: Encoder> PlanBuilder<E> {
         template: &[TriplePattern],
         mut variables: Vec<Variable>,
     ) -> Result<Vec<TripleTemplate>> {
-        PlanBuilder { encoder }.build_for_graph_template(template, &mut variables)
+        PlanBuilder {
+            encoder,
+            at_root: false,
+            pre_bound_variables: Vec::default(),
+        }
+        .build_for_graph_template(template, &mut variables)
     }
 
     fn build_for_graph_pattern(
         &mut self,
         pattern: &GraphPattern,
         variables: &mut Vec<Variable>,
-        graph_name: PatternValue,
+        graph_name: &PatternValue,
     ) -> Result<PlanNode> {
+        let is_root_projection = self.at_root && matches!(pattern, GraphPattern::Project(..));
+        if !matches!(
+            pattern,
+            GraphPattern::Slice(..) | GraphPattern::Distinct(_) | GraphPattern::Reduced(_)
+        ) {
+            self.at_root = false;
+        }
         Ok(match pattern {
-            GraphPattern::BGP(p) => self.build_for_bgp(p, variables, graph_name)?,
-            GraphPattern::Join(a, b) => PlanNode::Join {
-                left: Box::new(self.build_for_graph_pattern(a, variables, graph_name)?),
-                right: Box::new(self.build_for_graph_pattern(b, variables, graph_name)?),
-            },
+            GraphPattern::BGP(p) => {
+                self.build_for_bgp(p, PlanNode::Init, HashSet::default(), variables, graph_name)?
+            }
+            GraphPattern::Join(a, b) => {
+                if let GraphPattern::BGP(p) = &**b {
+                    // The patterns are evaluated from each result of the left side
+                    let left = self.build_for_graph_pattern(a, variables, graph_name)?;
+                    self.build_for_bgp(
+                        p,
+                        left,
+                        a.visible_variables().into_iter().collect(),
+                        variables,
+                        graph_name,
+                    )?
+                } else {
+                    PlanNode::Join {
+                        left: Box::new(self.build_for_graph_pattern(a, variables, graph_name)?),
+                        right: Box::new(self.build_for_graph_pattern(b, variables, graph_name)?),
+                    }
+                }
+            }
             GraphPattern::LeftJoin(a, b, e) => {
                 let left = self.build_for_graph_pattern(a, variables, graph_name)?;
                 let right = self.build_for_graph_pattern(b, variables, graph_name)?;
@@ -68,10 +110,33 @@ impl<E: Encoder> PlanBuilder<E> {
                     possible_problem_vars,
                 }
             }
-            GraphPattern::Filter(e, p) => PlanNode::Filter {
-                child: Box::new(self.build_for_graph_pattern(p, variables, graph_name)?),
-                expression: self.build_for_expression(e, variables, graph_name)?,
-            },
+            GraphPattern::Filter(e, p) => {
+                let child = self.build_for_graph_pattern(p, variables, graph_name)?;
+                if let Some((n, negated)) = exists_pattern(e) {
+                    let right = self.build_for_graph_pattern(n, variables, graph_name)?;
+                    let correlated = !right.is_independent_of(&child.variables());
+                    let left = Box::new(child);
+                    let right = Box::new(right);
+                    if negated {
+                        PlanNode::AntiSemiJoin {
+                            left,
+                            right,
+                            correlated,
+                        }
+                    } else {
+                        PlanNode::SemiJoin {
+                            left,
+                            right,
+                            correlated,
+                        }
+                    }
+                } else {
+                    PlanNode::Filter {
+                        child: Box::new(child),
+                        expression: self.build_for_expression(e, variables, graph_name)?,
+                    }
+                }
+            }
             GraphPattern::Union(a, b) => {
                 //We flatten the UNIONs
                 let mut stack: Vec<&GraphPattern> = vec![a, b];
@@ -92,7 +157,7 @@ impl<E: Encoder> PlanBuilder<E> {
             }
             GraphPattern::Graph(g, p) => {
                 let graph_name = self.pattern_value_from_named_node_or_variable(g, variables)?;
-                self.build_for_graph_pattern(p, variables, graph_name)?
+                self.build_for_graph_pattern(p, variables, &graph_name)?
             }
             GraphPattern::Extend(p, v, e) => PlanNode::Extend {
                 child: Box::new(self.build_for_graph_pattern(p, variables, graph_name)?),
@@ -104,8 +169,8 @@ impl<E: Encoder> PlanBuilder<E> {
                 right: Box::new(self.build_for_graph_pattern(b, variables, graph_name)?),
             },
             GraphPattern::Service(_n, _p, _s) => {
-                return Err(format_err!(
-                    "SPARQL SERVICE clauses are not implemented yet"
+                return Err(Error::unsupported(
+                    "SPARQL SERVICE clauses are not implemented yet",
                 ))
             }
             GraphPattern::AggregateJoin(GroupPattern(key, p), aggregates) => {
@@ -117,7 +182,7 @@ impl<E: Encoder> PlanBuilder<E> {
                     child: Box::new(self.build_for_graph_pattern(
                         p,
                         &mut inner_variables,
-                        inner_graph_name,
+                        &inner_graph_name,
                     )?),
                     key_mapping: key
                         .iter()
@@ -158,19 +223,28 @@ impl<E: Encoder> PlanBuilder<E> {
                 let mut inner_variables = new_variables.clone();
                 let inner_graph_name =
                     self.convert_pattern_value_id(graph_name, variables, &mut inner_variables);
+                let child =
+                    self.build_for_graph_pattern(l, &mut inner_variables, &inner_graph_name)?;
+                let mapping: Vec<_> = new_variables
+                    .iter()
+                    .enumerate()
+                    .map(|(new_variable, variable)| {
+                        (new_variable, variable_key(variables, variable))
+                    })
+                    .collect();
+                let mut pre_bound = Vec::default();
+                if is_root_projection {
+                    for (key, variable) in inner_variables.iter().enumerate() {
+                        if !new_variables.contains(variable) {
+                            pre_bound.push((key, variables.len() + self.pre_bound_variables.len()));
+                            self.pre_bound_variables.push(variable.clone());
+                        }
+                    }
+                }
                 PlanNode::Project {
-                    child: Box::new(self.build_for_graph_pattern(
-                        l,
-                        &mut inner_variables,
-                        inner_graph_name,
-                    )?),
-                    mapping: new_variables
-                        .iter()
-                        .enumerate()
-                        .map(|(new_variable, variable)| {
-                            (new_variable, variable_key(variables, variable))
-                        })
-                        .collect(),
+                    child: Box::new(child),
+                    mapping,
+                    pre_bound,
                 }
             }
             GraphPattern::Distinct(l) => PlanNode::HashDeduplicate {
@@ -179,6 +253,10 @@ impl<E: Encoder> PlanBuilder<E> {
             GraphPattern::Reduced(l) => self.build_for_graph_pattern(l, variables, graph_name)?,
             GraphPattern::Slice(l, start, length) => {
                 let mut plan = self.build_for_graph_pattern(l, variables, graph_name)?;
+                // If the number of kept tuples overflows the Sort is kept as it is
+                if let Some(count) = length.and_then(|length| start.checked_add(length)) {
+                    plan = with_top_k(plan, count);
+                }
                 if *start > 0 {
                     plan = PlanNode::Skip {
                         child: Box::new(plan),
@@ -196,14 +274,16 @@ impl<E: Encoder> PlanBuilder<E> {
         })
     }
 
-    fn build_for_bgp(
+    fn build_for_bgp<'a>(
         &mut self,
-        p: &[TripleOrPathPattern],
+        p: &'a [TripleOrPathPattern],
+        child: PlanNode,
+        bound_variables: HashSet<&'a Variable>,
         variables: &mut Vec<Variable>,
-        graph_name: PatternValue,
+        graph_name: &PatternValue,
     ) -> Result<PlanNode> {
-        let mut plan = PlanNode::Init;
-        for pattern in sort_bgp(p) {
+        let mut plan = child;
+        for pattern in sort_bgp(p, bound_variables) {
             plan = match pattern {
                 TripleOrPathPattern::Triple(pattern) => PlanNode::QuadPatternJoin {
                     child: Box::new(plan),
@@ -212,7 +292,7 @@ impl<E: Encoder> PlanBuilder<E> {
                     predicate: self
                         .pattern_value_from_named_node_or_variable(&pattern.predicate, variables)?,
                     object: self.pattern_value_from_term_or_variable(&pattern.object, variables)?,
-                    graph_name,
+                    graph_name: graph_name.clone(),
                 },
                 TripleOrPathPattern::Path(pattern) => PlanNode::PathPatternJoin {
                     child: Box::new(plan),
@@ -220,7 +300,7 @@ impl<E: Encoder> PlanBuilder<E> {
                         .pattern_value_from_term_or_variable(&pattern.subject, variables)?,
                     path: self.build_for_path(&pattern.path)?,
                     object: self.pattern_value_from_term_or_variable(&pattern.object, variables)?,
-                    graph_name,
+                    graph_name: graph_name.clone(),
                 },
             }
         }
@@ -264,13 +344,10 @@ impl<E: Encoder> PlanBuilder<E> {
         &mut self,
         expression: &Expression,
         variables: &mut Vec<Variable>,
-        graph_name: PatternValue,
+        graph_name: &PatternValue,
     ) -> Result<PlanExpression> {
         Ok(match expression {
-            Expression::Constant(t) => match t {
-                TermOrVariable::Term(t) => PlanExpression::Constant(self.encoder.encode_term(t)?),
-                TermOrVariable::Variable(v) => PlanExpression::Variable(variable_key(variables, v)),
-            },
+            Expression::Constant(t) => self.build_for_term_or_variable_expression(t, variables)?,
             Expression::Or(a, b) => PlanExpression::Or(
                 Box::new(self.build_for_expression(a, variables, graph_name)?),
                 Box::new(self.build_for_expression(b, variables, graph_name)?),
@@ -554,6 +631,27 @@ impl<E: Encoder> PlanBuilder<E> {
                 Function::IsNumeric => PlanExpression::IsNumeric(Box::new(
                     self.build_for_expression(&parameters[0], variables, graph_name)?,
                 )),
+                Function::Triple => PlanExpression::Triple(
+                    Box::new(self.build_for_expression(&parameters[0], variables, graph_name)?),
+                    Box::new(self.build_for_expression(&parameters[1], variables, graph_name)?),
+                    Box::new(self.build_for_expression(&parameters[2], variables, graph_name)?),
+                ),
+                Function::Subject => PlanExpression::Subject(Box::new(self.build_for_expression(
+                    &parameters[0],
+                    variables,
+                    graph_name,
+                )?)),
+                Function::Predicate => PlanExpression::Predicate(Box::new(
+                    self.build_for_expression(&parameters[0], variables, graph_name)?,
+                )),
+                Function::Object => PlanExpression::Object(Box::new(self.build_for_expression(
+                    &parameters[0],
+                    variables,
+                    graph_name,
+                )?)),
+                Function::IsTriple => PlanExpression::IsTriple(Box::new(
+                    self.build_for_expression(&parameters[0], variables, graph_name)?,
+                )),
                 Function::Regex => PlanExpression::Regex(
                     Box::new(self.build_for_expression(&parameters[0], variables, graph_name)?),
                     Box::new(self.build_for_expression(&parameters[1], variables, graph_name)?),
@@ -638,7 +736,10 @@ impl<E: Encoder> PlanBuilder<E> {
                             "string",
                         )?
                     } else {
-                        return Err(format_err!("Not supported custom function {}", expression));
+                        return Err(Error::unsupported(format!(
+                            "Not supported custom function {}",
+                            expression
+                        )));
                     }
                 }
             },
@@ -654,7 +755,7 @@ impl<E: Encoder> PlanBuilder<E> {
         parameters: &[Expression],
         constructor: impl Fn(Box<PlanExpression>) -> PlanExpression,
         variables: &mut Vec<Variable>,
-        graph_name: PatternValue,
+        graph_name: &PatternValue,
         name: &'static str,
     ) -> Result<PlanExpression> {
         if parameters.len() == 1 {
@@ -664,10 +765,10 @@ impl<E: Encoder> PlanBuilder<E> {
                 graph_name,
             )?)))
         } else {
-            Err(format_err!(
+            Err(Error::evaluation(format!(
                 "The xsd:{} casting takes only one parameter",
                 name
-            ))
+            )))
         }
     }
 
@@ -675,7 +776,7 @@ impl<E: Encoder> PlanBuilder<E> {
         &mut self,
         l: &[Expression],
         variables: &mut Vec<Variable>,
-        graph_name: PatternValue,
+        graph_name: &PatternValue,
     ) -> Result<Vec<PlanExpression>> {
         l.iter()
             .map(|e| self.build_for_expression(e, variables, graph_name))
@@ -692,6 +793,66 @@ impl<E: Encoder> PlanBuilder<E> {
             TermOrVariable::Variable(variable) => {
                 PatternValue::Variable(variable_key(variables, variable))
             }
+            TermOrVariable::Triple(triple) => {
+                let subject =
+                    self.pattern_value_from_term_or_variable(&triple.subject, variables)?;
+                let predicate =
+                    self.pattern_value_from_named_node_or_variable(&triple.predicate, variables)?;
+                let object = self.pattern_value_from_term_or_variable(&triple.object, variables)?;
+                match (subject, predicate, object) {
+                    (
+                        PatternValue::Constant(subject),
+                        PatternValue::Constant(predicate),
+                        PatternValue::Constant(object),
+                    ) => PatternValue::Constant(
+                        self.encoder
+                            .encode_quoted_triple_terms(subject, predicate, object)?,
+                    ),
+                    (subject, predicate, object) => {
+                        PatternValue::Triple(Box::new(TriplePatternValue {
+                            subject,
+                            predicate,
+                            object,
+                        }))
+                    }
+                }
+            }
+        })
+    }
+
+    fn build_for_term_or_variable_expression(
+        &mut self,
+        term_or_variable: &TermOrVariable,
+        variables: &mut Vec<Variable>,
+    ) -> Result<PlanExpression> {
+        Ok(match term_or_variable {
+            TermOrVariable::Term(t) => PlanExpression::Constant(self.encoder.encode_term(t)?),
+            TermOrVariable::Variable(v) => PlanExpression::Variable(variable_key(variables, v)),
+            TermOrVariable::Triple(triple) => {
+                let subject =
+                    self.build_for_term_or_variable_expression(&triple.subject, variables)?;
+                let predicate = self.build_for_term_or_variable_expression(
+                    &triple.predicate.clone().into(),
+                    variables,
+                )?;
+                let object =
+                    self.build_for_term_or_variable_expression(&triple.object, variables)?;
+                match (subject, predicate, object) {
+                    (
+                        PlanExpression::Constant(subject),
+                        PlanExpression::Constant(predicate),
+                        PlanExpression::Constant(object),
+                    ) => PlanExpression::Constant(
+                        self.encoder
+                            .encode_quoted_triple_terms(subject, predicate, object)?,
+                    ),
+                    (subject, predicate, object) => PlanExpression::Triple(
+                        Box::new(subject),
+                        Box::new(predicate),
+                        Box::new(object),
+                    ),
+                }
+            }
         })
     }
 
@@ -739,7 +900,7 @@ impl<E: Encoder> PlanBuilder<E> {
         &mut self,
         aggregate: &Aggregation,
         variables: &mut Vec<Variable>,
-        graph_name: PatternValue,
+        graph_name: &PatternValue,
     ) -> Result<PlanAggregation> {
         Ok(match aggregate {
             Aggregation::Count(e, distinct) => PlanAggregation {
@@ -832,6 +993,25 @@ impl<E: Encoder> PlanBuilder<E> {
                     TripleTemplateValue::BlankNode(variable_key(bnodes, variable))
                 }
             }
+            TermOrVariable::Triple(triple) => {
+                TripleTemplateValue::Triple(Box::new(TripleTemplate {
+                    subject: self.template_value_from_term_or_variable(
+                        &triple.subject,
+                        variables,
+                        bnodes,
+                    )?,
+                    predicate: self.template_value_from_named_node_or_variable(
+                        &triple.predicate,
+                        variables,
+                        bnodes,
+                    )?,
+                    object: self.template_value_from_term_or_variable(
+                        &triple.object,
+                        variables,
+                        bnodes,
+                    )?,
+                }))
+            }
         })
     }
 
@@ -857,15 +1037,20 @@ impl<E: Encoder> PlanBuilder<E> {
 
     fn convert_pattern_value_id(
         &self,
-        from_value: PatternValue,
+        from_value: &PatternValue,
         from: &[Variable],
         to: &mut Vec<Variable>,
     ) -> PatternValue {
         match from_value {
-            PatternValue::Constant(v) => PatternValue::Constant(v),
+            PatternValue::Constant(v) => PatternValue::Constant(*v),
             PatternValue::Variable(from_id) => {
-                PatternValue::Variable(self.convert_variable_id(from_id, from, to))
+                PatternValue::Variable(self.convert_variable_id(*from_id, from, to))
             }
+            PatternValue::Triple(triple) => PatternValue::Triple(Box::new(TriplePatternValue {
+                subject: self.convert_pattern_value_id(&triple.subject, from, to),
+                predicate: self.convert_pattern_value_id(&triple.predicate, from, to),
+                object: self.convert_pattern_value_id(&triple.object, from, to),
+            })),
         }
     }
 
@@ -899,6 +1084,35 @@ fn variable_key(variables: &mut Vec<Variable>, variable: &Variable) -> usize {
     }
 }
 
+/// Replaces the `Sort` at the root of the plan, possibly below projections, by a `TopK` keeping only `count` tuples
+fn with_top_k(node: PlanNode, count: usize) -> PlanNode {
+    match node {
+        PlanNode::Sort { child, by } => PlanNode::TopK { child, by, count },
+        PlanNode::Project {
+            child,
+            mapping,
+            pre_bound,
+        } => PlanNode::Project {
+            child: Box::new(with_top_k(*child, count)),
+            mapping,
+            pre_bound,
+        },
+        node => node,
+    }
+}
+
+/// Returns the pattern of a `FILTER EXISTS` or `FILTER NOT EXISTS` and if it is negated
+fn exists_pattern(expression: &Expression) -> Option<(&GraphPattern, bool)> {
+    match expression {
+        Expression::Exists(p) => Some((p, false)),
+        Expression::UnaryNot(e) => match &**e {
+            Expression::Exists(p) => Some((p, true)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 fn slice_key<T: Eq>(slice: &[T], element: &T) -> Option<usize> {
     for (i, item) in slice.iter().enumerate() {
         if item == element {
@@ -908,8 +1122,13 @@ fn slice_key<T: Eq>(slice: &[T], element: &T) -> Option<usize> {
     None
 }
 
-fn sort_bgp(p: &[TripleOrPathPattern]) -> Vec<&TripleOrPathPattern> {
-    let mut assigned_variables = HashSet::default();
+/// Orders the patterns of a BGP such that each pattern shares as many variables as possible with the previous ones
+///
+/// `assigned_variables` are the variables already bound before the evaluation of the BGP.
+pub(crate) fn sort_bgp<'a>(
+    p: &'a [TripleOrPathPattern],
+    mut assigned_variables: HashSet<&'a Variable>,
+) -> Vec<&'a TripleOrPathPattern> {
     let mut new_p: Vec<_> = p.iter().collect();
 
     for i in 0..new_p.len() {
@@ -0,0 +1,508 @@
+//! Temporary files used by the operators that could not keep all their tuples in memory
+//!
+//! `ORDER BY` writes sorted runs that are merged at the end.
+//! `DISTINCT` and `GROUP BY` write the tuples they could not handle in memory to hash partitions
+//! that are evaluated one after the other once the input is consumed.
+
+use crate::sparql::plan::EncodedTuple;
+use crate::store::numeric_encoder::*;
+use crate::Result;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, BufWriter, Seek, SeekFrom};
+use std::iter::once;
+use std::mem::{size_of, size_of_val};
+
+/// Number of files a hash partitioning writes to
+const PARTITION_COUNT: usize = 16;
+/// Maximal number of sorted runs merged at the same time
+const MAX_MERGED_RUNS: usize = 64;
+
+type TuplesIterator<'a> = Box<dyn Iterator<Item = Result<EncodedTuple>> + 'a>;
+
+/// Returns an estimation of the number of bytes used by the tuple in memory
+pub fn tuple_memory_size(tuple: &[Option<EncodedTerm>]) -> usize {
+    size_of::<EncodedTuple>() + size_of_val(tuple)
+}
+
+/// A sequence of tuples written to an anonymous temporary file
+pub struct SpillFile {
+    writer: BufWriter<File>,
+    len: usize,
+}
+
+impl SpillFile {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(tempfile::tempfile()?),
+            len: 0,
+        })
+    }
+
+    pub fn write(&mut self, tuple: &[Option<EncodedTerm>]) -> Result<()> {
+        self.writer.write_u32::<LittleEndian>(tuple.len() as u32)?;
+        for value in tuple {
+            if let Some(value) = value {
+                self.writer.write_u8(1)?;
+                self.writer.write_term(*value)?;
+            } else {
+                self.writer.write_u8(0)?;
+            }
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Returns the tuples written to the file, the file being removed when the iterator is dropped
+    pub fn read(self) -> Result<SpillFileIterator> {
+        let mut file = self
+            .writer
+            .into_inner()
+            .map_err(|error| error.into_error())?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(SpillFileIterator {
+            reader: BufReader::new(file),
+            remaining: self.len,
+        })
+    }
+}
+
+pub struct SpillFileIterator {
+    reader: BufReader<File>,
+    remaining: usize,
+}
+
+impl SpillFileIterator {
+    fn read_tuple(&mut self) -> Result<EncodedTuple> {
+        let len = self.reader.read_u32::<LittleEndian>()? as usize;
+        let mut tuple = Vec::with_capacity(len);
+        for _ in 0..len {
+            tuple.push(if self.reader.read_u8()? == 0 {
+                None
+            } else {
+                Some(self.reader.read_term()?)
+            });
+        }
+        Ok(tuple)
+    }
+}
+
+impl Iterator for SpillFileIterator {
+    type Item = Result<EncodedTuple>;
+
+    fn next(&mut self) -> Option<Result<EncodedTuple>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let tuple = self.read_tuple();
+        self.remaining = if tuple.is_ok() { self.remaining - 1 } else { 0 };
+        Some(tuple)
+    }
+}
+
+/// Hash partitions of tuples written to temporary files
+///
+/// The files are only created when the first tuple is written.
+/// The `level` salts the hash function so that a partition that is partitioned again is split.
+pub struct SpillPartitions {
+    files: Vec<SpillFile>,
+    level: usize,
+}
+
+impl SpillPartitions {
+    pub fn new(level: usize) -> Self {
+        Self {
+            files: Vec::default(),
+            level,
+        }
+    }
+
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    /// Writes the tuple to the partition of the given key
+    pub fn write(&mut self, key: &impl Hash, tuple: &[Option<EncodedTerm>]) -> Result<()> {
+        if self.files.is_empty() {
+            for _ in 0..PARTITION_COUNT {
+                self.files.push(SpillFile::new()?);
+            }
+        }
+        let mut hasher = DefaultHasher::new();
+        self.level.hash(&mut hasher);
+        key.hash(&mut hasher);
+        self.files[(hasher.finish() as usize) % PARTITION_COUNT].write(tuple)
+    }
+
+    /// Returns the tuples of each non empty partition
+    pub fn read(self) -> impl Iterator<Item = Result<SpillFileIterator>> {
+        self.files
+            .into_iter()
+            .filter(|file| file.len > 0)
+            .map(SpillFile::read)
+    }
+}
+
+/// Sorts the tuples, the input errors being returned first
+///
+/// When the tuples kept in memory use more than `memory_limit` bytes they are sorted and written to a temporary file.
+/// These sorted runs are then merged.
+pub fn sort<'a>(
+    tuples: impl Iterator<Item = Result<EncodedTuple>>,
+    memory_limit: usize,
+    compare: impl Fn(&EncodedTuple, &EncodedTuple) -> Ordering + Clone + 'a,
+) -> TuplesIterator<'a> {
+    let mut errors = Vec::default();
+    let mut runs = Vec::default();
+    let mut buffer = Vec::default();
+    let mut buffer_size = 0;
+    for tuple in tuples {
+        match tuple {
+            Ok(tuple) => {
+                buffer_size += tuple_memory_size(&tuple);
+                buffer.push(tuple);
+                if buffer_size > memory_limit && buffer.len() > 1 {
+                    buffer.sort_unstable_by(&compare);
+                    match write_run(buffer.drain(..).map(Ok)) {
+                        Ok(run) => runs.push(run),
+                        Err(error) => return Box::new(errors.into_iter().chain(once(Err(error)))),
+                    }
+                    buffer_size = 0;
+                }
+            }
+            Err(error) => errors.push(Err(error)),
+        }
+    }
+    buffer.sort_unstable_by(&compare);
+    if runs.is_empty() {
+        return Box::new(errors.into_iter().chain(buffer.into_iter().map(Ok)));
+    }
+
+    // We merge the runs by groups until there are few enough of them to be merged at the same time
+    while runs.len() >= MAX_MERGED_RUNS {
+        let mut merged_runs = Vec::default();
+        let mut runs_to_merge = runs.into_iter();
+        loop {
+            let group = runs_to_merge
+                .by_ref()
+                .take(MAX_MERGED_RUNS)
+                .map(|run| run.read().map(|run| Box::new(run) as TuplesIterator<'a>))
+                .collect::<Result<Vec<_>>>();
+            let merged_run = group.and_then(|group| {
+                if group.is_empty() {
+                    Ok(None)
+                } else {
+                    write_run(MergeIterator::new(group, compare.clone())).map(Some)
+                }
+            });
+            match merged_run {
+                Ok(Some(merged_run)) => merged_runs.push(merged_run),
+                Ok(None) => break,
+                Err(error) => return Box::new(errors.into_iter().chain(once(Err(error)))),
+            }
+        }
+        runs = merged_runs;
+    }
+    let sources = runs
+        .into_iter()
+        .map(|run| run.read().map(|run| Box::new(run) as TuplesIterator<'a>))
+        .chain(once(Ok(
+            Box::new(buffer.into_iter().map(Ok)) as TuplesIterator<'a>
+        )))
+        .collect::<Result<Vec<_>>>();
+    match sources {
+        Ok(sources) => Box::new(
+            errors
+                .into_iter()
+                .chain(MergeIterator::new(sources, compare)),
+        ),
+        Err(error) => Box::new(errors.into_iter().chain(once(Err(error)))),
+    }
+}
+
+fn write_run(tuples: impl Iterator<Item = Result<EncodedTuple>>) -> Result<SpillFile> {
+    let mut run = SpillFile::new()?;
+    for tuple in tuples {
+        run.write(&tuple?)?;
+    }
+    Ok(run)
+}
+
+/// Merges sorted sequences of tuples
+struct MergeIterator<'a, F: Fn(&EncodedTuple, &EncodedTuple) -> Ordering> {
+    sources: Vec<TuplesIterator<'a>>,
+    heads: Vec<Option<EncodedTuple>>,
+    compare: F,
+    started: bool,
+}
+
+impl<'a, F: Fn(&EncodedTuple, &EncodedTuple) -> Ordering> MergeIterator<'a, F> {
+    fn new(sources: Vec<TuplesIterator<'a>>, compare: F) -> Self {
+        Self {
+            heads: vec![None; sources.len()],
+            sources,
+            compare,
+            started: false,
+        }
+    }
+
+    fn fill_head(&mut self, i: usize) -> Result<()> {
+        self.heads[i] = self.sources[i].next().transpose()?;
+        Ok(())
+    }
+}
+
+impl<'a, F: Fn(&EncodedTuple, &EncodedTuple) -> Ordering> Iterator for MergeIterator<'a, F> {
+    type Item = Result<EncodedTuple>;
+
+    fn next(&mut self) -> Option<Result<EncodedTuple>> {
+        if !self.started {
+            self.started = true;
+            for i in 0..self.sources.len() {
+                if let Err(error) = self.fill_head(i) {
+                    return Some(Err(error));
+                }
+            }
+        }
+        let compare = &self.compare;
+        let heads = &self.heads;
+        let min = (0..heads.len())
+            .filter_map(|i| heads[i].as_ref().map(|head| (i, head)))
+            .min_by(|(_, a), (_, b)| compare(a, b))
+            .map(|(i, _)| i)?;
+        let tuple = self.heads[min].take()?;
+        if let Err(error) = self.fill_head(min) {
+            return Some(Err(error));
+        }
+        Some(Ok(tuple))
+    }
+}
+
+/// Removes the duplicated tuples
+///
+/// When the tuples already seen use more than `memory_limit` bytes the tuples that have not been seen yet
+/// are written to hash partitions that are deduplicated after the end of the input.
+pub fn deduplicate<'a>(
+    tuples: impl Iterator<Item = Result<EncodedTuple>> + 'a,
+    memory_limit: usize,
+) -> TuplesIterator<'a> {
+    Box::new(DeduplicateIterator::new(
+        Box::new(tuples),
+        memory_limit,
+        0,
+        0,
+    ))
+}
+
+/// Removes the duplicated tuples while keeping the order of their first occurrence
+///
+/// The tuples are numbered, deduplicated using hash partitions and then sorted back by their number.
+pub fn deduplicate_keeping_order<'a>(
+    tuples: impl Iterator<Item = Result<EncodedTuple>> + 'a,
+    memory_limit: usize,
+) -> TuplesIterator<'a> {
+    let numbered = tuples.enumerate().map(|(i, tuple)| {
+        let mut tuple = tuple?;
+        tuple.insert(0, Some(EncodedTerm::IntegerLiteral(i as i128)));
+        Ok(tuple)
+    });
+    let deduplicated = DeduplicateIterator::new(Box::new(numbered), memory_limit, 0, 1);
+    Box::new(
+        sort(deduplicated, memory_limit, |a, b| {
+            position(a).cmp(&position(b))
+        })
+        .map(|tuple| {
+            let mut tuple = tuple?;
+            tuple.remove(0);
+            Ok(tuple)
+        }),
+    )
+}
+
+fn position(tuple: &[Option<EncodedTerm>]) -> i128 {
+    match tuple.first() {
+        Some(Some(EncodedTerm::IntegerLiteral(position))) => *position,
+        _ => 0,
+    }
+}
+
+/// Deduplicates the tuples using their values from the `key_start` position
+struct DeduplicateIterator<'a> {
+    input: TuplesIterator<'a>,
+    key_start: usize,
+    input_done: bool,
+    seen: HashSet<EncodedTuple>,
+    seen_size: usize,
+    memory_limit: usize,
+    partitions: SpillPartitions,
+    partitions_to_read: Vec<Result<SpillFileIterator>>,
+    current_partition: TuplesIterator<'a>,
+}
+
+impl<'a> DeduplicateIterator<'a> {
+    fn new(input: TuplesIterator<'a>, memory_limit: usize, level: usize, key_start: usize) -> Self {
+        Self {
+            input,
+            key_start,
+            input_done: false,
+            seen: HashSet::default(),
+            seen_size: 0,
+            memory_limit,
+            partitions: SpillPartitions::new(level),
+            partitions_to_read: Vec::default(),
+            current_partition: Box::new(std::iter::empty()),
+        }
+    }
+}
+
+impl<'a> Iterator for DeduplicateIterator<'a> {
+    type Item = Result<EncodedTuple>;
+
+    fn next(&mut self) -> Option<Result<EncodedTuple>> {
+        loop {
+            if !self.input_done {
+                match self.input.next() {
+                    Some(Ok(tuple)) => {
+                        let key = &tuple[self.key_start.min(tuple.len())..];
+                        if self.seen.contains(key) {
+                            continue;
+                        }
+                        if self.seen_size < self.memory_limit || self.seen.is_empty() {
+                            self.seen_size += tuple_memory_size(key);
+                            self.seen.insert(key.to_vec());
+                            return Some(Ok(tuple));
+                        }
+                        // The tuple is handled later from a partition that does not contain any seen tuple
+                        if let Err(error) = self.partitions.write(&key, &tuple) {
+                            return Some(Err(error));
+                        }
+                    }
+                    Some(Err(error)) => return Some(Err(error)),
+                    None => {
+                        self.input_done = true;
+                        self.seen = HashSet::default();
+                        let level = self.partitions.level() + 1;
+                        self.partitions_to_read =
+                            std::mem::replace(&mut self.partitions, SpillPartitions::new(level))
+                                .read()
+                                .collect();
+                    }
+                }
+            } else {
+                if let Some(tuple) = self.current_partition.next() {
+                    return Some(tuple);
+                }
+                let level = self.partitions.level();
+                self.current_partition = match self.partitions_to_read.pop()? {
+                    Ok(partition) => Box::new(DeduplicateIterator::new(
+                        Box::new(partition),
+                        self.memory_limit,
+                        level,
+                        self.key_start,
+                    )),
+                    Err(error) => Box::new(once(Err(error))),
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn numbered_tuples(count: i128) -> Vec<EncodedTuple> {
+    (0..count)
+        .map(|i| {
+            vec![
+                Some(EncodedTerm::IntegerLiteral((i * 7) % 13)),
+                Some(EncodedTerm::IntegerLiteral(i % 5)),
+            ]
+        })
+        .collect()
+}
+
+#[test]
+fn test_sort_with_many_runs() {
+    // Each run contains two tuples so there are more runs than the ones that could be merged at the same time
+    let tuples = numbered_tuples(300);
+    let mut expected = tuples.clone();
+    expected.sort();
+    let sorted = sort(tuples.into_iter().map(Ok), 1, |a, b| a.cmp(b))
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(sorted, expected);
+}
+
+#[test]
+fn test_deduplicate_with_partitions() {
+    let tuples = numbered_tuples(300);
+    let mut expected = Vec::default();
+    for tuple in &tuples {
+        if !expected.contains(tuple) {
+            expected.push(tuple.clone());
+        }
+    }
+
+    let mut deduplicated = deduplicate(tuples.clone().into_iter().map(Ok), 1)
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+    deduplicated.sort();
+    let mut sorted_expected = expected.clone();
+    sorted_expected.sort();
+    assert_eq!(deduplicated, sorted_expected);
+
+    assert_eq!(
+        deduplicate_keeping_order(tuples.into_iter().map(Ok), 1)
+            .collect::<Result<Vec<_>>>()
+            .unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn test_memory_limit() {
+    use crate::sparql::eval::{numbers_repository, query_values};
+    use crate::sparql::QueryOptions;
+
+    let repository = numbers_repository(300);
+    for (query, is_ordered) in &[
+        (
+            "SELECT ?o ?s WHERE { ?s <http://example.com/p> ?o } ORDER BY ?o ?s",
+            true,
+        ),
+        (
+            "SELECT ?o ?s WHERE { ?s <http://example.com/p> ?o } ORDER BY DESC(?o) ?s",
+            true,
+        ),
+        (
+            "SELECT DISTINCT ?o WHERE { ?s <http://example.com/p> ?o }",
+            false,
+        ),
+        (
+            "SELECT DISTINCT ?o WHERE { ?s <http://example.com/p> ?o } ORDER BY ?o",
+            true,
+        ),
+        (
+            "SELECT ?o (COUNT(?s) AS ?c) (MIN(STR(?s)) AS ?m) WHERE { ?s <http://example.com/p> ?o } GROUP BY ?o",
+            false,
+        ),
+        (
+            "SELECT (COUNT(DISTINCT ?o) AS ?c) WHERE { ?s <http://example.com/p> ?o }",
+            true,
+        ),
+    ] {
+        let mut expected = query_values(&repository, query, QueryOptions::default());
+        let mut actual = query_values(
+            &repository,
+            query,
+            QueryOptions::default().with_memory_limit(1),
+        );
+        if !is_ordered {
+            expected.sort_by_key(|values| format!("{:?}", values));
+            actual.sort_by_key(|values| format!("{:?}", values));
+        }
+        assert_eq!(actual, expected, "{}", query);
+    }
+}
@@ -2,8 +2,8 @@
 
 use crate::model::*;
 use crate::sparql::model::*;
+use crate::Error;
 use crate::Result;
-use failure::format_err;
 use quick_xml::events::BytesDecl;
 use quick_xml::events::BytesEnd;
 use quick_xml::events::BytesStart;
@@ -57,39 +57,7 @@ pub fn write_xml_results<W: Write>(results: QueryResult<'_>, sink: W) -> Result<
                         let mut binding_tag = BytesStart::borrowed_name(b"binding");
                         binding_tag.push_attribute(("name", variables[i].name()?));
                         writer.write_event(Event::Start(binding_tag))?;
-                        match term {
-                            Term::NamedNode(uri) => {
-                                writer
-                                    .write_event(Event::Start(BytesStart::borrowed_name(b"uri")))?;
-                                writer.write_event(Event::Text(BytesText::from_plain_str(
-                                    uri.as_str(),
-                                )))?;
-                                writer.write_event(Event::End(BytesEnd::borrowed(b"uri")))?;
-                            }
-                            Term::BlankNode(bnode) => {
-                                writer.write_event(Event::Start(BytesStart::borrowed_name(
-                                    b"bnode",
-                                )))?;
-                                writer.write_event(Event::Text(BytesText::from_plain_str(
-                                    bnode.as_str(),
-                                )))?;
-                                writer.write_event(Event::End(BytesEnd::borrowed(b"bnode")))?;
-                            }
-                            Term::Literal(literal) => {
-                                let mut literal_tag = BytesStart::borrowed_name(b"literal");
-                                if let Some(language) = literal.language() {
-                                    literal_tag.push_attribute(("xml:lang", language.as_str()));
-                                } else if !literal.is_plain() {
-                                    literal_tag
-                                        .push_attribute(("datatype", literal.datatype().as_str()));
-                                }
-                                writer.write_event(Event::Start(literal_tag))?;
-                                writer.write_event(Event::Text(BytesText::from_plain_str(
-                                    &literal.value(),
-                                )))?;
-                                writer.write_event(Event::End(BytesEnd::borrowed(b"literal")))?;
-                            }
-                        }
+                        write_xml_term(&term, &mut writer)?;
                         writer.write_event(Event::End(BytesEnd::borrowed(b"binding")))?;
                     }
                 }
@@ -99,14 +67,54 @@ pub fn write_xml_results<W: Write>(results: QueryResult<'_>, sink: W) -> Result<
             writer.write_event(Event::End(BytesEnd::borrowed(b"sparql")))?;
         }
         QueryResult::Graph(_) => {
-            return Err(format_err!(
-                "Graphs could not be formatted to SPARQL query results XML format"
+            return Err(Error::unsupported(
+                "Graphs could not be formatted to SPARQL query results XML format",
             ));
         }
     }
     Ok(writer.into_inner())
 }
 
+fn write_xml_term<W: Write>(term: &Term, writer: &mut Writer<W>) -> Result<()> {
+    match term {
+        Term::NamedNode(uri) => {
+            writer.write_event(Event::Start(BytesStart::borrowed_name(b"uri")))?;
+            writer.write_event(Event::Text(BytesText::from_plain_str(uri.as_str())))?;
+            writer.write_event(Event::End(BytesEnd::borrowed(b"uri")))?;
+        }
+        Term::BlankNode(bnode) => {
+            writer.write_event(Event::Start(BytesStart::borrowed_name(b"bnode")))?;
+            writer.write_event(Event::Text(BytesText::from_plain_str(bnode.as_str())))?;
+            writer.write_event(Event::End(BytesEnd::borrowed(b"bnode")))?;
+        }
+        Term::Literal(literal) => {
+            let mut literal_tag = BytesStart::borrowed_name(b"literal");
+            if let Some(language) = literal.language() {
+                literal_tag.push_attribute(("xml:lang", language.as_str()));
+            } else if !literal.is_plain() {
+                literal_tag.push_attribute(("datatype", literal.datatype().as_str()));
+            }
+            writer.write_event(Event::Start(literal_tag))?;
+            writer.write_event(Event::Text(BytesText::from_plain_str(&literal.value())))?;
+            writer.write_event(Event::End(BytesEnd::borrowed(b"literal")))?;
+        }
+        Term::Triple(triple) => {
+            writer.write_event(Event::Start(BytesStart::borrowed_name(b"triple")))?;
+            writer.write_event(Event::Start(BytesStart::borrowed_name(b"subject")))?;
+            write_xml_term(&triple.subject().clone().into(), writer)?;
+            writer.write_event(Event::End(BytesEnd::borrowed(b"subject")))?;
+            writer.write_event(Event::Start(BytesStart::borrowed_name(b"predicate")))?;
+            write_xml_term(&triple.predicate().clone().into(), writer)?;
+            writer.write_event(Event::End(BytesEnd::borrowed(b"predicate")))?;
+            writer.write_event(Event::Start(BytesStart::borrowed_name(b"object")))?;
+            write_xml_term(triple.object(), writer)?;
+            writer.write_event(Event::End(BytesEnd::borrowed(b"object")))?;
+            writer.write_event(Event::End(BytesEnd::borrowed(b"triple")))?;
+        }
+    }
+    Ok(())
+}
+
 pub fn read_xml_results<'a>(source: impl BufRead + 'a) -> Result<QueryResult<'a>> {
     enum State {
         Start,
@@ -130,10 +138,10 @@ pub fn read_xml_results<'a>(source: impl BufRead + 'a) -> Result<QueryResult<'a>
             let (ns, event) = reader.read_namespaced_event(&mut buffer, &mut namespace_buffer)?;
             if let Some(ns) = ns {
                 if ns != b"http://www.w3.org/2005/sparql-results#".as_ref() {
-                    return Err(format_err!(
+                    return Err(Error::syntax(format!(
                         "Unexpected namespace found in RDF/XML query result: {}",
                         reader.decode(ns)?
-                    ));
+                    )));
                 }
             }
             event
@@ -144,14 +152,14 @@ pub fn read_xml_results<'a>(source: impl BufRead + 'a) -> Result<QueryResult<'a>
                     if event.name() == b"sparql" {
                         state = State::Sparql;
                     } else {
-                        return Err(format_err!("Expecting <sparql> tag, found {}", reader.decode(event.name())?));
+                        return Err(Error::syntax(format!("Expecting <sparql> tag, found {}", reader.decode(event.name())?)));
                     }
                 }
                 State::Sparql => {
                     if event.name() == b"head" {
                         state = State::Head;
                     } else {
-                        return Err(format_err!("Expecting <head> tag, found {}", reader.decode(event.name())?));
+                        return Err(Error::syntax(format!("Expecting <head> tag, found {}", reader.decode(event.name())?)));
                     }
                 }
                 State::Head => {
@@ -159,12 +167,12 @@ pub fn read_xml_results<'a>(source: impl BufRead + 'a) -> Result<QueryResult<'a>
                         let name = event.attributes()
                             .filter_map(|attr| attr.ok())
                             .find(|attr| attr.key == b"name")
-                            .ok_or_else(|| format_err!("No name attribute found for the <variable> tag"))?;
+                            .ok_or_else(|| Error::syntax("No name attribute found for the <variable> tag"))?;
                         variables.push(name.unescape_and_decode_value(&reader)?);
                     } else if event.name() == b"link" {
                         // no op
                     } else {
-                        return Err(format_err!("Expecting <variable> or <link> tag, found {}", reader.decode(event.name())?));
+                        return Err(Error::syntax(format!("Expecting <variable> or <link> tag, found {}", reader.decode(event.name())?)));
                     }
                 }
                 State::AfterHead => {
@@ -186,17 +194,17 @@ pub fn read_xml_results<'a>(source: impl BufRead + 'a) -> Result<QueryResult<'a>
                             }),
                         )));
                     } else if event.name() != b"link" && event.name() != b"results" && event.name() != b"boolean" {
-                        return Err(format_err!("Expecting sparql tag, found {}", reader.decode(event.name())?));
+                        return Err(Error::syntax(format!("Expecting sparql tag, found {}", reader.decode(event.name())?)));
                     }
                 }
-                State::Boolean => return Err(format_err!("Unexpected tag inside of <boolean> tag: {}", reader.decode(event.name())?))
+                State::Boolean => return Err(Error::syntax(format!("Unexpected tag inside of <boolean> tag: {}", reader.decode(event.name())?)))
             },
             Event::Empty(event) => match state {
                 State::Sparql => {
                     if event.name() == b"head" {
                         state = State::AfterHead;
                     } else {
-                        return Err(format_err!("Expecting <head> tag, found {}", reader.decode(event.name())?));
+                        return Err(Error::syntax(format!("Expecting <head> tag, found {}", reader.decode(event.name())?)));
                     }
                 }
                 State::Head => {
@@ -204,12 +212,12 @@ pub fn read_xml_results<'a>(source: impl BufRead + 'a) -> Result<QueryResult<'a>
                         let name = event.attributes()
                             .filter_map(|attr| attr.ok())
                             .find(|attr| attr.key == b"name")
-                            .ok_or_else(|| format_err!("No name attribute found for the <variable> tag"))?;
+                            .ok_or_else(|| Error::syntax("No name attribute found for the <variable> tag"))?;
                         variables.push(name.unescape_and_decode_value(&reader)?);
                     } else if event.name() == b"link" {
                         // no op
                     } else {
-                        return Err(format_err!("Expecting <variable> or <link> tag, found {}", reader.decode(event.name())?));
+                        return Err(Error::syntax(format!("Expecting <variable> or <link> tag, found {}", reader.decode(event.name())?)));
                     }
                 },
                 State::AfterHead => {
@@ -219,10 +227,10 @@ pub fn read_xml_results<'a>(source: impl BufRead + 'a) -> Result<QueryResult<'a>
                             Box::new(empty()),
                         )))
                     } else {
-                        return Err(format_err!("Unexpected autoclosing tag <{}>", reader.decode(event.name())?))
+                        return Err(Error::syntax(format!("Unexpected autoclosing tag <{}>", reader.decode(event.name())?)))
                     }
                 }
-                _ => return Err(format_err!("Unexpected autoclosing tag <{}>", reader.decode(event.name())?))
+                _ => return Err(Error::syntax(format!("Unexpected autoclosing tag <{}>", reader.decode(event.name())?)))
             },
             Event::Text(event) => {
                 let value = event.unescaped()?;
@@ -233,18 +241,18 @@ pub fn read_xml_results<'a>(source: impl BufRead + 'a) -> Result<QueryResult<'a>
                         } else if value.as_ref() == b"false" {
                             Ok(QueryResult::Boolean(false))
                         } else {
-                            Err(format_err!("Unexpected boolean value. Found {}", reader.decode(&value)?))
+                            Err(Error::syntax(format!("Unexpected boolean value. Found {}", reader.decode(&value)?)))
                         };
                     }
-                    _ => Err(format_err!("Unexpected textual value found: {}", reader.decode(&value)?))
+                    _ => Err(Error::syntax(format!("Unexpected textual value found: {}", reader.decode(&value)?)))
                 };
             },
             Event::End(_) => if let State::Head = state {
                 state = State::AfterHead;
             } else {
-                return Err(format_err!("Unexpected early file end. All results file should have a <head> and a <result> or <boolean> tag"));
+                return Err(Error::syntax("Unexpected early file end. All results file should have a <head> and a <result> or <boolean> tag"));
             },
-            Event::Eof => return Err(format_err!("Unexpected early file end. All results file should have a <head> and a <result> or <boolean> tag")),
+            Event::Eof => return Err(Error::syntax("Unexpected early file end. All results file should have a <head> and a <result> or <boolean> tag")),
             _ => (),
         }
     }
@@ -292,10 +300,10 @@ impl<R: BufRead> ResultsIterator<R> {
                 .read_namespaced_event(&mut self.buffer, &mut self.namespace_buffer)?;
             if let Some(ns) = ns {
                 if ns != b"http://www.w3.org/2005/sparql-results#".as_ref() {
-                    return Err(format_err!(
+                    return Err(Error::syntax(format!(
                         "Unexpected namespace found in RDF/XML query result: {}",
                         self.reader.decode(ns)?
-                    ));
+                    )));
                 }
             }
             match event {
@@ -304,10 +312,10 @@ impl<R: BufRead> ResultsIterator<R> {
                         if event.name() == b"result" {
                             state = State::Result;
                         } else {
-                            return Err(format_err!(
+                            return Err(Error::syntax(format!(
                                 "Expecting <result>, found {}",
                                 self.reader.decode(event.name())?
-                            ));
+                            )));
                         }
                     }
                     State::Result => {
@@ -319,23 +327,23 @@ impl<R: BufRead> ResultsIterator<R> {
                             {
                                 Some(attr) => current_var = Some(attr.unescaped_value()?.to_vec()),
                                 None => {
-                                    return Err(format_err!(
-                                        "No name attribute found for the <binding> tag"
+                                    return Err(Error::syntax(
+                                        "No name attribute found for the <binding> tag",
                                     ));
                                 }
                             }
                             state = State::Binding;
                         } else {
-                            return Err(format_err!(
+                            return Err(Error::syntax(format!(
                                 "Expecting <binding>, found {}",
                                 self.reader.decode(event.name())?
-                            ));
+                            )));
                         }
                     }
                     State::Binding => {
                         if term.is_some() {
-                            return Err(format_err!(
-                                "There is already a value for the current binding"
+                            return Err(Error::syntax(
+                                "There is already a value for the current binding",
                             ));
                         }
                         if event.name() == b"uri" {
@@ -356,10 +364,10 @@ impl<R: BufRead> ResultsIterator<R> {
                             }
                             state = State::Literal;
                         } else {
-                            return Err(format_err!(
+                            return Err(Error::syntax(format!(
                                 "Expecting <uri>, <bnode> or <literal> found {}",
                                 self.reader.decode(event.name())?
-                            ));
+                            )));
                         }
                     }
                     _ => (),
@@ -390,10 +398,10 @@ impl<R: BufRead> ResultsIterator<R> {
                             );
                         }
                         _ => {
-                            return Err(format_err!(
+                            return Err(Error::syntax(format!(
                                 "Unexpected textual value found: {}",
                                 self.reader.decode(&data)?
-                            ));
+                            )));
                         }
                     }
                 }
@@ -406,12 +414,12 @@ impl<R: BufRead> ResultsIterator<R> {
                                 new_bindings[self.mapping[var]] = Some(term.clone())
                             }
                             (Some(var), None) => {
-                                return Err(format_err!(
+                                return Err(Error::syntax(format!(
                                     "No variable found for variable {}",
                                     self.reader.decode(&var)?
-                                ));
+                                )));
                             }
-                            _ => return Err(format_err!("No name found for <binding> tag")),
+                            _ => return Err(Error::syntax("No name found for <binding> tag")),
                         }
                         term = None;
                         state = State::Result;
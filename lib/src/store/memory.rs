@@ -1,8 +1,9 @@
 use crate::store::numeric_encoder::*;
 use crate::store::*;
-use crate::{Repository, Result};
-use failure::{Backtrace, Fail};
+use crate::{Error, Repository, Result};
 use std::collections::{BTreeMap, BTreeSet};
+use std::error;
+use std::fmt;
 use std::iter::{empty, once};
 use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
@@ -15,6 +16,8 @@ use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 /// use rudf::{Repository, RepositoryConnection, MemoryRepository, Result};
 /// use crate::rudf::sparql::PreparedQuery;
 /// use rudf::sparql::QueryResult;
+/// use rudf::sparql::QueryOptions;
+/// use std::collections::BTreeMap;
 ///
 /// let repository = MemoryRepository::default();
 /// let mut connection = repository.connection().unwrap();
@@ -29,8 +32,8 @@ use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 /// assert_eq!(vec![quad], results.unwrap());
 ///
 /// // SPARQL query
-/// let prepared_query = connection.prepare_query("SELECT ?s WHERE { ?s ?p ?o }", None).unwrap();
-/// let results = prepared_query.exec().unwrap();
+/// let prepared_query = connection.prepare_query("SELECT ?s WHERE { ?s ?p ?o }", QueryOptions::default()).unwrap();
+/// let results = prepared_query.exec(&BTreeMap::default()).unwrap();
 /// if let QueryResult::Bindings(results) = results {
 ///     assert_eq!(results.into_values_iter().next().unwrap().unwrap()[0], Some(ex.into()));
 /// }
@@ -58,6 +61,9 @@ struct MemoryStoreIndexes {
     gpos: QuadMap<EncodedTerm>,
     gosp: QuadMap<EncodedTerm>,
     str_store: MemoryStrStore,
+    namespaces: BTreeMap<String, String>,
+    inferred_graph: Option<EncodedTerm>,
+    skolemizer: Option<Skolemizer>,
 }
 
 impl<'a> Repository for &'a MemoryRepository {
@@ -185,11 +191,50 @@ impl<'a> StoreConnection for &'a MemoryStore {
             },
         }
     }
+
+    fn namespaces(&self) -> Result<BTreeMap<String, String>> {
+        Ok(self.indexes()?.namespaces.clone())
+    }
+
+    fn namespace(&self, prefix: &str) -> Result<Option<String>> {
+        Ok(self.indexes()?.namespaces.get(prefix).cloned())
+    }
+
+    fn set_namespace(&self, prefix: &str, namespace: &str) -> Result<()> {
+        self.indexes_mut()?
+            .namespaces
+            .insert(prefix.to_owned(), namespace.to_owned());
+        Ok(())
+    }
+
+    fn remove_namespace(&self, prefix: &str) -> Result<()> {
+        self.indexes_mut()?.namespaces.remove(prefix);
+        Ok(())
+    }
+
+    fn inferred_graph(&self) -> Result<Option<EncodedTerm>> {
+        Ok(self.indexes()?.inferred_graph)
+    }
+
+    fn set_inferred_graph(&self, graph_name: EncodedTerm) -> Result<()> {
+        self.indexes_mut()?.inferred_graph = Some(graph_name);
+        Ok(())
+    }
+
+    fn skolemizer(&self) -> Result<Option<Skolemizer>> {
+        Ok(self.indexes()?.skolemizer.clone())
+    }
+
+    fn set_skolemizer(&self, skolemizer: &Skolemizer) -> Result<()> {
+        self.indexes_mut()?.skolemizer = Some(skolemizer.clone());
+        Ok(())
+    }
 }
 
 /// TODO: implement properly
 impl<'a> StoreTransaction for &'a MemoryStore {
     fn insert(&mut self, quad: &EncodedQuad) -> Result<()> {
+        check_graph_name(quad)?;
         let mut quad_indexes = self.indexes_mut()?;
         insert_into_quad_map(
             &mut quad_indexes.gosp,
@@ -605,16 +650,25 @@ fn quad_map_flatten<'a, T: Copy>(gspo: &'a QuadMap<T>) -> impl Iterator<Item = (
     })
 }
 
-#[derive(Debug, Fail)]
-#[fail(display = "Mutex Mutex was poisoned")]
-pub struct MutexPoisonError {
-    backtrace: Backtrace,
+#[derive(Debug)]
+pub struct MutexPoisonError {}
+
+impl fmt::Display for MutexPoisonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Mutex was poisoned")
+    }
 }
 
+impl error::Error for MutexPoisonError {}
+
 impl<T> From<PoisonError<T>> for MutexPoisonError {
     fn from(_: PoisonError<T>) -> Self {
-        Self {
-            backtrace: Backtrace::new(),
-        }
+        Self {}
+    }
+}
+
+impl From<MutexPoisonError> for Error {
+    fn from(error: MutexPoisonError) -> Self {
+        Error::storage(error)
     }
 }
@@ -2,23 +2,35 @@
 
 mod memory;
 pub(crate) mod numeric_encoder;
+mod reasoning;
 #[cfg(feature = "rocksdb")]
 mod rocksdb;
+mod turtle_star;
 
 pub use crate::store::memory::MemoryRepository;
 #[cfg(feature = "rocksdb")]
 pub use crate::store::rocksdb::RocksDbRepository;
 
 use crate::model::*;
-use crate::sparql::SimplePreparedQuery;
+use crate::sparql::{QueryOptions, QueryPlan, SimplePreparedQuery};
 use crate::store::numeric_encoder::*;
-use crate::{DatasetSyntax, GraphSyntax, RepositoryConnection, Result};
+use crate::store::reasoning::OwlRlReasoner;
+use crate::store::turtle_star::{DirectiveRecorder, TurtleStarParser};
+use crate::{
+    DatasetSyntax, Error, GraphSyntax, RepositoryConnection, Result, SyntaxError, TextPosition,
+};
+use rio_api::model as rio;
 use rio_api::parser::{QuadsParser, TriplesParser};
 use rio_turtle::{NQuadsParser, NTriplesParser, TriGParser, TurtleParser};
 use rio_xml::RdfXmlParser;
-use std::collections::HashMap;
-use std::io::BufRead;
-use std::iter::Iterator;
+use std::cell::RefCell;
+use std::cmp::min;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::io::{BufRead, Read};
+use std::iter::{once, Iterator};
+use std::mem::take;
+use std::rc::Rc;
 
 /// Defines the `Store` traits that is used to have efficient binary storage
 pub trait Store {
@@ -28,7 +40,9 @@ pub trait Store {
 }
 
 /// A connection to a `Store`
-pub trait StoreConnection: StrLookup + Sized + Clone {
+///
+/// Connections are sent to the threads evaluating the queries in parallel.
+pub trait StoreConnection: StrLookup + Sized + Clone + Send {
     type Transaction: StoreTransaction;
 
     /// Creates an edition transaction
@@ -45,6 +59,24 @@ pub trait StoreConnection: StrLookup + Sized + Clone {
         object: Option<EncodedTerm>,
         graph_name: Option<EncodedTerm>,
     ) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'a>;
+
+    fn namespaces(&self) -> Result<BTreeMap<String, String>>;
+
+    fn namespace(&self, prefix: &str) -> Result<Option<String>>;
+
+    fn set_namespace(&self, prefix: &str, namespace: &str) -> Result<()>;
+
+    fn remove_namespace(&self, prefix: &str) -> Result<()>;
+
+    /// The graph where the OWL RL reasoner writes the inferred triples if it is enabled
+    fn inferred_graph(&self) -> Result<Option<EncodedTerm>>;
+
+    fn set_inferred_graph(&self, graph_name: EncodedTerm) -> Result<()>;
+
+    /// The skolemizer applied to the blank nodes of the loaded, inserted, removed or looked up quads if it is enabled
+    fn skolemizer(&self) -> Result<Option<Skolemizer>>;
+
+    fn set_skolemizer(&self, skolemizer: &Skolemizer) -> Result<()>;
 }
 
 /// A transaction
@@ -60,19 +92,39 @@ pub trait StoreTransaction: StrContainer + Sized {
 #[derive(Clone)]
 pub struct StoreRepositoryConnection<S: StoreConnection> {
     inner: S,
+    strict: bool,
 }
 
 impl<S: StoreConnection> From<S> for StoreRepositoryConnection<S> {
     fn from(inner: S) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            strict: false,
+        }
     }
 }
 
 impl<S: StoreConnection> RepositoryConnection for StoreRepositoryConnection<S> {
     type PreparedQuery = SimplePreparedQuery<S>;
 
-    fn prepare_query(&self, query: &str, base_iri: Option<&str>) -> Result<SimplePreparedQuery<S>> {
-        SimplePreparedQuery::new(self.inner.clone(), query, base_iri) //TODO: avoid clone
+    fn prepare_query(
+        &self,
+        query: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<SimplePreparedQuery<S>> {
+        SimplePreparedQuery::new(self.inner.clone(), query, options) //TODO: avoid clone
+    }
+
+    fn prepare_query_plan(
+        &self,
+        plan: &QueryPlan,
+        options: QueryOptions<'_>,
+    ) -> Result<SimplePreparedQuery<S>> {
+        Ok(SimplePreparedQuery::from_plan(
+            self.inner.clone(),
+            plan,
+            options,
+        ))
     }
 
     fn quads_for_pattern<'a>(
@@ -85,10 +137,19 @@ impl<S: StoreConnection> RepositoryConnection for StoreRepositoryConnection<S> {
     where
         Self: 'a,
     {
-        let subject = subject.map(|s| s.into());
+        let skolemizer = match self.inner.skolemizer() {
+            Ok(skolemizer) => skolemizer,
+            Err(error) => return Box::new(once(Err(error))),
+        };
+        let skolemizer = skolemizer.as_ref();
+        let subject = subject.map(|s| encode_lookup_term(skolemizer, s.clone().into()));
         let predicate = predicate.map(|p| p.into());
-        let object = object.map(|o| o.into());
-        let graph_name = graph_name.map(|g| g.map_or(ENCODED_DEFAULT_GRAPH, |g| g.into()));
+        let object = object.map(|o| encode_lookup_term(skolemizer, o.clone()));
+        let graph_name = graph_name.map(|g| {
+            g.map_or(ENCODED_DEFAULT_GRAPH, |g| {
+                encode_lookup_term(skolemizer, g.clone().into())
+            })
+        });
         Box::new(
             self.inner
                 .quads_for_pattern(subject, predicate, object, graph_name)
@@ -106,13 +167,37 @@ impl<S: StoreConnection> RepositoryConnection for StoreRepositoryConnection<S> {
         let base_iri = base_iri.unwrap_or(&"");
         match syntax {
             GraphSyntax::NTriples => {
-                self.load_from_triple_parser(NTriplesParser::new(reader)?, to_graph_name)
+                let mut reader = LineTracker::new(reader);
+                let line = reader.current_line();
+                self.load_from_triple_parser(
+                    NTriplesParser::new(&mut reader)?,
+                    to_graph_name,
+                    Some(&line),
+                )
             }
             GraphSyntax::Turtle => {
-                self.load_from_triple_parser(TurtleParser::new(reader, base_iri)?, to_graph_name)
+                let reader = LineTracker::new(reader);
+                let line = reader.current_line();
+                let mut reader = PrefixRecorder::new(reader, base_iri)?;
+                self.load_from_triple_parser(
+                    TurtleParser::new(&mut reader, base_iri)?,
+                    to_graph_name,
+                    Some(&line),
+                )?;
+                self.add_namespaces(reader.finish())
+            }
+            GraphSyntax::RdfXml => self.load_from_triple_parser(
+                RdfXmlParser::new(reader, base_iri)?,
+                to_graph_name,
+                None,
+            ),
+            GraphSyntax::NTriplesStar => {
+                self.load_from_turtle_star(reader, to_graph_name, base_iri)?;
+                Ok(())
             }
-            GraphSyntax::RdfXml => {
-                self.load_from_triple_parser(RdfXmlParser::new(reader, base_iri)?, to_graph_name)
+            GraphSyntax::TurtleStar => {
+                let namespaces = self.load_from_turtle_star(reader, to_graph_name, base_iri)?;
+                self.add_namespaces(namespaces)
             }
         }
     }
@@ -125,28 +210,95 @@ impl<S: StoreConnection> RepositoryConnection for StoreRepositoryConnection<S> {
     ) -> Result<()> {
         let base_iri = base_iri.unwrap_or(&"");
         match syntax {
-            DatasetSyntax::NQuads => self.load_from_quad_parser(NQuadsParser::new(reader)?),
-            DatasetSyntax::TriG => self.load_from_quad_parser(TriGParser::new(reader, base_iri)?),
+            DatasetSyntax::NQuads => {
+                let mut reader = LineTracker::new(reader);
+                let line = reader.current_line();
+                self.load_from_quad_parser(NQuadsParser::new(&mut reader)?, &line)
+            }
+            DatasetSyntax::TriG => {
+                let reader = LineTracker::new(reader);
+                let line = reader.current_line();
+                let mut reader = PrefixRecorder::new(reader, base_iri)?;
+                self.load_from_quad_parser(TriGParser::new(&mut reader, base_iri)?, &line)?;
+                self.add_namespaces(reader.finish())
+            }
         }
     }
 
     fn contains(&self, quad: &Quad) -> Result<bool> {
-        self.inner.contains(&quad.into())
+        let quad: EncodedQuad = match self.inner.skolemizer()? {
+            Some(skolemizer) => (&skolemizer.skolemize_quad(quad)).into(),
+            None => quad.into(),
+        };
+        self.inner.contains(&quad)
     }
 
     fn insert(&mut self, quad: &Quad) -> Result<()> {
+        let skolemized;
+        let quad = if let Some(skolemizer) = self.inner.skolemizer()? {
+            skolemized = skolemizer.skolemize_quad(quad);
+            &skolemized
+        } else {
+            quad
+        };
         let mut transaction = self.inner.transaction()?;
         let quad = transaction.encode_quad(quad)?;
         transaction.insert(&quad)?;
-        transaction.commit()
+        transaction.commit()?;
+        if let Some(reasoner) = self.reasoner()? {
+            reasoner.after_insert(&self.inner, &[quad])?;
+        }
+        Ok(())
     }
 
     fn remove(&mut self, quad: &Quad) -> Result<()> {
+        let skolemized;
+        let quad = if let Some(skolemizer) = self.inner.skolemizer()? {
+            skolemized = skolemizer.skolemize_quad(quad);
+            &skolemized
+        } else {
+            quad
+        };
+        if let Some(reasoner) = self.reasoner()? {
+            return reasoner.remove(&self.inner, &[quad.into()]);
+        }
         let mut transaction = self.inner.transaction()?;
         let quad = transaction.encode_quad(quad)?;
         transaction.remove(&quad)?;
         transaction.commit()
     }
+
+    fn enable_owl_rl_reasoning(&mut self, inferred_graph: &NamedOrBlankNode) -> Result<()> {
+        let mut transaction = self.inner.transaction()?;
+        let inferred_graph = transaction.encode_named_or_blank_node(inferred_graph)?;
+        transaction.commit()?;
+        self.inner.set_inferred_graph(inferred_graph)?;
+        OwlRlReasoner::new(inferred_graph).materialize(&self.inner)
+    }
+
+    fn enable_skolemization(&mut self, skolemizer: &Skolemizer) -> Result<()> {
+        self.inner.set_skolemizer(skolemizer)
+    }
+
+    fn enable_strict_loading(&mut self) {
+        self.strict = true;
+    }
+
+    fn namespaces(&self) -> Result<BTreeMap<String, String>> {
+        self.inner.namespaces()
+    }
+
+    fn namespace(&self, prefix: &str) -> Result<Option<String>> {
+        self.inner.namespace(prefix)
+    }
+
+    fn set_namespace(&mut self, prefix: &str, namespace: &str) -> Result<()> {
+        self.inner.set_namespace(prefix, namespace)
+    }
+
+    fn remove_namespace(&mut self, prefix: &str) -> Result<()> {
+        self.inner.remove_namespace(prefix)
+    }
 }
 
 impl<S: StoreConnection> StoreRepositoryConnection<S> {
@@ -154,9 +306,10 @@ impl<S: StoreConnection> StoreRepositoryConnection<S> {
         &mut self,
         mut parser: P,
         to_graph_name: Option<&NamedOrBlankNode>,
+        line: Option<&Rc<RefCell<CurrentLine>>>,
     ) -> Result<()>
     where
-        P::Error: Send + Sync + 'static,
+        Error: From<P::Error>,
     {
         let mut transaction = self.inner.transaction()?;
         let mut bnode_map = HashMap::default();
@@ -165,25 +318,504 @@ impl<S: StoreConnection> StoreRepositoryConnection<S> {
         } else {
             EncodedTerm::DefaultGraph
         };
+        let mut inserted = Vec::default();
+        let record = self.reasoner()?.is_some();
+        let skolemizer = self.inner.skolemizer()?;
+        let strict = self.strict;
         let tr = &mut transaction;
-        parser.parse_all(&mut move |t| {
+        let ins = &mut inserted;
+        parser.parse_all(&mut move |t| -> Result<()> {
+            if let (true, rio::Term::Literal(literal)) = (strict, t.object) {
+                check_rio_literal(literal, line)?;
+            }
             let quad = tr.encode_rio_triple_in_graph(t, graph_name, &mut bnode_map)?;
-            tr.insert(&quad)
+            let quad = skolemize_encoded_quad(tr, skolemizer.as_ref(), quad)?;
+            tr.insert(&quad)?;
+            if record {
+                ins.push(quad);
+            }
+            Ok(())
         })?;
-        transaction.commit() //TODO: partials commits
+        transaction.commit()?; //TODO: partials commits
+        self.after_load(&inserted)
     }
 
-    fn load_from_quad_parser<P: QuadsParser>(&mut self, mut parser: P) -> Result<()>
+    fn load_from_quad_parser<P: QuadsParser>(
+        &mut self,
+        mut parser: P,
+        line: &Rc<RefCell<CurrentLine>>,
+    ) -> Result<()>
     where
-        P::Error: Send + Sync + 'static,
+        Error: From<P::Error>,
     {
         let mut transaction = self.inner.transaction()?;
         let mut bnode_map = HashMap::default();
+        let mut inserted = Vec::default();
+        let record = self.reasoner()?.is_some();
+        let skolemizer = self.inner.skolemizer()?;
+        let strict = self.strict;
         let tr = &mut transaction;
-        parser.parse_all(&mut move |q| {
+        let ins = &mut inserted;
+        parser.parse_all(&mut move |q| -> Result<()> {
+            if let (true, rio::Term::Literal(literal)) = (strict, q.object) {
+                check_rio_literal(literal, Some(line))?;
+            }
             let quad = tr.encode_rio_quad(q, &mut bnode_map)?;
-            tr.insert(&quad)
+            let quad = skolemize_encoded_quad(tr, skolemizer.as_ref(), quad)?;
+            tr.insert(&quad)?;
+            if record {
+                ins.push(quad);
+            }
+            Ok(())
+        })?;
+        transaction.commit()?; //TODO: partials commits
+        self.after_load(&inserted)
+    }
+
+    /// Loads a Turtle-star or N-Triples-star document and returns its prefixes
+    fn load_from_turtle_star(
+        &mut self,
+        reader: impl BufRead,
+        to_graph_name: Option<&NamedOrBlankNode>,
+        base_iri: &str,
+    ) -> Result<Vec<(String, String)>> {
+        let base_iri = if base_iri.is_empty() {
+            None
+        } else {
+            Some(base_iri)
+        };
+        let mut parser = TurtleStarParser::new(reader, base_iri, self.strict)?;
+        let mut transaction = self.inner.transaction()?;
+        let mut inserted = Vec::default();
+        let record = self.reasoner()?.is_some();
+        let skolemizer = self.inner.skolemizer()?;
+        let tr = &mut transaction;
+        let ins = &mut inserted;
+        parser.parse_all(&mut move |triple| -> Result<()> {
+            let quad = triple.in_graph(to_graph_name.cloned());
+            let quad = match &skolemizer {
+                Some(skolemizer) => skolemizer.skolemize_quad(&quad),
+                None => quad,
+            };
+            let quad = tr.encode_quad(&quad)?;
+            tr.insert(&quad)?;
+            if record {
+                ins.push(quad);
+            }
+            Ok(())
         })?;
-        transaction.commit() //TODO: partials commits
+        transaction.commit()?;
+        self.after_load(&inserted)?;
+        Ok(parser.prefixes().to_vec())
+    }
+
+    /// The reasoner enabled on the store, if any
+    fn reasoner(&self) -> Result<Option<OwlRlReasoner>> {
+        Ok(self.inner.inferred_graph()?.map(OwlRlReasoner::new))
+    }
+
+    /// Updates the inferred triples after a load
+    fn after_load(&self, inserted: &[EncodedQuad]) -> Result<()> {
+        if let Some(reasoner) = self.reasoner()? {
+            reasoner.after_insert(&self.inner, inserted)?;
+        }
+        Ok(())
+    }
+
+    /// Adds the prefixes declared in a loaded file that are not already bound in the namespace table
+    fn add_namespaces(&mut self, namespaces: Vec<(String, String)>) -> Result<()> {
+        for (prefix, namespace) in namespaces {
+            if self.inner.namespace(&prefix)?.is_none() {
+                self.inner.set_namespace(&prefix, &namespace)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks that the graph name of a quad to insert is not a quoted triple
+///
+/// [RDF-star](https://w3c.github.io/rdf-star/cg-spec/) only allows quoted triples as subjects and objects.
+fn check_graph_name(quad: &EncodedQuad) -> Result<()> {
+    if quad.graph_name.is_triple() {
+        Err(Error::unsupported("Quoted triples can not be graph names"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Replaces the blank nodes of an encoded quad by their Skolem IRIs if skolemization is enabled
+fn skolemize_encoded_quad(
+    encoder: &mut impl Encoder,
+    skolemizer: Option<&Skolemizer>,
+    quad: EncodedQuad,
+) -> Result<EncodedQuad> {
+    let skolemizer = match skolemizer {
+        Some(skolemizer) => skolemizer,
+        None => return Ok(quad),
+    };
+    let mut skolemize = |term| match term {
+        EncodedTerm::BlankNode { id } => encoder.encode_named_node(
+            &skolemizer.skolemize_blank_node(&BlankNode::new_from_unique_id(id)),
+        ),
+        term => Ok(term),
+    };
+    Ok(EncodedQuad {
+        subject: skolemize(quad.subject)?,
+        predicate: quad.predicate,
+        object: skolemize(quad.object)?,
+        graph_name: skolemize(quad.graph_name)?,
+    })
+}
+
+/// Encodes a term without storing its strings, for lookups, replacing its blank nodes by their Skolem IRIs if skolemization is enabled
+fn encode_lookup_term(skolemizer: Option<&Skolemizer>, term: Term) -> EncodedTerm {
+    match skolemizer {
+        Some(skolemizer) => (&skolemizer.skolemize_term(&term)).into(),
+        None => (&term).into(),
     }
 }
+
+/// Checks that a literal read by a rio parser is well-formed
+///
+/// The errors are located in the line the parser is reading if it is known.
+fn check_rio_literal(
+    literal: rio::Literal<'_>,
+    line: Option<&Rc<RefCell<CurrentLine>>>,
+) -> Result<()> {
+    let (result, needle, is_language_tag) = match literal {
+        rio::Literal::Simple { .. } => return Ok(()),
+        rio::Literal::LanguageTaggedString { language, .. } => {
+            (check_language_tag(language), language, true)
+        }
+        rio::Literal::Typed { value, datatype } => {
+            (check_lexical_form(value, datatype.iri), value, false)
+        }
+    };
+    result.map_err(|message| match line {
+        Some(line) => line
+            .borrow()
+            .syntax_error(&message, needle, is_language_tag)
+            .into(),
+        None => Error::syntax(message),
+    })
+}
+
+/// Records the `@prefix` and `PREFIX` declarations of a Turtle or TriG file while it is read by a rio parser.
+///
+/// rio parsers do not expose the prefixes they have encountered so the consumed lines are also given
+/// to a `DirectiveRecorder` that parses the directives with the Turtle-star grammar.
+struct PrefixRecorder<R: BufRead> {
+    inner: R,
+    line: Vec<u8>,
+    directives: DirectiveRecorder,
+}
+
+impl<R: BufRead> PrefixRecorder<R> {
+    fn new(inner: R, base_iri: &str) -> Result<Self> {
+        Ok(Self {
+            inner,
+            line: Vec::default(),
+            directives: DirectiveRecorder::new(if base_iri.is_empty() {
+                None
+            } else {
+                Some(base_iri)
+            })?,
+        })
+    }
+
+    /// Returns the declared prefixes in the file order
+    fn finish(mut self) -> Vec<(String, String)> {
+        let line = take(&mut self.line);
+        self.record_line(&line);
+        self.directives.prefixes().to_vec()
+    }
+
+    fn record_line(&mut self, line: &[u8]) {
+        self.directives.read_line(&String::from_utf8_lossy(line));
+    }
+}
+
+impl<R: BufRead> Read for PrefixRecorder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = min(available.len(), buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R: BufRead> BufRead for PrefixRecorder<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        // The data to consume is still in the inner buffer so fill_buf does not do any I/O here
+        if let Ok(buffer) = self.inner.fill_buf() {
+            self.line
+                .extend_from_slice(&buffer[..min(amt, buffer.len())]);
+        }
+        self.inner.consume(amt);
+        while let Some(end) = self.line.iter().position(|b| *b == b'\n') {
+            let line = self.line.drain(..=end).collect::<Vec<_>>();
+            self.record_line(&line);
+        }
+    }
+}
+
+/// The line of the input the parser is reading
+#[derive(Default)]
+struct CurrentLine {
+    lines_started: usize,
+    content: Vec<u8>,
+}
+
+impl CurrentLine {
+    /// Builds an error located at the lexical form or the language tag `needle` of a literal of the line
+    ///
+    /// Only the occurrences of `needle` written as a literal part are considered, i.e. a lexical form followed
+    /// by its closing quote and `^^` or a language tag following a closing quote and `@`.
+    /// The same text in the subject or the predicate of the triple is not matched.
+    /// If the literal is written with escape sequences, the first occurrence of `needle` is used.
+    fn syntax_error(&self, message: &str, needle: &str, is_language_tag: bool) -> SyntaxError {
+        let content = String::from_utf8_lossy(&self.content);
+        let content = content.trim_end_matches(['\n', '\r']);
+        let is_literal_part = |start: usize| {
+            let (before, after) = (&content[..start], &content[start + needle.len()..]);
+            if is_language_tag {
+                before.ends_with('@')
+                    && before[..before.len() - 1].ends_with(['"', '\''])
+                    && !after.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '-')
+            } else {
+                before.ends_with(['"', '\''])
+                    && after.trim_start_matches(['"', '\'']).starts_with("^^")
+            }
+        };
+        let column = content
+            .match_indices(needle)
+            .map(|(i, _)| i)
+            .find(|i| is_literal_part(*i))
+            .or_else(|| content.find(needle))
+            .map_or(0, |i| content[..i].chars().count());
+        let position = TextPosition::new(self.lines_started.saturating_sub(1), column);
+        SyntaxError::new(format!("{} at {}", message, position), Some(position))
+            .with_snippet(content)
+    }
+}
+
+/// Records the line of a N-Triples, N-Quads, Turtle or TriG file a rio parser is reading.
+///
+/// These parsers read their input one line at a time so the last line consumed contains the triple being parsed.
+struct LineTracker<R: BufRead> {
+    inner: R,
+    line: Rc<RefCell<CurrentLine>>,
+    at_line_start: bool,
+}
+
+impl<R: BufRead> LineTracker<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            line: Rc::default(),
+            at_line_start: true,
+        }
+    }
+
+    fn current_line(&self) -> Rc<RefCell<CurrentLine>> {
+        self.line.clone()
+    }
+}
+
+impl<R: BufRead> Read for LineTracker<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = min(available.len(), buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R: BufRead> BufRead for LineTracker<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        // The data to consume is still in the inner buffer so fill_buf does not do any I/O here
+        if let Ok(buffer) = self.inner.fill_buf() {
+            let mut line = self.line.borrow_mut();
+            for b in &buffer[..min(amt, buffer.len())] {
+                if self.at_line_start {
+                    line.lines_started += 1;
+                    line.content.clear();
+                }
+                line.content.push(*b);
+                self.at_line_start = *b == b'\n';
+            }
+        }
+        self.inner.consume(amt);
+    }
+}
+
+#[cfg(test)]
+fn strict_loading_error_position(file: &str, syntax: LoadedSyntax) -> (usize, usize) {
+    use crate::{MemoryRepository, Repository};
+
+    let repository = MemoryRepository::default();
+    let mut connection = repository.connection().unwrap();
+    connection.enable_strict_loading();
+    let result = match syntax {
+        LoadedSyntax::Graph(syntax) => connection.load_graph(file.as_bytes(), syntax, None, None),
+        LoadedSyntax::Dataset(syntax) => connection.load_dataset(file.as_bytes(), syntax, None),
+    };
+    match result {
+        Err(Error::Syntax(error)) => {
+            let position = error.position().unwrap();
+            (position.line(), position.column())
+        }
+        result => panic!("{:?} should be rejected but got {:?}", file, result),
+    }
+}
+
+#[cfg(test)]
+enum LoadedSyntax {
+    Graph(GraphSyntax),
+    Dataset(DatasetSyntax),
+}
+
+#[test]
+fn test_strict_loading_error_position() {
+    // The lexical form also appears in the subject and the predicate
+    let line = "<http://example.com/abc> <http://example.com/abc> \"abc\"^^<http://www.w3.org/2001/XMLSchema#integer> .";
+    assert_eq!(
+        strict_loading_error_position(line, LoadedSyntax::Graph(GraphSyntax::NTriples)),
+        (0, line.find("\"abc\"").unwrap() + 1)
+    );
+
+    // The lexical form also appears in the graph name
+    let line = "<http://example.com/s> <http://example.com/p> \"1.5\"^^<http://www.w3.org/2001/XMLSchema#int> <http://example.com/1.5> .";
+    assert_eq!(
+        strict_loading_error_position(
+            &format!(
+                "<http://example.com/s> <http://example.com/p> \"1\" .\n{}",
+                line
+            ),
+            LoadedSyntax::Dataset(DatasetSyntax::NQuads)
+        ),
+        (1, line.find("\"1.5\"").unwrap() + 1)
+    );
+
+    // The language tag also appears in the predicate of a predicate list
+    let line = "    ex:abcdefghijk \"abcdefghijk\"@abcdefghijk .";
+    assert_eq!(
+        strict_loading_error_position(
+            &format!(
+                "@prefix ex: <http://example.com/> .\nex:s ex:p \"1\"@en ;\n{}",
+                line
+            ),
+            LoadedSyntax::Graph(GraphSyntax::Turtle)
+        ),
+        (2, line.find('@').unwrap() + 1)
+    );
+
+    // A long string
+    let line = "ex:s ex:p \"\"\"true\"\"\"^^<http://www.w3.org/2001/XMLSchema#date> .";
+    assert_eq!(
+        strict_loading_error_position(
+            &format!("@prefix ex: <http://example.com/> .\n{}", line),
+            LoadedSyntax::Graph(GraphSyntax::Turtle)
+        ),
+        (1, line.find("true").unwrap())
+    );
+}
+
+#[cfg(test)]
+fn loaded_namespaces(file: &str, syntax: LoadedSyntax) -> Vec<(String, String)> {
+    use crate::{MemoryRepository, Repository};
+
+    let repository = MemoryRepository::default();
+    let mut connection = repository.connection().unwrap();
+    match syntax {
+        LoadedSyntax::Graph(syntax) => connection
+            .load_graph(file.as_bytes(), syntax, None, Some("http://example.com/"))
+            .unwrap(),
+        LoadedSyntax::Dataset(syntax) => connection
+            .load_dataset(file.as_bytes(), syntax, Some("http://example.com/"))
+            .unwrap(),
+    }
+    connection.namespaces().unwrap().into_iter().collect()
+}
+
+#[test]
+fn test_load_namespaces() {
+    let namespace = |prefix: &str, namespace: &str| (prefix.to_owned(), namespace.to_owned());
+
+    assert_eq!(
+        loaded_namespaces(
+            "@prefix ex: <http://example.com/ex#> . @prefix\n  foo:\n  <foo/> .
+            # @prefix comment: <http://example.com/comment#> .
+            ex:s ex:p \"@prefix string: <http://example.com/string#> .\" ,
+                \"\"\"multi
+            @prefix long: <http://example.com/long#> .
+            line\"\"\" .
+            @base <http://example.org/> .
+            PrEfIx bar: <bar/>
+            bar:s bar:p <o> .
+            BASE <http://example.net/>
+            PREFIX : <>
+            :s :p :o .",
+            LoadedSyntax::Graph(GraphSyntax::Turtle)
+        ),
+        vec![
+            namespace("", "http://example.net/"),
+            namespace("bar", "http://example.org/bar/"),
+            namespace("ex", "http://example.com/ex#"),
+            namespace("foo", "http://example.com/foo/"),
+        ]
+    );
+
+    // The prefixes in the graph blocks are prefixed names
+    assert_eq!(
+        loaded_namespaces(
+            "@prefix ex: <http://example.com/ex#> .
+            PREFIX prefix: <http://example.com/prefix#>
+            GRAPH ex:g { prefix:s ex:p ex:o . prefix:s ex:p ex:o }
+            { ex:s ex:p ex:o }
+            @prefix foo: <foo#> .",
+            LoadedSyntax::Dataset(DatasetSyntax::TriG)
+        ),
+        vec![
+            namespace("ex", "http://example.com/ex#"),
+            namespace("foo", "http://example.com/foo#"),
+            namespace("prefix", "http://example.com/prefix#"),
+        ]
+    );
+}
+
+#[test]
+fn test_quoted_triple_graph_name() {
+    use crate::{MemoryRepository, Repository};
+
+    let ex = NamedNode::parse("http://example.com/").unwrap();
+    let quad = Quad::new(
+        ex.clone(),
+        ex.clone(),
+        ex.clone(),
+        Some(Triple::new(ex.clone(), ex.clone(), ex).into()),
+    );
+    let repository = MemoryRepository::default();
+    let mut connection = repository.connection().unwrap();
+    assert!(connection.insert(&quad).is_err());
+    assert_eq!(
+        connection.quads_for_pattern(None, None, None, None).count(),
+        0
+    );
+
+    let mut dataset = SimpleDataset::default();
+    assert!(!dataset.insert(quad.clone()));
+    assert!(!dataset.contains(&quad));
+    assert!(dataset.is_empty());
+}
@@ -1,11 +1,12 @@
 use crate::model::vocab::rdf;
+use crate::model::vocab::rdfs;
 use crate::model::vocab::xsd;
 use crate::model::*;
+use crate::Error;
 use crate::Result;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use chrono::format::{parse, Parsed, StrftimeItems};
 use chrono::prelude::*;
-use failure::format_err;
 use md5::digest::Digest;
 use md5::Md5;
 use ordered_float::OrderedFloat;
@@ -30,6 +31,11 @@ const XSD_DECIMAL_ID: u128 = 80624473126247401518595349505346497075;
 const XSD_DATE_TIME_ID: u128 = 257903479904871420659358808477547675664;
 const XSD_DATE_ID: u128 = 269408747350206033502011401422135526584;
 const XSD_TIME_ID: u128 = 163434887606038564205926318428306098363;
+const RDF_TYPE_ID: u128 = 127035770319883610436635574336989384391;
+const RDFS_SUB_CLASS_OF_ID: u128 = 190690543160124063944744110551276439990;
+const RDFS_SUB_PROPERTY_OF_ID: u128 = 92530278511770584007706562113397853622;
+const RDFS_DOMAIN_ID: u128 = 54356798223488681625059012346217207256;
+const RDFS_RANGE_ID: u128 = 285925980055655641817886082256362573143;
 
 pub fn get_str_id(value: &str) -> u128 {
     let mut id = [0 as u8; 16];
@@ -54,6 +60,7 @@ const TYPE_NAIVE_DATE_TIME_LITERAL: u8 = 14;
 const TYPE_DATE_LITERAL: u8 = 15;
 const TYPE_NAIVE_DATE_LITERAL: u8 = 16;
 const TYPE_NAIVE_TIME_LITERAL: u8 = 17;
+const TYPE_TRIPLE: u8 = 18;
 
 pub const ENCODED_DEFAULT_GRAPH: EncodedTerm = EncodedTerm::DefaultGraph;
 pub const ENCODED_EMPTY_STRING_LITERAL: EncodedTerm = EncodedTerm::StringLiteral {
@@ -89,15 +96,42 @@ pub const ENCODED_XSD_TIME_NAMED_NODE: EncodedTerm = EncodedTerm::NamedNode {
 pub const ENCODED_XSD_DATE_TIME_NAMED_NODE: EncodedTerm = EncodedTerm::NamedNode {
     iri_id: XSD_DATE_TIME_ID,
 };
+pub const ENCODED_RDF_TYPE_NAMED_NODE: EncodedTerm = EncodedTerm::NamedNode {
+    iri_id: RDF_TYPE_ID,
+};
+pub const ENCODED_RDFS_SUB_CLASS_OF_NAMED_NODE: EncodedTerm = EncodedTerm::NamedNode {
+    iri_id: RDFS_SUB_CLASS_OF_ID,
+};
+pub const ENCODED_RDFS_SUB_PROPERTY_OF_NAMED_NODE: EncodedTerm = EncodedTerm::NamedNode {
+    iri_id: RDFS_SUB_PROPERTY_OF_ID,
+};
+pub const ENCODED_RDFS_DOMAIN_NAMED_NODE: EncodedTerm = EncodedTerm::NamedNode {
+    iri_id: RDFS_DOMAIN_ID,
+};
+pub const ENCODED_RDFS_RANGE_NAMED_NODE: EncodedTerm = EncodedTerm::NamedNode {
+    iri_id: RDFS_RANGE_ID,
+};
 
 #[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Clone, Copy, Hash)]
 pub enum EncodedTerm {
     DefaultGraph,
-    NamedNode { iri_id: u128 },
-    BlankNode { id: u128 },
-    StringLiteral { value_id: u128 },
-    LangStringLiteral { value_id: u128, language_id: u128 },
-    TypedLiteral { value_id: u128, datatype_id: u128 },
+    NamedNode {
+        iri_id: u128,
+    },
+    BlankNode {
+        id: u128,
+    },
+    StringLiteral {
+        value_id: u128,
+    },
+    LangStringLiteral {
+        value_id: u128,
+        language_id: u128,
+    },
+    TypedLiteral {
+        value_id: u128,
+        datatype_id: u128,
+    },
     BooleanLiteral(bool),
     FloatLiteral(OrderedFloat<f32>),
     DoubleLiteral(OrderedFloat<f64>),
@@ -108,9 +142,24 @@ pub enum EncodedTerm {
     NaiveTimeLiteral(NaiveTime),
     DateTimeLiteral(DateTime<FixedOffset>),
     NaiveDateTimeLiteral(NaiveDateTime),
+    /// A quoted triple identified by the hash of its encoded terms that are kept in the string store
+    Triple {
+        id: u128,
+    },
 }
 
 impl EncodedTerm {
+    /// Builds the quoted triple made of the given terms
+    pub fn quoted_triple(
+        subject: EncodedTerm,
+        predicate: EncodedTerm,
+        object: EncodedTerm,
+    ) -> Self {
+        EncodedTerm::Triple {
+            id: get_str_id(&quoted_triple_key(subject, predicate, object)),
+        }
+    }
+
     pub fn is_named_node(&self) -> bool {
         match self {
             EncodedTerm::NamedNode { .. } => true,
@@ -125,6 +174,13 @@ impl EncodedTerm {
         }
     }
 
+    pub fn is_triple(&self) -> bool {
+        match self {
+            EncodedTerm::Triple { .. } => true,
+            _ => false,
+        }
+    }
+
     pub fn is_literal(&self) -> bool {
         match self {
             EncodedTerm::StringLiteral { .. }
@@ -185,6 +241,7 @@ impl EncodedTerm {
             EncodedTerm::NaiveTimeLiteral(_) => TYPE_NAIVE_TIME_LITERAL,
             EncodedTerm::DateTimeLiteral(_) => TYPE_DATE_TIME_LITERAL,
             EncodedTerm::NaiveDateTimeLiteral(_) => TYPE_NAIVE_DATE_TIME_LITERAL,
+            EncodedTerm::Triple { .. } => TYPE_TRIPLE,
         }
     }
 }
@@ -316,53 +373,39 @@ impl<'a> From<rio::Literal<'a>> for EncodedTerm {
                 }
             }
             rio::Literal::Typed { value, datatype } => {
-                match match datatype.iri {
-                    "http://www.w3.org/2001/XMLSchema#boolean" => parse_boolean_str(value),
-                    "http://www.w3.org/2001/XMLSchema#string" => Some(EncodedTerm::StringLiteral {
+                if datatype.iri == "http://www.w3.org/2001/XMLSchema#string" {
+                    EncodedTerm::StringLiteral {
                         value_id: get_str_id(value),
-                    }),
-                    "http://www.w3.org/2001/XMLSchema#float" => parse_float_str(value),
-                    "http://www.w3.org/2001/XMLSchema#double" => parse_double_str(value),
-                    "http://www.w3.org/2001/XMLSchema#integer"
-                    | "http://www.w3.org/2001/XMLSchema#byte"
-                    | "http://www.w3.org/2001/XMLSchema#short"
-                    | "http://www.w3.org/2001/XMLSchema#int"
-                    | "http://www.w3.org/2001/XMLSchema#long"
-                    | "http://www.w3.org/2001/XMLSchema#unsignedByte"
-                    | "http://www.w3.org/2001/XMLSchema#unsignedShort"
-                    | "http://www.w3.org/2001/XMLSchema#unsignedInt"
-                    | "http://www.w3.org/2001/XMLSchema#unsignedLong"
-                    | "http://www.w3.org/2001/XMLSchema#positiveInteger"
-                    | "http://www.w3.org/2001/XMLSchema#negativeInteger"
-                    | "http://www.w3.org/2001/XMLSchema#nonPositiveInteger"
-                    | "http://www.w3.org/2001/XMLSchema#nonNegativeInteger" => {
-                        parse_integer_str(value)
                     }
-                    "http://www.w3.org/2001/XMLSchema#decimal" => parse_decimal_str(value),
-                    "http://www.w3.org/2001/XMLSchema#date" => parse_date_str(value),
-                    "http://www.w3.org/2001/XMLSchema#time" => parse_time_str(value),
-                    "http://www.w3.org/2001/XMLSchema#dateTime"
-                    | "http://www.w3.org/2001/XMLSchema#dateTimeStamp" => {
-                        parse_date_time_str(value)
-                    }
-                    _ => None,
-                } {
-                    Some(v) => v,
-                    None => EncodedTerm::TypedLiteral {
+                } else if let Some(v) = parse_canonical_typed_literal(value, datatype.iri) {
+                    v
+                } else {
+                    EncodedTerm::TypedLiteral {
                         value_id: get_str_id(value),
                         datatype_id: get_str_id(datatype.iri),
-                    },
+                    }
                 }
             }
         }
     }
 }
 
+impl From<&Triple> for EncodedTerm {
+    fn from(triple: &Triple) -> Self {
+        EncodedTerm::quoted_triple(
+            triple.subject().into(),
+            triple.predicate().into(),
+            triple.object().into(),
+        )
+    }
+}
+
 impl From<&NamedOrBlankNode> for EncodedTerm {
     fn from(node: &NamedOrBlankNode) -> Self {
         match node {
             NamedOrBlankNode::NamedNode(node) => node.into(),
             NamedOrBlankNode::BlankNode(node) => node.into(),
+            NamedOrBlankNode::Triple(triple) => (&**triple).into(),
         }
     }
 }
@@ -373,10 +416,37 @@ impl From<&Term> for EncodedTerm {
             Term::NamedNode(node) => node.into(),
             Term::BlankNode(node) => node.into(),
             Term::Literal(literal) => literal.into(),
+            Term::Triple(triple) => (&**triple).into(),
         }
     }
 }
 
+/// The string stored for a quoted triple: the hexadecimal serialization of its encoded terms
+fn quoted_triple_key(subject: EncodedTerm, predicate: EncodedTerm, object: EncodedTerm) -> String {
+    let mut buffer = Vec::with_capacity(3 * WRITTEN_TERM_MAX_SIZE);
+    // Writing into a Vec never fails
+    buffer.write_term(subject).unwrap();
+    buffer.write_term(predicate).unwrap();
+    buffer.write_term(object).unwrap();
+    hex::encode(buffer)
+}
+
+/// Returns the subject, the predicate and the object of an encoded quoted triple
+pub fn quoted_triple_terms<S: StrLookup>(
+    lookup: &S,
+    id: u128,
+) -> Result<(EncodedTerm, EncodedTerm, EncodedTerm)> {
+    let key = get_required_str(lookup, id)?;
+    let buffer = hex::decode(&*key)
+        .map_err(|_| Error::storage(format!("The quoted triple with id {} is not valid", id)))?;
+    let mut reader = buffer.as_slice();
+    Ok((
+        reader.read_term()?,
+        reader.read_term()?,
+        reader.read_term()?,
+    ))
+}
+
 #[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Clone, Hash)]
 pub struct EncodedQuad {
     pub subject: EncodedTerm,
@@ -464,38 +534,41 @@ impl<R: Read> TermReader for R {
             }
             TYPE_DATE_LITERAL => Ok(EncodedTerm::DateLiteral(Date::from_utc(
                 NaiveDate::from_num_days_from_ce_opt(self.read_i32::<LittleEndian>()?)
-                    .ok_or_else(|| format_err!("Invalid date serialization"))?,
+                    .ok_or_else(|| Error::storage("Invalid date serialization"))?,
                 FixedOffset::east_opt(self.read_i32::<LittleEndian>()?)
-                    .ok_or_else(|| format_err!("Invalid timezone offset"))?,
+                    .ok_or_else(|| Error::storage("Invalid timezone offset"))?,
             ))),
             TYPE_NAIVE_DATE_LITERAL => Ok(EncodedTerm::NaiveDateLiteral(
                 NaiveDate::from_num_days_from_ce_opt(self.read_i32::<LittleEndian>()?)
-                    .ok_or_else(|| format_err!("Invalid date serialization"))?,
+                    .ok_or_else(|| Error::storage("Invalid date serialization"))?,
             )),
             TYPE_NAIVE_TIME_LITERAL => Ok(EncodedTerm::NaiveTimeLiteral(
                 NaiveTime::from_num_seconds_from_midnight_opt(
                     self.read_u32::<LittleEndian>()?,
                     self.read_u32::<LittleEndian>()?,
                 )
-                .ok_or_else(|| format_err!("Invalid time serialization"))?,
+                .ok_or_else(|| Error::storage("Invalid time serialization"))?,
             )),
             TYPE_DATE_TIME_LITERAL => Ok(EncodedTerm::DateTimeLiteral(DateTime::from_utc(
                 NaiveDateTime::from_timestamp_opt(
                     self.read_i64::<LittleEndian>()?,
                     self.read_u32::<LittleEndian>()?,
                 )
-                .ok_or_else(|| format_err!("Invalid date time serialization"))?,
+                .ok_or_else(|| Error::storage("Invalid date time serialization"))?,
                 FixedOffset::east_opt(self.read_i32::<LittleEndian>()?)
-                    .ok_or_else(|| format_err!("Invalid timezone offset"))?,
+                    .ok_or_else(|| Error::storage("Invalid timezone offset"))?,
             ))),
+            TYPE_TRIPLE => Ok(EncodedTerm::Triple {
+                id: self.read_u128::<LittleEndian>()?,
+            }),
             TYPE_NAIVE_DATE_TIME_LITERAL => Ok(EncodedTerm::NaiveDateTimeLiteral(
                 NaiveDateTime::from_timestamp_opt(
                     self.read_i64::<LittleEndian>()?,
                     self.read_u32::<LittleEndian>()?,
                 )
-                .ok_or_else(|| format_err!("Invalid date time serialization"))?,
+                .ok_or_else(|| Error::storage("Invalid date time serialization"))?,
             )),
-            _ => Err(format_err!("the term buffer has an invalid type id")),
+            _ => Err(Error::storage("the term buffer has an invalid type id")),
         }
     }
 
@@ -639,6 +712,7 @@ impl<W: Write> TermWriter for W {
                 self.write_i64::<LittleEndian>(value.timestamp())?;
                 self.write_u32::<LittleEndian>(value.timestamp_subsec_nanos())?;
             }
+            EncodedTerm::Triple { id } => self.write_u128::<LittleEndian>(id)?,
         }
         Ok(())
     }
@@ -714,10 +788,16 @@ pub trait StrContainer {
         self.insert_str(XSD_DATE_TIME_ID, xsd::DATE_TIME.as_str())?;
         self.insert_str(XSD_DATE_ID, xsd::DATE.as_str())?;
         self.insert_str(XSD_TIME_ID, xsd::TIME.as_str())?;
+        self.insert_str(RDF_TYPE_ID, rdf::TYPE.as_str())?;
+        self.insert_str(RDFS_SUB_CLASS_OF_ID, rdfs::SUB_CLASS_OF.as_str())?;
+        self.insert_str(RDFS_SUB_PROPERTY_OF_ID, rdfs::SUB_PROPERTY_OF.as_str())?;
+        self.insert_str(RDFS_DOMAIN_ID, rdfs::DOMAIN.as_str())?;
+        self.insert_str(RDFS_RANGE_ID, rdfs::RANGE.as_str())?;
         Ok(())
     }
 }
 
+#[derive(Clone)]
 pub struct MemoryStrStore {
     id2str: HashMap<u128, String>,
 }
@@ -732,6 +812,14 @@ impl Default for MemoryStrStore {
     }
 }
 
+impl MemoryStrStore {
+    pub fn merge(&mut self, other: MemoryStrStore) {
+        for (key, value) in other.id2str {
+            self.id2str.entry(key).or_insert(value);
+        }
+    }
+}
+
 impl StrLookup for MemoryStrStore {
     type StrType = String;
 
@@ -748,6 +836,12 @@ impl StrContainer for MemoryStrStore {
     }
 }
 
+impl<S: StrContainer> StrContainer for &mut S {
+    fn insert_str(&mut self, key: u128, value: &str) -> Result<()> {
+        (*self).insert_str(key, value)
+    }
+}
+
 pub trait Encoder {
     fn encode_named_node(&mut self, named_node: &NamedNode) -> Result<EncodedTerm> {
         self.encode_rio_named_node(named_node.into())
@@ -765,6 +859,7 @@ pub trait Encoder {
         match term {
             NamedOrBlankNode::NamedNode(named_node) => self.encode_named_node(named_node),
             NamedOrBlankNode::BlankNode(blank_node) => self.encode_blank_node(blank_node),
+            NamedOrBlankNode::Triple(triple) => self.encode_quoted_triple(triple),
         }
     }
 
@@ -773,9 +868,25 @@ pub trait Encoder {
             Term::NamedNode(named_node) => self.encode_named_node(named_node),
             Term::BlankNode(blank_node) => self.encode_blank_node(blank_node),
             Term::Literal(literal) => self.encode_literal(literal),
+            Term::Triple(triple) => self.encode_quoted_triple(triple),
         }
     }
 
+    fn encode_quoted_triple(&mut self, triple: &Triple) -> Result<EncodedTerm> {
+        let subject = self.encode_named_or_blank_node(triple.subject())?;
+        let predicate = self.encode_named_node(triple.predicate())?;
+        let object = self.encode_term(triple.object())?;
+        self.encode_quoted_triple_terms(subject, predicate, object)
+    }
+
+    /// Builds a quoted triple from already encoded terms
+    fn encode_quoted_triple_terms(
+        &mut self,
+        subject: EncodedTerm,
+        predicate: EncodedTerm,
+        object: EncodedTerm,
+    ) -> Result<EncodedTerm>;
+
     fn encode_quad(&mut self, quad: &Quad) -> Result<EncodedQuad> {
         Ok(EncodedQuad {
             subject: self.encode_named_or_blank_node(quad.subject())?,
@@ -868,6 +979,18 @@ pub trait Encoder {
 }
 
 impl<S: StrContainer> Encoder for S {
+    fn encode_quoted_triple_terms(
+        &mut self,
+        subject: EncodedTerm,
+        predicate: EncodedTerm,
+        object: EncodedTerm,
+    ) -> Result<EncodedTerm> {
+        let key = quoted_triple_key(subject, predicate, object);
+        let id = get_str_id(&key);
+        self.insert_str(id, &key)?;
+        Ok(EncodedTerm::Triple { id })
+    }
+
     fn encode_rio_named_node(&mut self, named_node: rio::NamedNode) -> Result<EncodedTerm> {
         let iri_id = get_str_id(named_node.iri);
         self.insert_str(iri_id, named_node.iri)?;
@@ -916,49 +1039,20 @@ impl<S: StrContainer> Encoder for S {
                 }
             }
             rio::Literal::Typed { value, datatype } => {
-                match match datatype.iri {
-                    "http://www.w3.org/2001/XMLSchema#boolean" => parse_boolean_str(value),
-                    "http://www.w3.org/2001/XMLSchema#string" => {
-                        let value_id = get_str_id(value);
-                        self.insert_str(value_id, value)?;
-                        Some(EncodedTerm::StringLiteral { value_id })
-                    }
-                    "http://www.w3.org/2001/XMLSchema#float" => parse_float_str(value),
-                    "http://www.w3.org/2001/XMLSchema#double" => parse_double_str(value),
-                    "http://www.w3.org/2001/XMLSchema#integer"
-                    | "http://www.w3.org/2001/XMLSchema#byte"
-                    | "http://www.w3.org/2001/XMLSchema#short"
-                    | "http://www.w3.org/2001/XMLSchema#int"
-                    | "http://www.w3.org/2001/XMLSchema#long"
-                    | "http://www.w3.org/2001/XMLSchema#unsignedByte"
-                    | "http://www.w3.org/2001/XMLSchema#unsignedShort"
-                    | "http://www.w3.org/2001/XMLSchema#unsignedInt"
-                    | "http://www.w3.org/2001/XMLSchema#unsignedLong"
-                    | "http://www.w3.org/2001/XMLSchema#positiveInteger"
-                    | "http://www.w3.org/2001/XMLSchema#negativeInteger"
-                    | "http://www.w3.org/2001/XMLSchema#nonPositiveInteger"
-                    | "http://www.w3.org/2001/XMLSchema#nonNegativeInteger" => {
-                        parse_integer_str(value)
-                    }
-                    "http://www.w3.org/2001/XMLSchema#decimal" => parse_decimal_str(value),
-                    "http://www.w3.org/2001/XMLSchema#date" => parse_date_str(value),
-                    "http://www.w3.org/2001/XMLSchema#time" => parse_time_str(value),
-                    "http://www.w3.org/2001/XMLSchema#dateTime"
-                    | "http://www.w3.org/2001/XMLSchema#dateTimeStamp" => {
-                        parse_date_time_str(value)
-                    }
-                    _ => None,
-                } {
-                    Some(v) => v,
-                    None => {
-                        let value_id = get_str_id(value);
-                        self.insert_str(value_id, value)?;
-                        let datatype_id = get_str_id(datatype.iri);
-                        self.insert_str(datatype_id, datatype.iri)?;
-                        EncodedTerm::TypedLiteral {
-                            value_id,
-                            datatype_id,
-                        }
+                if datatype.iri == "http://www.w3.org/2001/XMLSchema#string" {
+                    let value_id = get_str_id(value);
+                    self.insert_str(value_id, value)?;
+                    EncodedTerm::StringLiteral { value_id }
+                } else if let Some(v) = parse_canonical_typed_literal(value, datatype.iri) {
+                    v
+                } else {
+                    let value_id = get_str_id(value);
+                    self.insert_str(value_id, value)?;
+                    let datatype_id = get_str_id(datatype.iri);
+                    self.insert_str(datatype_id, datatype.iri)?;
+                    EncodedTerm::TypedLiteral {
+                        value_id,
+                        datatype_id,
                     }
                 }
             }
@@ -966,6 +1060,68 @@ impl<S: StrContainer> Encoder for S {
     }
 }
 
+/// Returns the value encoding of a typed literal if the datatype is supported and the lexical form is valid
+///
+/// Non canonical lexical forms like `"01"^^xsd:integer` or derived datatypes like `xsd:byte` are parsed too.
+pub fn parse_typed_literal_value(value: &str, datatype: &str) -> Option<EncodedTerm> {
+    match datatype {
+        "http://www.w3.org/2001/XMLSchema#boolean" => parse_boolean_str(value),
+        "http://www.w3.org/2001/XMLSchema#float" => parse_float_str(value),
+        "http://www.w3.org/2001/XMLSchema#double" => parse_double_str(value),
+        "http://www.w3.org/2001/XMLSchema#integer"
+        | "http://www.w3.org/2001/XMLSchema#byte"
+        | "http://www.w3.org/2001/XMLSchema#short"
+        | "http://www.w3.org/2001/XMLSchema#int"
+        | "http://www.w3.org/2001/XMLSchema#long"
+        | "http://www.w3.org/2001/XMLSchema#unsignedByte"
+        | "http://www.w3.org/2001/XMLSchema#unsignedShort"
+        | "http://www.w3.org/2001/XMLSchema#unsignedInt"
+        | "http://www.w3.org/2001/XMLSchema#unsignedLong"
+        | "http://www.w3.org/2001/XMLSchema#positiveInteger"
+        | "http://www.w3.org/2001/XMLSchema#negativeInteger"
+        | "http://www.w3.org/2001/XMLSchema#nonPositiveInteger"
+        | "http://www.w3.org/2001/XMLSchema#nonNegativeInteger" => parse_integer_str(value),
+        "http://www.w3.org/2001/XMLSchema#decimal" => parse_decimal_str(value),
+        "http://www.w3.org/2001/XMLSchema#date" => parse_date_str(value),
+        "http://www.w3.org/2001/XMLSchema#time" => parse_time_str(value),
+        "http://www.w3.org/2001/XMLSchema#dateTime"
+        | "http://www.w3.org/2001/XMLSchema#dateTimeStamp" => parse_date_time_str(value),
+        _ => None,
+    }
+}
+
+/// Returns the value encoding of a typed literal only if decoding it gives back the same lexical form and datatype
+///
+/// The other literals are kept as `EncodedTerm::TypedLiteral` in order to preserve how they have been written.
+fn parse_canonical_typed_literal(value: &str, datatype: &str) -> Option<EncodedTerm> {
+    let encoded = parse_typed_literal_value(value, datatype)?;
+    if encoded.datatype()
+        != Some(EncodedTerm::NamedNode {
+            iri_id: get_str_id(datatype),
+        })
+    {
+        return None;
+    }
+    let canonical = match encoded {
+        EncodedTerm::BooleanLiteral(value) => Literal::from(value),
+        EncodedTerm::FloatLiteral(value) => Literal::from(*value),
+        EncodedTerm::DoubleLiteral(value) => Literal::from(*value),
+        EncodedTerm::IntegerLiteral(value) => Literal::from(value),
+        EncodedTerm::DecimalLiteral(value) => Literal::from(value),
+        EncodedTerm::DateLiteral(value) => Literal::from(value),
+        EncodedTerm::NaiveDateLiteral(value) => Literal::from(value),
+        EncodedTerm::NaiveTimeLiteral(value) => Literal::from(value),
+        EncodedTerm::DateTimeLiteral(value) => Literal::from(value),
+        EncodedTerm::NaiveDateTimeLiteral(value) => Literal::from(value),
+        _ => return None,
+    };
+    if canonical.value() == value {
+        Some(encoded)
+    } else {
+        None
+    }
+}
+
 pub fn parse_boolean_str(value: &str) -> Option<EncodedTerm> {
     match value {
         "true" | "1" => Some(EncodedTerm::BooleanLiteral(true)),
@@ -1039,8 +1195,9 @@ pub trait Decoder {
         match self.decode_term(encoded)? {
             Term::NamedNode(named_node) => Ok(named_node.into()),
             Term::BlankNode(blank_node) => Ok(blank_node.into()),
-            Term::Literal(_) => Err(format_err!(
-                "A literal has ben found instead of a named node"
+            Term::Triple(triple) => Ok(NamedOrBlankNode::Triple(triple)),
+            Term::Literal(_) => Err(Error::evaluation(
+                "A literal has ben found instead of a named node",
             )),
         }
     }
@@ -1048,11 +1205,14 @@ pub trait Decoder {
     fn decode_named_node(&self, encoded: EncodedTerm) -> Result<NamedNode> {
         match self.decode_term(encoded)? {
             Term::NamedNode(named_node) => Ok(named_node),
-            Term::BlankNode(_) => Err(format_err!(
-                "A blank node has been found instead of a named node"
+            Term::BlankNode(_) => Err(Error::evaluation(
+                "A blank node has been found instead of a named node",
             )),
-            Term::Literal(_) => Err(format_err!(
-                "A literal has ben found instead of a named node"
+            Term::Literal(_) => Err(Error::evaluation(
+                "A literal has ben found instead of a named node",
+            )),
+            Term::Triple(_) => Err(Error::evaluation(
+                "A quoted triple has been found instead of a named node",
             )),
         }
     }
@@ -1072,6 +1232,9 @@ pub trait Decoder {
             self.decode_term(encoded.object)?,
             match encoded.graph_name {
                 EncodedTerm::DefaultGraph => None,
+                EncodedTerm::Triple { .. } => {
+                    return Err(Error::evaluation("A quoted triple can not be a graph name"))
+                }
                 graph_name => Some(self.decode_named_or_blank_node(graph_name)?),
             },
         ))
@@ -1081,9 +1244,9 @@ pub trait Decoder {
 impl<S: StrLookup> Decoder for S {
     fn decode_term(&self, encoded: EncodedTerm) -> Result<Term> {
         match encoded {
-            EncodedTerm::DefaultGraph => {
-                Err(format_err!("The default graph tag is not a valid term"))
-            }
+            EncodedTerm::DefaultGraph => Err(Error::evaluation(
+                "The default graph tag is not a valid term",
+            )),
             EncodedTerm::NamedNode { iri_id } => {
                 Ok(NamedNode::new_from_string(get_required_str(self, iri_id)?).into())
             }
@@ -1117,16 +1280,25 @@ impl<S: StrLookup> Decoder for S {
             EncodedTerm::NaiveTimeLiteral(value) => Ok(Literal::from(value).into()),
             EncodedTerm::DateTimeLiteral(value) => Ok(Literal::from(value).into()),
             EncodedTerm::NaiveDateTimeLiteral(value) => Ok(Literal::from(value).into()),
+            EncodedTerm::Triple { id } => {
+                let (subject, predicate, object) = quoted_triple_terms(self, id)?;
+                Ok(Triple::new(
+                    self.decode_named_or_blank_node(subject)?,
+                    self.decode_named_node(predicate)?,
+                    self.decode_term(object)?,
+                )
+                .into())
+            }
         }
     }
 }
 
 fn get_required_str<S: StrLookup>(lookup: &S, id: u128) -> Result<S::StrType> {
     lookup.get_str(id)?.ok_or_else(|| {
-        format_err!(
+        Error::storage(format!(
             "Not able to find the string with id {} in the string store",
             id
-        )
+        ))
     })
 }
 
@@ -1145,10 +1317,41 @@ fn test_encoding() {
         Literal::from("foo").into(),
         Literal::new_language_tagged_literal("foo", "fr").into(),
         Literal::new_language_tagged_literal("foo", "FR").into(),
+        Literal::new_typed_literal("01", xsd::INTEGER.clone()).into(),
+        Literal::new_typed_literal("1", xsd::BYTE.clone()).into(),
+        Literal::new_typed_literal("1.0E0", xsd::DOUBLE.clone()).into(),
+        Literal::new_typed_literal("1", xsd::BOOLEAN.clone()).into(),
+        Triple::new(
+            BlankNode::default(),
+            NamedNode::new_from_string("http://foo.com"),
+            Triple::new(
+                NamedNode::new_from_string("http://bar.com"),
+                NamedNode::new_from_string("http://foo.com"),
+                Literal::from(1),
+            ),
+        )
+        .into(),
     ];
     for term in terms {
         let encoded = store.encode_term(&term).unwrap();
         assert_eq!(term, store.decode_term(encoded).unwrap());
         assert_eq!(encoded, EncodedTerm::from(&term));
     }
+    assert_eq!(ENCODED_RDF_TYPE_NAMED_NODE, EncodedTerm::from(&*rdf::TYPE));
+    assert_eq!(
+        ENCODED_RDFS_SUB_CLASS_OF_NAMED_NODE,
+        EncodedTerm::from(&*rdfs::SUB_CLASS_OF)
+    );
+    assert_eq!(
+        ENCODED_RDFS_SUB_PROPERTY_OF_NAMED_NODE,
+        EncodedTerm::from(&*rdfs::SUB_PROPERTY_OF)
+    );
+    assert_eq!(
+        ENCODED_RDFS_DOMAIN_NAMED_NODE,
+        EncodedTerm::from(&*rdfs::DOMAIN)
+    );
+    assert_eq!(
+        ENCODED_RDFS_RANGE_NAMED_NODE,
+        EncodedTerm::from(&*rdfs::RANGE)
+    );
 }
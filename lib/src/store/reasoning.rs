@@ -0,0 +1,684 @@
+//! Forward chaining reasoner for a subset of the [OWL 2 RL](https://www.w3.org/TR/owl2-profiles/#OWL_2_RL) rules
+//!
+//! The premises are read from all the graphs of the store and the entailed triples that are not already in one of them
+//! are written into a dedicated named graph.
+//! Insertions are handled by semi-naive evaluation starting from the inserted triples.
+//! Removals are handled by the "delete and rederive" algorithm: all the inferred triples that could have been entailed
+//! by the removed ones are deleted, then the ones that are still entailed are inferred again.
+
+use crate::model::vocab::{owl, rdf};
+use crate::store::numeric_encoder::*;
+use crate::store::{StoreConnection, StoreTransaction};
+use crate::Result;
+use std::collections::HashSet;
+
+/// Maximal length of the RDF lists read for `owl:propertyChainAxiom`
+const MAX_PROPERTY_CHAIN_LENGTH: usize = 64;
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+struct EncodedTriple {
+    subject: EncodedTerm,
+    predicate: EncodedTerm,
+    object: EncodedTerm,
+}
+
+impl EncodedTriple {
+    fn new(subject: EncodedTerm, predicate: EncodedTerm, object: EncodedTerm) -> Self {
+        Self {
+            subject,
+            predicate,
+            object,
+        }
+    }
+
+    fn in_graph(self, graph_name: EncodedTerm) -> EncodedQuad {
+        EncodedQuad::new(self.subject, self.predicate, self.object, graph_name)
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.subject.is_literal() && self.predicate.is_named_node()
+    }
+}
+
+impl From<&EncodedQuad> for EncodedTriple {
+    fn from(quad: &EncodedQuad) -> Self {
+        Self::new(quad.subject, quad.predicate, quad.object)
+    }
+}
+
+/// The rule vocabulary
+#[derive(Debug, Clone)]
+struct Vocabulary {
+    rdf_type: EncodedTerm,
+    rdf_first: EncodedTerm,
+    rdf_rest: EncodedTerm,
+    rdf_nil: EncodedTerm,
+    sub_class_of: EncodedTerm,
+    sub_property_of: EncodedTerm,
+    domain: EncodedTerm,
+    range: EncodedTerm,
+    same_as: EncodedTerm,
+    inverse_of: EncodedTerm,
+    equivalent_class: EncodedTerm,
+    equivalent_property: EncodedTerm,
+    property_chain_axiom: EncodedTerm,
+    symmetric_property: EncodedTerm,
+    transitive_property: EncodedTerm,
+}
+
+impl Default for Vocabulary {
+    fn default() -> Self {
+        Self {
+            rdf_type: ENCODED_RDF_TYPE_NAMED_NODE,
+            rdf_first: (&*rdf::FIRST).into(),
+            rdf_rest: (&*rdf::REST).into(),
+            rdf_nil: (&*rdf::NIL).into(),
+            sub_class_of: ENCODED_RDFS_SUB_CLASS_OF_NAMED_NODE,
+            sub_property_of: ENCODED_RDFS_SUB_PROPERTY_OF_NAMED_NODE,
+            domain: ENCODED_RDFS_DOMAIN_NAMED_NODE,
+            range: ENCODED_RDFS_RANGE_NAMED_NODE,
+            same_as: (&*owl::SAME_AS).into(),
+            inverse_of: (&*owl::INVERSE_OF).into(),
+            equivalent_class: (&*owl::EQUIVALENT_CLASS).into(),
+            equivalent_property: (&*owl::EQUIVALENT_PROPERTY).into(),
+            property_chain_axiom: (&*owl::PROPERTY_CHAIN_AXIOM).into(),
+            symmetric_property: (&*owl::SYMMETRIC_PROPERTY).into(),
+            transitive_property: (&*owl::TRANSITIVE_PROPERTY).into(),
+        }
+    }
+}
+
+/// A property defined by `owl:propertyChainAxiom`
+struct PropertyChain {
+    property: EncodedTerm,
+    chain: Vec<EncodedTerm>,
+}
+
+/// Materializes the triples entailed by the supported OWL 2 RL rules in a named graph
+#[derive(Debug, Clone)]
+pub struct OwlRlReasoner {
+    inferred_graph: EncodedTerm,
+    vocabulary: Vocabulary,
+}
+
+impl OwlRlReasoner {
+    pub fn new(inferred_graph: EncodedTerm) -> Self {
+        Self {
+            inferred_graph,
+            vocabulary: Vocabulary::default(),
+        }
+    }
+
+    /// Recomputes the content of the inferred graph from scratch
+    pub fn materialize(&self, store: &impl StoreConnection) -> Result<()> {
+        let previous = store
+            .quads_for_pattern(None, None, None, Some(self.inferred_graph))
+            .collect::<Result<Vec<_>>>()?;
+        let mut transaction = store.transaction()?;
+        for quad in &previous {
+            transaction.remove(quad)?;
+        }
+        transaction.commit()?;
+
+        let mut premises = HashSet::new();
+        for quad in store.quads_for_pattern(None, None, None, None) {
+            premises.insert(EncodedTriple::from(&quad?));
+        }
+        self.saturate(store, premises.into_iter().collect())
+    }
+
+    /// Infers the consequences of quads that have just been inserted in the store
+    pub fn after_insert(&self, store: &impl StoreConnection, quads: &[EncodedQuad]) -> Result<()> {
+        let premises = quads
+            .iter()
+            .filter(|quad| quad.graph_name != self.inferred_graph)
+            .map(EncodedTriple::from)
+            .collect::<HashSet<_>>();
+        self.saturate(store, premises.into_iter().collect())
+    }
+
+    /// Removes quads from the store and updates the inferred graph accordingly
+    pub fn remove(&self, store: &impl StoreConnection, quads: &[EncodedQuad]) -> Result<()> {
+        let removed = quads.iter().map(EncodedTriple::from).collect::<Vec<_>>();
+
+        // Over-deletion of everything that might have been entailed by the removed triples
+        let mut over_deleted = HashSet::new();
+        let mut delta = removed.clone();
+        while !delta.is_empty() {
+            let chains = self.property_chains(store)?;
+            let mut new_delta = Vec::default();
+            for triple in delta {
+                for consequence in self.consequences(store, triple, &chains)? {
+                    if !over_deleted.contains(&consequence)
+                        && store.contains(&consequence.in_graph(self.inferred_graph))?
+                    {
+                        over_deleted.insert(consequence);
+                        new_delta.push(consequence);
+                    }
+                }
+            }
+            delta = new_delta;
+        }
+        let mut transaction = store.transaction()?;
+        for quad in quads {
+            transaction.remove(quad)?;
+        }
+        for triple in &over_deleted {
+            transaction.remove(&triple.in_graph(self.inferred_graph))?;
+        }
+        transaction.commit()?;
+
+        // Rederivation: all the rules have a premise containing the subject of the conclusion
+        let subjects = over_deleted
+            .iter()
+            .chain(&removed)
+            .map(|triple| triple.subject)
+            .collect::<HashSet<_>>();
+        let mut premises = HashSet::new();
+        for subject in subjects {
+            for quad in store
+                .quads_for_pattern(Some(subject), None, None, None)
+                .chain(store.quads_for_pattern(None, None, Some(subject), None))
+            {
+                premises.insert(EncodedTriple::from(&quad?));
+            }
+        }
+        self.saturate(store, premises.into_iter().collect())
+    }
+
+    /// Applies the rules until no new triple is inferred
+    fn saturate(
+        &self,
+        store: &impl StoreConnection,
+        mut premises: Vec<EncodedTriple>,
+    ) -> Result<()> {
+        while !premises.is_empty() {
+            let chains = self.property_chains(store)?;
+            let mut inferred = HashSet::new();
+            for premise in premises {
+                for consequence in self.consequences(store, premise, &chains)? {
+                    if !inferred.contains(&consequence) && !self.holds(store, consequence)? {
+                        inferred.insert(consequence);
+                    }
+                }
+            }
+            let mut transaction = store.transaction()?;
+            for triple in &inferred {
+                transaction.insert(&triple.in_graph(self.inferred_graph))?;
+            }
+            transaction.commit()?;
+            premises = inferred.into_iter().collect();
+        }
+        Ok(())
+    }
+
+    /// The triples entailed in one step by rules having `triple` as one of their premises
+    fn consequences(
+        &self,
+        store: &impl StoreConnection,
+        triple: EncodedTriple,
+        chains: &[PropertyChain],
+    ) -> Result<Vec<EncodedTriple>> {
+        let v = &self.vocabulary;
+        let EncodedTriple {
+            subject: s,
+            predicate: p,
+            object: o,
+        } = triple;
+        let mut result = Vec::default();
+
+        // Rules where the triple is the data premise
+        // eq-rep-s, eq-rep-p and eq-rep-o
+        for s2 in objects(store, s, v.same_as)? {
+            result.push(EncodedTriple::new(s2, p, o));
+        }
+        for p2 in objects(store, p, v.same_as)? {
+            result.push(EncodedTriple::new(s, p2, o));
+        }
+        for o2 in objects(store, o, v.same_as)? {
+            result.push(EncodedTriple::new(s, p, o2));
+        }
+        // prp-inv1 and prp-inv2
+        for inverse in
+            objects(store, p, v.inverse_of)?
+                .into_iter()
+                .chain(subjects(store, v.inverse_of, p)?)
+        {
+            result.push(EncodedTriple::new(o, inverse, s));
+        }
+        // prp-symp
+        if self.holds(
+            store,
+            EncodedTriple::new(p, v.rdf_type, v.symmetric_property),
+        )? {
+            result.push(EncodedTriple::new(o, p, s));
+        }
+        // prp-trp
+        if self.holds(
+            store,
+            EncodedTriple::new(p, v.rdf_type, v.transitive_property),
+        )? {
+            for z in objects(store, o, p)? {
+                result.push(EncodedTriple::new(s, p, z));
+            }
+            for x in subjects(store, p, s)? {
+                result.push(EncodedTriple::new(x, p, o));
+            }
+        }
+        // prp-spo1
+        for super_property in objects(store, p, v.sub_property_of)? {
+            result.push(EncodedTriple::new(s, super_property, o));
+        }
+        // prp-dom and prp-rng
+        for class in objects(store, p, v.domain)? {
+            result.push(EncodedTriple::new(s, v.rdf_type, class));
+        }
+        for class in objects(store, p, v.range)? {
+            result.push(EncodedTriple::new(o, v.rdf_type, class));
+        }
+        // prp-spo2
+        for PropertyChain { property, chain } in chains {
+            for (i, step) in chain.iter().enumerate() {
+                if *step == p {
+                    let starts = follow(store, s, &chain[..i], true)?;
+                    let ends = follow(store, o, &chain[i + 1..], false)?;
+                    for start in &starts {
+                        for end in &ends {
+                            result.push(EncodedTriple::new(*start, *property, *end));
+                        }
+                    }
+                }
+            }
+        }
+        // cax-sco
+        if p == v.rdf_type {
+            for class in objects(store, o, v.sub_class_of)? {
+                result.push(EncodedTriple::new(s, v.rdf_type, class));
+            }
+        }
+
+        // Rules where the triple is the schema or equality premise
+        if p == v.same_as {
+            // eq-sym
+            result.push(EncodedTriple::new(o, v.same_as, s));
+            // eq-trans
+            for z in objects(store, o, v.same_as)? {
+                result.push(EncodedTriple::new(s, v.same_as, z));
+            }
+            for x in subjects(store, v.same_as, s)? {
+                result.push(EncodedTriple::new(x, v.same_as, o));
+            }
+            // eq-rep-s, eq-rep-o and eq-rep-p
+            for quad in store.quads_for_pattern(Some(s), None, None, None) {
+                let quad = quad?;
+                result.push(EncodedTriple::new(o, quad.predicate, quad.object));
+            }
+            for quad in store.quads_for_pattern(None, None, Some(s), None) {
+                let quad = quad?;
+                result.push(EncodedTriple::new(quad.subject, quad.predicate, o));
+            }
+            for (x, y) in pairs(store, s)? {
+                result.push(EncodedTriple::new(x, o, y));
+            }
+        } else if p == v.inverse_of {
+            // prp-inv1 and prp-inv2
+            for (x, y) in pairs(store, s)? {
+                result.push(EncodedTriple::new(y, o, x));
+            }
+            for (x, y) in pairs(store, o)? {
+                result.push(EncodedTriple::new(y, s, x));
+            }
+        } else if p == v.sub_property_of {
+            // prp-spo1
+            for (x, y) in pairs(store, s)? {
+                result.push(EncodedTriple::new(x, o, y));
+            }
+            // scm-spo
+            for z in objects(store, o, v.sub_property_of)? {
+                result.push(EncodedTriple::new(s, v.sub_property_of, z));
+            }
+            for x in subjects(store, v.sub_property_of, s)? {
+                result.push(EncodedTriple::new(x, v.sub_property_of, o));
+            }
+        } else if p == v.equivalent_property {
+            // scm-eqp1
+            result.push(EncodedTriple::new(s, v.sub_property_of, o));
+            result.push(EncodedTriple::new(o, v.sub_property_of, s));
+        } else if p == v.domain {
+            // prp-dom
+            for (x, _) in pairs(store, s)? {
+                result.push(EncodedTriple::new(x, v.rdf_type, o));
+            }
+        } else if p == v.range {
+            // prp-rng
+            for (_, y) in pairs(store, s)? {
+                result.push(EncodedTriple::new(y, v.rdf_type, o));
+            }
+        } else if p == v.sub_class_of {
+            // cax-sco
+            for x in subjects(store, v.rdf_type, s)? {
+                result.push(EncodedTriple::new(x, v.rdf_type, o));
+            }
+            // scm-sco
+            for z in objects(store, o, v.sub_class_of)? {
+                result.push(EncodedTriple::new(s, v.sub_class_of, z));
+            }
+            for x in subjects(store, v.sub_class_of, s)? {
+                result.push(EncodedTriple::new(x, v.sub_class_of, o));
+            }
+        } else if p == v.equivalent_class {
+            // scm-eqc1
+            result.push(EncodedTriple::new(s, v.sub_class_of, o));
+            result.push(EncodedTriple::new(o, v.sub_class_of, s));
+        } else if p == v.property_chain_axiom {
+            // prp-spo2
+            for PropertyChain { property, chain } in chains {
+                if *property == s && !chain.is_empty() {
+                    for (x, y) in pairs(store, chain[0])? {
+                        for end in follow(store, y, &chain[1..], false)? {
+                            result.push(EncodedTriple::new(x, s, end));
+                        }
+                    }
+                }
+            }
+        } else if p == v.rdf_type && o == v.symmetric_property {
+            // prp-symp
+            for (x, y) in pairs(store, s)? {
+                result.push(EncodedTriple::new(y, s, x));
+            }
+        } else if p == v.rdf_type && o == v.transitive_property {
+            // prp-trp
+            for (x, y) in pairs(store, s)? {
+                for z in objects(store, y, s)? {
+                    result.push(EncodedTriple::new(x, s, z));
+                }
+            }
+        }
+
+        result.retain(EncodedTriple::is_valid);
+        Ok(result)
+    }
+
+    /// Checks if the triple is in one of the graphs of the store
+    fn holds(&self, store: &impl StoreConnection, triple: EncodedTriple) -> Result<bool> {
+        Ok(store
+            .quads_for_pattern(
+                Some(triple.subject),
+                Some(triple.predicate),
+                Some(triple.object),
+                None,
+            )
+            .next()
+            .transpose()?
+            .is_some())
+    }
+
+    /// Reads the `owl:propertyChainAxiom` statements of the store
+    fn property_chains(&self, store: &impl StoreConnection) -> Result<Vec<PropertyChain>> {
+        let v = &self.vocabulary;
+        let mut chains = Vec::default();
+        for (property, list) in pairs(store, v.property_chain_axiom)? {
+            let mut chain = Vec::default();
+            let mut current = list;
+            while current != v.rdf_nil && chain.len() < MAX_PROPERTY_CHAIN_LENGTH {
+                match (
+                    objects(store, current, v.rdf_first)?.first(),
+                    objects(store, current, v.rdf_rest)?.first(),
+                ) {
+                    (Some(first), Some(rest)) => {
+                        chain.push(*first);
+                        current = *rest;
+                    }
+                    _ => break,
+                }
+            }
+            if current == v.rdf_nil {
+                chains.push(PropertyChain { property, chain });
+            }
+        }
+        Ok(chains)
+    }
+}
+
+fn objects(
+    store: &impl StoreConnection,
+    subject: EncodedTerm,
+    predicate: EncodedTerm,
+) -> Result<Vec<EncodedTerm>> {
+    store
+        .quads_for_pattern(Some(subject), Some(predicate), None, None)
+        .map(|quad| Ok(quad?.object))
+        .collect()
+}
+
+fn subjects(
+    store: &impl StoreConnection,
+    predicate: EncodedTerm,
+    object: EncodedTerm,
+) -> Result<Vec<EncodedTerm>> {
+    store
+        .quads_for_pattern(None, Some(predicate), Some(object), None)
+        .map(|quad| Ok(quad?.subject))
+        .collect()
+}
+
+fn pairs(
+    store: &impl StoreConnection,
+    predicate: EncodedTerm,
+) -> Result<Vec<(EncodedTerm, EncodedTerm)>> {
+    store
+        .quads_for_pattern(None, Some(predicate), None, None)
+        .map(|quad| {
+            let quad = quad?;
+            Ok((quad.subject, quad.object))
+        })
+        .collect()
+}
+
+/// Follows a sequence of properties from a term, backward if `inverse` is set
+fn follow(
+    store: &impl StoreConnection,
+    start: EncodedTerm,
+    path: &[EncodedTerm],
+    inverse: bool,
+) -> Result<HashSet<EncodedTerm>> {
+    let mut current = HashSet::new();
+    current.insert(start);
+    let steps: Box<dyn Iterator<Item = &EncodedTerm>> = if inverse {
+        Box::new(path.iter().rev())
+    } else {
+        Box::new(path.iter())
+    };
+    for step in steps {
+        let mut next = HashSet::new();
+        for term in current {
+            if inverse {
+                next.extend(subjects(store, *step, term)?);
+            } else {
+                next.extend(objects(store, term, *step)?);
+            }
+        }
+        current = next;
+    }
+    Ok(current)
+}
+
+#[cfg(test)]
+fn inferred_triples(data: &str) -> std::collections::BTreeSet<String> {
+    use crate::model::NamedNode;
+    use crate::{GraphSyntax, MemoryRepository, Repository, RepositoryConnection};
+
+    let data = format!(
+        "@prefix ex: <http://example.com/> .
+        @prefix owl: <http://www.w3.org/2002/07/owl#> .
+        @prefix rdf: <http://www.w3.org/1999/02/22-rdf-syntax-ns#> .
+        @prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .
+        {}",
+        data
+    );
+    let inferred = NamedNode::parse("http://example.com/inferred").unwrap();
+    let triples = |connection: &<&MemoryRepository as Repository>::Connection| {
+        connection
+            .quads_for_pattern(None, None, None, Some(Some(&inferred.clone().into())))
+            .map(|quad| {
+                let quad = quad.unwrap();
+                format!("{} {} {}", quad.subject(), quad.predicate(), quad.object())
+                    .replace("http://example.com/", "")
+                    .replace("http://www.w3.org/2002/07/owl#", "owl:")
+                    .replace("http://www.w3.org/1999/02/22-rdf-syntax-ns#", "rdf:")
+                    .replace("http://www.w3.org/2000/01/rdf-schema#", "rdfs:")
+            })
+            .collect()
+    };
+
+    // Incremental reasoning during the load
+    let repository = MemoryRepository::default();
+    let mut connection = repository.connection().unwrap();
+    connection
+        .enable_owl_rl_reasoning(&inferred.clone().into())
+        .unwrap();
+    connection
+        .load_graph(data.as_bytes(), GraphSyntax::Turtle, None, None)
+        .unwrap();
+    let incremental = triples(&connection);
+
+    // Materialization after the load
+    let repository = MemoryRepository::default();
+    let mut connection = repository.connection().unwrap();
+    connection
+        .load_graph(data.as_bytes(), GraphSyntax::Turtle, None, None)
+        .unwrap();
+    connection
+        .enable_owl_rl_reasoning(&inferred.clone().into())
+        .unwrap();
+    assert_eq!(incremental, triples(&connection));
+    incremental
+}
+
+#[cfg(test)]
+fn assert_inferred(data: &str, expected: &[&str]) {
+    assert_eq!(
+        inferred_triples(data),
+        expected.iter().map(|t| t.to_string()).collect(),
+        "{}",
+        data
+    );
+}
+
+#[test]
+fn test_rdfs_rules() {
+    assert_inferred(
+        "ex:A rdfs:subClassOf ex:B . ex:B rdfs:subClassOf ex:C . ex:x a ex:A .",
+        &[
+            "<A> <rdfs:subClassOf> <C>",
+            "<x> <rdf:type> <B>",
+            "<x> <rdf:type> <C>",
+        ],
+    );
+    assert_inferred(
+        "ex:p rdfs:subPropertyOf ex:q . ex:q rdfs:subPropertyOf ex:r . ex:x ex:p ex:y .",
+        &["<p> <rdfs:subPropertyOf> <r>", "<x> <q> <y>", "<x> <r> <y>"],
+    );
+    assert_inferred(
+        "ex:p rdfs:domain ex:A ; rdfs:range ex:B . ex:A rdfs:subClassOf ex:C . ex:x ex:p ex:y .",
+        &[
+            "<x> <rdf:type> <A>",
+            "<x> <rdf:type> <C>",
+            "<y> <rdf:type> <B>",
+        ],
+    );
+    assert_inferred(
+        "ex:p rdfs:subPropertyOf ex:q . ex:q rdfs:domain ex:A . ex:x ex:p ex:y .",
+        &["<x> <q> <y>", "<x> <rdf:type> <A>"],
+    );
+}
+
+#[test]
+fn test_owl_rules() {
+    assert_inferred(
+        "ex:a owl:sameAs ex:b . ex:b owl:sameAs ex:c . ex:a ex:p ex:d .",
+        &[
+            "<a> <owl:sameAs> <a>",
+            "<a> <owl:sameAs> <c>",
+            "<b> <owl:sameAs> <a>",
+            "<b> <owl:sameAs> <b>",
+            "<b> <p> <d>",
+            "<c> <owl:sameAs> <a>",
+            "<c> <owl:sameAs> <b>",
+            "<c> <owl:sameAs> <c>",
+            "<c> <p> <d>",
+        ],
+    );
+    assert_inferred(
+        "ex:p owl:inverseOf ex:q . ex:x ex:p ex:y . ex:z ex:q ex:t .",
+        &["<t> <p> <z>", "<y> <q> <x>"],
+    );
+    assert_inferred(
+        "ex:p a owl:SymmetricProperty . ex:x ex:p ex:y .",
+        &["<y> <p> <x>"],
+    );
+    assert_inferred(
+        "ex:p a owl:TransitiveProperty . ex:x ex:p ex:y . ex:y ex:p ex:z . ex:z ex:p ex:t .",
+        &["<x> <p> <t>", "<x> <p> <z>", "<y> <p> <t>"],
+    );
+    assert_inferred(
+        "ex:p owl:propertyChainAxiom ( ex:q ex:r ) . ex:x ex:q ex:y . ex:y ex:r ex:z .",
+        &["<x> <p> <z>"],
+    );
+    assert_inferred(
+        "ex:A owl:equivalentClass ex:B . ex:p owl:equivalentProperty ex:q . ex:x a ex:A ; ex:p ex:y .",
+        &[
+            "<A> <rdfs:subClassOf> <A>",
+            "<A> <rdfs:subClassOf> <B>",
+            "<B> <rdfs:subClassOf> <A>",
+            "<B> <rdfs:subClassOf> <B>",
+            "<p> <rdfs:subPropertyOf> <p>",
+            "<p> <rdfs:subPropertyOf> <q>",
+            "<q> <rdfs:subPropertyOf> <p>",
+            "<q> <rdfs:subPropertyOf> <q>",
+            "<x> <q> <y>",
+            "<x> <rdf:type> <B>",
+        ],
+    );
+}
+
+#[test]
+fn test_reasoning_on_repository() {
+    use crate::model::{NamedNode, Quad};
+    use crate::{MemoryRepository, Repository, RepositoryConnection};
+
+    let ex = |name: &str| NamedNode::parse(format!("http://example.com/{}", name)).unwrap();
+    let inferred = ex("inferred");
+    let repository = MemoryRepository::default();
+    repository
+        .connection()
+        .unwrap()
+        .enable_owl_rl_reasoning(&inferred.clone().into())
+        .unwrap();
+
+    // The connections opened later maintain the inferred triples
+    let mut connection = repository.connection().unwrap();
+    connection
+        .insert(&Quad::new(
+            ex("p"),
+            crate::model::vocab::rdf::TYPE.clone(),
+            NamedNode::parse("http://www.w3.org/2002/07/owl#SymmetricProperty").unwrap(),
+            None,
+        ))
+        .unwrap();
+    connection
+        .insert(&Quad::new(ex("x"), ex("p"), ex("y"), None))
+        .unwrap();
+    let inferred_quad = Quad::new(ex("y"), ex("p"), ex("x"), Some(inferred.into()));
+    assert!(repository
+        .connection()
+        .unwrap()
+        .contains(&inferred_quad)
+        .unwrap());
+
+    let mut connection = repository.connection().unwrap();
+    connection
+        .remove(&Quad::new(ex("x"), ex("p"), ex("y"), None))
+        .unwrap();
+    assert!(!connection.contains(&inferred_quad).unwrap());
+}
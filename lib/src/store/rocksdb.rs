@@ -1,7 +1,9 @@
+use crate::model::Skolemizer;
 use crate::store::numeric_encoder::*;
-use crate::store::{Store, StoreConnection, StoreRepositoryConnection, StoreTransaction};
-use crate::{Repository, Result};
-use failure::format_err;
+use crate::store::{
+    check_graph_name, Store, StoreConnection, StoreRepositoryConnection, StoreTransaction,
+};
+use crate::{Error, Repository, Result};
 use rocksdb::ColumnFamily;
 use rocksdb::DBCompactionStyle;
 use rocksdb::DBRawIterator;
@@ -9,6 +11,7 @@ use rocksdb::DBVector;
 use rocksdb::Options;
 use rocksdb::WriteBatch;
 use rocksdb::DB;
+use std::collections::BTreeMap;
 use std::io::Cursor;
 use std::iter::{empty, once};
 use std::mem::swap;
@@ -26,6 +29,8 @@ use std::str;
 /// use rudf::{Repository, RepositoryConnection, RocksDbRepository, Result};
 /// use crate::rudf::sparql::PreparedQuery;
 /// use rudf::sparql::QueryResult;
+/// use rudf::sparql::QueryOptions;
+/// use std::collections::BTreeMap;
 ///
 /// let repository = RocksDbRepository::open("example.db").unwrap();
 /// let mut connection = repository.connection().unwrap();
@@ -40,8 +45,8 @@ use std::str;
 /// assert_eq!(vec![quad], results.unwrap());
 ///
 /// // SPARQL query
-/// let prepared_query = connection.prepare_query("SELECT ?s WHERE { ?s ?p ?o }", None).unwrap();
-/// let results = prepared_query.exec().unwrap();
+/// let prepared_query = connection.prepare_query("SELECT ?s WHERE { ?s ?p ?o }", QueryOptions::default()).unwrap();
+/// let results = prepared_query.exec(&BTreeMap::default()).unwrap();
 /// if let QueryResult::Bindings(results) = results {
 ///     assert_eq!(results.into_values_iter().next().unwrap().unwrap()[0], Some(ex.into()));
 /// }
@@ -59,13 +64,38 @@ const OSPG_CF: &str = "ospg";
 const GSPO_CF: &str = "gspo";
 const GPOS_CF: &str = "gpos";
 const GOSP_CF: &str = "gosp";
+const NAMESPACES_CF: &str = "namespaces";
+
+/// Version of the encoding of the stored terms
+///
+/// The databases of version 0 do not have a version key. They have been written when the typed literals with a
+/// supported datatype were always stored with their canonical lexical form, e.g. `"01"^^xsd:integer` as `"1"^^xsd:integer`.
+/// Version 1 keeps the non canonical lexical forms. The canonical forms are encoded as in version 0 so the
+/// databases of version 0 are read as is: their literals are returned with their canonical lexical form.
+const FORMAT_VERSION: u8 = 1;
+
+/// Key of the format version in the default column family
+const FORMAT_VERSION_KEY: &[u8] = b"format_version";
+
+/// Key of the graph name written by the OWL RL reasoner in the default column family
+const INFERRED_GRAPH_KEY: &[u8] = b"inferred_graph";
+
+/// Key of the namespace of the Skolem IRIs in the default column family
+const SKOLEMIZER_KEY: &[u8] = b"skolemizer";
 
 const EMPTY_BUF: [u8; 0] = [0 as u8; 0];
 
 //TODO: indexes for the default graph and indexes for the named graphs (no more Optional and space saving)
 
-const COLUMN_FAMILIES: [&str; 7] = [
-    ID2STR_CF, SPOG_CF, POSG_CF, OSPG_CF, GSPO_CF, GPOS_CF, GOSP_CF,
+const COLUMN_FAMILIES: [&str; 8] = [
+    ID2STR_CF,
+    SPOG_CF,
+    POSG_CF,
+    OSPG_CF,
+    GSPO_CF,
+    GPOS_CF,
+    GOSP_CF,
+    NAMESPACES_CF,
 ];
 
 const MAX_TRANSACTION_SIZE: usize = 1024;
@@ -84,6 +114,7 @@ pub struct RocksDbStoreConnection<'a> {
     gspo_cf: ColumnFamily<'a>,
     gpos_cf: ColumnFamily<'a>,
     gosp_cf: ColumnFamily<'a>,
+    namespaces_cf: ColumnFamily<'a>,
 }
 
 impl RocksDbRepository {
@@ -112,6 +143,7 @@ impl RocksDbStore {
         let new = Self {
             db: DB::open_cf(&options, path, &COLUMN_FAMILIES)?,
         };
+        new.upgrade_format()?;
 
         let mut transaction = (&new).connection()?.transaction()?;
         transaction.set_first_strings()?;
@@ -119,6 +151,22 @@ impl RocksDbStore {
 
         Ok(new)
     }
+
+    /// Checks that the database could be read and marks it as written with the current format version
+    fn upgrade_format(&self) -> Result<()> {
+        if let Some(version) = self.db.get(FORMAT_VERSION_KEY)? {
+            match *version {
+                [version] if version <= FORMAT_VERSION => (),
+                _ => {
+                    return Err(Error::storage(
+                        "The database has been written with an unsupported format version",
+                    ))
+                }
+            }
+        }
+        self.db.put(FORMAT_VERSION_KEY, [FORMAT_VERSION])?;
+        Ok(())
+    }
 }
 
 impl<'a> Store for &'a RocksDbStore {
@@ -134,6 +182,7 @@ impl<'a> Store for &'a RocksDbStore {
             gspo_cf: get_cf(&self.db, GSPO_CF)?,
             gpos_cf: get_cf(&self.db, GPOS_CF)?,
             gosp_cf: get_cf(&self.db, GOSP_CF)?,
+            namespaces_cf: get_cf(&self.db, NAMESPACES_CF)?,
         })
     }
 }
@@ -242,6 +291,70 @@ impl<'a> StoreConnection for RocksDbStoreConnection<'a> {
             },
         }
     }
+
+    fn namespaces(&self) -> Result<BTreeMap<String, String>> {
+        let mut iter = self.store.db.raw_iterator_cf(self.namespaces_cf)?;
+        iter.seek_to_first();
+        let mut namespaces = BTreeMap::default();
+        while iter.valid() {
+            if let (Some(prefix), Some(namespace)) = (iter.key(), iter.value()) {
+                namespaces.insert(String::from_utf8(prefix)?, String::from_utf8(namespace)?);
+            }
+            iter.next();
+        }
+        Ok(namespaces)
+    }
+
+    fn namespace(&self, prefix: &str) -> Result<Option<String>> {
+        match self
+            .store
+            .db
+            .get_cf(self.namespaces_cf, prefix.as_bytes())?
+        {
+            Some(namespace) => Ok(Some(str::from_utf8(&namespace)?.to_owned())),
+            None => Ok(None),
+        }
+    }
+
+    fn set_namespace(&self, prefix: &str, namespace: &str) -> Result<()> {
+        self.store
+            .db
+            .put_cf(self.namespaces_cf, prefix.as_bytes(), namespace.as_bytes())?;
+        Ok(())
+    }
+
+    fn remove_namespace(&self, prefix: &str) -> Result<()> {
+        self.store
+            .db
+            .delete_cf(self.namespaces_cf, prefix.as_bytes())?;
+        Ok(())
+    }
+
+    fn inferred_graph(&self) -> Result<Option<EncodedTerm>> {
+        match self.store.db.get(INFERRED_GRAPH_KEY)? {
+            Some(value) => Ok(Some(Cursor::new(&value[..]).read_term()?)),
+            None => Ok(None),
+        }
+    }
+
+    fn set_inferred_graph(&self, graph_name: EncodedTerm) -> Result<()> {
+        let mut value = Vec::default();
+        value.write_term(graph_name)?;
+        self.store.db.put(INFERRED_GRAPH_KEY, &value)?;
+        Ok(())
+    }
+
+    fn skolemizer(&self) -> Result<Option<Skolemizer>> {
+        match self.store.db.get(SKOLEMIZER_KEY)? {
+            Some(value) => Ok(Some(Skolemizer::new(&String::from_utf8(value.to_vec())?)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn set_skolemizer(&self, skolemizer: &Skolemizer) -> Result<()> {
+        self.store.db.put(SKOLEMIZER_KEY, skolemizer.namespace())?;
+        Ok(())
+    }
 }
 
 impl<'a> RocksDbStoreConnection<'a> {
@@ -450,6 +563,7 @@ impl StrContainer for RocksDbStoreTransaction<'_> {
 
 impl<'a> StoreTransaction for RocksDbStoreTransaction<'a> {
     fn insert(&mut self, quad: &EncodedQuad) -> Result<()> {
+        check_graph_name(quad)?;
         self.buffer.write_spog_quad(quad)?;
         self.batch
             .put_cf(self.connection.spog_cf, &self.buffer, &EMPTY_BUF)?;
@@ -537,7 +651,7 @@ impl<'a> StoreTransaction for RocksDbStoreTransaction<'a> {
 
 fn get_cf<'a>(db: &'a DB, name: &str) -> Result<ColumnFamily<'a>> {
     db.cf_handle(name)
-        .ok_or_else(|| format_err!("column family {} not found", name))
+        .ok_or_else(|| Error::storage(format!("column family {} not found", name)))
 }
 
 fn wrap_error<'a, E: 'a, I: Iterator<Item = Result<E>> + 'a>(
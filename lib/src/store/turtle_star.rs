@@ -0,0 +1,553 @@
+mod grammar {
+    #![allow(
+        clippy::suspicious_else_formatting,
+        clippy::len_zero,
+        clippy::single_match,
+        clippy::unit_arg,
+        clippy::naive_bytecount,
+        clippy::cognitive_complexity,
+        clippy::many_single_char_names,
+        clippy::type_complexity,
+        ellipsis_inclusive_range_patterns
+    )]
+
+    use crate::model::*;
+    use crate::sparql::parser::unescape_pn_local;
+    use crate::{SyntaxError, TextPosition};
+    use rio_api::iri::{Iri, IriParseError};
+    use std::borrow::Cow;
+    use std::char;
+    use std::collections::{BTreeSet, HashMap};
+    use std::io::BufRead;
+    use std::str::Chars;
+
+    /// A predicate and an object of the current subject with the annotations of the resulting triple
+    pub struct PredicateObject {
+        predicate: NamedNode,
+        object: Term,
+        annotation: Vec<PredicateObject>,
+    }
+
+    pub struct ParserState {
+        base_iri: Option<Iri<String>>,
+        namespaces: HashMap<String, String>,
+        prefixes: Vec<(String, String)>,
+        bnodes_map: HashMap<String, BlankNode>,
+        triples: Vec<Triple>,
+        strict: bool,
+        invalid_literal: Option<(usize, String)>,
+    }
+
+    impl ParserState {
+        fn new(base_iri: Option<&str>, strict: bool) -> crate::Result<Self> {
+            Ok(Self {
+                base_iri: if let Some(base_iri) = base_iri {
+                    Some(Iri::parse(base_iri.to_owned())?)
+                } else {
+                    None
+                },
+                namespaces: HashMap::default(),
+                prefixes: Vec::default(),
+                bnodes_map: HashMap::default(),
+                triples: Vec::default(),
+                strict,
+                invalid_literal: None,
+            })
+        }
+
+        fn parse_iri(&self, iri: &str) -> Result<Iri<String>, IriParseError> {
+            if let Some(base_iri) = &self.base_iri {
+                base_iri.resolve(iri)
+            } else {
+                Iri::parse(iri.to_owned())
+            }
+        }
+
+        fn add_prefix(&mut self, prefix: &str, namespace: Iri<String>) {
+            let namespace = namespace.into_inner();
+            self.namespaces.insert(prefix.to_owned(), namespace.clone());
+            self.prefixes.push((prefix.to_owned(), namespace));
+        }
+
+        /// Records the first ill-typed literal or malformed language tag if the parsing is strict
+        fn check_literal(&mut self, position: usize, literal: &Literal) {
+            if self.strict && self.invalid_literal.is_none() {
+                if let Err(message) = check_literal(literal) {
+                    self.invalid_literal = Some((position, message));
+                }
+            }
+        }
+
+        /// Emits the triples of `subject` and, recursively, the triples annotating them
+        fn add_triples(
+            &mut self,
+            subject: &NamedOrBlankNode,
+            predicate_objects: Vec<PredicateObject>,
+        ) {
+            for predicate_object in predicate_objects {
+                let triple = Triple::new(
+                    subject.clone(),
+                    predicate_object.predicate,
+                    predicate_object.object,
+                );
+                if !predicate_object.annotation.is_empty() {
+                    self.add_triples(&triple.clone().into(), predicate_object.annotation);
+                }
+                self.triples.push(triple);
+            }
+        }
+    }
+
+    include!(concat!(env!("OUT_DIR"), "/turtle_star_grammar.rs"));
+
+    /// Replaces the `ECHAR` and `UCHAR` escape sequences of a string or an IRI
+    fn unescape(input: &str) -> Result<Cow<'_, str>, &'static str> {
+        if !input.contains('\\') {
+            return Ok(input.into());
+        }
+        let mut output = String::with_capacity(input.len());
+        let mut chars = input.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                output.push(c);
+                continue;
+            }
+            output.push(match chars.next() {
+                Some('t') => '\t',
+                Some('b') => '\u{8}',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('f') => '\u{c}',
+                Some('u') => unescape_codepoint(&mut chars, 4)?,
+                Some('U') => unescape_codepoint(&mut chars, 8)?,
+                Some(c) => c,
+                None => return Err("Unterminated escape sequence"),
+            });
+        }
+        Ok(output.into())
+    }
+
+    fn unescape_codepoint(chars: &mut Chars<'_>, length: usize) -> Result<char, &'static str> {
+        let code = chars.by_ref().take(length).collect::<String>();
+        u32::from_str_radix(&code, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or("Invalid Unicode code point")
+    }
+
+    fn to_syntax_error(input: &str, first_line: usize, error: &ParseError) -> SyntaxError {
+        let position = TextPosition::new(first_line + error.line - 1, error.column - 1);
+        let expected = error
+            .expected
+            .iter()
+            .map(|token| format!("`{}`", token))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let found = match input[error.offset..].chars().next() {
+            Some(c) => format!("`{}`", c),
+            None => "end of input".to_owned(),
+        };
+        let mut message = format!("Unexpected {} at {}", found, position);
+        if !expected.is_empty() {
+            message.push_str(", expected one of ");
+            message.push_str(&expected.join(", "));
+        }
+        SyntaxError::new(message, Some(position))
+            .with_expected(expected)
+            .with_snippet(line_at(input, error.offset))
+    }
+
+    fn invalid_literal_error(
+        input: &str,
+        first_line: usize,
+        offset: usize,
+        message: &str,
+    ) -> SyntaxError {
+        let line_start = input[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let position = TextPosition::new(
+            first_line + input[..line_start].matches('\n').count(),
+            input[line_start..offset].chars().count(),
+        );
+        SyntaxError::new(format!("{} at {}", message, position), Some(position))
+            .with_snippet(line_at(input, offset))
+    }
+
+    /// Returns the line of the input containing the byte at `offset`
+    fn line_at(input: &str, offset: usize) -> &str {
+        let line_start = input[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = input[offset..]
+            .find('\n')
+            .map_or(input.len(), |i| offset + i);
+        input[line_start..line_end].trim_end_matches('\r')
+    }
+
+    /// A streaming [Turtle-star](https://w3c.github.io/rdf-star/cg-spec/#turtle-star) parser
+    ///
+    /// N-Triples-star documents are also accepted as they are valid Turtle-star.
+    /// The document is read line by line and parsed each time the read lines end with a full statement
+    /// so only the current statement is kept in memory.
+    /// If `strict` is set, ill-typed literals and malformed language tags are syntax errors.
+    pub struct TurtleStarParser<R: BufRead> {
+        reader: R,
+        state: ParserState,
+        splitter: StatementSplitter,
+        buffer: String,
+        buffer_first_line: usize,
+    }
+
+    impl<R: BufRead> TurtleStarParser<R> {
+        pub fn new(reader: R, base_iri: Option<&str>, strict: bool) -> crate::Result<Self> {
+            Ok(Self {
+                reader,
+                state: ParserState::new(base_iri, strict)?,
+                splitter: StatementSplitter::default(),
+                buffer: String::default(),
+                buffer_first_line: 0,
+            })
+        }
+
+        /// Parses the whole document and calls `on_triple` on each of its triples
+        pub fn parse_all(
+            &mut self,
+            on_triple: &mut impl FnMut(Triple) -> crate::Result<()>,
+        ) -> crate::Result<()> {
+            loop {
+                let line_start = self.buffer.len();
+                let is_end = self.reader.read_line(&mut self.buffer)? == 0;
+                self.splitter.read_line(&self.buffer[line_start..]);
+                if is_end || self.splitter.is_at_statement_end() {
+                    self.parse_buffer()?;
+                    for triple in self.state.triples.drain(..) {
+                        on_triple(triple)?;
+                    }
+                }
+                if is_end {
+                    return Ok(());
+                }
+            }
+        }
+
+        /// The prefixes declared in the document, in declaration order
+        pub fn prefixes(&self) -> &[(String, String)] {
+            &self.state.prefixes
+        }
+
+        fn parse_buffer(&mut self) -> crate::Result<()> {
+            let input = &self.buffer;
+            let first_line = self.buffer_first_line;
+            turtleDoc(input, &mut self.state)
+                .map_err(|error| to_syntax_error(input, first_line, &error))?;
+            if let Some((offset, message)) = &self.state.invalid_literal {
+                return Err(invalid_literal_error(input, first_line, *offset, message).into());
+            }
+            self.buffer_first_line += input.matches('\n').count();
+            self.buffer.clear();
+            Ok(())
+        }
+    }
+
+    /// Records the prefix declarations of a Turtle or TriG document read line by line
+    ///
+    /// The directives are found at the start of the statements outside of the graph blocks
+    /// by following the IRIs, strings and comments of the document.
+    /// They are then parsed with the Turtle-star grammar that reports the declared prefixes.
+    /// The malformed directives are ignored: it is the job of the document parser to report them.
+    pub struct DirectiveRecorder {
+        state: ParserState,
+        long_string_delimiter: Option<&'static [u8]>,
+        is_at_statement_start: bool,
+        graph_block_depth: usize,
+        directive: Option<String>,
+    }
+
+    impl DirectiveRecorder {
+        pub fn new(base_iri: Option<&str>) -> crate::Result<Self> {
+            Ok(Self {
+                state: ParserState::new(base_iri, false)?,
+                long_string_delimiter: None,
+                is_at_statement_start: true,
+                graph_block_depth: 0,
+                directive: None,
+            })
+        }
+
+        /// The prefixes declared in the lines already read, in declaration order
+        pub fn prefixes(&self) -> &[(String, String)] {
+            &self.state.prefixes
+        }
+
+        pub fn read_line(&mut self, line: &str) {
+            let bytes = line.as_bytes();
+            let mut i = 0;
+            while i < bytes.len() {
+                if let Some(delimiter) = self.long_string_delimiter {
+                    if bytes[i] == b'\\' {
+                        i += 2;
+                    } else if bytes[i..].starts_with(delimiter) {
+                        self.long_string_delimiter = None;
+                        i += delimiter.len();
+                    } else {
+                        i += 1;
+                    }
+                    continue;
+                }
+                if self.directive.is_some() {
+                    i = self.read_directive(line, i);
+                    continue;
+                }
+                match bytes[i] {
+                    b'#' => break,
+                    b' ' | b'\t' | b'\r' | b'\n' => i += 1,
+                    quote @ b'"' | quote @ b'\'' => {
+                        if bytes[i..].starts_with(&[quote, quote, quote]) {
+                            self.long_string_delimiter =
+                                Some(if quote == b'"' { b"\"\"\"" } else { b"\'\'\'" });
+                            i += 3;
+                        } else {
+                            i += 1;
+                            while i < bytes.len() && bytes[i] != quote {
+                                i += if bytes[i] == b'\\' { 2 } else { 1 };
+                            }
+                            i += 1;
+                        }
+                        self.is_at_statement_start = false;
+                    }
+                    b'<' if bytes.get(i + 1) != Some(&b'<') => {
+                        i = iri_end(bytes, i);
+                        self.is_at_statement_start = false;
+                    }
+                    b'{' => {
+                        self.graph_block_depth += 1;
+                        self.is_at_statement_start = true;
+                        i += 1;
+                    }
+                    b'}' => {
+                        self.graph_block_depth = self.graph_block_depth.saturating_sub(1);
+                        self.is_at_statement_start = true;
+                        i += 1;
+                    }
+                    b'.' => {
+                        self.is_at_statement_start = true;
+                        i += 1;
+                    }
+                    _ => {
+                        if self.is_at_statement_start
+                            && self.graph_block_depth == 0
+                            && is_directive_start(&line[i..])
+                        {
+                            self.directive = Some(String::default());
+                        } else {
+                            i += 1;
+                        }
+                        self.is_at_statement_start = false;
+                    }
+                }
+            }
+        }
+
+        /// Reads the directive being recorded from the byte `i` of the line and returns the position after it
+        fn read_directive(&mut self, line: &str, mut i: usize) -> usize {
+            let bytes = line.as_bytes();
+            let directive = match &mut self.directive {
+                Some(directive) => directive,
+                None => return i,
+            };
+            // The directives starting with `@` end with a `.`, the SPARQL ones with their IRI
+            let is_sparql = !directive.starts_with('@') && !line[i..].starts_with('@');
+            let end = match bytes[i] {
+                b'#' => return bytes.len(),
+                b'<' => {
+                    let end = iri_end(bytes, i);
+                    directive.push_str(&line[i..end.min(bytes.len())]);
+                    i = end;
+                    is_sparql
+                }
+                b'.' if !is_sparql => {
+                    directive.push('.');
+                    i += 1;
+                    true
+                }
+                _ => {
+                    let len = line[i..].chars().next().map_or(1, char::len_utf8);
+                    directive.push_str(&line[i..i + len]);
+                    i += len;
+                    false
+                }
+            };
+            if end {
+                if let Some(directive) = self.directive.take() {
+                    // The parsing errors are reported by the document parser
+                    let _ = directives(&directive, &mut self.state);
+                }
+                self.is_at_statement_start = true;
+            }
+            i
+        }
+    }
+
+    /// Returns the position after the IRI starting at the byte `start`
+    fn iri_end(line: &[u8], start: usize) -> usize {
+        let mut i = start;
+        while i < line.len() && line[i] != b'>' {
+            i += 1;
+        }
+        i + 1
+    }
+
+    /// Checks if the text starts with a `@prefix`, `@base`, `PREFIX` or `BASE` keyword
+    fn is_directive_start(text: &str) -> bool {
+        if text.starts_with("@prefix") || text.starts_with("@base") {
+            return true;
+        }
+        ["prefix", "base"]
+            .iter()
+            .any(|keyword| match text.get(..keyword.len()) {
+                Some(start) if start.eq_ignore_ascii_case(keyword) => {
+                    let rest = &text[keyword.len()..];
+                    rest.is_empty() || rest.starts_with(char::is_whitespace)
+                }
+                _ => false,
+            })
+    }
+
+    /// Finds the lines of a Turtle-star document ending a statement
+    ///
+    /// A statement ends with a `.` outside of the IRIs, strings and comments.
+    /// Such a `.` at the end of a line could not be a part of a number or a prefixed name.
+    #[derive(Default)]
+    struct StatementSplitter {
+        long_string_delimiter: Option<&'static [u8]>,
+        is_at_statement_end: bool,
+    }
+
+    impl StatementSplitter {
+        fn read_line(&mut self, line: &str) {
+            let line = line.as_bytes();
+            let mut i = 0;
+            while i < line.len() {
+                if let Some(delimiter) = self.long_string_delimiter {
+                    if line[i] == b'\\' {
+                        i += 2;
+                    } else if line[i..].starts_with(delimiter) {
+                        self.long_string_delimiter = None;
+                        i += delimiter.len();
+                    } else {
+                        i += 1;
+                    }
+                    continue;
+                }
+                match line[i] {
+                    b'#' => break,
+                    b' ' | b'\t' | b'\r' | b'\n' => i += 1,
+                    quote @ b'"' | quote @ b'\'' => {
+                        if line[i..].starts_with(&[quote, quote, quote]) {
+                            self.long_string_delimiter =
+                                Some(if quote == b'"' { b"\"\"\"" } else { b"'''" });
+                            i += 3;
+                        } else {
+                            i += 1;
+                            while i < line.len() && line[i] != quote {
+                                i += if line[i] == b'\\' { 2 } else { 1 };
+                            }
+                            i += 1;
+                        }
+                        self.is_at_statement_end = false;
+                    }
+                    b'<' if line.get(i + 1) != Some(&b'<') => {
+                        while i < line.len() && line[i] != b'>' {
+                            i += 1;
+                        }
+                        i += 1;
+                        self.is_at_statement_end = false;
+                    }
+                    c => {
+                        self.is_at_statement_end = c == b'.';
+                        i += 1;
+                    }
+                }
+            }
+        }
+
+        fn is_at_statement_end(&self) -> bool {
+            self.long_string_delimiter.is_none() && self.is_at_statement_end
+        }
+    }
+}
+
+pub use self::grammar::{DirectiveRecorder, TurtleStarParser};
+
+#[cfg(test)]
+fn parse(data: &str) -> crate::Result<Vec<crate::model::Triple>> {
+    let data = format!("@prefix ex: <http://example.com/> .\n{}", data);
+    let mut parser = TurtleStarParser::new(data.as_bytes(), None, false)?;
+    let mut triples = Vec::default();
+    parser.parse_all(&mut |triple| {
+        triples.push(triple);
+        Ok(())
+    })?;
+    Ok(triples)
+}
+
+#[test]
+fn test_turtle_star_parser() {
+    use crate::model::{Literal, NamedNode, Triple};
+
+    let ex = |name: &str| NamedNode::parse(format!("http://example.com/{}", name)).unwrap();
+    let abc = Triple::new(ex("a"), ex("b"), ex("c"));
+
+    // Nested quoted triples
+    assert_eq!(
+        parse("<< << ex:a ex:b ex:c >> ex:d << ex:a ex:b ex:c >> >> ex:e ex:f .").unwrap(),
+        vec![Triple::new(
+            Triple::new(abc.clone(), ex("d"), abc.clone()),
+            ex("e"),
+            ex("f")
+        )]
+    );
+
+    // Escapes are only unescaped inside of the IRIs and the strings
+    assert_eq!(
+        parse(
+            "<http://example.com/\\u0061> ex:b \"a\\u0022b\" , 'c\\\\u0041' , \"\"\"d\\U0001F600\\\"\"\"\" ."
+        )
+        .unwrap(),
+        vec![
+            Triple::new(ex("a"), ex("b"), Literal::from("a\"b")),
+            Triple::new(ex("a"), ex("b"), Literal::from("c\\u0041")),
+            Triple::new(ex("a"), ex("b"), Literal::from("d\u{1F600}\"")),
+        ]
+    );
+    assert!(parse("ex:a ex:b \"\\uD800\" .").is_err());
+
+    // Annotations
+    let abc_d_e = Triple::new(abc.clone(), ex("d"), ex("e"));
+    assert_eq!(
+        parse("ex:a ex:b ex:c {| ex:d ex:e {| ex:f ex:g |} |} .").unwrap(),
+        vec![
+            Triple::new(abc_d_e.clone(), ex("f"), ex("g")),
+            abc_d_e,
+            abc.clone()
+        ]
+    );
+
+    // Statements on several lines
+    assert_eq!(
+        parse(
+            "ex:a ex:b \"\"\"c.\n.\"\"\" ;\n ex:c ex:d.e .\nex:a ex:b ex:c.\n# .\nex:a\n ex:b 1.\n"
+        )
+        .unwrap(),
+        vec![
+            Triple::new(ex("a"), ex("b"), Literal::from("c.\n.")),
+            Triple::new(ex("a"), ex("c"), ex("d.e")),
+            abc,
+            Triple::new(ex("a"), ex("b"), Literal::from(1)),
+        ]
+    );
+
+    // The error positions are the ones in the original document
+    let error = parse("ex:a ex:b ex:c .\nex:a ex:b \"\\u0022\" ex:d .")
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("line 3 column 20"), "{}", error);
+}
@@ -30,6 +30,10 @@ pub enum GraphSyntax {
     Turtle,
     /// [RDF XML](https://www.w3.org/TR/rdf-syntax-grammar/)
     RdfXml,
+    /// [N-Triples-star](https://w3c.github.io/rdf-star/cg-spec/#n-triples-star), N-Triples with quoted triples
+    NTriplesStar,
+    /// [Turtle-star](https://w3c.github.io/rdf-star/cg-spec/#turtle-star), Turtle with quoted triples and annotations
+    TurtleStar,
 }
 
 impl FileSyntax for GraphSyntax {
@@ -38,6 +42,8 @@ impl FileSyntax for GraphSyntax {
             GraphSyntax::NTriples => "http://www.w3.org/ns/formats/N-Triples",
             GraphSyntax::Turtle => "http://www.w3.org/ns/formats/Turtle",
             GraphSyntax::RdfXml => "http://www.w3.org/ns/formats/RDF_XML",
+            GraphSyntax::NTriplesStar => "https://w3c.github.io/rdf-star/cg-spec/#n-triples-star",
+            GraphSyntax::TurtleStar => "https://w3c.github.io/rdf-star/cg-spec/#turtle-star",
         }
     }
 
@@ -46,6 +52,8 @@ impl FileSyntax for GraphSyntax {
             GraphSyntax::NTriples => "application/n-triples",
             GraphSyntax::Turtle => "text/turtle",
             GraphSyntax::RdfXml => "application/rdf+xml",
+            GraphSyntax::NTriplesStar => "application/x-n-triples-star",
+            GraphSyntax::TurtleStar => "application/x-turtlestar",
         }
     }
 
@@ -54,6 +62,8 @@ impl FileSyntax for GraphSyntax {
             GraphSyntax::NTriples => "nt",
             GraphSyntax::Turtle => "ttl",
             GraphSyntax::RdfXml => "rdf",
+            GraphSyntax::NTriplesStar => "nts",
+            GraphSyntax::TurtleStar => "ttls",
         }
     }
 
@@ -63,6 +73,8 @@ impl FileSyntax for GraphSyntax {
                 "application/n-triples" => Some(GraphSyntax::NTriples),
                 "text/turtle" => Some(GraphSyntax::Turtle),
                 "application/rdf+xml" => Some(GraphSyntax::RdfXml),
+                "application/x-n-triples-star" => Some(GraphSyntax::NTriplesStar),
+                "application/x-turtlestar" => Some(GraphSyntax::TurtleStar),
                 _ => None,
             }
         } else {
@@ -0,0 +1,241 @@
+//! Integration tests based on [RDF Dataset Canonicalization Test Cases](https://w3c.github.io/rdf-canon/tests/)
+//!
+//! A few test cases in the format of the test suite are in the `tests/rdf-canon` directory.
+//! The full suite from https://github.com/w3c/rdf-canon.git could be copied there to run it.
+use failure::format_err;
+use rio_api::model as rio;
+use rio_api::parser::QuadsParser;
+use rio_turtle::NQuadsParser;
+use rudf::model::vocab::rdf;
+use rudf::model::*;
+use rudf::{GraphSyntax, MemoryRepository, Repository, RepositoryConnection};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::Read;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+
+type Result<T> = std::result::Result<T, failure::Error>;
+
+const MANIFEST_URL: &str = "https://w3c.github.io/rdf-canon/tests/manifest.ttl";
+
+#[test]
+fn rdf_canon_w3c_testsuite() -> Result<()> {
+    let manifest = load_manifest(MANIFEST_URL)?;
+    let entries = match manifest
+        .triples_for_predicate(&mf::ENTRIES)
+        .next()
+        .map(|t| t.object())
+    {
+        Some(Term::BlankNode(list)) => rdf_list(&manifest, list.clone().into()),
+        _ => return Err(format_err!("entries not found in {}", MANIFEST_URL)),
+    };
+
+    let mut failed = Vec::default();
+    for test in entries {
+        let test = match test {
+            Term::NamedNode(test) => NamedOrBlankNode::from(test),
+            term => return Err(format_err!("invalid test {}", term)),
+        };
+        let kind = match manifest.object_for_subject_predicate(&test, &rdf::TYPE) {
+            Some(Term::NamedNode(kind)) => kind.as_str().rsplit('#').next().unwrap().to_owned(),
+            _ => return Err(format_err!("type not found for test {}", test)),
+        };
+        let is_sha256 = match manifest.object_for_subject_predicate(&test, &rdfc::HASH_ALGORITHM) {
+            Some(Term::Literal(algorithm)) => algorithm.value() == "SHA256",
+            Some(_) => false,
+            None => true,
+        };
+        if !is_sha256 {
+            continue; // Only SHA-256 is supported
+        }
+        let action = match manifest.object_for_subject_predicate(&test, &mf::ACTION) {
+            Some(Term::NamedNode(action)) => action.as_str().to_owned(),
+            _ => return Err(format_err!("action not found for test {}", test)),
+        };
+        let result = match manifest.object_for_subject_predicate(&test, &mf::RESULT) {
+            Some(Term::NamedNode(result)) => Some(result.as_str().to_owned()),
+            _ => None,
+        };
+        let (dataset, input_labels) = load_dataset(&action)?;
+
+        match kind.as_str() {
+            "RDFC10EvalTest" => {
+                let expected = read_file_to_string(&result.unwrap())?;
+                match dataset.to_canonical_nquads() {
+                    Ok(actual) => {
+                        if actual != expected {
+                            failed.push(format!(
+                                "Failure on {}.\nExpected:\n{}\nActual:\n{}\n",
+                                test, expected, actual
+                            ))
+                        }
+                    }
+                    Err(error) => failed.push(format!("Failure on {}: {}", test, error)),
+                }
+            }
+            "RDFC10MapTest" => {
+                let expected = read_json_map(&read_file_to_string(&result.unwrap())?);
+                match dataset.canonical_labels() {
+                    Ok(actual) => {
+                        let actual = actual
+                            .into_iter()
+                            .map(|(node, label)| (input_labels[&node].clone(), label))
+                            .collect::<BTreeMap<_, _>>();
+                        if expected != actual {
+                            failed.push(format!(
+                                "Failure on {}. Expected {:?}, found {:?}",
+                                test, expected, actual
+                            ))
+                        }
+                    }
+                    Err(error) => failed.push(format!("Failure on {}: {}", test, error)),
+                }
+            }
+            "RDFC10NegativeEvalTest" => {
+                if dataset.to_canonical_nquads().is_ok() {
+                    failed.push(format!("The canonicalization of {} should fail", test))
+                }
+            }
+            kind => return Err(format_err!("Not supported test kind {}", kind)),
+        }
+    }
+    assert!(
+        failed.is_empty(),
+        "{} failing tests:\n{}\n",
+        failed.len(),
+        failed.join("\n")
+    );
+    Ok(())
+}
+
+fn load_manifest(url: &str) -> Result<SimpleGraph> {
+    let repository = MemoryRepository::default();
+    let mut connection = repository.connection()?;
+    connection.load_graph(read_file(url)?, GraphSyntax::Turtle, None, Some(url))?;
+    Ok(connection
+        .quads_for_pattern(None, None, None, None)
+        .map(|q| q.map(|q| q.into_triple()))
+        .collect::<rudf::Result<_>>()?)
+}
+
+/// Loads a N-Quads dataset and returns it with the labels of its blank nodes in the file
+fn load_dataset(url: &str) -> Result<(SimpleDataset, HashMap<BlankNode, String>)> {
+    let mut dataset = SimpleDataset::default();
+    let mut nodes = HashMap::<String, BlankNode>::default();
+    NQuadsParser::new(read_file(url)?)?.parse_all(&mut |quad| -> Result<()> {
+        let mut blank_node = |label: &str| {
+            nodes
+                .entry(label.to_owned())
+                .or_insert_with(BlankNode::default)
+                .clone()
+        };
+        let subject: NamedOrBlankNode = match quad.subject {
+            rio::NamedOrBlankNode::NamedNode(node) => NamedNode::parse(node.iri)?.into(),
+            rio::NamedOrBlankNode::BlankNode(node) => blank_node(node.id).into(),
+        };
+        let object: Term = match quad.object {
+            rio::Term::NamedNode(node) => NamedNode::parse(node.iri)?.into(),
+            rio::Term::BlankNode(node) => blank_node(node.id).into(),
+            rio::Term::Literal(rio::Literal::Simple { value }) => {
+                Literal::new_simple_literal(value).into()
+            }
+            rio::Term::Literal(rio::Literal::LanguageTaggedString { value, language }) => {
+                Literal::new_language_tagged_literal(value, language).into()
+            }
+            rio::Term::Literal(rio::Literal::Typed { value, datatype }) => {
+                Literal::new_typed_literal(value, NamedNode::parse(datatype.iri)?).into()
+            }
+        };
+        let graph_name: Option<NamedOrBlankNode> = match quad.graph_name {
+            Some(rio::NamedOrBlankNode::NamedNode(node)) => {
+                Some(NamedNode::parse(node.iri)?.into())
+            }
+            Some(rio::NamedOrBlankNode::BlankNode(node)) => Some(blank_node(node.id).into()),
+            None => None,
+        };
+        dataset.insert(Quad::new(
+            subject,
+            NamedNode::parse(quad.predicate.iri)?,
+            object,
+            graph_name,
+        ));
+        Ok(())
+    })?;
+    let labels = nodes
+        .into_iter()
+        .map(|(label, node)| (node, label))
+        .collect();
+    Ok((dataset, labels))
+}
+
+/// Reads a flat JSON object of strings like `{"e0": "c14n0"}`
+fn read_json_map(json: &str) -> BTreeMap<String, String> {
+    let strings = json.split('"').skip(1).step_by(2).collect::<Vec<_>>();
+    strings
+        .chunks(2)
+        .map(|pair| (pair[0].to_owned(), pair[1].to_owned()))
+        .collect()
+}
+
+fn rdf_list(graph: &SimpleGraph, root: NamedOrBlankNode) -> Vec<Term> {
+    let mut elements = Vec::default();
+    let mut current = Some(root);
+    while let Some(node) = current {
+        elements.extend(
+            graph
+                .object_for_subject_predicate(&node, &rdf::FIRST)
+                .cloned(),
+        );
+        current = match graph.object_for_subject_predicate(&node, &rdf::REST) {
+            Some(Term::NamedNode(n)) if *n == *rdf::NIL => None,
+            Some(Term::NamedNode(n)) => Some(n.clone().into()),
+            Some(Term::BlankNode(n)) => Some(n.clone().into()),
+            _ => None,
+        };
+    }
+    elements
+}
+
+fn read_file(url: &str) -> Result<impl BufRead> {
+    let mut base_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    base_path.push("tests");
+    base_path.push(url.replace("https://w3c.github.io/rdf-canon/", "rdf-canon/"));
+
+    Ok(BufReader::new(File::open(&base_path).map_err(|e| {
+        format_err!("Opening file {} failed with {}", base_path.display(), e)
+    })?))
+}
+
+fn read_file_to_string(url: &str) -> Result<String> {
+    let mut string = String::default();
+    read_file(url)?.read_to_string(&mut string)?;
+    Ok(string)
+}
+
+mod mf {
+    use lazy_static::lazy_static;
+    use rudf::model::NamedNode;
+
+    lazy_static! {
+        pub static ref ENTRIES: NamedNode =
+            NamedNode::parse("http://www.w3.org/2001/sw/DataAccess/tests/test-manifest#entries")
+                .unwrap();
+        pub static ref ACTION: NamedNode =
+            NamedNode::parse("http://www.w3.org/2001/sw/DataAccess/tests/test-manifest#action")
+                .unwrap();
+        pub static ref RESULT: NamedNode =
+            NamedNode::parse("http://www.w3.org/2001/sw/DataAccess/tests/test-manifest#result")
+                .unwrap();
+    }
+}
+
+mod rdfc {
+    use lazy_static::lazy_static;
+    use rudf::model::NamedNode;
+
+    lazy_static! {
+        pub static ref HASH_ALGORITHM: NamedNode =
+            NamedNode::parse("https://w3c.github.io/rdf-canon/tests/vocab#hashAlgorithm").unwrap();
+    }
+}
@@ -1,19 +1,36 @@
+use clap::value_t;
 use clap::App;
 use clap::Arg;
 use clap::ArgMatches;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 use rouille::input::priority_header_preferred;
 use rouille::url::form_urlencoded;
-use rouille::{content_encoding, start_server, Request, Response};
+use rouille::{content_encoding, start_server, Request, Response, ResponseBody};
+use rudf::model::Skolemizer;
 use rudf::sparql::QueryResult;
-use rudf::sparql::{PreparedQuery, QueryResultSyntax};
+use rudf::sparql::{PreparedQuery, QueryOptions, QueryPlan, QueryResultSyntax};
 use rudf::{
-    DatasetSyntax, FileSyntax, GraphSyntax, MemoryRepository, Repository, RepositoryConnection,
-    RocksDbRepository,
+    DatasetSyntax, Error, FileSyntax, GraphSyntax, MemoryRepository, Repository,
+    RepositoryConnection, RocksDbRepository, SyntaxError,
 };
-use std::io::{BufReader, Read};
-use std::sync::Arc;
+use std::cmp::min;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::process;
+use std::str::Chars;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
 
 const MAX_SPARQL_BODY_SIZE: u64 = 1_048_576;
+const CHUNK_SIZE: usize = 65_536;
+const MAX_PENDING_CHUNKS: usize = 16;
+const DEFAULT_QUERY_CACHE_SIZE: usize = 1024;
+const MAX_RUNNING_QUERIES: usize = 64;
 const HTML_ROOT_PAGE: &str = include_str!("../templates/query.html");
 const SERVER: &str = concat!("Rudf/", env!("CARGO_PKG_VERSION"));
 
@@ -34,195 +51,918 @@ pub fn main() {
                 .help("Directory in which persist the data. By default data are kept in memory.")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("skolemize")
+                .long("skolemize")
+                .help("Replaces the blank nodes of the loaded data by Skolem IRIs in the /.well-known/genid/ namespace of the given base IRI")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("threads")
+                .long("threads")
+                .help("Evaluates the queries in parallel using a pool of this number of threads shared by all the queries. By default each query is evaluated in a single thread.")
+                .takes_value(true)
+                .validator(validate_count),
+        )
+        .arg(
+            Arg::with_name("memory-limit")
+                .long("memory-limit")
+                .help("Number of bytes each ORDER BY, DISTINCT or GROUP BY of a query could keep in memory before writing its tuples to temporary files. By default there is no limit.")
+                .takes_value(true)
+                .validator(validate_count),
+        )
+        .arg(
+            Arg::with_name("query-cache-size")
+                .long("query-cache-size")
+                .help("Number of parsed and planned queries kept in memory to evaluate again the same queries without parsing and planning them. By default 1024. 0 disables the cache.")
+                .takes_value(true)
+                .validator(validate_count),
+        )
+        .arg(
+            Arg::with_name("base")
+                .long("base")
+                .help("Base IRI used to resolve the relative IRIs of the queries")
+                .takes_value(true),
+        )
         .get_matches();
 
     let file = matches.value_of("file").map(|v| v.to_string());
-    if let Some(file) = file {
-        main_with_dataset(Arc::new(RocksDbRepository::open(file).unwrap()), &matches)
+    let result = if let Some(file) = file {
+        RocksDbRepository::open(file)
+            .and_then(|repository| main_with_dataset(Arc::new(repository), &matches))
     } else {
         main_with_dataset(Arc::new(MemoryRepository::default()), &matches)
+    };
+    if let Err(error) = result {
+        eprintln!("{}", error);
+        process::exit(1);
     }
 }
 
-fn main_with_dataset<R: Send + Sync + 'static>(repository: Arc<R>, matches: &ArgMatches)
+fn main_with_dataset<R: Send + Sync + 'static>(
+    repository: Arc<R>,
+    matches: &ArgMatches,
+) -> Result<(), Error>
 where
     for<'a> &'a R: Repository,
 {
     let addr = matches.value_of("bind").unwrap().to_owned();
+    let skolemizer = match matches.value_of("skolemize") {
+        Some(base_iri) => {
+            let skolemizer = Skolemizer::new(base_iri)?;
+            repository.connection()?.enable_skolemization(&skolemizer)?;
+            Some(skolemizer)
+        }
+        None => None,
+    };
+    let settings = QuerySettings {
+        thread_pool: match value_t!(matches, "threads", usize) {
+            Ok(threads) => Some(Arc::new(
+                ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .map_err(|error| io::Error::new(io::ErrorKind::Other, error))?,
+            )),
+            Err(_) => None,
+        },
+        memory_limit: value_t!(matches, "memory-limit", usize).ok(),
+        base_iri: matches.value_of("base").map(|base_iri| base_iri.to_owned()),
+        plan_cache: Arc::new(QueryPlanCache::new(
+            value_t!(matches, "query-cache-size", usize).unwrap_or(DEFAULT_QUERY_CACHE_SIZE),
+        )),
+        skolemizer,
+        workers: Arc::default(),
+    };
     println!("Listening for requests at http://{}", &addr);
 
     start_server(addr.to_string(), move |request| {
         content_encoding::apply(
             request,
-            handle_request(request, repository.connection().unwrap(), &addr),
+            handle_request(request, repository.clone(), &addr, &settings),
         )
         .with_unique_header("Server", SERVER)
     })
 }
 
-fn handle_request<R: RepositoryConnection>(
-    request: &Request,
-    mut connection: R,
-    host: &str,
-) -> Response {
-    match (request.url().as_str(), request.method()) {
-        ("/", "GET") => {
-            Response::html(HTML_ROOT_PAGE.replace("{{endpoint}}", &format!("//{}/query", host)))
+/// Checks that the value of a command line argument is a number
+///
+/// clap exits with the usage message if it is not.
+fn validate_count(value: String) -> Result<(), String> {
+    value
+        .parse::<usize>()
+        .map(|_| ())
+        .map_err(|_| format!("{} is not a non-negative integer", value))
+}
+
+/// The settings shared by all the query evaluations
+#[derive(Clone, Default)]
+struct QuerySettings {
+    thread_pool: Option<Arc<ThreadPool>>,
+    memory_limit: Option<usize>,
+    base_iri: Option<String>,
+    plan_cache: Arc<QueryPlanCache>,
+    /// Maps the Skolem IRIs of the results back to blank nodes
+    skolemizer: Option<Skolemizer>,
+    workers: Arc<QueryWorkers>,
+}
+
+impl QuerySettings {
+    fn options(&self) -> QueryOptions<'_> {
+        let mut options = QueryOptions::default();
+        if let Some(thread_pool) = &self.thread_pool {
+            options = options.with_thread_pool(thread_pool);
         }
-        ("/", "POST") => {
-            if let Some(body) = request.data() {
-                if let Some(content_type) = request.header("Content-Type") {
-                    match if let Some(format) = GraphSyntax::from_mime_type(content_type) {
-                        connection.load_graph(BufReader::new(body), format, None, None)
-                    } else if let Some(format) = DatasetSyntax::from_mime_type(content_type) {
-                        connection.load_dataset(BufReader::new(body), format, None)
-                    } else {
-                        return Response::text(format!(
-                            "No supported content Content-Type given: {}",
-                            content_type
-                        ))
-                        .with_status_code(415);
-                    } {
-                        Ok(()) => Response::empty_204(),
-                        Err(error) => Response::text(error.to_string()).with_status_code(400),
-                    }
-                } else {
-                    Response::text("No Content-Type given").with_status_code(400)
+        if let Some(memory_limit) = self.memory_limit {
+            options = options.with_memory_limit(memory_limit);
+        }
+        options
+    }
+}
+
+/// The threads evaluating the queries
+///
+/// A new thread is started only when all the other ones are busy, and then waits for the next queries.
+/// There are at most `capacity` threads: the queries received when they are all busy wait for one of them.
+struct QueryWorkers {
+    capacity: usize,
+    state: Mutex<QueryWorkersState>,
+    queued: Condvar,
+}
+
+#[derive(Default)]
+struct QueryWorkersState {
+    jobs: VecDeque<Box<dyn FnOnce() + Send>>,
+    threads: usize,
+    idle: usize,
+}
+
+impl QueryWorkers {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::default(),
+            queued: Condvar::new(),
+        }
+    }
+
+    /// Queues the job and starts a new thread for it if there are not enough idle threads
+    fn execute(self: &Arc<Self>, job: impl FnOnce() + Send + 'static) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.jobs.push_back(Box::new(job));
+        if state.jobs.len() > state.idle && state.threads < self.capacity {
+            let workers = self.clone();
+            match thread::Builder::new()
+                .name("query".to_owned())
+                .spawn(move || workers.work())
+            {
+                Ok(_) => state.threads += 1,
+                Err(error) => {
+                    if state.threads == 0 {
+                        state.jobs.pop_back();
+                        return Err(error);
+                    } // Else the job waits for one of the existing threads
                 }
+            }
+        }
+        self.queued.notify_one();
+        Ok(())
+    }
+
+    fn work(&self) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(job) = state.jobs.pop_front() {
+                drop(state);
+                // A panicking query should not stop the thread
+                let _ = catch_unwind(AssertUnwindSafe(job));
+                state = self.state.lock().unwrap();
             } else {
-                Response::text("No content given").with_status_code(400)
+                state.idle += 1;
+                state = self.queued.wait(state).unwrap();
+                state.idle -= 1;
             }
         }
-        ("/query", "GET") => evaluate_urlencoded_sparql_query(
-            connection,
-            request.raw_query_string().as_bytes(),
-            request,
-        ),
-        ("/query", "POST") => {
-            if let Some(body) = request.data() {
-                if let Some(content_type) = request.header("Content-Type") {
-                    if content_type.starts_with("application/sparql-query") {
-                        let mut buffer = String::default();
-                        body.take(MAX_SPARQL_BODY_SIZE)
-                            .read_to_string(&mut buffer)
-                            .unwrap();
-                        evaluate_sparql_query(connection, &buffer, request)
-                    } else if content_type.starts_with("application/x-www-form-urlencoded") {
-                        let mut buffer = Vec::default();
-                        body.take(MAX_SPARQL_BODY_SIZE)
-                            .read_to_end(&mut buffer)
-                            .unwrap();
-                        evaluate_urlencoded_sparql_query(connection, &buffer, request)
-                    } else {
-                        Response::text(format!(
-                            "No supported content Content-Type given: {}",
-                            content_type
-                        ))
-                        .with_status_code(415)
-                    }
+    }
+}
+
+impl Default for QueryWorkers {
+    fn default() -> Self {
+        Self::new(MAX_RUNNING_QUERIES)
+    }
+}
+
+/// A least recently used cache of the parsed and planned queries
+///
+/// The queries are identified by their normalized text (see `normalize_query`) and their base IRI.
+struct QueryPlanCache {
+    capacity: usize,
+    entries: Mutex<QueryPlanCacheEntries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+type QueryPlanKey = (String, Option<String>);
+
+#[derive(Default)]
+struct QueryPlanCacheEntries {
+    plans: HashMap<QueryPlanKey, (QueryPlan, u64)>,
+    /// The keys of `plans` by their last use
+    uses: BTreeMap<u64, QueryPlanKey>,
+    clock: u64,
+}
+
+impl QueryPlanCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::default(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the plan of the query from the cache or parses and plans it
+    ///
+    /// Parsing and planning happen outside of the lock so that they do not block the other queries.
+    fn plan(&self, query: &str, base_iri: Option<&str>) -> Result<QueryPlan, Error> {
+        let key = (normalize_query(query), base_iri.map(|iri| iri.to_owned()));
+        if let Some(plan) = self.entries.lock().unwrap().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(plan);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let plan = QueryPlan::parse(query, base_iri)?;
+        if self.capacity > 0 {
+            self.entries
+                .lock()
+                .unwrap()
+                .insert(key, plan.clone(), self.capacity);
+        }
+        Ok(plan)
+    }
+
+    /// Drops all the plans
+    fn clear(&self) {
+        *self.entries.lock().unwrap() = QueryPlanCacheEntries::default();
+    }
+
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"capacity\":{},\"size\":{},\"hits\":{},\"misses\":{}}}",
+            self.capacity,
+            self.entries.lock().unwrap().plans.len(),
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed)
+        )
+    }
+}
+
+impl Default for QueryPlanCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_QUERY_CACHE_SIZE)
+    }
+}
+
+impl QueryPlanCacheEntries {
+    fn get(&mut self, key: &QueryPlanKey) -> Option<QueryPlan> {
+        self.clock += 1;
+        let (plan, last_use) = self.plans.get_mut(key)?;
+        self.uses.remove(last_use);
+        *last_use = self.clock;
+        self.uses.insert(self.clock, key.clone());
+        Some(plan.clone())
+    }
+
+    fn insert(&mut self, key: QueryPlanKey, plan: QueryPlan, capacity: usize) {
+        self.clock += 1;
+        if let Some((_, last_use)) = self.plans.insert(key.clone(), (plan, self.clock)) {
+            // Another request has planned the same query in the meantime
+            self.uses.remove(&last_use);
+        }
+        self.uses.insert(self.clock, key);
+        while self.plans.len() > capacity {
+            match self.uses.pop_first() {
+                Some((_, key)) => {
+                    self.plans.remove(&key);
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+/// Collapses the whitespaces and removes the comments of a query outside of its IRIs and strings
+///
+/// Two queries with the same normalized text are always the same query.
+fn normalize_query(query: &str) -> String {
+    let mut normalized = String::with_capacity(query.len());
+    let mut chars = query.chars();
+    let mut separated = false;
+    while let Some(c) = chars.clone().next() {
+        if c.is_whitespace() {
+            chars.next();
+            separated = true;
+            continue;
+        }
+        if c == '#' {
+            chars.by_ref().find(|c| *c == '\n' || *c == '\r');
+            separated = true;
+            continue;
+        }
+        if separated && !normalized.is_empty() {
+            normalized.push(' ');
+        }
+        separated = false;
+        match c {
+            '<' => {
+                let input = chars.as_str();
+                let iri = iri_ref_len(input);
+                let line_end = input[iri..]
+                    .find(&['\n', '\r'][..])
+                    .map_or(input.len(), |i| iri + i);
+                if input[..iri].contains('#') && input[iri..line_end].contains(&['"', '\''][..]) {
+                    // It might also be a "<" operator followed by a comment and then the rest of
+                    // the line would not contain the start of a string
+                    copy_verbatim(&mut chars, input.len(), &mut normalized);
                 } else {
-                    Response::text("No Content-Type given").with_status_code(400)
+                    copy_verbatim(&mut chars, iri.max(1), &mut normalized);
                 }
-            } else {
-                Response::text("No content given").with_status_code(400)
             }
+            '"' | '\'' => {
+                let delimiter = if chars.as_str().starts_with(&[c, c, c][..]) {
+                    &chars.as_str()[..3]
+                } else {
+                    &chars.as_str()[..1]
+                };
+                let len = string_literal_len(chars.as_str(), delimiter);
+                copy_verbatim(&mut chars, len, &mut normalized);
+            }
+            '\\' => copy_verbatim(&mut chars, 2, &mut normalized),
+            _ => copy_verbatim(&mut chars, 1, &mut normalized),
+        }
+    }
+    normalized
+}
+
+/// Returns the length in bytes of the IRIREF at the beginning of `input` or 0 if there is none
+fn iri_ref_len(input: &str) -> usize {
+    for (i, c) in input.char_indices().skip(1) {
+        match c {
+            '>' => return i + 1,
+            '<' | '"' | '{' | '}' | '|' | '^' | '`' | '\\' => return 0,
+            c if c <= ' ' => return 0,
+            _ => (),
         }
-        _ => Response::empty_404(),
     }
+    0
 }
 
-fn evaluate_urlencoded_sparql_query<R: RepositoryConnection>(
-    connection: R,
+/// Returns the length in bytes of the string literal delimited by `delimiter` at the beginning of `input`
+///
+/// If the literal is not closed its length is the one of `input`.
+fn string_literal_len(input: &str, delimiter: &str) -> usize {
+    let mut i = delimiter.len();
+    while i < input.len() {
+        if input[i..].starts_with('\\') {
+            i += 1 + input[i + 1..].chars().next().map_or(0, char::len_utf8);
+        } else if input[i..].starts_with(delimiter) {
+            return i + delimiter.len();
+        } else {
+            i += input[i..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    input.len()
+}
+
+/// Moves the `len` first bytes of `chars` (or less if they are not that many) to `output`
+fn copy_verbatim(chars: &mut Chars<'_>, len: usize, output: &mut String) {
+    let input = chars.as_str();
+    let mut len = len.min(input.len());
+    while !input.is_char_boundary(len) {
+        len += 1;
+    }
+    output.push_str(&input[..len]);
+    *chars = input[len..].chars();
+}
+
+fn handle_request<R: Send + Sync + 'static>(
+    request: &Request,
+    repository: Arc<R>,
+    host: &str,
+    settings: &QuerySettings,
+) -> Response
+where
+    for<'a> &'a R: Repository,
+{
+    match (request.url().as_str(), request.method()) {
+        ("/", "GET") => get_ui(&repository, host),
+        ("/", "POST") => load_data(&repository, request, &settings.plan_cache),
+        ("/query", "GET") => evaluate_urlencoded_sparql_query(
+            repository.clone(),
+            request.raw_query_string().as_bytes(),
+            request,
+            settings,
+        ),
+        ("/query", "POST") => evaluate_posted_sparql_query(repository.clone(), request, settings),
+        ("/query-cache", "GET") => Ok(Response::from_data(
+            "application/json",
+            settings.plan_cache.to_json(),
+        )),
+        _ => Ok(Response::empty_404()),
+    }
+    .unwrap_or_else(|error| error.into())
+}
+
+fn get_ui<R>(repository: &R, host: &str) -> Result<Response, HttpError>
+where
+    for<'a> &'a R: Repository,
+{
+    let namespaces = repository
+        .connection()
+        .and_then(|connection| connection.namespaces())
+        .map_err(HttpError::Storage)?;
+    Ok(Response::html(
+        HTML_ROOT_PAGE
+            .replace("{{endpoint}}", &format!("//{}/query", host))
+            .replace("{{query}}", &to_js_string(&default_query(&namespaces))),
+    ))
+}
+
+fn load_data<R>(
+    repository: &R,
+    request: &Request,
+    plan_cache: &QueryPlanCache,
+) -> Result<Response, HttpError>
+where
+    for<'a> &'a R: Repository,
+{
+    let body = request
+        .data()
+        .ok_or_else(|| HttpError::BadRequest("No content given".to_owned()))?;
+    let content_type = request
+        .header("Content-Type")
+        .ok_or_else(|| HttpError::BadRequest("No Content-Type given".to_owned()))?;
+    let mut connection = repository.connection().map_err(HttpError::Storage)?;
+    let result = if let Some(format) = GraphSyntax::from_mime_type(content_type) {
+        connection.load_graph(BufReader::new(body), format, None, None)
+    } else if let Some(format) = DatasetSyntax::from_mime_type(content_type) {
+        connection.load_dataset(BufReader::new(body), format, None)
+    } else {
+        return Err(HttpError::UnsupportedMediaType(content_type.to_owned()));
+    };
+    // Even a failed load might have changed the data: the plans built for the previous data are dropped
+    plan_cache.clear();
+    result.map_err(HttpError::from_parse_error)?;
+    Ok(Response::empty_204())
+}
+
+fn evaluate_posted_sparql_query<R: Send + Sync + 'static>(
+    repository: Arc<R>,
+    request: &Request,
+    settings: &QuerySettings,
+) -> Result<Response, HttpError>
+where
+    for<'a> &'a R: Repository,
+{
+    let body = request
+        .data()
+        .ok_or_else(|| HttpError::BadRequest("No content given".to_owned()))?;
+    let content_type = request
+        .header("Content-Type")
+        .ok_or_else(|| HttpError::BadRequest("No Content-Type given".to_owned()))?;
+    if content_type.starts_with("application/sparql-query") {
+        let mut buffer = String::default();
+        body.take(MAX_SPARQL_BODY_SIZE)
+            .read_to_string(&mut buffer)
+            .map_err(HttpError::Io)?;
+        evaluate_sparql_query(repository, buffer, request, settings)
+    } else if content_type.starts_with("application/x-www-form-urlencoded") {
+        let mut buffer = Vec::default();
+        body.take(MAX_SPARQL_BODY_SIZE)
+            .read_to_end(&mut buffer)
+            .map_err(HttpError::Io)?;
+        evaluate_urlencoded_sparql_query(repository, &buffer, request, settings)
+    } else {
+        Err(HttpError::UnsupportedMediaType(content_type.to_owned()))
+    }
+}
+
+fn evaluate_urlencoded_sparql_query<R: Send + Sync + 'static>(
+    repository: Arc<R>,
     encoded: &[u8],
     request: &Request,
-) -> Response {
+    settings: &QuerySettings,
+) -> Result<Response, HttpError>
+where
+    for<'a> &'a R: Repository,
+{
     if let Some((_, query)) = form_urlencoded::parse(encoded).find(|(k, _)| k == "query") {
-        evaluate_sparql_query(connection, &query, request)
+        evaluate_sparql_query(repository, query.into_owned(), request, settings)
     } else {
-        Response::text("You should set the 'query' parameter").with_status_code(400)
+        Err(HttpError::BadRequest(
+            "You should set the 'query' parameter".to_owned(),
+        ))
     }
 }
 
-fn evaluate_sparql_query<R: RepositoryConnection>(
-    connection: R,
-    query: &str,
+/// Evaluates the query in one of the query threads and streams the serialized results into the response body
+///
+/// The evaluation stops as soon as the response body is dropped i.e. when the client disconnects.
+/// If the evaluation fails after the beginning of the response, the body is ended with an I/O error.
+fn evaluate_sparql_query<R: Send + Sync + 'static>(
+    repository: Arc<R>,
+    query: String,
     request: &Request,
-) -> Response {
-    //TODO: stream
-    match connection.prepare_query(query, None) {
-        Ok(query) => {
-            let results = query.exec().unwrap();
-            if let QueryResult::Graph(_) = results {
-                let supported_formats = [
-                    GraphSyntax::NTriples.media_type(),
-                    GraphSyntax::Turtle.media_type(),
-                    GraphSyntax::RdfXml.media_type(),
-                ];
-                let format = if let Some(accept) = request.header("Accept") {
-                    if let Some(media_type) =
-                        priority_header_preferred(accept, supported_formats.iter().cloned())
-                            .and_then(|p| GraphSyntax::from_mime_type(supported_formats[p]))
-                    {
-                        media_type
-                    } else {
-                        return Response::text(format!(
-                            "No supported Accept given: {}. Supported format: {:?}",
-                            accept, supported_formats
-                        ))
-                        .with_status_code(415);
-                    }
+    settings: &QuerySettings,
+) -> Result<Response, HttpError>
+where
+    for<'a> &'a R: Repository,
+{
+    let accept = request.header("Accept").map(|accept| accept.to_owned());
+    let workers = settings.workers.clone();
+    let settings = settings.clone();
+    let (head_sender, head_receiver) = sync_channel(1);
+    let (body_sender, body_receiver) = sync_channel(MAX_PENDING_CHUNKS);
+    workers
+        .execute(move || {
+            let mut head = Some(head_sender);
+            let error_sender = body_sender.clone();
+            if let Err(error) = write_query_results(
+                &*repository,
+                &query,
+                accept.as_deref(),
+                &settings,
+                &mut head,
+                body_sender,
+            ) {
+                // If the response has already started, the body is ended with the error
+                if let Some(head) = head {
+                    let _ = head.send(Err(error));
                 } else {
-                    GraphSyntax::NTriples
-                };
+                    let _ = error_sender
+                        .send(Err(io::Error::new(io::ErrorKind::Other, error.to_string())));
+                }
+            }
+        })
+        .map_err(|error| HttpError::Evaluation(error.into()))?;
 
-                Response::from_data(
-                    format.media_type(),
-                    results.write_graph(Vec::default(), format).unwrap(),
-                )
-            } else {
-                let supported_formats = [
-                    QueryResultSyntax::Xml.media_type(),
-                    QueryResultSyntax::Json.media_type(),
-                ];
-                let format = if let Some(accept) = request.header("Accept") {
-                    if let Some(media_type) =
-                        priority_header_preferred(accept, supported_formats.iter().cloned())
-                            .and_then(|p| QueryResultSyntax::from_mime_type(supported_formats[p]))
-                    {
-                        media_type
-                    } else {
-                        return Response::text(format!(
-                            "No supported Accept given: {}. Supported format: {:?}",
-                            accept, supported_formats
-                        ))
-                        .with_status_code(415);
-                    }
-                } else {
-                    QueryResultSyntax::Json
-                };
+    match head_receiver.recv() {
+        Ok(media_type) => {
+            let mut response = Response::from_data(media_type?, Vec::default());
+            response.data = ResponseBody::from_reader(ChannelReader::new(body_receiver));
+            Ok(response)
+        }
+        Err(_) => Ok(Response::text("The query evaluation failed").with_status_code(500)),
+    }
+}
+
+/// Evaluates the query, sends the results media type to `head` and then writes the results into `body`
+fn write_query_results<R>(
+    repository: &R,
+    query: &str,
+    accept: Option<&str>,
+    settings: &QuerySettings,
+    head: &mut Option<SyncSender<Result<&'static str, HttpError>>>,
+    body: SyncSender<io::Result<Vec<u8>>>,
+) -> Result<(), HttpError>
+where
+    for<'a> &'a R: Repository,
+{
+    let connection = repository.connection().map_err(HttpError::Storage)?;
+    let plan = settings
+        .plan_cache
+        .plan(query, settings.base_iri.as_deref())
+        .map_err(HttpError::from_parse_error)?;
+    let query = connection
+        .prepare_query_plan(&plan, settings.options())
+        .map_err(HttpError::Evaluation)?;
+    let mut results = query
+        .exec(&BTreeMap::default())
+        .map_err(HttpError::Evaluation)?;
+    if let Some(skolemizer) = &settings.skolemizer {
+        results = results.deskolemize(skolemizer.clone());
+    }
+    let body = BufWriter::with_capacity(CHUNK_SIZE, ChannelWriter::new(body));
+    if let QueryResult::Graph(_) = results {
+        let format = content_negotiation(
+            accept,
+            &[
+                GraphSyntax::NTriples.media_type(),
+                GraphSyntax::Turtle.media_type(),
+                GraphSyntax::RdfXml.media_type(),
+                GraphSyntax::NTriplesStar.media_type(),
+                GraphSyntax::TurtleStar.media_type(),
+            ],
+            GraphSyntax::from_mime_type,
+        )?
+        .unwrap_or(GraphSyntax::NTriples);
+        send_head(head, format.media_type());
+        results
+            .write_graph(body, format)
+            .and_then(|mut body| Ok(body.flush()?))
+            .map_err(HttpError::Evaluation)?;
+    } else {
+        let format = content_negotiation(
+            accept,
+            &[
+                QueryResultSyntax::Xml.media_type(),
+                QueryResultSyntax::Json.media_type(),
+            ],
+            QueryResultSyntax::from_mime_type,
+        )?
+        .unwrap_or(QueryResultSyntax::Json);
+        send_head(head, format.media_type());
+        results
+            .write(body, format)
+            .and_then(|mut body| Ok(body.flush()?))
+            .map_err(HttpError::Evaluation)?;
+    }
+    Ok(())
+}
+
+fn send_head(
+    head: &mut Option<SyncSender<Result<&'static str, HttpError>>>,
+    media_type: &'static str,
+) {
+    if let Some(head) = head.take() {
+        let _ = head.send(Ok(media_type));
+    }
+}
+
+/// Returns the format to use according to the `Accept` header if there is one
+fn content_negotiation<F>(
+    accept: Option<&str>,
+    supported: &[&str],
+    parse: impl Fn(&str) -> Option<F>,
+) -> Result<Option<F>, HttpError> {
+    if let Some(accept) = accept {
+        if let Some(format) = priority_header_preferred(accept, supported.iter().cloned())
+            .and_then(|p| parse(supported[p]))
+        {
+            Ok(Some(format))
+        } else {
+            Err(HttpError::NotAcceptable(format!(
+                "No supported Accept given: {}. Supported format: {:?}",
+                accept, supported
+            )))
+        }
+    } else {
+        Ok(None)
+    }
+}
+
+/// An error raised while handling a request
+#[derive(Debug)]
+enum HttpError {
+    /// The request is malformed e.g. its body is missing
+    BadRequest(String),
+    /// The request body could not be read
+    Io(io::Error),
+    /// The SPARQL query or the data to load could not be parsed
+    Parse(Error),
+    /// The query evaluation failed
+    Evaluation(Error),
+    /// The repository failed
+    Storage(Error),
+    /// The request relies on a feature that is not supported
+    NotImplemented(Error),
+    /// The request Content-Type is not supported
+    UnsupportedMediaType(String),
+    /// None of the media types of the Accept header is supported
+    NotAcceptable(String),
+}
+
+impl HttpError {
+    /// Only the syntax errors are caused by the request, the other errors of the parsing steps are server failures
+    fn from_parse_error(error: Error) -> Self {
+        match error {
+            Error::Syntax(_) => HttpError::Parse(error),
+            Error::Io(error) => HttpError::Io(error),
+            Error::Evaluation(_) => HttpError::Evaluation(error),
+            Error::Storage(_) => HttpError::Storage(error),
+            Error::Unsupported(_) => HttpError::NotImplemented(error),
+        }
+    }
+
+    fn status_code(&self) -> u16 {
+        match self {
+            HttpError::BadRequest(_) | HttpError::Io(_) | HttpError::Parse(_) => 400,
+            HttpError::Evaluation(_) | HttpError::Storage(_) => 500,
+            HttpError::NotImplemented(_) => 501,
+            HttpError::UnsupportedMediaType(_) => 415,
+            HttpError::NotAcceptable(_) => 406,
+        }
+    }
+}
 
-                Response::from_data(
-                    format.media_type(),
-                    results.write(Vec::default(), format).unwrap(),
-                )
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpError::BadRequest(message) | HttpError::NotAcceptable(message) => {
+                write!(f, "{}", message)
             }
+            HttpError::Io(error) => write!(f, "The request could not be read: {}", error),
+            HttpError::Parse(error) => write!(f, "{}", error),
+            HttpError::Evaluation(error) => write!(f, "The query evaluation failed: {}", error),
+            HttpError::Storage(error) => write!(f, "The repository failed: {}", error),
+            HttpError::NotImplemented(error) => write!(f, "{}", error),
+            HttpError::UnsupportedMediaType(content_type) => write!(
+                f,
+                "No supported content Content-Type given: {}",
+                content_type
+            ),
+        }
+    }
+}
+
+impl From<HttpError> for Response {
+    fn from(error: HttpError) -> Self {
+        let status_code = error.status_code();
+        if let HttpError::Parse(Error::Syntax(error)) = error {
+            // Structured so that the UI is able to highlight the error location
+            Response::from_data("application/json", syntax_error_to_json(&error))
+        } else {
+            Response::text(error.to_string())
         }
-        Err(error) => Response::text(error.to_string()).with_status_code(400),
+        .with_status_code(status_code)
     }
 }
 
+/// Serializes the syntax error to JSON
+///
+/// The line and the column start from 1 like in the error message.
+fn syntax_error_to_json(error: &SyntaxError) -> String {
+    let mut json = format!("{{\"message\":{}", to_js_string(error.message()));
+    if let Some(position) = error.position() {
+        json.push_str(&format!(
+            ",\"line\":{},\"column\":{}",
+            position.line() + 1,
+            position.column() + 1
+        ));
+    }
+    json.push_str(",\"expected\":[");
+    for (i, expected) in error.expected().iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        json.push_str(&to_js_string(expected));
+    }
+    json.push(']');
+    if let Some(snippet) = error.snippet() {
+        json.push_str(&format!(",\"snippet\":{}", to_js_string(snippet)));
+    }
+    json.push('}');
+    json
+}
+
+/// A writer sending each written buffer to a `ChannelReader`
+///
+/// Writes fail when the reader has been dropped.
+struct ChannelWriter {
+    sender: SyncSender<io::Result<Vec<u8>>>,
+}
+
+impl ChannelWriter {
+    fn new(sender: SyncSender<io::Result<Vec<u8>>>) -> Self {
+        Self { sender }
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.sender.send(Ok(buf.to_vec())).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "The response body has been dropped",
+            )
+        })?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A reader returning the chunks sent by a `ChannelWriter`
+struct ChannelReader {
+    receiver: Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl ChannelReader {
+    fn new(receiver: Receiver<io::Result<Vec<u8>>>) -> Self {
+        Self {
+            receiver,
+            chunk: Vec::default(),
+            position: 0,
+        }
+    }
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position >= self.chunk.len() {
+            match self.receiver.recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk?;
+                    self.position = 0;
+                }
+                Err(_) => return Ok(0), // The writer is done
+            }
+        }
+        let len = min(buf.len(), self.chunk.len() - self.position);
+        buf[..len].copy_from_slice(&self.chunk[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+/// Builds the query displayed by default in the UI with the repository namespaces
+fn default_query(namespaces: &BTreeMap<String, String>) -> String {
+    let mut query = String::default();
+    for (prefix, namespace) in namespaces {
+        query.push_str(&format!("PREFIX {}: <{}>\n", prefix, namespace));
+    }
+    query.push_str("SELECT * WHERE {\n  ?s ?p ?o\n} LIMIT 10");
+    query
+}
+
+fn to_js_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '<' => result.push_str("\\u003C"),
+            c if c.is_control() => result.push_str(&format!("\\u{:04X}", u32::from(c))),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::handle_request;
+    use crate::{
+        handle_request, normalize_query, validate_count, HttpError, QueryPlanCache, QuerySettings,
+        QueryWorkers,
+    };
     use rouille::Request;
-    use rudf::{MemoryRepository, Repository};
+    use rudf::model::Skolemizer;
+    use rudf::{Error, MemoryRepository, Repository, RepositoryConnection};
     use std::io::Read;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc::channel;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
 
     #[test]
     fn get_ui() {
         exec(Request::fake_http("GET", "/", vec![], vec![]))
     }
 
+    #[test]
+    fn validate_counts() {
+        assert!(validate_count("4".to_owned()).is_ok());
+        assert!(validate_count("0".to_owned()).is_ok());
+        assert!(validate_count("abc".to_owned()).is_err());
+        assert!(validate_count("-1".to_owned()).is_err());
+    }
+
+    #[test]
+    fn query_workers_are_bounded() {
+        let workers = Arc::new(QueryWorkers::new(2));
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let (sender, receiver) = channel();
+        for _ in 0..10 {
+            let running = running.clone();
+            let max_running = max_running.clone();
+            let sender = sender.clone();
+            workers
+                .execute(move || {
+                    let current = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_running.fetch_max(current, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(10));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    sender.send(()).unwrap();
+                })
+                .unwrap();
+        }
+        for _ in 0..10 {
+            receiver.recv().unwrap();
+        }
+        assert!(max_running.load(Ordering::SeqCst) <= 2);
+        assert!(workers.state.lock().unwrap().threads <= 2);
+    }
+
     #[test]
     fn get_query() {
         exec(Request::fake_http(
@@ -249,11 +989,279 @@ mod tests {
         ))
     }
 
+    #[test]
+    fn post_ask_query_streams_results() {
+        let request = Request::fake_http(
+            "POST",
+            "/query",
+            vec![(
+                "Content-Type".to_string(),
+                "application/sparql-query".to_string(),
+            )],
+            b"ASK { }".to_vec(),
+        );
+        let response = handle_request(
+            &request,
+            Arc::new(MemoryRepository::default()),
+            "localhost",
+            &QuerySettings::default(),
+        );
+        let mut body = String::default();
+        response
+            .data
+            .into_reader_and_size()
+            .0
+            .read_to_string(&mut body)
+            .unwrap();
+        assert_eq!(body, "{\"head\":{},\"boolean\":true}");
+    }
+
+    #[test]
+    fn post_invalid_query() {
+        let request = Request::fake_http(
+            "POST",
+            "/query",
+            vec![(
+                "Content-Type".to_string(),
+                "application/sparql-query".to_string(),
+            )],
+            b"SELECT * WHERE".to_vec(),
+        );
+        let response = handle_request(
+            &request,
+            Arc::new(MemoryRepository::default()),
+            "localhost",
+            &QuerySettings::default(),
+        );
+        assert_eq!(response.status_code, 400);
+        let mut body = String::default();
+        response
+            .data
+            .into_reader_and_size()
+            .0
+            .read_to_string(&mut body)
+            .unwrap();
+        assert!(
+            body.starts_with("{\"message\":\"Unexpected end of query at line 1 column 15"),
+            "{}",
+            body
+        );
+        assert!(body.contains("\"line\":1,\"column\":15,"), "{}", body);
+        assert!(
+            body.ends_with(",\"snippet\":\"SELECT * WHERE\"}"),
+            "{}",
+            body
+        );
+    }
+
+    #[test]
+    fn post_invalid_data() {
+        let request = Request::fake_http(
+            "POST",
+            "/",
+            vec![(
+                "Content-Type".to_string(),
+                "application/n-triples".to_string(),
+            )],
+            b"<http://example.com/s> <http://example.com/p> .".to_vec(),
+        );
+        let response = handle_request(
+            &request,
+            Arc::new(MemoryRepository::default()),
+            "localhost",
+            &QuerySettings::default(),
+        );
+        assert_eq!(response.status_code, 400);
+    }
+
+    #[test]
+    fn parse_error_status_codes() {
+        let status_code = |error| HttpError::from_parse_error(error).status_code();
+        let syntax_error = QueryPlanCache::new(0).plan("SELECT", None).err().unwrap();
+        assert_eq!(status_code(syntax_error), 400);
+        assert_eq!(status_code(Error::Evaluation("failure".to_owned())), 500);
+        assert_eq!(status_code(Error::Storage("failure".into())), 500);
+        assert_eq!(status_code(Error::Unsupported("feature".to_owned())), 501);
+    }
+
+    #[test]
+    fn normalize_queries() {
+        assert_eq!(
+            normalize_query("  SELECT *\nWHERE {\t?s ?p ?o # all\n}\n"),
+            "SELECT * WHERE { ?s ?p ?o }"
+        );
+        assert_eq!(
+            normalize_query(
+                "SELECT * WHERE { ?s ?p \"a  # b\" , 'c  \\'  d' , \"\"\"e\n\"  f\"\"\" }"
+            ),
+            "SELECT * WHERE { ?s ?p \"a  # b\" , 'c  \\'  d' , \"\"\"e\n\"  f\"\"\" }"
+        );
+        assert_eq!(
+            normalize_query("PREFIX rdf: <http://www.w3.org/1999/02/22-rdf-syntax-ns#>\nASK {  ?s rdf:type ?c FILTER(?c<?d) }"),
+            "PREFIX rdf: <http://www.w3.org/1999/02/22-rdf-syntax-ns#> ASK { ?s rdf:type ?c FILTER(?c<?d) }"
+        );
+        // "<?d#a>" might be an IRI or a "<" operator followed by a comment
+        assert_eq!(
+            normalize_query("ASK { FILTER(?c<?d#a> \"\n)  FILTER(\"a  b\") }"),
+            "ASK { FILTER(?c<?d#a> \"\n)  FILTER(\"a  b\") }"
+        );
+    }
+
+    #[test]
+    fn query_plan_cache() {
+        let repository = Arc::new(MemoryRepository::default());
+        let settings = QuerySettings::default();
+        for query in &["ASK { ?s ?p ?o }", "ASK {\n  ?s ?p ?o # any triple\n}"] {
+            let request = Request::fake_http(
+                "POST",
+                "/query",
+                vec![(
+                    "Content-Type".to_string(),
+                    "application/sparql-query".to_string(),
+                )],
+                query.as_bytes().to_vec(),
+            );
+            let response = handle_request(&request, repository.clone(), "localhost", &settings);
+            assert_eq!(response.status_code, 200);
+        }
+        assert_eq!(
+            cache_stats(repository.clone(), &settings),
+            "{\"capacity\":1024,\"size\":1,\"hits\":1,\"misses\":1}"
+        );
+
+        // Loading data drops the plans
+        let request = Request::fake_http(
+            "POST",
+            "/",
+            vec![(
+                "Content-Type".to_string(),
+                "application/n-triples".to_string(),
+            )],
+            b"<http://example.com/s> <http://example.com/p> <http://example.com/o> .".to_vec(),
+        );
+        let response = handle_request(&request, repository.clone(), "localhost", &settings);
+        assert_eq!(response.status_code, 204);
+        assert_eq!(
+            cache_stats(repository, &settings),
+            "{\"capacity\":1024,\"size\":0,\"hits\":1,\"misses\":1}"
+        );
+    }
+
+    #[test]
+    fn query_plan_cache_eviction() {
+        let cache = QueryPlanCache::new(2);
+        cache.plan("ASK { ?a ?b ?c }", None).unwrap();
+        cache.plan("ASK { ?d ?e ?f }", None).unwrap();
+        cache.plan("ASK { ?a ?b ?c }", None).unwrap(); // hit: the second query is now the least recently used
+        cache.plan("ASK { ?g ?h ?i }", None).unwrap(); // evicts the second query
+        cache.plan("ASK { ?a ?b ?c }", None).unwrap(); // hit
+        cache.plan("ASK { ?d ?e ?f }", None).unwrap(); // miss
+        assert_eq!(
+            cache.to_json(),
+            "{\"capacity\":2,\"size\":2,\"hits\":2,\"misses\":4}"
+        );
+        let entries = cache.entries.lock().unwrap();
+        assert_eq!(entries.plans.len(), entries.uses.len());
+
+        // A cache without capacity keeps nothing
+        let cache = QueryPlanCache::new(0);
+        cache.plan("ASK { ?a ?b ?c }", None).unwrap();
+        cache.plan("ASK { ?a ?b ?c }", None).unwrap();
+        assert_eq!(
+            cache.to_json(),
+            "{\"capacity\":0,\"size\":0,\"hits\":0,\"misses\":2}"
+        );
+    }
+
+    #[test]
+    fn skolemization() {
+        let repository = Arc::new(MemoryRepository::default());
+        let skolemizer = Skolemizer::new("http://example.com/").unwrap();
+        repository
+            .connection()
+            .unwrap()
+            .enable_skolemization(&skolemizer)
+            .unwrap();
+        let settings = QuerySettings {
+            skolemizer: Some(skolemizer),
+            ..QuerySettings::default()
+        };
+
+        let request = Request::fake_http(
+            "POST",
+            "/",
+            vec![(
+                "Content-Type".to_string(),
+                "application/n-triples".to_string(),
+            )],
+            b"_:b <http://example.com/p> <http://example.com/o> .".to_vec(),
+        );
+        let response = handle_request(&request, repository.clone(), "localhost", &settings);
+        assert_eq!(response.status_code, 204);
+
+        // The blank node is stored as a Skolem IRI
+        let quad = repository
+            .connection()
+            .unwrap()
+            .quads_for_pattern(None, None, None, None)
+            .next()
+            .unwrap()
+            .unwrap();
+        let iri = quad.subject().to_string();
+        assert!(
+            iri.starts_with("<http://example.com/.well-known/genid/"),
+            "{}",
+            iri
+        );
+
+        // The Skolem IRI could be used in queries and is returned as a blank node
+        let request = Request::fake_http(
+            "POST",
+            "/query",
+            vec![
+                (
+                    "Content-Type".to_string(),
+                    "application/sparql-query".to_string(),
+                ),
+                (
+                    "Accept".to_string(),
+                    "application/sparql-results+xml".to_string(),
+                ),
+            ],
+            format!("SELECT ?s WHERE {{ ?s ?p ?o FILTER(?s = {}) }}", iri).into_bytes(),
+        );
+        let response = handle_request(&request, repository, "localhost", &settings);
+        assert_eq!(response.status_code, 200);
+        let mut body = String::default();
+        response
+            .data
+            .into_reader_and_size()
+            .0
+            .read_to_string(&mut body)
+            .unwrap();
+        let id = &iri["<http://example.com/.well-known/genid/".len()..iri.len() - 1];
+        assert!(body.contains(&format!("<bnode>{}</bnode>", id)), "{}", body);
+    }
+
+    fn cache_stats(repository: Arc<MemoryRepository>, settings: &QuerySettings) -> String {
+        let request = Request::fake_http("GET", "/query-cache", vec![], vec![]);
+        let response = handle_request(&request, repository, "localhost", settings);
+        let mut body = String::default();
+        response
+            .data
+            .into_reader_and_size()
+            .0
+            .read_to_string(&mut body)
+            .unwrap();
+        body
+    }
+
     fn exec(request: Request) {
         let response = handle_request(
             &request,
-            MemoryRepository::default().connection().unwrap(),
+            Arc::new(MemoryRepository::default()),
             "localhost",
+            &QuerySettings::default(),
         );
         let mut body = String::default();
         request